@@ -2,5 +2,6 @@
 
 set -xe
 
+# The jvm reads $JAVA_HOME/lib/modules directly, this is only needed to run without a JDK
 # https://stackoverflow.com/a/61181216/19856457
 jimage extract --dir=stdlib $JAVA_HOME/lib/modules
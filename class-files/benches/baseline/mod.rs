@@ -0,0 +1,184 @@
+//! The `Read + Seek` parser that [`ClassFile::parse`] replaced, kept to benchmark against. It reads
+//! the same structure into owned data: every Utf8 constant is decoded into a `String` as it is
+//! read and every attribute is copied into its own `Vec`.
+//!
+//! [`ClassFile::parse`]: class_files::ClassFile::parse
+
+// the parsed classes are only thrown away
+#![allow(dead_code)]
+
+use std::io::{self, Read, Seek};
+
+use anyhow::{bail, ensure, Context};
+use class_files::bytes::ReadNum;
+
+pub enum Constant {
+    Unused,
+    Class(u16),
+    /// Fieldref, Methodref, InterfaceMethodref, NameAndType, Dynamic and InvokeDynamic
+    Pair(u8, u16, u16),
+    String(u16),
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    Utf8(String),
+    RawUtf8(Vec<u8>),
+    MethodHandle(u8, u16),
+    MethodType(u16),
+    Module(u16),
+    Package(u16),
+}
+
+impl Constant {
+    /// Read a constant, and whether it takes up two slots in the constant pool
+    fn read_from<R>(r: &mut R) -> anyhow::Result<(Self, bool)>
+    where
+        R: Read,
+    {
+        let tag = r.read_u8()?;
+        let constant = match tag {
+            7 => Self::Class(r.read_u16()?),
+            9 | 10 | 11 | 12 | 17 | 18 => Self::Pair(tag, r.read_u16()?, r.read_u16()?),
+            8 => Self::String(r.read_u16()?),
+            3 => Self::Integer(r.read_i32()?),
+            4 => Self::Float(r.read_f32()?),
+            5 => return Ok((Self::Long(r.read_i64()?), true)),
+            6 => return Ok((Self::Double(r.read_f64()?), true)),
+            1 => {
+                let mut bytes = vec![0u8; r.read_u16()?.into()];
+                r.read_exact(&mut bytes)?;
+                match cesu8::from_java_cesu8(&bytes) {
+                    Ok(string) => Self::Utf8(string.into_owned()),
+                    Err(_) => Self::RawUtf8(bytes),
+                }
+            }
+            15 => Self::MethodHandle(r.read_u8()?, r.read_u16()?),
+            16 => Self::MethodType(r.read_u16()?),
+            19 => Self::Module(r.read_u16()?),
+            20 => Self::Package(r.read_u16()?),
+            tag => bail!("Invalid constant tag: {}", tag),
+        };
+        Ok((constant, false))
+    }
+}
+
+pub struct Attribute {
+    name_index: u16,
+    info: Vec<u8>,
+}
+
+impl Attribute {
+    fn read_all<R>(r: &mut R) -> anyhow::Result<Vec<Self>>
+    where
+        R: Read,
+    {
+        let count = r.read_u16()?;
+        let mut attributes = Vec::with_capacity(count.into());
+        for _ in 0..count {
+            let name_index = r.read_u16()?;
+            let len = r.read_u32()?;
+            let mut info = Vec::new();
+            r.take(len.into()).read_to_end(&mut info)?;
+            if info.len() != len as usize {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            attributes.push(Self { name_index, info });
+        }
+        Ok(attributes)
+    }
+}
+
+/// A field or method
+pub struct Member {
+    access_flags: u16,
+    name_index: u16,
+    descriptor_index: u16,
+    attributes: Vec<Attribute>,
+}
+
+impl Member {
+    fn read_all<R>(r: &mut R) -> anyhow::Result<Vec<Self>>
+    where
+        R: Read,
+    {
+        let count = r.read_u16()?;
+        let mut members = Vec::with_capacity(count.into());
+        for _ in 0..count {
+            members.push(Self {
+                access_flags: r.read_u16()?,
+                name_index: r.read_u16()?,
+                descriptor_index: r.read_u16()?,
+                attributes: Attribute::read_all(r)?,
+            });
+        }
+        Ok(members)
+    }
+}
+
+pub struct ClassFile {
+    version: (u16, u16),
+    constant_pool: Vec<Constant>,
+    access_flags: u16,
+    this_class: u16,
+    super_class: u16,
+    interfaces: Vec<u16>,
+    fields: Vec<Member>,
+    methods: Vec<Member>,
+    attributes: Vec<Attribute>,
+}
+
+impl ClassFile {
+    pub fn read_from<R>(r: &mut R) -> anyhow::Result<Self>
+    where
+        R: Read + Seek,
+    {
+        let magic = r.read_u32().context("parsing magic")?;
+        ensure!(magic == 0xcafe_babe, "Invalid magic value: 0x{:x}", magic);
+        let minor_version = r.read_u16()?;
+        let major_version = r.read_u16()?;
+
+        let constant_pool_count = r.read_u16()?;
+        let mut constant_pool =
+            Vec::with_capacity((constant_pool_count as usize).saturating_sub(1));
+        let mut i = 1;
+        while i < constant_pool_count {
+            let (constant, skip_next) = Constant::read_from(r).context("parsing constant")?;
+            constant_pool.push(constant);
+            if skip_next {
+                i += 1;
+                constant_pool.push(Constant::Unused);
+            }
+            i += 1;
+        }
+
+        let access_flags = r.read_u16()?;
+        let this_class = r.read_u16()?;
+        let super_class = r.read_u16()?;
+        let interfaces = (0..r.read_u16()?)
+            .map(|_| r.read_u16())
+            .collect::<anyhow::Result<_>>()?;
+        let fields = Member::read_all(r).context("parsing fields")?;
+        let methods = Member::read_all(r).context("parsing methods")?;
+        let attributes = Attribute::read_all(r).context("parsing attributes")?;
+
+        let remaining_bytes = io::copy(r, &mut io::sink())?;
+        ensure!(
+            remaining_bytes == 0,
+            "{} bytes remaining in file",
+            remaining_bytes
+        );
+
+        Ok(Self {
+            version: (major_version, minor_version),
+            constant_pool,
+            access_flags,
+            this_class,
+            super_class,
+            interfaces,
+            fields,
+            methods,
+            attributes,
+        })
+    }
+}
@@ -0,0 +1,90 @@
+//! Compares the `Read + Seek` parser that was replaced (see [`baseline`]) against reading class
+//! files into owned data with [`ClassFile::read_from`] and borrowing from the input with
+//! [`ClassFile::parse`], over the classes in `java.lang` of the extracted JDK (see `build.sh`).
+
+mod baseline;
+
+use std::{
+    fs::{self, File},
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+use class_files::ClassFile;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use memmap2::Mmap;
+
+fn java_lang_classes() -> Vec<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../stdlib/java.base/java/lang");
+    let Ok(entries) = fs::read_dir(&root) else {
+        eprintln!("{} not found, run build.sh to extract it", root.display());
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "class"))
+        .collect()
+}
+
+fn parse(c: &mut Criterion) {
+    let paths = java_lang_classes();
+    if paths.is_empty() {
+        return;
+    }
+    let files: Vec<Vec<u8>> = paths.iter().map(|p| fs::read(p).unwrap()).collect();
+    let maps: Vec<Mmap> = paths
+        .iter()
+        .map(|p| unsafe { Mmap::map(&File::open(p).unwrap()).unwrap() })
+        .collect();
+
+    let mut group = c.benchmark_group("java.lang");
+    group.throughput(Throughput::Bytes(
+        files.iter().map(|f| f.len() as u64).sum(),
+    ));
+
+    group.bench_function("baseline", |b| {
+        b.iter(|| {
+            for bytes in &files {
+                baseline::ClassFile::read_from(&mut Cursor::new(bytes)).unwrap();
+            }
+        })
+    });
+    group.bench_function("read_from", |b| {
+        b.iter(|| {
+            for bytes in &files {
+                ClassFile::read_from(&mut Cursor::new(bytes)).unwrap();
+            }
+        })
+    });
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            for bytes in &files {
+                ClassFile::parse(bytes).unwrap();
+            }
+        })
+    });
+    group.bench_function("parse_mmap", |b| {
+        b.iter(|| {
+            for map in &maps {
+                ClassFile::parse(map).unwrap();
+            }
+        })
+    });
+    // looking up every string as well, which decodes the ones that aren't valid UTF-8
+    group.bench_function("parse_utf8", |b| {
+        b.iter(|| {
+            for bytes in &files {
+                let class = ClassFile::parse(bytes).unwrap();
+                for index in 1..=class.constant_pool.len() {
+                    let _ = class.constant_pool.utf8(index);
+                }
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,701 @@
+//! Assembles instructions with symbolic labels into the contents of a `Code` attribute
+
+use std::collections::HashMap;
+
+use anyhow::{bail, ensure, Context};
+
+use crate::{
+    bytecode::{Instruction, Instructions},
+    bytes::WriteNum,
+    constant_pool::ConstantPool,
+    descriptors::MethodDescriptor,
+    types::{
+        raw::RawAttribute,
+        resolved::{Attribute, Exception},
+    },
+};
+
+/// A position in the code, created by [`Assembler::new_label`] and placed with
+/// [`Assembler::bind`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label(usize);
+
+/// The instructions which branch to a single label
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jump {
+    Ifeq,
+    Ifne,
+    Iflt,
+    Ifge,
+    Ifgt,
+    Ifle,
+    IfIcmpeq,
+    IfIcmpne,
+    IfIcmplt,
+    IfIcmpge,
+    IfIcmpgt,
+    IfIcmple,
+    IfAcmpeq,
+    IfAcmpne,
+    Ifnull,
+    Ifnonnull,
+    Goto,
+    Jsr,
+}
+
+impl Jump {
+    fn instruction(self, offset: i16) -> Instruction {
+        match self {
+            Self::Ifeq => Instruction::Ifeq(offset),
+            Self::Ifne => Instruction::Ifne(offset),
+            Self::Iflt => Instruction::Iflt(offset),
+            Self::Ifge => Instruction::Ifge(offset),
+            Self::Ifgt => Instruction::Ifgt(offset),
+            Self::Ifle => Instruction::Ifle(offset),
+            Self::IfIcmpeq => Instruction::IfIcmpeq(offset),
+            Self::IfIcmpne => Instruction::IfIcmpne(offset),
+            Self::IfIcmplt => Instruction::IfIcmplt(offset),
+            Self::IfIcmpge => Instruction::IfIcmpge(offset),
+            Self::IfIcmpgt => Instruction::IfIcmpgt(offset),
+            Self::IfIcmple => Instruction::IfIcmple(offset),
+            Self::IfAcmpeq => Instruction::IfAcmpeq(offset),
+            Self::IfAcmpne => Instruction::IfAcmpne(offset),
+            Self::Ifnull => Instruction::Ifnull(offset),
+            Self::Ifnonnull => Instruction::Ifnonnull(offset),
+            Self::Goto => Instruction::Goto(offset),
+            Self::Jsr => Instruction::Jsr(offset),
+        }
+    }
+
+    /// The branch which is taken exactly when this one isn't, `None` for `goto` and `jsr`
+    fn negate(self) -> Option<Self> {
+        Some(match self {
+            Self::Ifeq => Self::Ifne,
+            Self::Ifne => Self::Ifeq,
+            Self::Iflt => Self::Ifge,
+            Self::Ifge => Self::Iflt,
+            Self::Ifgt => Self::Ifle,
+            Self::Ifle => Self::Ifgt,
+            Self::IfIcmpeq => Self::IfIcmpne,
+            Self::IfIcmpne => Self::IfIcmpeq,
+            Self::IfIcmplt => Self::IfIcmpge,
+            Self::IfIcmpge => Self::IfIcmplt,
+            Self::IfIcmpgt => Self::IfIcmple,
+            Self::IfIcmple => Self::IfIcmpgt,
+            Self::IfAcmpeq => Self::IfAcmpne,
+            Self::IfAcmpne => Self::IfAcmpeq,
+            Self::Ifnull => Self::Ifnonnull,
+            Self::Ifnonnull => Self::Ifnull,
+            Self::Goto | Self::Jsr => return None,
+        })
+    }
+
+    /// The size of the jump once assembled, `wide` if the offset doesn't fit in an `i16`
+    fn len(self, wide: bool) -> usize {
+        match (self, wide) {
+            (_, false) => 3,
+            // goto_w and jsr_w
+            (Self::Goto | Self::Jsr, true) => 5,
+            // the negated branch over a goto_w
+            (_, true) => 3 + 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Label(Label),
+    Instruction(Instruction),
+    Jump(Jump, Label),
+    Tableswitch {
+        default: Label,
+        low: i32,
+        targets: Vec<Label>,
+    },
+    Lookupswitch {
+        default: Label,
+        pairs: Vec<(i32, Label)>,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Handler {
+    start: Label,
+    end: Label,
+    handler: Label,
+    catch_type: usize,
+}
+
+/// The result of [`Assembler::assemble`], see
+/// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.3>
+#[derive(Debug, Clone, Default)]
+pub struct Code {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub code: Vec<u8>,
+    pub exception_table: Vec<Exception>,
+}
+
+impl Code {
+    /// Borrow this as a resolved `Code` attribute with no nested attributes
+    pub fn as_attribute(&self) -> Attribute<'_> {
+        Attribute::Code {
+            max_stack: self.max_stack,
+            max_locals: self.max_locals,
+            code: &self.code,
+            exception_table: self.exception_table.clone(),
+            attributes: Vec::new(),
+        }
+    }
+
+    /// Serialise this as a `Code` attribute, where `name_index` is the index of a Utf8 constant
+    /// containing `Code`
+    pub fn to_raw(&self, name_index: usize) -> anyhow::Result<RawAttribute<'static>> {
+        let mut info = Vec::new();
+        info.write_u16(self.max_stack)?;
+        info.write_u16(self.max_locals)?;
+        info.write_u32(u32::try_from(self.code.len()).context("code too long")?)?;
+        info.extend_from_slice(&self.code);
+        info.write_u16(
+            u16::try_from(self.exception_table.len()).context("exception table too long")?,
+        )?;
+        for exception in &self.exception_table {
+            info.write_u16(exception.start_pc)?;
+            info.write_u16(exception.end_pc)?;
+            info.write_u16(exception.handler_pc)?;
+            info.write_u16(exception.catch_type)?;
+        }
+        // attributes_count
+        info.write_u16(0)?;
+
+        Ok(RawAttribute {
+            attribute_name_index: name_index,
+            info: info.into(),
+        })
+    }
+}
+
+/// Builds the code of a method from instructions and [`Label`]s. Branch offsets, switch padding
+/// and `max_stack`/`max_locals` are worked out by [`Assembler::assemble`].
+///
+/// ```
+/// # use class_files::{assembler::{Assembler, Jump}, bytecode::Instruction, constant_pool::ConstantPool};
+/// // static int abs(int)
+/// let mut asm = Assembler::new(1);
+/// let positive = asm.new_label();
+/// asm.push(Instruction::Iload0)
+///     .jump(Jump::Ifge, positive)
+///     .push(Instruction::Iload0)
+///     .push(Instruction::Ineg)
+///     .push(Instruction::Ireturn)
+///     .bind(positive)
+///     .push(Instruction::Iload0)
+///     .push(Instruction::Ireturn);
+///
+/// let code = asm.assemble(&ConstantPool::default()).unwrap();
+/// assert_eq!(code.code, [0x1a, 0x9c, 0x00, 0x06, 0x1a, 0x74, 0xac, 0x1a, 0xac]);
+/// assert_eq!((code.max_stack, code.max_locals), (1, 1));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Assembler {
+    items: Vec<Item>,
+    labels: usize,
+    handlers: Vec<Handler>,
+    /// Local variable slots taken up by the parameters (and `this`)
+    parameter_slots: usize,
+}
+
+impl Assembler {
+    /// An assembler for a method whose parameters, including `this` for instance methods, take
+    /// up `parameter_slots` local variables
+    pub fn new(parameter_slots: usize) -> Self {
+        Self {
+            parameter_slots,
+            ..Default::default()
+        }
+    }
+
+    /// An assembler for a method with the given descriptor
+    pub fn for_method(descriptor: &MethodDescriptor, is_static: bool) -> Self {
+        let this = if is_static { 0 } else { 1 };
+        Self::new(this + descriptor.params.iter().map(|p| p.slots()).sum::<usize>())
+    }
+
+    pub fn new_label(&mut self) -> Label {
+        self.labels += 1;
+        Label(self.labels - 1)
+    }
+
+    /// Place `label` before the next instruction
+    pub fn bind(&mut self, label: Label) -> &mut Self {
+        self.items.push(Item::Label(label));
+        self
+    }
+
+    /// Add an instruction which does not branch, branches should use [`Assembler::jump`],
+    /// [`Assembler::tableswitch`] or [`Assembler::lookupswitch`] instead
+    pub fn push(&mut self, instruction: Instruction) -> &mut Self {
+        self.items.push(Item::Instruction(instruction));
+        self
+    }
+
+    pub fn jump(&mut self, jump: Jump, target: Label) -> &mut Self {
+        self.items.push(Item::Jump(jump, target));
+        self
+    }
+
+    /// Add a `tableswitch` which jumps to `targets[i]` for the value `low + i`
+    pub fn tableswitch(&mut self, low: i32, default: Label, targets: Vec<Label>) -> &mut Self {
+        self.items.push(Item::Tableswitch {
+            default,
+            low,
+            targets,
+        });
+        self
+    }
+
+    /// Add a `lookupswitch`, `pairs` don't need to be sorted
+    pub fn lookupswitch(&mut self, default: Label, mut pairs: Vec<(i32, Label)>) -> &mut Self {
+        pairs.sort_by_key(|(key, _)| *key);
+        self.items.push(Item::Lookupswitch { default, pairs });
+        self
+    }
+
+    /// Add an entry to the exception table, which jumps to `handler` for exceptions thrown in
+    /// `start..end`. `catch_type` is the index of a Class constant, or zero to catch everything.
+    pub fn exception_handler(
+        &mut self,
+        start: Label,
+        end: Label,
+        handler: Label,
+        catch_type: usize,
+    ) -> &mut Self {
+        self.handlers.push(Handler {
+            start,
+            end,
+            handler,
+            catch_type,
+        });
+        self
+    }
+
+    /// The `pc` of each item and of each label, when the jumps marked in `wide` use the long form
+    fn layout(&self, wide: &[bool]) -> anyhow::Result<(Vec<usize>, HashMap<Label, usize>)> {
+        let mut positions = Vec::with_capacity(self.items.len());
+        let mut labels = HashMap::new();
+        let mut pc = 0;
+        for (item, wide) in self.items.iter().zip(wide) {
+            positions.push(pc);
+            pc += match item {
+                Item::Label(label) => {
+                    ensure!(
+                        labels.insert(*label, pc).is_none(),
+                        "{:?} is bound more than once",
+                        label
+                    );
+                    0
+                }
+                Item::Instruction(instruction) => instruction.len(pc),
+                Item::Jump(jump, _) => jump.len(*wide),
+                Item::Tableswitch { targets, .. } => {
+                    1 + (4 - (pc + 1) % 4) % 4 + 12 + 4 * targets.len()
+                }
+                Item::Lookupswitch { pairs, .. } => {
+                    1 + (4 - (pc + 1) % 4) % 4 + 8 + 8 * pairs.len()
+                }
+            };
+        }
+        positions.push(pc);
+        Ok((positions, labels))
+    }
+
+    /// Produce the code, `constant_pool` is used to find the descriptors of fields and methods to
+    /// calculate `max_stack`
+    pub fn assemble(&self, constant_pool: &ConstantPool) -> anyhow::Result<Code> {
+        for item in &self.items {
+            match item {
+                Item::Instruction(instruction) => ensure!(
+                    instruction.branch_targets(0).is_empty(),
+                    "{} must be added with a label",
+                    instruction.mnemonic()
+                ),
+                Item::Tableswitch { low, targets, .. } => {
+                    ensure!(
+                        !targets.is_empty(),
+                        "tableswitch must have at least one target"
+                    );
+                    ensure!(
+                        i64::from(*low) + targets.len() as i64 - 1 <= i64::from(i32::MAX),
+                        "tableswitch from {} with {} targets goes past the largest int",
+                        low,
+                        targets.len()
+                    );
+                }
+                Item::Lookupswitch { pairs, .. } => {
+                    // `pairs` are sorted, so duplicates are next to each other
+                    if let Some(pair) = pairs.windows(2).find(|p| p[0].0 == p[1].0) {
+                        bail!("lookupswitch has more than one target for {}", pair[0].0);
+                    }
+                }
+                Item::Label(_) | Item::Jump(..) => {}
+            }
+        }
+
+        // start with every jump short and widen the ones which don't fit until nothing changes,
+        // widening only ever moves code further apart so this terminates
+        let mut wide = vec![false; self.items.len()];
+        let (positions, labels) = loop {
+            let (positions, labels) = self.layout(&wide)?;
+            let mut changed = false;
+            for (i, item) in self.items.iter().enumerate() {
+                if let Item::Jump(_, target) = item {
+                    let target = *labels
+                        .get(target)
+                        .with_context(|| format!("{:?} is not bound", target))?;
+                    let offset = target as i64 - positions[i] as i64;
+                    if !wide[i] && i16::try_from(offset).is_err() {
+                        wide[i] = true;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break (positions, labels);
+            }
+        };
+
+        let label = |label: &Label| {
+            labels
+                .get(label)
+                .copied()
+                .with_context(|| format!("{:?} is not bound", label))
+        };
+        let offset = |from: usize, to: &Label| -> anyhow::Result<i32> {
+            Ok(i32::try_from(label(to)? as i64 - from as i64)?)
+        };
+
+        let mut code = Vec::with_capacity(*positions.last().unwrap_or(&0));
+        for (i, item) in self.items.iter().enumerate() {
+            let pc = positions[i];
+            debug_assert_eq!(pc, code.len());
+            let instruction = match item {
+                Item::Label(_) => continue,
+                Item::Instruction(instruction) => instruction.clone(),
+                Item::Jump(jump, target) if !wide[i] => {
+                    jump.instruction(offset(pc, target)? as i16)
+                }
+                Item::Jump(jump, target) => {
+                    let long = |offset| match jump {
+                        Jump::Jsr => Instruction::JsrW(offset),
+                        _ => Instruction::GotoW(offset),
+                    };
+                    match jump.negate() {
+                        Some(negated) => {
+                            // skip over the goto_w when the original condition is false
+                            negated.instruction(3 + 5).write_to(&mut code, pc)?;
+                            long(offset(pc + 3, target)?)
+                        }
+                        None => long(offset(pc, target)?),
+                    }
+                }
+                Item::Tableswitch {
+                    default,
+                    low,
+                    targets,
+                } => Instruction::Tableswitch {
+                    default: offset(pc, default)?,
+                    low: *low,
+                    // checked above
+                    high: (i64::from(*low) + targets.len() as i64 - 1) as i32,
+                    offsets: targets
+                        .iter()
+                        .map(|t| offset(pc, t))
+                        .collect::<anyhow::Result<_>>()?,
+                },
+                Item::Lookupswitch { default, pairs } => Instruction::Lookupswitch {
+                    default: offset(pc, default)?,
+                    pairs: pairs
+                        .iter()
+                        .map(|(key, t)| Ok((*key, offset(pc, t)?)))
+                        .collect::<anyhow::Result<_>>()?,
+                },
+            };
+            let start = code.len();
+            instruction.write_to(&mut code, start)?;
+        }
+
+        ensure!(!code.is_empty(), "Code must not be empty");
+        ensure!(
+            code.len() <= u16::MAX as usize,
+            "Code is {} bytes, the most allowed is {}",
+            code.len(),
+            u16::MAX
+        );
+
+        let exception_table = self
+            .handlers
+            .iter()
+            .map(|h| {
+                Ok(Exception {
+                    start_pc: label(&h.start)? as u16,
+                    end_pc: label(&h.end)? as u16,
+                    handler_pc: label(&h.handler)? as u16,
+                    catch_type: u16::try_from(h.catch_type).context("catch_type out of range")?,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut max_locals = self.parameter_slots;
+        for instruction in Instructions::new(&code) {
+            let (_, instruction) = instruction?;
+            max_locals = max_locals.max(instruction.locals_used().unwrap_or(0));
+        }
+
+        Ok(Code {
+            max_stack: max_stack(&code, &exception_table, constant_pool)?,
+            max_locals: u16::try_from(max_locals).context("too many local variables")?,
+            code,
+            exception_table,
+        })
+    }
+}
+
+/// Follow every path through `code` to find the deepest the operand stack gets
+fn max_stack(
+    code: &[u8],
+    exception_table: &[Exception],
+    constant_pool: &ConstantPool,
+) -> anyhow::Result<u16> {
+    let instructions = Instructions::new(code)
+        .map(|i| i.map(|(pc, i)| (pc, (pc + i.len(pc), i))))
+        .collect::<Result<HashMap<_, _>, _>>()?;
+
+    // the stack depth before each instruction
+    let mut depths: HashMap<usize, usize> = HashMap::new();
+    let mut pending = vec![(0, 0)];
+    // handlers start with only the exception on the stack
+    pending.extend(exception_table.iter().map(|e| (e.handler_pc.into(), 1)));
+
+    let mut max = 0;
+    while let Some((pc, depth)) = pending.pop() {
+        match depths.get(&pc) {
+            Some(&d) if d == depth => continue,
+            Some(&d) => bail!("Stack depth at {} is both {} and {}", pc, d, depth),
+            None => {
+                depths.insert(pc, depth);
+            }
+        }
+
+        let (next, instruction) = instructions
+            .get(&pc)
+            .with_context(|| format!("No instruction at {}", pc))?;
+        let (pop, push) = instruction.stack_effect(constant_pool)?;
+        let after = depth.checked_sub(pop).with_context(|| {
+            format!(
+                "{} at {} pops from an empty stack",
+                instruction.mnemonic(),
+                pc
+            )
+        })? + push;
+        max = max.max(after);
+
+        if matches!(instruction, Instruction::Athrow) {
+            continue;
+        }
+        for target in instruction.branch_targets(pc) {
+            // the return address pushed by jsr is only on the stack in the subroutine
+            pending.push((target, after));
+        }
+        if instruction.falls_through() {
+            ensure!(
+                *next < code.len(),
+                "Execution falls off the end of the code"
+            );
+            let after = match instruction {
+                Instruction::Jsr(_) | Instruction::JsrW(_) => depth,
+                _ => after,
+            };
+            pending.push((*next, after));
+        }
+    }
+
+    u16::try_from(max).context("Operand stack too deep")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{bytecode::Wide, test::stdlib_classes, types::raw::RawConstant, ClassFile};
+    use std::{fs, io::Cursor};
+
+    #[test]
+    fn long_jumps() {
+        // static void f(boolean): while (arg) {} with a body too big for a short branch
+        let mut asm = Assembler::new(1);
+        let (start, end) = (asm.new_label(), asm.new_label());
+        asm.bind(start)
+            .push(Instruction::Iload0)
+            .jump(Jump::Ifeq, end);
+        for _ in 0..20_000 {
+            asm.push(Instruction::Iinc { index: 1, value: 1 });
+        }
+        asm.jump(Jump::Goto, start)
+            .bind(end)
+            .push(Instruction::Return);
+
+        let code = asm.assemble(&ConstantPool::default()).unwrap();
+        let instructions: Vec<_> = Instructions::new(&code.code).map(|i| i.unwrap()).collect();
+        // ifeq became ifne over a goto_w
+        assert_eq!(instructions[1], (1, Instruction::Ifne(8)));
+        assert_eq!(instructions[2], (4, Instruction::GotoW(60_010)));
+        let (pc, goto) = &instructions[instructions.len() - 2];
+        assert_eq!(*goto, Instruction::GotoW(-(*pc as i32)));
+        assert_eq!((code.max_stack, code.max_locals), (1, 2));
+    }
+
+    #[test]
+    fn switches_and_handlers() {
+        let pool = ConstantPool {
+            constants: vec![RawConstant::utf8("Code")],
+        };
+
+        // static int f(int)
+        let mut asm = Assembler::new(1);
+        let labels: Vec<_> = (0..5).map(|_| asm.new_label()).collect();
+        let [start, end, one, other, handler] = labels[..] else {
+            unreachable!()
+        };
+        asm.bind(start)
+            .push(Instruction::Iconst0)
+            .push(Instruction::Iload0)
+            .tableswitch(1, other, vec![one, other])
+            .bind(one)
+            .push(Instruction::Pop)
+            .push(Instruction::Wide(Wide::Lload(300)))
+            .push(Instruction::L2i)
+            .push(Instruction::Ireturn)
+            .bind(other)
+            .bind(end)
+            .push(Instruction::Ireturn)
+            .bind(handler)
+            .push(Instruction::Pop)
+            .push(Instruction::Iconst1)
+            .push(Instruction::Ireturn)
+            .lookupswitch(other, vec![(5, one), (-1, handler)])
+            .exception_handler(start, end, handler, 0);
+
+        let code = asm.assemble(&pool).unwrap();
+        let instructions: Vec<_> = Instructions::new(&code.code).map(|i| i.unwrap()).collect();
+        assert_eq!(
+            instructions[2],
+            (
+                2,
+                Instruction::Tableswitch {
+                    default: 29,
+                    low: 1,
+                    high: 2,
+                    offsets: vec![22, 29],
+                }
+            )
+        );
+        assert_eq!(
+            instructions.last().unwrap().1,
+            Instruction::Lookupswitch {
+                default: -4,
+                pairs: vec![(-1, -3), (5, -11)],
+            }
+        );
+        assert_eq!((code.max_stack, code.max_locals), (2, 302));
+        assert_eq!(code.exception_table[0].handler_pc, 32);
+
+        let raw = code.to_raw(1).unwrap();
+        let Attribute::Code {
+            code: parsed,
+            exception_table,
+            ..
+        } = Attribute::from_raw(&raw, &pool).unwrap()
+        else {
+            panic!("expected Code attribute");
+        };
+        assert_eq!(parsed, code.code);
+        assert_eq!(exception_table.len(), 1);
+    }
+
+    #[test]
+    fn mistakes() {
+        let mut asm = Assembler::new(0);
+        let label = asm.new_label();
+        asm.jump(Jump::Goto, label);
+        assert!(asm.assemble(&ConstantPool::default()).is_err());
+
+        let mut asm = Assembler::new(0);
+        asm.push(Instruction::Goto(0));
+        assert!(asm.assemble(&ConstantPool::default()).is_err());
+
+        let mut asm = Assembler::new(0);
+        asm.push(Instruction::Pop).push(Instruction::Return);
+        assert!(asm.assemble(&ConstantPool::default()).is_err());
+
+        let mut asm = Assembler::new(0);
+        asm.push(Instruction::Iconst0);
+        assert!(asm.assemble(&ConstantPool::default()).is_err());
+
+        let switch = |targets: usize, low: i32, keys: &[i32]| {
+            let mut asm = Assembler::new(0);
+            let label = asm.new_label();
+            asm.bind(label).push(Instruction::Iconst0);
+            if keys.is_empty() {
+                asm.tableswitch(low, label, vec![label; targets]);
+            } else {
+                asm.lookupswitch(label, keys.iter().map(|&k| (k, label)).collect());
+            }
+            asm.assemble(&ConstantPool::default())
+                .map_err(|e| e.to_string())
+        };
+        assert!(switch(1, i32::MIN, &[]).is_ok());
+        assert!(switch(2, i32::MAX, &[])
+            .unwrap_err()
+            .contains("largest int"));
+        for low in [0, i32::MIN] {
+            assert!(switch(0, low, &[])
+                .unwrap_err()
+                .contains("at least one target"));
+        }
+        assert!(switch(0, 0, &[3, 1, 2]).is_ok());
+        assert!(switch(0, 0, &[3, 1, 3])
+            .unwrap_err()
+            .contains("more than one target for 3"));
+    }
+
+    #[test]
+    fn stdlib_max_stack() {
+        let Some(paths) = stdlib_classes("java.base") else {
+            return;
+        };
+
+        for path in paths {
+            let bytes = fs::read(&path).unwrap();
+            let class = ClassFile::read_from(&mut Cursor::new(bytes)).unwrap();
+            for method in class.methods() {
+                let method = method.unwrap();
+                let Some(Attribute::Code {
+                    max_stack: expected,
+                    code,
+                    exception_table,
+                    ..
+                }) = method.code().unwrap()
+                else {
+                    continue;
+                };
+                let actual = max_stack(code, &exception_table, &class.constant_pool);
+                assert_eq!(
+                    actual.ok(),
+                    Some(expected),
+                    "{}: {}",
+                    path.display(),
+                    method.name
+                );
+            }
+        }
+    }
+}
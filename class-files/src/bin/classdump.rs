@@ -0,0 +1,1273 @@
+//! Prints class files in the same format as `javap -c -v -p`, or as JSON with `--json` when built
+//! with the `serde` feature
+
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+use anyhow::{bail, Context};
+use class_files::{
+    bytecode::{Instruction, Wide},
+    constant_pool::{Constant, ConstantPool, Loadable, MemberKind, MemberRef, ReferenceKind},
+    descriptors::{FieldType, MethodDescriptor, ReturnDescriptor},
+    types::{
+        resolved::{
+            Annotation, Attribute, ElementValue, Field, Method, Module, StackMapFrame,
+            StackMapFrameKind, TargetInfo, TypeAnnotation, TypePathKind, VerificationType,
+        },
+        ClassAccessFlags, FieldAccessFlags, MethodAccessFlags,
+    },
+    ClassFile,
+};
+
+/// The type as it would be written in Java source, e.g. `java.lang.String[]`
+fn java_type(ty: &FieldType) -> String {
+    match ty {
+        FieldType::Byte => "byte".into(),
+        FieldType::Char => "char".into(),
+        FieldType::Double => "double".into(),
+        FieldType::Float => "float".into(),
+        FieldType::Int => "int".into(),
+        FieldType::Long => "long".into(),
+        FieldType::ObjReference(name) => name.replace('/', "."),
+        FieldType::Short => "short".into(),
+        FieldType::Boolean => "boolean".into(),
+        FieldType::ArrReference(component) => format!("{}[]", java_type(component)),
+    }
+}
+
+fn parse_field_type(descriptor: &str) -> anyhow::Result<FieldType> {
+    let mut chars = descriptor.chars();
+    let id = chars.next().context("Empty field descriptor")?;
+    FieldType::from_chars(id, &mut chars)
+}
+
+/// `flags: (0x0021) ACC_PUBLIC, ACC_SUPER`
+fn write_flags<W, F>(w: &mut W, flags: F) -> anyhow::Result<()>
+where
+    W: Write,
+    F: bitflags::Flags<Bits = u16>,
+{
+    let names: Vec<_> = flags
+        .iter_names()
+        .map(|(name, _)| format!("ACC_{}", name))
+        .collect();
+    let line = format!("flags: (0x{:04x}) {}", flags.bits(), names.join(", "));
+    writeln!(w, "{}", line.trim_end())?;
+    Ok(())
+}
+
+fn member_name(member: &MemberRef) -> String {
+    let name = if member.name.starts_with('<') {
+        format!("\"{}\"", member.name)
+    } else {
+        member.name.to_string()
+    };
+    format!("{}.{}:{}", member.class, name, member.descriptor)
+}
+
+fn reference_kind(kind: ReferenceKind) -> &'static str {
+    match kind {
+        ReferenceKind::GetField => "REF_getField",
+        ReferenceKind::GetStatic => "REF_getStatic",
+        ReferenceKind::PutField => "REF_putField",
+        ReferenceKind::PutStatic => "REF_putStatic",
+        ReferenceKind::InvokeVirtual => "REF_invokeVirtual",
+        ReferenceKind::InvokeStatic => "REF_invokeStatic",
+        ReferenceKind::InvokeSpecial => "REF_invokeSpecial",
+        ReferenceKind::NewInvokeSpecial => "REF_newInvokeSpecial",
+        ReferenceKind::InvokeInterface => "REF_invokeInterface",
+    }
+}
+
+/// The name of the kind of constant as listed in the constant pool, and its resolved value
+fn describe_constant(constant: &Constant) -> (&'static str, String) {
+    match constant {
+        Constant::Utf8(s) => ("Utf8", escape(s)),
+        Constant::RawUtf8(bytes) => ("Utf8", format!("{:02x?}", bytes)),
+        Constant::NameAndType(nt) => {
+            let name = if nt.name.starts_with('<') {
+                format!("\"{}\"", nt.name)
+            } else {
+                nt.name.to_string()
+            };
+            ("NameAndType", format!("{}:{}", name, nt.descriptor))
+        }
+        Constant::MemberRef(member) => (
+            match member.kind {
+                MemberKind::Field => "Fieldref",
+                MemberKind::Method => "Methodref",
+                MemberKind::InterfaceMethod => "InterfaceMethodref",
+            },
+            member_name(member),
+        ),
+        Constant::InvokeDynamic(dynamic) => (
+            "InvokeDynamic",
+            format!(
+                "#{}:{}:{}",
+                dynamic.bootstrap_method_attr_index, dynamic.name, dynamic.descriptor
+            ),
+        ),
+        Constant::Module(name) => ("Module", name.to_string()),
+        Constant::Package(name) => ("Package", name.to_string()),
+        Constant::Loadable(loadable) => match loadable {
+            Loadable::Integer(n) => ("Integer", n.to_string()),
+            Loadable::Float(n) => ("Float", format!("{}f", java_float(*n))),
+            Loadable::Long(n) => ("Long", format!("{}l", n)),
+            Loadable::Double(n) => ("Double", format!("{}d", java_double(*n))),
+            Loadable::Class(name) => ("Class", name.to_string()),
+            Loadable::String(s) => ("String", escape(s)),
+            Loadable::RawString(bytes) => ("String", format!("{:02x?}", bytes)),
+            Loadable::MethodHandle(handle) => (
+                "MethodHandle",
+                format!(
+                    "{} {}",
+                    reference_kind(handle.kind),
+                    member_name(&handle.reference)
+                ),
+            ),
+            Loadable::MethodType(descriptor) => ("MethodType", descriptor.to_string()),
+            Loadable::Dynamic(dynamic) => (
+                "Dynamic",
+                format!(
+                    "#{}:{}:{}",
+                    dynamic.bootstrap_method_attr_index, dynamic.name, dynamic.descriptor
+                ),
+            ),
+        },
+    }
+}
+
+/// The comment after an instruction which refers to the constant at `index`, e.g.
+/// `Method java/lang/Object."<init>":()V`
+fn constant_comment(pool: &ConstantPool, index: usize) -> String {
+    let constant = match pool.constant(index) {
+        Ok(constant) => constant,
+        Err(e) => return format!("<{}>", e),
+    };
+    let (kind, value) = describe_constant(&constant);
+    let kind = match kind {
+        "Fieldref" => "Field",
+        "Methodref" => "Method",
+        "InterfaceMethodref" => "InterfaceMethod",
+        "Integer" => "int",
+        "Float" => "float",
+        "Long" => "long",
+        "Double" => "double",
+        "Class" => "class",
+        kind => kind,
+    };
+    format!("{} {}", kind, value)
+}
+
+/// Each constant with the indices it refers to followed by their resolved value, e.g.
+/// `#1 = Methodref          #2.#3         // java/lang/Object."<init>":()V`
+fn write_constant_pool<W: Write>(w: &mut W, pool: &ConstantPool) -> anyhow::Result<()> {
+    writeln!(w, "Constant pool:")?;
+    // javap makes the index column wide enough for the largest index, and keeps the comments
+    // lined up after it
+    let width = (pool.len() + 1).to_string().len() + 1;
+    let operands_width = 17 - width;
+    for (index, constant) in pool.iter() {
+        let constant = constant.and_then(|c| Ok((c, pool.operands(index)?)));
+        let index = format!("#{}", index);
+        let (constant, operands) = match constant {
+            Ok(constant) => constant,
+            Err(e) => {
+                writeln!(w, "  {:>width$} = <{}>", index, e)?;
+                continue;
+            }
+        };
+        let (kind, value) = describe_constant(&constant);
+        let operands = match (kind, &operands[..]) {
+            (_, []) => {
+                writeln!(w, "  {:>width$} = {:<18} {}", index, kind, value)?;
+                continue;
+            }
+            ("Fieldref" | "Methodref" | "InterfaceMethodref", [class, nt]) => {
+                format!("#{}.#{}", class, nt)
+            }
+            ("MethodHandle", [kind, member]) => format!("{}:#{}", kind, member),
+            (_, [a, b]) => format!("#{}:#{}", a, b),
+            (_, operands) => operands
+                .iter()
+                .map(|i| format!("#{}", i))
+                .collect::<Vec<_>>()
+                .join(","),
+        };
+        // javap leaves an extra space before method types
+        let value = match kind {
+            "MethodType" => format!(" {}", value),
+            _ => value,
+        };
+        writeln!(
+            w,
+            "  {:>width$} = {:<18} {:<operands_width$} // {}",
+            index, kind, operands, value
+        )?;
+    }
+    Ok(())
+}
+
+/// The mnemonic and operands of an instruction, padded like javap
+fn write_instruction<W: Write>(
+    w: &mut W,
+    pool: &ConstantPool,
+    pc: usize,
+    instruction: &Instruction,
+) -> anyhow::Result<()> {
+    let mnemonic = instruction.mnemonic();
+    let with_constant = |w: &mut W, operands: String, index: usize| -> anyhow::Result<()> {
+        // javap lines the comments up even when the mnemonic is longer than usual
+        writeln!(
+            w,
+            "{:>10}: {:<33} // {}",
+            pc,
+            format!("{:<13} {}", mnemonic, operands),
+            constant_comment(pool, index)
+        )?;
+        Ok(())
+    };
+    let target = |offset: i32| pc as i64 + i64::from(offset);
+
+    match instruction {
+        Instruction::Ldc(index)
+        | Instruction::LdcW(index)
+        | Instruction::Ldc2W(index)
+        | Instruction::Getstatic(index)
+        | Instruction::Putstatic(index)
+        | Instruction::Getfield(index)
+        | Instruction::Putfield(index)
+        | Instruction::Invokevirtual(index)
+        | Instruction::Invokespecial(index)
+        | Instruction::Invokestatic(index)
+        | Instruction::New(index)
+        | Instruction::Anewarray(index)
+        | Instruction::Checkcast(index)
+        | Instruction::Instanceof(index) => with_constant(w, format!("#{}", index), *index)?,
+        Instruction::Invokeinterface { index, count } => {
+            with_constant(w, format!("#{},  {}", index, count), *index)?
+        }
+        Instruction::Invokedynamic(index) => with_constant(w, format!("#{},  0", index), *index)?,
+        Instruction::Multianewarray { index, dimensions } => {
+            with_constant(w, format!("#{},  {}", index, dimensions), *index)?
+        }
+        Instruction::Tableswitch {
+            default,
+            low,
+            high,
+            offsets,
+        } => {
+            writeln!(w, "{:>10}: {:<13} {{ // {} to {}", pc, mnemonic, low, high)?;
+            for (value, offset) in (*low..=*high).zip(offsets) {
+                writeln!(w, "{:>24}: {}", value, target(*offset))?;
+            }
+            writeln!(w, "{:>24}: {}", "default", target(*default))?;
+            writeln!(w, "{:>12}}}", "")?;
+        }
+        Instruction::Lookupswitch { default, pairs } => {
+            writeln!(w, "{:>10}: {:<13} {{ // {}", pc, mnemonic, pairs.len())?;
+            for (value, offset) in pairs {
+                writeln!(w, "{:>24}: {}", value, target(*offset))?;
+            }
+            writeln!(w, "{:>24}: {}", "default", target(*default))?;
+            writeln!(w, "{:>12}}}", "")?;
+        }
+        instruction => {
+            let operands = match instruction {
+                Instruction::Bipush(n) => n.to_string(),
+                Instruction::Sipush(n) => n.to_string(),
+                Instruction::Iload(i)
+                | Instruction::Lload(i)
+                | Instruction::Fload(i)
+                | Instruction::Dload(i)
+                | Instruction::Aload(i)
+                | Instruction::Istore(i)
+                | Instruction::Lstore(i)
+                | Instruction::Fstore(i)
+                | Instruction::Dstore(i)
+                | Instruction::Astore(i)
+                | Instruction::Ret(i) => i.to_string(),
+                Instruction::Iinc { index, value } => format!("{}, {}", index, value),
+                Instruction::Newarray(atype) => format!("{:?}", atype).to_lowercase(),
+                Instruction::Wide(wide) => match wide {
+                    Wide::Iload(i)
+                    | Wide::Lload(i)
+                    | Wide::Fload(i)
+                    | Wide::Dload(i)
+                    | Wide::Aload(i)
+                    | Wide::Istore(i)
+                    | Wide::Lstore(i)
+                    | Wide::Fstore(i)
+                    | Wide::Dstore(i)
+                    | Wide::Astore(i)
+                    | Wide::Ret(i) => i.to_string(),
+                    Wide::Iinc { index, value } => format!("{}, {}", index, value),
+                },
+                instruction => match instruction.branch_targets(pc)[..] {
+                    [target] => target.to_string(),
+                    _ => String::new(),
+                },
+            };
+            // javap shows wide instructions as the instruction they modify
+            let mnemonic = match instruction {
+                Instruction::Wide(wide) => match wide {
+                    Wide::Iload(_) => "iload_w",
+                    Wide::Lload(_) => "lload_w",
+                    Wide::Fload(_) => "fload_w",
+                    Wide::Dload(_) => "dload_w",
+                    Wide::Aload(_) => "aload_w",
+                    Wide::Istore(_) => "istore_w",
+                    Wide::Lstore(_) => "lstore_w",
+                    Wide::Fstore(_) => "fstore_w",
+                    Wide::Dstore(_) => "dstore_w",
+                    Wide::Astore(_) => "astore_w",
+                    Wide::Ret(_) => "ret_w",
+                    Wide::Iinc { .. } => "iinc_w",
+                },
+                _ => mnemonic,
+            };
+            if operands.is_empty() {
+                writeln!(w, "{:>10}: {}", pc, mnemonic)?;
+            } else {
+                writeln!(w, "{:>10}: {:<13} {}", pc, mnemonic, operands)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_code<W: Write>(
+    w: &mut W,
+    pool: &ConstantPool,
+    method: &Method,
+    code: Attribute,
+) -> anyhow::Result<()> {
+    let Attribute::Code {
+        max_stack,
+        max_locals,
+        code,
+        exception_table,
+        attributes,
+    } = code
+    else {
+        bail!("Expected Code attribute");
+    };
+
+    let descriptor: MethodDescriptor = method.descriptor.parse()?;
+    let this = usize::from(!method.access_flags.contains(MethodAccessFlags::STATIC));
+    let args_size: usize = this + descriptor.params.iter().map(|p| p.slots()).sum::<usize>();
+
+    writeln!(w, "    Code:")?;
+    writeln!(
+        w,
+        "      stack={}, locals={}, args_size={}",
+        max_stack, max_locals, args_size
+    )?;
+    for instruction in class_files::bytecode::Instructions::new(code) {
+        match instruction {
+            Ok((pc, instruction)) => write_instruction(w, pool, pc, &instruction)?,
+            Err(e) => {
+                writeln!(w, "      <{}>", e)?;
+                break;
+            }
+        }
+    }
+
+    if !exception_table.is_empty() {
+        writeln!(w, "      Exception table:")?;
+        writeln!(w, "         from    to  target type")?;
+        for exception in exception_table {
+            let catch_type = match exception.catch_type {
+                0 => "any".to_string(),
+                index => match pool.class_name(index.into()) {
+                    Ok(name) => format!("Class {}", name),
+                    Err(e) => format!("<{}>", e),
+                },
+            };
+            writeln!(
+                w,
+                "         {:>5} {:>5} {:>5}   {}",
+                exception.start_pc, exception.end_pc, exception.handler_pc, catch_type
+            )?;
+        }
+    }
+
+    for attribute in attributes {
+        write_attribute(w, "      ", attribute)?;
+    }
+    Ok(())
+}
+
+/// A string constant escaped the way javap does, e.g. `b\n` or `\u0001`
+fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\'' => escaped.push_str("\\'"),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `"b\n"` for a string constant, escaped the way javap does
+fn quote(s: &str) -> String {
+    format!("\"{}\"", escape(s))
+}
+
+/// For each power of two, how many of the low decimal digits of an integer that large are
+/// dropped by Java's `Float.toString` and `Double.toString`
+const INSIGNIFICANT_DIGITS: [u32; 64] = [
+    0, 0, 0, 0, 1, 1, 1, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 8, 8, 8, 9, 9,
+    9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 12, 12, 13, 13, 13, 14, 14, 14, 15, 15, 15, 15, 16, 16,
+    16, 17, 17, 17, 18, 18, 18, 19,
+];
+
+/// `n` formatted like Java's `Float.toString` or `Double.toString`, e.g. `1.0E-4`, `0.1` or
+/// `Infinity`. `scientific` is `n` formatted with `{:e}` as its own type (which gives the
+/// shortest digits that read back as `n`) and `significand_bits` is 24 for a float and 53 for
+/// a double.
+fn java_number(n: f64, scientific: &str, significand_bits: u32) -> String {
+    if n.is_nan() {
+        return "NaN".to_string();
+    }
+    let sign = if n.is_sign_negative() { "-" } else { "" };
+    if n.is_infinite() {
+        return format!("{}Infinity", sign);
+    }
+    if n == 0.0 {
+        return format!("{}0.0", sign);
+    }
+
+    // Whole numbers that fit in a long are printed from their exact value with the digits
+    // beyond the type's precision rounded off, which can give more digits than the shortest
+    // ones (e.g. 1.07374182E9 rather than 1.0737418E9).
+    let (digits, exponent) = if n.fract() == 0.0 && n.abs() < 2f64.powi(63) {
+        let mut value = n.abs() as u64;
+        let bin_exp = value.ilog2();
+        let insignificant = match bin_exp.checked_sub(significand_bits + 1) {
+            Some(p2) => INSIGNIFICANT_DIGITS.get(p2 as usize).copied().unwrap_or(0),
+            None => 0,
+        };
+        if insignificant > 0 {
+            let pow10 = 10u64.pow(insignificant);
+            let residue = value % pow10;
+            value /= pow10;
+            if residue >= pow10 / 2 {
+                value += 1;
+            }
+        }
+        let digits = value.to_string();
+        let exponent = digits.len() as i32 - 1 + insignificant as i32;
+        (digits.trim_end_matches('0').to_string(), exponent)
+    } else {
+        let (mantissa, exponent) = scientific
+            .trim_start_matches('-')
+            .split_once('e')
+            .expect("{:e} always has an exponent");
+        (
+            mantissa.replace('.', "").trim_end_matches('0').to_string(),
+            exponent.parse().expect("{:e} exponents are integers"),
+        )
+    };
+
+    let (first, rest) = digits.split_at(1);
+    let rest = if rest.is_empty() { "0" } else { rest };
+    match exponent {
+        0..=6 => {
+            let exponent = exponent as usize;
+            let whole = digits.len().min(exponent + 1);
+            let fraction = &digits[whole..];
+            format!(
+                "{}{}{}.{}",
+                sign,
+                &digits[..whole],
+                "0".repeat(exponent + 1 - whole),
+                if fraction.is_empty() { "0" } else { fraction }
+            )
+        }
+        -3..=-1 => format!(
+            "{}0.{}{}",
+            sign,
+            "0".repeat((-exponent - 1) as usize),
+            digits
+        ),
+        _ => format!("{}{}.{}E{}", sign, first, rest, exponent),
+    }
+}
+
+/// `Float.toString(n)`. When the shortest digits are a single one Java picks the closest two
+/// digit decimal instead (e.g. 1.4E-45 rather than 1.0E-45), which is what `{:.1e}` gives.
+fn java_float(n: f32) -> String {
+    let scientific = match format!("{:e}", n) {
+        shortest if shortest.contains('.') => shortest,
+        _ => format!("{:.1e}", n),
+    };
+    java_number(n.into(), &scientific, 24)
+}
+
+/// `Double.toString(n)`, see [`java_float`]
+fn java_double(n: f64) -> String {
+    let scientific = match format!("{:e}", n) {
+        shortest if shortest.contains('.') => shortest,
+        _ => format!("{:.1e}", n),
+    };
+    java_number(n, &scientific, 53)
+}
+
+/// The value of an annotation element, e.g. `(byte) 1`, `"x"` or
+/// `Ljava/lang/annotation/RetentionPolicy;.RUNTIME`. Nested annotations span several lines, the
+/// later ones starting with `indent`.
+fn element_value(value: &ElementValue, indent: &str) -> String {
+    match value {
+        ElementValue::Byte(n) => format!("(byte) {}", n),
+        ElementValue::Char(c) => match char::from_u32((*c).into()) {
+            Some(c) => format!("'{}'", c),
+            None => format!("'\\u{:04x}'", c),
+        },
+        ElementValue::Double(n) => format!("{}d", java_double(*n)),
+        ElementValue::Float(n) => format!("{}f", java_float(*n)),
+        ElementValue::Int(n) => n.to_string(),
+        ElementValue::Long(n) => format!("{}l", n),
+        ElementValue::Short(n) => format!("(short) {}", n),
+        ElementValue::Boolean(b) => b.to_string(),
+        ElementValue::String(s) => quote(s),
+        ElementValue::Enum {
+            type_name,
+            const_name,
+        } => format!("{}.{}", type_name, const_name),
+        ElementValue::Class { return_descriptor } => format!("class {}", return_descriptor),
+        ElementValue::Annotation(nested) => format!("@{}", annotation(nested, indent)),
+        ElementValue::Array(values) => {
+            let values: Vec<_> = values.iter().map(|v| element_value(v, indent)).collect();
+            format!("[{}]", values.join(","))
+        }
+    }
+}
+
+/// The annotation type followed by its elements one per line, e.g.
+/// `java.lang.Deprecated(\n  since="9"\n)` where the lines after the first start with `indent`
+fn annotation(annotation: &Annotation, indent: &str) -> String {
+    let mut text = match parse_field_type(annotation.ty) {
+        Ok(ty) => java_type(&ty),
+        Err(_) => annotation.ty.to_string(),
+    };
+    if annotation.elements.is_empty() {
+        return text;
+    }
+    text.push_str("(\n");
+    let nested = format!("{}  ", indent);
+    for element in &annotation.elements {
+        text.push_str(&format!(
+            "{}{}={}\n",
+            nested,
+            element.name,
+            element_value(&element.value, &nested)
+        ));
+    }
+    text.push_str(&format!("{})", indent));
+    text
+}
+
+fn write_annotations<W: Write>(
+    w: &mut W,
+    indent: &str,
+    annotations: &[Annotation],
+) -> anyhow::Result<()> {
+    let nested = format!("{}  ", indent);
+    for (i, a) in annotations.iter().enumerate() {
+        writeln!(w, "{}  {}: {}", indent, i, annotation(a, &nested))?;
+    }
+    Ok(())
+}
+
+fn write_parameter_annotations<W: Write>(
+    w: &mut W,
+    indent: &str,
+    parameter_annotations: &[Vec<Annotation>],
+) -> anyhow::Result<()> {
+    let nested = format!("{}  ", indent);
+    for (i, annotations) in parameter_annotations.iter().enumerate() {
+        writeln!(w, "{}parameter {}:", nested, i)?;
+        write_annotations(w, &nested, annotations)?;
+    }
+    Ok(())
+}
+
+/// The position of a type annotation, e.g. `LOCAL_VARIABLE, {start_pc=8, length=5, index=1}` or
+/// `FIELD, location=[TYPE_ARGUMENT(0)]`
+fn type_annotation_target(annotation: &TypeAnnotation) -> String {
+    let target_type = match annotation.target_type {
+        0x00 => "CLASS_TYPE_PARAMETER",
+        0x01 => "METHOD_TYPE_PARAMETER",
+        0x10 => "CLASS_EXTENDS",
+        0x11 => "CLASS_TYPE_PARAMETER_BOUND",
+        0x12 => "METHOD_TYPE_PARAMETER_BOUND",
+        0x13 => "FIELD",
+        0x14 => "METHOD_RETURN",
+        0x15 => "METHOD_RECEIVER",
+        0x16 => "METHOD_FORMAL_PARAMETER",
+        0x17 => "THROWS",
+        0x40 => "LOCAL_VARIABLE",
+        0x41 => "RESOURCE_VARIABLE",
+        0x42 => "EXCEPTION_PARAMETER",
+        0x43 => "INSTANCEOF",
+        0x44 => "NEW",
+        0x45 => "CONSTRUCTOR_REFERENCE",
+        0x46 => "METHOD_REFERENCE",
+        0x47 => "CAST",
+        0x48 => "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT",
+        0x49 => "METHOD_INVOCATION_TYPE_ARGUMENT",
+        0x4a => "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT",
+        0x4b => "METHOD_REFERENCE_TYPE_ARGUMENT",
+        _ => "UNKNOWN",
+    };
+    let mut parts = vec![target_type.to_string()];
+    match &annotation.target_info {
+        TargetInfo::TypeParameter {
+            type_parameter_index,
+        } => parts.push(format!("param_index={}", type_parameter_index)),
+        TargetInfo::Supertype { supertype_index } => {
+            parts.push(format!("type_index={}", supertype_index))
+        }
+        TargetInfo::TypeParameterBound {
+            type_parameter_index,
+            bound_index,
+        } => parts.push(format!(
+            "param_index={}, bound_index={}",
+            type_parameter_index, bound_index
+        )),
+        TargetInfo::Empty => {}
+        TargetInfo::FormalParameter {
+            formal_parameter_index,
+        } => parts.push(format!("param_index={}", formal_parameter_index)),
+        TargetInfo::Throws { throws_type_index } => {
+            parts.push(format!("type_index={}", throws_type_index))
+        }
+        TargetInfo::LocalVar { table } => parts.extend(table.iter().map(|var| {
+            format!(
+                "{{start_pc={}, length={}, index={}}}",
+                var.start_pc, var.length, var.index
+            )
+        })),
+        TargetInfo::Catch {
+            exception_table_index,
+        } => parts.push(format!("exception_index={}", exception_table_index)),
+        TargetInfo::Offset { offset } => parts.push(format!("offset={}", offset)),
+        TargetInfo::TypeArgument {
+            offset,
+            type_argument_index,
+        } => parts.push(format!(
+            "offset={}, type_index={}",
+            offset, type_argument_index
+        )),
+    }
+    if !annotation.target_path.is_empty() {
+        let path: Vec<_> = annotation
+            .target_path
+            .iter()
+            .map(|entry| match entry.kind {
+                TypePathKind::Array => "ARRAY".to_string(),
+                TypePathKind::Nested => "INNER_TYPE".to_string(),
+                TypePathKind::WildcardBound => "WILDCARD".to_string(),
+                TypePathKind::TypeArgument => {
+                    format!("TYPE_ARGUMENT({})", entry.type_argument_index)
+                }
+            })
+            .collect();
+        parts.push(format!("location=[{}]", path.join(", ")));
+    }
+    parts.join(", ")
+}
+
+fn write_type_annotations<W: Write>(
+    w: &mut W,
+    indent: &str,
+    annotations: &[TypeAnnotation],
+) -> anyhow::Result<()> {
+    let nested = format!("{}    ", indent);
+    for (i, a) in annotations.iter().enumerate() {
+        writeln!(w, "{}  {}: {}", indent, i, type_annotation_target(a))?;
+        writeln!(w, "{}{}", nested, annotation(&a.annotation, &nested))?;
+    }
+    Ok(())
+}
+
+/// A type in a stack map frame, e.g. `int` or `class java/lang/String`
+fn verification_type(ty: &VerificationType) -> String {
+    match ty {
+        VerificationType::Top => "top".into(),
+        VerificationType::Integer => "int".into(),
+        VerificationType::Float => "float".into(),
+        VerificationType::Long => "long".into(),
+        VerificationType::Double => "double".into(),
+        VerificationType::Null => "null".into(),
+        VerificationType::UninitializedThis => "this".into(),
+        VerificationType::Object(name) if name.starts_with('[') => format!("class \"{}\"", name),
+        VerificationType::Object(name) => format!("class {}", name),
+        VerificationType::Uninitialized(offset) => format!("uninitialized {}", offset),
+    }
+}
+
+/// Each frame with its `frame_type` and `offset_delta` as they are in the class file, which are
+/// worked out again from the kind of frame and its offset
+fn write_stack_map_table<W: Write>(
+    w: &mut W,
+    indent: &str,
+    entries: &[StackMapFrame],
+) -> anyhow::Result<()> {
+    let types = |types: &[VerificationType]| {
+        if types.is_empty() {
+            return "[]".to_string();
+        }
+        let types: Vec<_> = types.iter().map(verification_type).collect();
+        format!("[ {} ]", types.join(", "))
+    };
+
+    writeln!(
+        w,
+        "{}StackMapTable: number_of_entries = {}",
+        indent,
+        entries.len()
+    )?;
+    let mut previous = None;
+    for frame in entries {
+        let delta = match previous {
+            None => frame.offset,
+            Some(previous) => frame.offset - previous - 1,
+        };
+        previous = Some(frame.offset);
+        let (frame_type, name) = match &frame.kind {
+            StackMapFrameKind::Same => (delta, "same"),
+            StackMapFrameKind::SameLocals1StackItem { .. } => {
+                (64 + delta, "same_locals_1_stack_item")
+            }
+            StackMapFrameKind::SameLocals1StackItemExtended { .. } => {
+                (247, "same_locals_1_stack_item_frame_extended")
+            }
+            StackMapFrameKind::Chop { absent_locals } => {
+                (251 - usize::from(*absent_locals), "chop")
+            }
+            StackMapFrameKind::SameExtended => (251, "same_frame_extended"),
+            StackMapFrameKind::Append { locals } => (251 + locals.len(), "append"),
+            StackMapFrameKind::Full { .. } => (255, "full_frame"),
+        };
+        writeln!(w, "{}  frame_type = {} /* {} */", indent, frame_type, name)?;
+        if frame_type >= 247 {
+            writeln!(w, "{}    offset_delta = {}", indent, delta)?;
+        }
+        match &frame.kind {
+            StackMapFrameKind::SameLocals1StackItem { stack }
+            | StackMapFrameKind::SameLocals1StackItemExtended { stack } => {
+                writeln!(w, "{}    stack = {}", indent, types(&[*stack]))?
+            }
+            StackMapFrameKind::Append { locals } => {
+                writeln!(w, "{}    locals = {}", indent, types(locals))?
+            }
+            StackMapFrameKind::Full { locals, stack } => {
+                writeln!(w, "{}    locals = {}", indent, types(locals))?;
+                writeln!(w, "{}    stack = {}", indent, types(stack))?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn write_module<W: Write>(w: &mut W, indent: &str, module: &Module) -> anyhow::Result<()> {
+    fn flags<'a>(names: impl Iterator<Item = (&'a str, impl Sized)>) -> String {
+        names.map(|(name, _)| format!(" ACC_{}", name)).collect()
+    }
+    writeln!(w, "{}Module:", indent)?;
+    writeln!(
+        w,
+        "{}  \"{}\"{}",
+        indent,
+        module.name,
+        flags(module.flags.iter_names())
+    )?;
+    if let Some(version) = module.version {
+        writeln!(w, "{}  {}", indent, version)?;
+    }
+    writeln!(w, "{}  {:<38}// requires", indent, module.requires.len())?;
+    for requires in &module.requires {
+        write!(
+            w,
+            "{}    \"{}\"{}",
+            indent,
+            requires.module,
+            flags(requires.flags.iter_names())
+        )?;
+        match requires.version {
+            Some(version) => writeln!(w, " {}", version)?,
+            None => writeln!(w)?,
+        }
+    }
+    for (kind, packages) in [("exports", &module.exports), ("opens", &module.opens)] {
+        writeln!(w, "{}  {:<38}// {}", indent, packages.len(), kind)?;
+        for exports in packages {
+            write!(
+                w,
+                "{}    {}{}",
+                indent,
+                exports.package,
+                flags(exports.flags.iter_names())
+            )?;
+            if exports.to.is_empty() {
+                writeln!(w)?;
+            } else {
+                writeln!(w, " to {}", exports.to.join(", "))?;
+            }
+        }
+    }
+    writeln!(w, "{}  {:<38}// uses", indent, module.uses.len())?;
+    for service in &module.uses {
+        writeln!(w, "{}    {}", indent, service)?;
+    }
+    writeln!(w, "{}  {:<38}// provides", indent, module.provides.len())?;
+    for provides in &module.provides {
+        writeln!(
+            w,
+            "{}    {} with {}",
+            indent,
+            provides.service,
+            provides.with.join(", ")
+        )?;
+    }
+    Ok(())
+}
+
+/// Write any attribute except `Code`, which needs the method it's in, with `indent` before its
+/// name and the lines after that indented further
+fn write_attribute<W: Write>(w: &mut W, indent: &str, attribute: Attribute) -> anyhow::Result<()> {
+    match attribute {
+        Attribute::Code { code, .. } => writeln!(w, "{}Code: length = 0x{:x}", indent, code.len())?,
+        Attribute::Signature { signature } => writeln!(w, "{}Signature: {}", indent, signature)?,
+        Attribute::Deprecated => writeln!(w, "{}Deprecated: true", indent)?,
+        Attribute::Synthetic => writeln!(w, "{}Synthetic: true", indent)?,
+        Attribute::ConstantValue { value } => {
+            let (kind, value) = describe_constant(&Constant::Loadable(value));
+            let kind = match kind {
+                "Integer" => "int".to_string(),
+                "String" => kind.to_string(),
+                kind => kind.to_lowercase(),
+            };
+            writeln!(w, "{}ConstantValue: {} {}", indent, kind, value)?
+        }
+        Attribute::Exceptions { exceptions } => {
+            writeln!(w, "{}Exceptions:", indent)?;
+            writeln!(w, "{}  throws {}", indent, exceptions.join(", "))?;
+        }
+        Attribute::LineNumberTable { table } => {
+            writeln!(w, "{}LineNumberTable:", indent)?;
+            for line in table {
+                writeln!(
+                    w,
+                    "{}  line {}: {}",
+                    indent, line.line_number, line.start_pc
+                )?;
+            }
+        }
+        Attribute::LocalVariableTable { table } => {
+            writeln!(w, "{}LocalVariableTable:", indent)?;
+            writeln!(w, "{}  Start  Length  Slot  Name   Signature", indent)?;
+            for var in table {
+                writeln!(
+                    w,
+                    "{}  {:>5}  {:>6}  {:>4}  {:>4}   {}",
+                    indent, var.start_pc, var.length, var.index, var.name, var.descriptor
+                )?;
+            }
+        }
+        Attribute::LocalVariableTypeTable { table } => {
+            writeln!(w, "{}LocalVariableTypeTable:", indent)?;
+            writeln!(w, "{}  Start  Length  Slot  Name   Signature", indent)?;
+            for var in table {
+                writeln!(
+                    w,
+                    "{}  {:>5}  {:>6}  {:>4}  {:>4}   {}",
+                    indent, var.start_pc, var.length, var.index, var.name, var.signature
+                )?;
+            }
+        }
+        Attribute::StackMapTable { entries } => write_stack_map_table(w, indent, &entries)?,
+        Attribute::RuntimeVisibleAnnotations { annotations } => {
+            writeln!(w, "{}RuntimeVisibleAnnotations:", indent)?;
+            write_annotations(w, indent, &annotations)?;
+        }
+        Attribute::RuntimeInvisibleAnnotations { annotations } => {
+            writeln!(w, "{}RuntimeInvisibleAnnotations:", indent)?;
+            write_annotations(w, indent, &annotations)?;
+        }
+        Attribute::RuntimeVisibleParameterAnnotations {
+            parameter_annotations,
+        } => {
+            writeln!(w, "{}RuntimeVisibleParameterAnnotations:", indent)?;
+            write_parameter_annotations(w, indent, &parameter_annotations)?;
+        }
+        Attribute::RuntimeInvisibleParameterAnnotations {
+            parameter_annotations,
+        } => {
+            writeln!(w, "{}RuntimeInvisibleParameterAnnotations:", indent)?;
+            write_parameter_annotations(w, indent, &parameter_annotations)?;
+        }
+        Attribute::RuntimeVisibleTypeAnnotations { annotations } => {
+            writeln!(w, "{}RuntimeVisibleTypeAnnotations:", indent)?;
+            write_type_annotations(w, indent, &annotations)?;
+        }
+        Attribute::RuntimeInvisibleTypeAnnotations { annotations } => {
+            writeln!(w, "{}RuntimeInvisibleTypeAnnotations:", indent)?;
+            write_type_annotations(w, indent, &annotations)?;
+        }
+        Attribute::AnnotationDefault { default_value } => {
+            writeln!(w, "{}AnnotationDefault:", indent)?;
+            writeln!(
+                w,
+                "{}  default_value: {}",
+                indent,
+                element_value(&default_value, &format!("{}  ", indent))
+            )?;
+        }
+        Attribute::MethodParameters { parameters } => {
+            writeln!(w, "{}MethodParameters:", indent)?;
+            writeln!(w, "{}  Name                           Flags", indent)?;
+            for parameter in parameters {
+                let flags: Vec<_> = parameter
+                    .access_flags
+                    .iter_names()
+                    .map(|(name, _)| name.to_lowercase())
+                    .collect();
+                writeln!(
+                    w,
+                    "{}  {:<30} {}",
+                    indent,
+                    parameter.name.unwrap_or("<no name>"),
+                    flags.join(" ")
+                )?;
+            }
+        }
+        Attribute::SourceFile { sourcefile } => {
+            writeln!(w, "{}SourceFile: \"{}\"", indent, sourcefile)?
+        }
+        Attribute::SourceDebugExtension { debug_extension } => {
+            writeln!(w, "{}SourceDebugExtension:", indent)?;
+            for line in String::from_utf8_lossy(debug_extension).lines() {
+                writeln!(w, "{}  {}", indent, line)?;
+            }
+        }
+        Attribute::EnclosingMethod { class, method } => match method {
+            Some(method) => writeln!(w, "{}EnclosingMethod: {}.{}", indent, class, method.name)?,
+            None => writeln!(w, "{}EnclosingMethod: {}", indent, class)?,
+        },
+        Attribute::InnerClasses { classes } => {
+            writeln!(w, "{}InnerClasses:", indent)?;
+            for inner in classes {
+                let flags: Vec<_> = inner
+                    .inner_class_access_flags
+                    .iter_names()
+                    .map(|(name, _)| name.to_lowercase())
+                    .collect();
+                write!(w, "{}  {} {}", indent, flags.join(" "), inner.inner_class)?;
+                if let Some(outer) = inner.outer_class {
+                    write!(w, " of {}", outer)?;
+                }
+                if let Some(name) = inner.inner_name {
+                    write!(w, " named {}", name)?;
+                }
+                writeln!(w)?;
+            }
+        }
+        Attribute::NestHost { host_class } => {
+            writeln!(w, "{}NestHost: class {}", indent, host_class)?
+        }
+        Attribute::NestMembers { classes } => {
+            writeln!(w, "{}NestMembers:", indent)?;
+            for class in classes {
+                writeln!(w, "{}  {}", indent, class)?;
+            }
+        }
+        Attribute::PermittedSubclasses { classes } => {
+            writeln!(w, "{}PermittedSubclasses:", indent)?;
+            for class in classes {
+                writeln!(w, "{}  {}", indent, class)?;
+            }
+        }
+        Attribute::Record { components } => {
+            writeln!(w, "{}Record:", indent)?;
+            for component in components {
+                let ty = java_type(&parse_field_type(component.descriptor)?);
+                writeln!(w, "{}  {} {};", indent, ty, component.name)?;
+                writeln!(w, "{}    descriptor: {}", indent, component.descriptor)?;
+                for attribute in component.attributes {
+                    write_attribute(w, &format!("{}    ", indent), attribute)?;
+                }
+                writeln!(w)?;
+            }
+        }
+        Attribute::Module { module } => write_module(w, indent, &module)?,
+        Attribute::ModulePackages { packages } => {
+            writeln!(w, "{}ModulePackages:", indent)?;
+            for package in packages {
+                writeln!(w, "{}  {}", indent, package.replace('/', "."))?;
+            }
+        }
+        Attribute::ModuleMainClass { main_class } => {
+            writeln!(w, "{}ModuleMainClass: {}", indent, main_class)?
+        }
+        Attribute::BootstrapMethods { methods } => {
+            writeln!(w, "{}BootstrapMethods:", indent)?;
+            for (i, method) in methods.iter().enumerate() {
+                let (_, handle) = describe_constant(&Constant::Loadable(Loadable::MethodHandle(
+                    method.method_ref,
+                )));
+                writeln!(w, "{}  {}: {}", indent, i, handle)?;
+                writeln!(w, "{}    Method arguments:", indent)?;
+                for argument in &method.arguments {
+                    let (_, value) = describe_constant(&Constant::Loadable(*argument));
+                    writeln!(w, "{}      {}", indent, value)?;
+                }
+            }
+        }
+        Attribute::Other { name, info } => {
+            writeln!(w, "{}{}: length = 0x{:x}", indent, name, info.len())?
+        }
+    }
+    Ok(())
+}
+
+fn write_field<W: Write>(w: &mut W, field: &Field) -> anyhow::Result<()> {
+    let flags = field.access_flags;
+    let mut modifiers = Vec::new();
+    for (flag, name) in [
+        (FieldAccessFlags::PUBLIC, "public"),
+        (FieldAccessFlags::PRIVATE, "private"),
+        (FieldAccessFlags::PROTECTED, "protected"),
+        (FieldAccessFlags::STATIC, "static"),
+        (FieldAccessFlags::FINAL, "final"),
+        (FieldAccessFlags::VOLATILE, "volatile"),
+        (FieldAccessFlags::TRANSIENT, "transient"),
+    ] {
+        if flags.contains(flag) {
+            modifiers.push(name);
+        }
+    }
+    modifiers.push("");
+
+    let ty = java_type(&parse_field_type(field.descriptor)?);
+    writeln!(w, "  {}{} {};", modifiers.join(" "), ty, field.name)?;
+    writeln!(w, "    descriptor: {}", field.descriptor)?;
+    write!(w, "    ")?;
+    write_flags(w, flags)?;
+    for attribute in field.attributes() {
+        write_attribute(w, "    ", attribute?)?;
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+fn write_method<W: Write>(w: &mut W, class: &ClassFile, method: &Method) -> anyhow::Result<()> {
+    let flags = method.access_flags;
+    let mut modifiers = Vec::new();
+    for (flag, name) in [
+        (MethodAccessFlags::PUBLIC, "public"),
+        (MethodAccessFlags::PRIVATE, "private"),
+        (MethodAccessFlags::PROTECTED, "protected"),
+        (MethodAccessFlags::STATIC, "static"),
+        (MethodAccessFlags::FINAL, "final"),
+        (MethodAccessFlags::SYNCHRONIZED, "synchronized"),
+        (MethodAccessFlags::NATIVE, "native"),
+        (MethodAccessFlags::ABSTRACT, "abstract"),
+        (MethodAccessFlags::STRICT, "strictfp"),
+    ] {
+        if flags.contains(flag) {
+            modifiers.push(name);
+        }
+    }
+    modifiers.push("");
+    let modifiers = modifiers.join(" ");
+
+    let descriptor: MethodDescriptor = method.descriptor.parse()?;
+    let params: Vec<_> = descriptor.params.iter().map(java_type).collect();
+    match method.name {
+        "<clinit>" => writeln!(w, "  static {{}};")?,
+        "<init>" => writeln!(
+            w,
+            "  {}{}({});",
+            modifiers,
+            class.this_class()?.replace('/', "."),
+            params.join(", ")
+        )?,
+        name => {
+            let ret = match &descriptor.return_value {
+                ReturnDescriptor::Void => "void".to_string(),
+                ReturnDescriptor::FieldType(ty) => java_type(ty),
+            };
+            writeln!(w, "  {}{} {}({});", modifiers, ret, name, params.join(", "))?
+        }
+    }
+    writeln!(w, "    descriptor: {}", method.descriptor)?;
+    write!(w, "    ")?;
+    write_flags(w, flags)?;
+
+    for attribute in method.attributes() {
+        match attribute? {
+            code @ Attribute::Code { .. } => write_code(w, &class.constant_pool, method, code)?,
+            attribute => write_attribute(w, "    ", attribute)?,
+        }
+    }
+    writeln!(w)?;
+    Ok(())
+}
+
+fn dump<W: Write>(w: &mut W, path: &str, size: usize, class: &ClassFile) -> anyhow::Result<()> {
+    writeln!(w, "Classfile {}", path)?;
+    writeln!(w, "  size {} bytes", size)?;
+    for attribute in class.attributes() {
+        if let Attribute::SourceFile { sourcefile } = attribute? {
+            writeln!(w, "  Compiled from \"{}\"", sourcefile)?;
+        }
+    }
+
+    let flags = class.access_flags;
+    let mut modifiers = Vec::new();
+    if flags.contains(ClassAccessFlags::PUBLIC) {
+        modifiers.push("public");
+    }
+    let kind = if flags.contains(ClassAccessFlags::MODULE) {
+        "module"
+    } else if flags.contains(ClassAccessFlags::ANNOTATION) {
+        "@interface"
+    } else if flags.contains(ClassAccessFlags::INTERFACE) {
+        "interface"
+    } else {
+        if flags.contains(ClassAccessFlags::ABSTRACT) {
+            modifiers.push("abstract");
+        }
+        if flags.contains(ClassAccessFlags::FINAL) {
+            modifiers.push("final");
+        }
+        if flags.contains(ClassAccessFlags::ENUM) {
+            "enum"
+        } else {
+            "class"
+        }
+    };
+    modifiers.push(kind);
+    write!(
+        w,
+        "{} {}",
+        modifiers.join(" "),
+        class.this_class()?.replace('/', ".")
+    )?;
+    if let Some(super_class) = class.super_class()?.filter(|&s| s != "java/lang/Object") {
+        write!(w, " extends {}", super_class.replace('/', "."))?;
+    }
+    let interfaces = class
+        .interfaces()
+        .map(|i| Ok(i?.replace('/', ".")))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if !interfaces.is_empty() {
+        write!(w, " implements {}", interfaces.join(", "))?;
+    }
+    writeln!(w)?;
+
+    let (major, minor) = class.version;
+    writeln!(w, "  minor version: {}", minor)?;
+    writeln!(w, "  major version: {}", major)?;
+    write!(w, "  ")?;
+    write_flags(w, flags)?;
+    writeln!(
+        w,
+        "  {:<39} // {}",
+        format!("this_class: #{}", class.this_class_index()),
+        class.this_class()?
+    )?;
+    match class.super_class()? {
+        Some(super_class) => writeln!(
+            w,
+            "  {:<39} // {}",
+            format!("super_class: #{}", class.super_class_index()),
+            super_class
+        )?,
+        None => writeln!(w, "  super_class: #0")?,
+    }
+    writeln!(
+        w,
+        "  interfaces: {}, fields: {}, methods: {}, attributes: {}",
+        interfaces.len(),
+        class.fields().count(),
+        class.methods().count(),
+        class.attributes().count()
+    )?;
+
+    write_constant_pool(w, &class.constant_pool)?;
+
+    writeln!(w, "{{")?;
+    for field in class.fields() {
+        write_field(w, &field?)?;
+    }
+    for method in class.methods() {
+        write_method(w, class, &method?)?;
+    }
+    writeln!(w, "}}")?;
+
+    for attribute in class.attributes() {
+        write_attribute(w, "", attribute?)?;
+    }
+    Ok(())
+}
+
+/// Print the class as a single line of JSON, so that dumping several classes gives JSON Lines
+#[cfg(feature = "serde")]
+fn dump_json<W>(w: &mut W, path: &str, class: &ClassFile) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    #[derive(serde::Serialize)]
+    struct Dump<'a> {
+        path: &'a str,
+        class: &'a ClassFile<'a>,
+    }
+
+    serde_json::to_writer(&mut *w, &Dump { path, class })?;
+    writeln!(w)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn dump_json<W>(_: &mut W, _: &str, _: &ClassFile) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    bail!("classdump was built without the serde feature, which --json needs")
+}
+
+fn main() -> anyhow::Result<()> {
+    let (flags, paths): (Vec<_>, Vec<_>) = std::env::args().skip(1).partition(|a| a == "--json");
+    let json = !flags.is_empty();
+    if paths.is_empty() {
+        bail!("Usage: classdump [--json] <class file>...");
+    }
+
+    let mut stdout = io::stdout().lock();
+    for path in paths {
+        let bytes = fs::read(&path).with_context(|| format!("reading {}", path))?;
+        let class = ClassFile::parse(&bytes).with_context(|| format!("parsing {}", path))?;
+        if json {
+            dump_json(&mut stdout, &path, &class)?;
+        } else {
+            dump(&mut stdout, &path, bytes.len(), &class)?;
+        }
+    }
+    Ok(())
+}
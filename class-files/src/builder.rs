@@ -0,0 +1,606 @@
+//! Building class files from scratch, or adding to existing ones
+//!
+//! ```
+//! # use class_files::{
+//! #     assembler::Assembler, builder::ClassFileBuilder, bytecode::Instruction,
+//! #     types::{ClassAccessFlags, MethodAccessFlags},
+//! # };
+//! let mut class = ClassFileBuilder::new("Hello", Some("java/lang/Object")).unwrap();
+//! class.access_flags(ClassAccessFlags::PUBLIC | ClassAccessFlags::SUPER);
+//!
+//! // public static void main(String[] args) { System.out.println("Hello"); }
+//! let pool = class.constant_pool();
+//! let out = pool.field_ref("java/lang/System", "out", "Ljava/io/PrintStream;").unwrap();
+//! let hello = pool.string("Hello").unwrap();
+//! let println = pool.method_ref("java/io/PrintStream", "println", "(Ljava/lang/String;)V").unwrap();
+//!
+//! let mut asm = Assembler::new(1);
+//! asm.push(Instruction::Getstatic(out))
+//!     .push(Instruction::Ldc(hello))
+//!     .push(Instruction::Invokevirtual(println))
+//!     .push(Instruction::Return);
+//! let code = asm.assemble(class.constant_pool().pool()).unwrap();
+//!
+//! let flags = MethodAccessFlags::PUBLIC | MethodAccessFlags::STATIC;
+//! class.add_method(flags, "main", "([Ljava/lang/String;)V", Some(&code)).unwrap();
+//! let class = class.build();
+//! assert_eq!(class.this_class().unwrap(), "Hello");
+//! class.check_format().unwrap();
+//! ```
+
+use std::collections::HashMap;
+
+use anyhow::bail;
+
+use crate::{
+    assembler::Code,
+    constant_pool::{ConstantPool, Loadable, MemberKind, MemberRef, ReferenceKind},
+    types::{
+        raw::{RawAttribute, RawConstant, RawField, RawMethod},
+        ClassAccessFlags, FieldAccessFlags, MethodAccessFlags,
+    },
+    ClassFile,
+};
+
+/// Adds constants to a constant pool, reusing the index of an equal constant if there is one
+/// already. Indices which have been handed out never change.
+#[derive(Debug, Clone, Default)]
+pub struct ConstantPoolBuilder {
+    pool: ConstantPool<'static>,
+    /// The index of each constant in the pool, keyed by the constant as it is written in the
+    /// class file so that e.g. different NaNs are still told apart
+    indices: HashMap<Vec<u8>, usize>,
+}
+
+impl ConstantPoolBuilder {
+    /// The largest `constant_pool_count`, which is one more than the largest index
+    pub const MAX_COUNT: usize = u16::MAX as usize;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from an existing pool, so that the constants in it are reused and keep their indices
+    pub fn from_pool(pool: ConstantPool) -> Self {
+        let mut builder = Self {
+            pool: pool.into_owned(),
+            indices: HashMap::new(),
+        };
+        for (i, constant) in builder.pool.constants.iter().enumerate() {
+            if matches!(constant, RawConstant::Unused) {
+                continue;
+            }
+            let mut key = Vec::new();
+            if constant.write_to(&mut key).is_ok() {
+                // the first of any duplicates is the one that is reused
+                builder.indices.entry(key).or_insert(i + 1);
+            }
+        }
+        builder
+    }
+
+    /// The constants that have been added so far, e.g. for [`Assembler::assemble`]
+    ///
+    /// [`Assembler::assemble`]: crate::assembler::Assembler::assemble
+    pub fn pool(&self) -> &ConstantPool<'static> {
+        &self.pool
+    }
+
+    pub fn build(self) -> ConstantPool<'static> {
+        self.pool
+    }
+
+    /// Add `constant` if there isn't an equal one already, returning its index
+    fn add(&mut self, constant: RawConstant<'static>) -> anyhow::Result<usize> {
+        let mut key = Vec::new();
+        constant.write_to(&mut key)?;
+        if let Some(index) = self.indices.get(&key) {
+            return Ok(*index);
+        }
+
+        // Longs and Doubles take up two slots
+        let slots = match constant {
+            RawConstant::Long { .. } | RawConstant::Double { .. } => 2,
+            _ => 1,
+        };
+        if self.pool.constants.len() + 1 + slots > Self::MAX_COUNT {
+            bail!(
+                "Constant pool is full, it can have at most {} entries",
+                Self::MAX_COUNT - 1
+            );
+        }
+
+        let index = self.pool.constants.len() + 1;
+        self.pool.constants.push(constant);
+        if slots == 2 {
+            self.pool.constants.push(RawConstant::Unused);
+        }
+        self.indices.insert(key, index);
+        Ok(index)
+    }
+
+    pub fn utf8(&mut self, string: &str) -> anyhow::Result<usize> {
+        self.add(RawConstant::utf8(string))
+    }
+
+    pub fn class(&mut self, name: &str) -> anyhow::Result<usize> {
+        let name_index = self.utf8(name)?;
+        self.add(RawConstant::Class { name_index })
+    }
+
+    pub fn string(&mut self, string: &str) -> anyhow::Result<usize> {
+        let string_index = self.utf8(string)?;
+        self.add(RawConstant::String { string_index })
+    }
+
+    pub fn integer(&mut self, num: i32) -> anyhow::Result<usize> {
+        self.add(RawConstant::Integer { num })
+    }
+
+    pub fn float(&mut self, num: f32) -> anyhow::Result<usize> {
+        self.add(RawConstant::Float { num })
+    }
+
+    pub fn long(&mut self, num: i64) -> anyhow::Result<usize> {
+        self.add(RawConstant::Long { num })
+    }
+
+    pub fn double(&mut self, num: f64) -> anyhow::Result<usize> {
+        self.add(RawConstant::Double { num })
+    }
+
+    pub fn name_and_type(&mut self, name: &str, descriptor: &str) -> anyhow::Result<usize> {
+        let name_index = self.utf8(name)?;
+        let descriptor_index = self.utf8(descriptor)?;
+        self.add(RawConstant::NameAndType {
+            name_index,
+            descriptor_index,
+        })
+    }
+
+    /// Add a `Fieldref`, `Methodref` or `InterfaceMethodref` constant
+    pub fn member_ref(&mut self, member: MemberRef) -> anyhow::Result<usize> {
+        let class_index = self.class(member.class)?;
+        let name_and_type_index = self.name_and_type(member.name, member.descriptor)?;
+        self.add(match member.kind {
+            MemberKind::Field => RawConstant::FieldRef {
+                class_index,
+                name_and_type_index,
+            },
+            MemberKind::Method => RawConstant::MethodRef {
+                class_index,
+                name_and_type_index,
+            },
+            MemberKind::InterfaceMethod => RawConstant::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            },
+        })
+    }
+
+    pub fn field_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> anyhow::Result<usize> {
+        self.member_ref(MemberRef {
+            class,
+            name,
+            descriptor,
+            kind: MemberKind::Field,
+        })
+    }
+
+    pub fn method_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> anyhow::Result<usize> {
+        self.member_ref(MemberRef {
+            class,
+            name,
+            descriptor,
+            kind: MemberKind::Method,
+        })
+    }
+
+    pub fn interface_method_ref(
+        &mut self,
+        class: &str,
+        name: &str,
+        descriptor: &str,
+    ) -> anyhow::Result<usize> {
+        self.member_ref(MemberRef {
+            class,
+            name,
+            descriptor,
+            kind: MemberKind::InterfaceMethod,
+        })
+    }
+
+    pub fn method_handle(
+        &mut self,
+        kind: ReferenceKind,
+        reference: MemberRef,
+    ) -> anyhow::Result<usize> {
+        let reference_index = self.member_ref(reference)?;
+        self.add(RawConstant::MethodHandle {
+            reference_kind: kind as u8,
+            reference_index,
+        })
+    }
+
+    pub fn method_type(&mut self, descriptor: &str) -> anyhow::Result<usize> {
+        let descriptor_index = self.utf8(descriptor)?;
+        self.add(RawConstant::MethodType { descriptor_index })
+    }
+
+    /// Add a `Dynamic` constant, where `bootstrap_method_attr_index` is an index into the
+    /// `BootstrapMethods` attribute
+    pub fn dynamic(
+        &mut self,
+        bootstrap_method_attr_index: usize,
+        name: &str,
+        descriptor: &str,
+    ) -> anyhow::Result<usize> {
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.add(RawConstant::Dynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        })
+    }
+
+    /// Add an `InvokeDynamic` constant, where `bootstrap_method_attr_index` is an index into the
+    /// `BootstrapMethods` attribute
+    pub fn invoke_dynamic(
+        &mut self,
+        bootstrap_method_attr_index: usize,
+        name: &str,
+        descriptor: &str,
+    ) -> anyhow::Result<usize> {
+        let name_and_type_index = self.name_and_type(name, descriptor)?;
+        self.add(RawConstant::InvokeDynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        })
+    }
+
+    pub fn module(&mut self, name: &str) -> anyhow::Result<usize> {
+        let name_index = self.utf8(name)?;
+        self.add(RawConstant::Module { name_index })
+    }
+
+    pub fn package(&mut self, name: &str) -> anyhow::Result<usize> {
+        let name_index = self.utf8(name)?;
+        self.add(RawConstant::Package { name_index })
+    }
+
+    /// Add any constant that `ldc` can load, e.g. one looked up from another pool
+    pub fn loadable(&mut self, loadable: Loadable) -> anyhow::Result<usize> {
+        match loadable {
+            Loadable::Integer(num) => self.integer(num),
+            Loadable::Float(num) => self.float(num),
+            Loadable::Long(num) => self.long(num),
+            Loadable::Double(num) => self.double(num),
+            Loadable::Class(name) => self.class(name),
+            Loadable::String(string) => self.string(string),
+            Loadable::RawString(bytes) => {
+                let string_index = self.add(RawConstant::Utf8 {
+                    bytes: bytes.to_vec().into(),
+                    converted: Default::default(),
+                })?;
+                self.add(RawConstant::String { string_index })
+            }
+            Loadable::MethodHandle(handle) => self.method_handle(handle.kind, handle.reference),
+            Loadable::MethodType(descriptor) => self.method_type(descriptor),
+            Loadable::Dynamic(dynamic) => self.dynamic(
+                dynamic.bootstrap_method_attr_index,
+                dynamic.name,
+                dynamic.descriptor,
+            ),
+        }
+    }
+}
+
+/// Builds a class file by adding fields, methods and attributes to it. The constants they need
+/// are added to the pool as they go.
+#[derive(Debug, Clone)]
+pub struct ClassFileBuilder {
+    class: ClassFile<'static>,
+    pool: ConstantPoolBuilder,
+}
+
+impl ClassFileBuilder {
+    /// A class with no members, which extends `super_class` (only `None` for
+    /// `java/lang/Object`). It is version 49 so that code without a `StackMapTable`, such as
+    /// from the [`Assembler`](crate::assembler::Assembler), can be verified.
+    pub fn new(name: &str, super_class: Option<&str>) -> anyhow::Result<Self> {
+        let mut pool = ConstantPoolBuilder::new();
+        let this_class = pool.class(name)?;
+        let super_class = match super_class {
+            Some(name) => pool.class(name)?,
+            None => 0,
+        };
+        Ok(Self {
+            class: ClassFile {
+                version: (49, 0),
+                access_flags: ClassAccessFlags::SUPER,
+                this_class,
+                super_class,
+                ..Default::default()
+            },
+            pool,
+        })
+    }
+
+    /// Add to an existing class, reusing the constants in its pool
+    pub fn from_class(class: ClassFile) -> Self {
+        let mut class = class.into_owned();
+        let pool = ConstantPoolBuilder::from_pool(std::mem::take(&mut class.constant_pool));
+        Self { class, pool }
+    }
+
+    /// The pool for adding the constants that are used by code before it is added with
+    /// [`ClassFileBuilder::add_method`]
+    pub fn constant_pool(&mut self) -> &mut ConstantPoolBuilder {
+        &mut self.pool
+    }
+
+    pub fn version(&mut self, major: u16, minor: u16) -> &mut Self {
+        self.class.version = (major, minor);
+        self
+    }
+
+    pub fn access_flags(&mut self, access_flags: ClassAccessFlags) -> &mut Self {
+        self.class.access_flags = access_flags;
+        self
+    }
+
+    pub fn add_interface(&mut self, name: &str) -> anyhow::Result<&mut Self> {
+        let index = self.pool.class(name)?;
+        self.class.interfaces.push(index);
+        Ok(self)
+    }
+
+    fn attribute(&mut self, name: &str, info: Vec<u8>) -> anyhow::Result<RawAttribute<'static>> {
+        Ok(RawAttribute {
+            attribute_name_index: self.pool.utf8(name)?,
+            info: info.into(),
+        })
+    }
+
+    /// Add a field, with a `ConstantValue` attribute if `constant_value` is given
+    pub fn add_field(
+        &mut self,
+        access_flags: FieldAccessFlags,
+        name: &str,
+        descriptor: &str,
+        constant_value: Option<Loadable>,
+    ) -> anyhow::Result<&mut Self> {
+        let mut attributes = Vec::new();
+        if let Some(value) = constant_value {
+            let index = self.pool.loadable(value)?;
+            let info = u16::try_from(index).unwrap().to_be_bytes().to_vec();
+            attributes.push(self.attribute("ConstantValue", info)?);
+        }
+
+        let field = RawField {
+            access_flags,
+            name_index: self.pool.utf8(name)?,
+            descriptor_index: self.pool.utf8(descriptor)?,
+            attributes,
+        };
+        self.class.fields.push(field);
+        Ok(self)
+    }
+
+    /// Add a method, which should have `code` unless it is abstract or native
+    pub fn add_method(
+        &mut self,
+        access_flags: MethodAccessFlags,
+        name: &str,
+        descriptor: &str,
+        code: Option<&Code>,
+    ) -> anyhow::Result<&mut Self> {
+        let mut attributes = Vec::new();
+        if let Some(code) = code {
+            let name_index = self.pool.utf8("Code")?;
+            attributes.push(code.to_raw(name_index)?);
+        }
+
+        let method = RawMethod {
+            access_flags,
+            name_index: self.pool.utf8(name)?,
+            descriptor_index: self.pool.utf8(descriptor)?,
+            attributes,
+        };
+        self.class.methods.push(method);
+        Ok(self)
+    }
+
+    /// Add an attribute to the class itself, where `info` is its contents after the length
+    pub fn add_attribute(&mut self, name: &str, info: Vec<u8>) -> anyhow::Result<&mut Self> {
+        let attribute = self.attribute(name, info)?;
+        self.class.attributes.push(attribute);
+        Ok(self)
+    }
+
+    /// Add a `SourceFile` attribute
+    pub fn source_file(&mut self, file_name: &str) -> anyhow::Result<&mut Self> {
+        let index = self.pool.utf8(file_name)?;
+        let info = u16::try_from(index).unwrap().to_be_bytes().to_vec();
+        self.add_attribute("SourceFile", info)
+    }
+
+    pub fn build(self) -> ClassFile<'static> {
+        ClassFile {
+            constant_pool: self.pool.build(),
+            ..self.class
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        assembler::Assembler, bytecode::Instruction, constant_pool::Constant, test::stdlib_classes,
+        types::resolved::Attribute, verifier,
+    };
+    use std::fs;
+
+    #[test]
+    fn deduplicates() {
+        let mut pool = ConstantPoolBuilder::new();
+        let class = pool.class("java/lang/Object").unwrap();
+        assert_eq!(class, 2);
+        assert_eq!(pool.utf8("java/lang/Object").unwrap(), 1);
+        assert_eq!(pool.class("java/lang/Object").unwrap(), class);
+
+        // longs take up two slots, and NaNs with different bits are different constants
+        assert_eq!(pool.long(1).unwrap(), 3);
+        assert_eq!(pool.integer(1).unwrap(), 5);
+        assert_eq!(pool.long(1).unwrap(), 3);
+        let nan = pool.double(f64::NAN).unwrap();
+        assert_eq!(pool.double(f64::NAN).unwrap(), nan);
+        assert_ne!(pool.double(-f64::NAN).unwrap(), nan);
+
+        let method = pool
+            .method_ref("java/lang/Object", "hashCode", "()I")
+            .unwrap();
+        let built = pool.build();
+        assert!(matches!(
+            built.constant(method),
+            Ok(Constant::MemberRef(MemberRef {
+                class: "java/lang/Object",
+                name: "hashCode",
+                descriptor: "()I",
+                kind: MemberKind::Method,
+            }))
+        ));
+        assert!(built.get(4).is_err());
+    }
+
+    #[test]
+    fn limit() {
+        let mut pool = ConstantPoolBuilder::new();
+        for i in 0..ConstantPoolBuilder::MAX_COUNT - 2 {
+            pool.integer(i as i32).unwrap();
+        }
+        // one slot is left, which isn't enough for a long
+        assert!(pool.long(0).is_err());
+        pool.float(0.0).unwrap();
+        assert!(pool.float(1.0).is_err());
+        // but existing constants can still be found
+        assert_eq!(pool.integer(0).unwrap(), 1);
+        assert_eq!(pool.build().len(), ConstantPoolBuilder::MAX_COUNT - 1);
+    }
+
+    #[test]
+    fn build_class() {
+        let mut builder = ClassFileBuilder::new("Counter", Some("java/lang/Object")).unwrap();
+        builder.access_flags(ClassAccessFlags::PUBLIC | ClassAccessFlags::SUPER);
+        builder
+            .add_field(
+                FieldAccessFlags::PUBLIC | FieldAccessFlags::STATIC | FieldAccessFlags::FINAL,
+                "START",
+                "J",
+                Some(Loadable::Long(10)),
+            )
+            .unwrap()
+            .add_field(FieldAccessFlags::PRIVATE, "count", "J", None)
+            .unwrap();
+
+        // public Counter() { super(); }
+        let super_init = builder
+            .constant_pool()
+            .method_ref("java/lang/Object", "<init>", "()V")
+            .unwrap();
+        let mut asm = Assembler::new(1);
+        asm.push(Instruction::Aload0)
+            .push(Instruction::Invokespecial(super_init))
+            .push(Instruction::Return);
+        let code = asm.assemble(builder.constant_pool().pool()).unwrap();
+        builder
+            .add_method(MethodAccessFlags::PUBLIC, "<init>", "()V", Some(&code))
+            .unwrap();
+
+        // public long next() { return ++count; }
+        let count = builder
+            .constant_pool()
+            .field_ref("Counter", "count", "J")
+            .unwrap();
+        let mut asm = Assembler::new(1);
+        asm.push(Instruction::Aload0)
+            .push(Instruction::Dup)
+            .push(Instruction::Getfield(count))
+            .push(Instruction::Lconst1)
+            .push(Instruction::Ladd)
+            .push(Instruction::Dup2X1)
+            .push(Instruction::Putfield(count))
+            .push(Instruction::Lreturn);
+        let code = asm.assemble(builder.constant_pool().pool()).unwrap();
+        assert_eq!((code.max_stack, code.max_locals), (5, 1));
+        builder
+            .add_method(MethodAccessFlags::PUBLIC, "next", "()J", Some(&code))
+            .unwrap()
+            .source_file("Counter.java")
+            .unwrap();
+
+        let class = builder.build();
+        class.check_format().unwrap();
+        verifier::verify_class(&class, &()).unwrap();
+
+        let bytes = class.to_bytes().unwrap();
+        let parsed = ClassFile::parse(&bytes).unwrap();
+        assert_eq!(parsed.this_class().unwrap(), "Counter");
+        assert_eq!(parsed.fields().count(), 2);
+        let field = parsed.fields().next().unwrap().unwrap();
+        assert!(matches!(
+            field.attributes().next(),
+            Some(Ok(Attribute::ConstantValue {
+                value: Loadable::Long(10)
+            }))
+        ));
+        assert_eq!(parsed.constant_pool.len(), class.constant_pool.len());
+    }
+
+    #[test]
+    fn patch_class() {
+        let Some(paths) = stdlib_classes("java.base/java/lang/Object.class") else {
+            return;
+        };
+        let bytes = fs::read(&paths[0]).unwrap();
+        let object = ClassFile::parse(&bytes).unwrap();
+        let len = object.constant_pool.len();
+
+        let mut builder = ClassFileBuilder::from_class(object.clone());
+        // already in the pool
+        let hash_code = builder
+            .constant_pool()
+            .method_ref("java/lang/Object", "hashCode", "()I")
+            .unwrap();
+        assert!(hash_code <= len);
+        builder
+            .add_field(
+                FieldAccessFlags::PRIVATE,
+                "extra",
+                "[[Ljava/lang/Runnable;",
+                None,
+            )
+            .unwrap();
+        let class = builder.build();
+
+        assert_eq!(class.constant_pool.len(), len + 2);
+        assert_eq!(class.fields().count(), object.fields().count() + 1);
+        class.check_format().unwrap();
+        // the existing constants keep their indices
+        let written = class.to_bytes().unwrap();
+        let parsed = ClassFile::parse(&written).unwrap();
+        for (index, constant) in object.constant_pool.iter() {
+            assert_eq!(parsed.constant_pool.constant(index), constant);
+        }
+    }
+}
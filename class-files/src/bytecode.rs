@@ -0,0 +1,2008 @@
+//! Decoding of the instructions in the `code` array of a `Code` attribute. See
+//! <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-6.html>
+
+use std::io::Write;
+
+use anyhow::{bail, ensure, Context};
+
+use crate::{
+    bytes::{ByteReader, WriteNum},
+    constant_pool::{Constant, ConstantPool},
+    descriptors::{FieldType, MethodDescriptor, ReturnDescriptor},
+    error::{Error, Result},
+};
+
+/// The element type of an array created by `newarray`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ArrayType {
+    Boolean = 4,
+    Char = 5,
+    Float = 6,
+    Double = 7,
+    Byte = 8,
+    Short = 9,
+    Int = 10,
+    Long = 11,
+}
+
+impl TryFrom<u8> for ArrayType {
+    type Error = Error;
+
+    fn try_from(atype: u8) -> Result<Self> {
+        Ok(match atype {
+            4 => Self::Boolean,
+            5 => Self::Char,
+            6 => Self::Float,
+            7 => Self::Double,
+            8 => Self::Byte,
+            9 => Self::Short,
+            10 => Self::Int,
+            11 => Self::Long,
+            tag => {
+                return Err(Error::InvalidTag {
+                    kind: "array type",
+                    tag,
+                })
+            }
+        })
+    }
+}
+
+/// An instruction modified by `wide`, which takes a two byte local variable index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Wide {
+    Iload(u16),
+    Lload(u16),
+    Fload(u16),
+    Dload(u16),
+    Aload(u16),
+    Istore(u16),
+    Lstore(u16),
+    Fstore(u16),
+    Dstore(u16),
+    Astore(u16),
+    Ret(u16),
+    Iinc { index: u16, value: i16 },
+}
+
+/// A single JVM instruction along with its operands, as it is encoded in the `code` array.
+///
+/// Constant pool indices are `usize` like everywhere else in this crate, local variable indices
+/// are as wide as they are in the encoding and branch offsets are relative to the `pc` of the
+/// instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Instruction {
+    Nop,
+    AconstNull,
+    IconstM1,
+    Iconst0,
+    Iconst1,
+    Iconst2,
+    Iconst3,
+    Iconst4,
+    Iconst5,
+    Lconst0,
+    Lconst1,
+    Fconst0,
+    Fconst1,
+    Fconst2,
+    Dconst0,
+    Dconst1,
+    Bipush(i8),
+    Sipush(i16),
+    Ldc(usize),
+    LdcW(usize),
+    Ldc2W(usize),
+    Iload(u8),
+    Lload(u8),
+    Fload(u8),
+    Dload(u8),
+    Aload(u8),
+    Iload0,
+    Iload1,
+    Iload2,
+    Iload3,
+    Lload0,
+    Lload1,
+    Lload2,
+    Lload3,
+    Fload0,
+    Fload1,
+    Fload2,
+    Fload3,
+    Dload0,
+    Dload1,
+    Dload2,
+    Dload3,
+    Aload0,
+    Aload1,
+    Aload2,
+    Aload3,
+    Iaload,
+    Laload,
+    Faload,
+    Daload,
+    Aaload,
+    Baload,
+    Caload,
+    Saload,
+    Istore(u8),
+    Lstore(u8),
+    Fstore(u8),
+    Dstore(u8),
+    Astore(u8),
+    Istore0,
+    Istore1,
+    Istore2,
+    Istore3,
+    Lstore0,
+    Lstore1,
+    Lstore2,
+    Lstore3,
+    Fstore0,
+    Fstore1,
+    Fstore2,
+    Fstore3,
+    Dstore0,
+    Dstore1,
+    Dstore2,
+    Dstore3,
+    Astore0,
+    Astore1,
+    Astore2,
+    Astore3,
+    Iastore,
+    Lastore,
+    Fastore,
+    Dastore,
+    Aastore,
+    Bastore,
+    Castore,
+    Sastore,
+    Pop,
+    Pop2,
+    Dup,
+    DupX1,
+    DupX2,
+    Dup2,
+    Dup2X1,
+    Dup2X2,
+    Swap,
+    Iadd,
+    Ladd,
+    Fadd,
+    Dadd,
+    Isub,
+    Lsub,
+    Fsub,
+    Dsub,
+    Imul,
+    Lmul,
+    Fmul,
+    Dmul,
+    Idiv,
+    Ldiv,
+    Fdiv,
+    Ddiv,
+    Irem,
+    Lrem,
+    Frem,
+    Drem,
+    Ineg,
+    Lneg,
+    Fneg,
+    Dneg,
+    Ishl,
+    Lshl,
+    Ishr,
+    Lshr,
+    Iushr,
+    Lushr,
+    Iand,
+    Land,
+    Ior,
+    Lor,
+    Ixor,
+    Lxor,
+    Iinc {
+        index: u8,
+        value: i8,
+    },
+    I2l,
+    I2f,
+    I2d,
+    L2i,
+    L2f,
+    L2d,
+    F2i,
+    F2l,
+    F2d,
+    D2i,
+    D2l,
+    D2f,
+    I2b,
+    I2c,
+    I2s,
+    Lcmp,
+    Fcmpl,
+    Fcmpg,
+    Dcmpl,
+    Dcmpg,
+    Ifeq(i16),
+    Ifne(i16),
+    Iflt(i16),
+    Ifge(i16),
+    Ifgt(i16),
+    Ifle(i16),
+    IfIcmpeq(i16),
+    IfIcmpne(i16),
+    IfIcmplt(i16),
+    IfIcmpge(i16),
+    IfIcmpgt(i16),
+    IfIcmple(i16),
+    IfAcmpeq(i16),
+    IfAcmpne(i16),
+    Goto(i16),
+    Jsr(i16),
+    Ret(u8),
+    Tableswitch {
+        default: i32,
+        low: i32,
+        high: i32,
+        /// The offset for each value from `low` to `high` inclusive
+        offsets: Vec<i32>,
+    },
+    Lookupswitch {
+        default: i32,
+        /// `(match, offset)` pairs, sorted by `match`
+        pairs: Vec<(i32, i32)>,
+    },
+    Ireturn,
+    Lreturn,
+    Freturn,
+    Dreturn,
+    Areturn,
+    Return,
+    Getstatic(usize),
+    Putstatic(usize),
+    Getfield(usize),
+    Putfield(usize),
+    Invokevirtual(usize),
+    Invokespecial(usize),
+    Invokestatic(usize),
+    Invokeinterface {
+        index: usize,
+        count: u8,
+    },
+    Invokedynamic(usize),
+    New(usize),
+    Newarray(ArrayType),
+    Anewarray(usize),
+    Arraylength,
+    Athrow,
+    Checkcast(usize),
+    Instanceof(usize),
+    Monitorenter,
+    Monitorexit,
+    Wide(Wide),
+    Multianewarray {
+        index: usize,
+        dimensions: u8,
+    },
+    Ifnull(i16),
+    Ifnonnull(i16),
+    GotoW(i32),
+    JsrW(i32),
+}
+
+/// Skip the zero to three bytes that align the operands of `tableswitch` and `lookupswitch` to a
+/// multiple of four from the start of the code
+fn skip_padding(r: &mut ByteReader) -> Result<()> {
+    let padding = (4 - r.offset() % 4) % 4;
+    r.read_bytes(padding)?;
+    Ok(())
+}
+
+/// Write a constant pool index as a `u2`
+fn write_index<W: Write>(w: &mut W, index: usize) -> anyhow::Result<()> {
+    w.write_u16(u16::try_from(index).context("constant pool index out of range")?)
+}
+
+/// Write the opcode that `wide` modifies followed by its two byte local variable index
+fn write_wide<W: Write>(w: &mut W, opcode: u8, index: u16) -> anyhow::Result<()> {
+    w.write_u8(opcode)?;
+    w.write_u16(index)
+}
+
+impl Instruction {
+    /// Decode the instruction that `r` is at. `r` must be reading the whole `code` array, since
+    /// `tableswitch` and `lookupswitch` are padded relative to its start.
+    pub fn read(r: &mut ByteReader) -> Result<Self> {
+        let pc = r.offset();
+        let opcode = r.read_u8()?;
+        Ok(match opcode {
+            0x00 => Self::Nop,
+            0x01 => Self::AconstNull,
+            0x02 => Self::IconstM1,
+            0x03 => Self::Iconst0,
+            0x04 => Self::Iconst1,
+            0x05 => Self::Iconst2,
+            0x06 => Self::Iconst3,
+            0x07 => Self::Iconst4,
+            0x08 => Self::Iconst5,
+            0x09 => Self::Lconst0,
+            0x0a => Self::Lconst1,
+            0x0b => Self::Fconst0,
+            0x0c => Self::Fconst1,
+            0x0d => Self::Fconst2,
+            0x0e => Self::Dconst0,
+            0x0f => Self::Dconst1,
+            0x10 => Self::Bipush(r.read_i8()?),
+            0x11 => Self::Sipush(r.read_i16()?),
+            0x12 => Self::Ldc(r.read_u8()?.into()),
+            0x13 => Self::LdcW(r.read_u16()?.into()),
+            0x14 => Self::Ldc2W(r.read_u16()?.into()),
+            0x15 => Self::Iload(r.read_u8()?),
+            0x16 => Self::Lload(r.read_u8()?),
+            0x17 => Self::Fload(r.read_u8()?),
+            0x18 => Self::Dload(r.read_u8()?),
+            0x19 => Self::Aload(r.read_u8()?),
+            0x1a => Self::Iload0,
+            0x1b => Self::Iload1,
+            0x1c => Self::Iload2,
+            0x1d => Self::Iload3,
+            0x1e => Self::Lload0,
+            0x1f => Self::Lload1,
+            0x20 => Self::Lload2,
+            0x21 => Self::Lload3,
+            0x22 => Self::Fload0,
+            0x23 => Self::Fload1,
+            0x24 => Self::Fload2,
+            0x25 => Self::Fload3,
+            0x26 => Self::Dload0,
+            0x27 => Self::Dload1,
+            0x28 => Self::Dload2,
+            0x29 => Self::Dload3,
+            0x2a => Self::Aload0,
+            0x2b => Self::Aload1,
+            0x2c => Self::Aload2,
+            0x2d => Self::Aload3,
+            0x2e => Self::Iaload,
+            0x2f => Self::Laload,
+            0x30 => Self::Faload,
+            0x31 => Self::Daload,
+            0x32 => Self::Aaload,
+            0x33 => Self::Baload,
+            0x34 => Self::Caload,
+            0x35 => Self::Saload,
+            0x36 => Self::Istore(r.read_u8()?),
+            0x37 => Self::Lstore(r.read_u8()?),
+            0x38 => Self::Fstore(r.read_u8()?),
+            0x39 => Self::Dstore(r.read_u8()?),
+            0x3a => Self::Astore(r.read_u8()?),
+            0x3b => Self::Istore0,
+            0x3c => Self::Istore1,
+            0x3d => Self::Istore2,
+            0x3e => Self::Istore3,
+            0x3f => Self::Lstore0,
+            0x40 => Self::Lstore1,
+            0x41 => Self::Lstore2,
+            0x42 => Self::Lstore3,
+            0x43 => Self::Fstore0,
+            0x44 => Self::Fstore1,
+            0x45 => Self::Fstore2,
+            0x46 => Self::Fstore3,
+            0x47 => Self::Dstore0,
+            0x48 => Self::Dstore1,
+            0x49 => Self::Dstore2,
+            0x4a => Self::Dstore3,
+            0x4b => Self::Astore0,
+            0x4c => Self::Astore1,
+            0x4d => Self::Astore2,
+            0x4e => Self::Astore3,
+            0x4f => Self::Iastore,
+            0x50 => Self::Lastore,
+            0x51 => Self::Fastore,
+            0x52 => Self::Dastore,
+            0x53 => Self::Aastore,
+            0x54 => Self::Bastore,
+            0x55 => Self::Castore,
+            0x56 => Self::Sastore,
+            0x57 => Self::Pop,
+            0x58 => Self::Pop2,
+            0x59 => Self::Dup,
+            0x5a => Self::DupX1,
+            0x5b => Self::DupX2,
+            0x5c => Self::Dup2,
+            0x5d => Self::Dup2X1,
+            0x5e => Self::Dup2X2,
+            0x5f => Self::Swap,
+            0x60 => Self::Iadd,
+            0x61 => Self::Ladd,
+            0x62 => Self::Fadd,
+            0x63 => Self::Dadd,
+            0x64 => Self::Isub,
+            0x65 => Self::Lsub,
+            0x66 => Self::Fsub,
+            0x67 => Self::Dsub,
+            0x68 => Self::Imul,
+            0x69 => Self::Lmul,
+            0x6a => Self::Fmul,
+            0x6b => Self::Dmul,
+            0x6c => Self::Idiv,
+            0x6d => Self::Ldiv,
+            0x6e => Self::Fdiv,
+            0x6f => Self::Ddiv,
+            0x70 => Self::Irem,
+            0x71 => Self::Lrem,
+            0x72 => Self::Frem,
+            0x73 => Self::Drem,
+            0x74 => Self::Ineg,
+            0x75 => Self::Lneg,
+            0x76 => Self::Fneg,
+            0x77 => Self::Dneg,
+            0x78 => Self::Ishl,
+            0x79 => Self::Lshl,
+            0x7a => Self::Ishr,
+            0x7b => Self::Lshr,
+            0x7c => Self::Iushr,
+            0x7d => Self::Lushr,
+            0x7e => Self::Iand,
+            0x7f => Self::Land,
+            0x80 => Self::Ior,
+            0x81 => Self::Lor,
+            0x82 => Self::Ixor,
+            0x83 => Self::Lxor,
+            0x84 => Self::Iinc {
+                index: r.read_u8()?,
+                value: r.read_i8()?,
+            },
+            0x85 => Self::I2l,
+            0x86 => Self::I2f,
+            0x87 => Self::I2d,
+            0x88 => Self::L2i,
+            0x89 => Self::L2f,
+            0x8a => Self::L2d,
+            0x8b => Self::F2i,
+            0x8c => Self::F2l,
+            0x8d => Self::F2d,
+            0x8e => Self::D2i,
+            0x8f => Self::D2l,
+            0x90 => Self::D2f,
+            0x91 => Self::I2b,
+            0x92 => Self::I2c,
+            0x93 => Self::I2s,
+            0x94 => Self::Lcmp,
+            0x95 => Self::Fcmpl,
+            0x96 => Self::Fcmpg,
+            0x97 => Self::Dcmpl,
+            0x98 => Self::Dcmpg,
+            0x99 => Self::Ifeq(r.read_i16()?),
+            0x9a => Self::Ifne(r.read_i16()?),
+            0x9b => Self::Iflt(r.read_i16()?),
+            0x9c => Self::Ifge(r.read_i16()?),
+            0x9d => Self::Ifgt(r.read_i16()?),
+            0x9e => Self::Ifle(r.read_i16()?),
+            0x9f => Self::IfIcmpeq(r.read_i16()?),
+            0xa0 => Self::IfIcmpne(r.read_i16()?),
+            0xa1 => Self::IfIcmplt(r.read_i16()?),
+            0xa2 => Self::IfIcmpge(r.read_i16()?),
+            0xa3 => Self::IfIcmpgt(r.read_i16()?),
+            0xa4 => Self::IfIcmple(r.read_i16()?),
+            0xa5 => Self::IfAcmpeq(r.read_i16()?),
+            0xa6 => Self::IfAcmpne(r.read_i16()?),
+            0xa7 => Self::Goto(r.read_i16()?),
+            0xa8 => Self::Jsr(r.read_i16()?),
+            0xa9 => Self::Ret(r.read_u8()?),
+            0xaa => {
+                skip_padding(r)?;
+                let default = r.read_i32()?;
+                let low = r.read_i32()?;
+                let high = r.read_i32()?;
+                if low > high {
+                    return Err(Error::InvalidInstruction {
+                        pc,
+                        reason: "tableswitch low is greater than high",
+                    });
+                }
+                Self::Tableswitch {
+                    default,
+                    low,
+                    high,
+                    offsets: (low..=high).map(|_| r.read_i32()).collect::<Result<_>>()?,
+                }
+            }
+            0xab => {
+                skip_padding(r)?;
+                let default = r.read_i32()?;
+                let npairs = r.read_i32()?;
+                if npairs < 0 {
+                    return Err(Error::InvalidInstruction {
+                        pc,
+                        reason: "lookupswitch has a negative number of pairs",
+                    });
+                }
+                Self::Lookupswitch {
+                    default,
+                    pairs: (0..npairs)
+                        .map(|_| Ok((r.read_i32()?, r.read_i32()?)))
+                        .collect::<Result<_>>()?,
+                }
+            }
+            0xac => Self::Ireturn,
+            0xad => Self::Lreturn,
+            0xae => Self::Freturn,
+            0xaf => Self::Dreturn,
+            0xb0 => Self::Areturn,
+            0xb1 => Self::Return,
+            0xb2 => Self::Getstatic(r.read_u16()?.into()),
+            0xb3 => Self::Putstatic(r.read_u16()?.into()),
+            0xb4 => Self::Getfield(r.read_u16()?.into()),
+            0xb5 => Self::Putfield(r.read_u16()?.into()),
+            0xb6 => Self::Invokevirtual(r.read_u16()?.into()),
+            0xb7 => Self::Invokespecial(r.read_u16()?.into()),
+            0xb8 => Self::Invokestatic(r.read_u16()?.into()),
+            0xb9 => {
+                let index = r.read_u16()?.into();
+                let count = r.read_u8()?;
+                if count == 0 || r.read_u8()? != 0 {
+                    return Err(Error::InvalidInstruction {
+                        pc,
+                        reason: "invokeinterface must have a non-zero count followed by a zero",
+                    });
+                }
+                Self::Invokeinterface { index, count }
+            }
+            0xba => {
+                let index = r.read_u16()?.into();
+                if r.read_u16()? != 0 {
+                    return Err(Error::InvalidInstruction {
+                        pc,
+                        reason: "invokedynamic must be followed by two zeros",
+                    });
+                }
+                Self::Invokedynamic(index)
+            }
+            0xbb => Self::New(r.read_u16()?.into()),
+            0xbc => Self::Newarray(ArrayType::try_from(r.read_u8()?)?),
+            0xbd => Self::Anewarray(r.read_u16()?.into()),
+            0xbe => Self::Arraylength,
+            0xbf => Self::Athrow,
+            0xc0 => Self::Checkcast(r.read_u16()?.into()),
+            0xc1 => Self::Instanceof(r.read_u16()?.into()),
+            0xc2 => Self::Monitorenter,
+            0xc3 => Self::Monitorexit,
+            0xc4 => Self::Wide(match r.read_u8()? {
+                0x15 => Wide::Iload(r.read_u16()?),
+                0x16 => Wide::Lload(r.read_u16()?),
+                0x17 => Wide::Fload(r.read_u16()?),
+                0x18 => Wide::Dload(r.read_u16()?),
+                0x19 => Wide::Aload(r.read_u16()?),
+                0x36 => Wide::Istore(r.read_u16()?),
+                0x37 => Wide::Lstore(r.read_u16()?),
+                0x38 => Wide::Fstore(r.read_u16()?),
+                0x39 => Wide::Dstore(r.read_u16()?),
+                0x3a => Wide::Astore(r.read_u16()?),
+                0xa9 => Wide::Ret(r.read_u16()?),
+                0x84 => Wide::Iinc {
+                    index: r.read_u16()?,
+                    value: r.read_i16()?,
+                },
+                tag => {
+                    return Err(Error::InvalidTag {
+                        kind: "wide opcode",
+                        tag,
+                    })
+                }
+            }),
+            0xc5 => Self::Multianewarray {
+                index: r.read_u16()?.into(),
+                dimensions: r.read_u8()?,
+            },
+            0xc6 => Self::Ifnull(r.read_i16()?),
+            0xc7 => Self::Ifnonnull(r.read_i16()?),
+            0xc8 => Self::GotoW(r.read_i32()?),
+            0xc9 => Self::JsrW(r.read_i32()?),
+            tag => {
+                return Err(Error::InvalidTag {
+                    kind: "opcode",
+                    tag,
+                })
+            }
+        })
+    }
+
+    pub fn opcode(&self) -> u8 {
+        match self {
+            Self::Nop => 0x00,
+            Self::AconstNull => 0x01,
+            Self::IconstM1 => 0x02,
+            Self::Iconst0 => 0x03,
+            Self::Iconst1 => 0x04,
+            Self::Iconst2 => 0x05,
+            Self::Iconst3 => 0x06,
+            Self::Iconst4 => 0x07,
+            Self::Iconst5 => 0x08,
+            Self::Lconst0 => 0x09,
+            Self::Lconst1 => 0x0a,
+            Self::Fconst0 => 0x0b,
+            Self::Fconst1 => 0x0c,
+            Self::Fconst2 => 0x0d,
+            Self::Dconst0 => 0x0e,
+            Self::Dconst1 => 0x0f,
+            Self::Bipush { .. } => 0x10,
+            Self::Sipush { .. } => 0x11,
+            Self::Ldc { .. } => 0x12,
+            Self::LdcW { .. } => 0x13,
+            Self::Ldc2W { .. } => 0x14,
+            Self::Iload { .. } => 0x15,
+            Self::Lload { .. } => 0x16,
+            Self::Fload { .. } => 0x17,
+            Self::Dload { .. } => 0x18,
+            Self::Aload { .. } => 0x19,
+            Self::Iload0 => 0x1a,
+            Self::Iload1 => 0x1b,
+            Self::Iload2 => 0x1c,
+            Self::Iload3 => 0x1d,
+            Self::Lload0 => 0x1e,
+            Self::Lload1 => 0x1f,
+            Self::Lload2 => 0x20,
+            Self::Lload3 => 0x21,
+            Self::Fload0 => 0x22,
+            Self::Fload1 => 0x23,
+            Self::Fload2 => 0x24,
+            Self::Fload3 => 0x25,
+            Self::Dload0 => 0x26,
+            Self::Dload1 => 0x27,
+            Self::Dload2 => 0x28,
+            Self::Dload3 => 0x29,
+            Self::Aload0 => 0x2a,
+            Self::Aload1 => 0x2b,
+            Self::Aload2 => 0x2c,
+            Self::Aload3 => 0x2d,
+            Self::Iaload => 0x2e,
+            Self::Laload => 0x2f,
+            Self::Faload => 0x30,
+            Self::Daload => 0x31,
+            Self::Aaload => 0x32,
+            Self::Baload => 0x33,
+            Self::Caload => 0x34,
+            Self::Saload => 0x35,
+            Self::Istore { .. } => 0x36,
+            Self::Lstore { .. } => 0x37,
+            Self::Fstore { .. } => 0x38,
+            Self::Dstore { .. } => 0x39,
+            Self::Astore { .. } => 0x3a,
+            Self::Istore0 => 0x3b,
+            Self::Istore1 => 0x3c,
+            Self::Istore2 => 0x3d,
+            Self::Istore3 => 0x3e,
+            Self::Lstore0 => 0x3f,
+            Self::Lstore1 => 0x40,
+            Self::Lstore2 => 0x41,
+            Self::Lstore3 => 0x42,
+            Self::Fstore0 => 0x43,
+            Self::Fstore1 => 0x44,
+            Self::Fstore2 => 0x45,
+            Self::Fstore3 => 0x46,
+            Self::Dstore0 => 0x47,
+            Self::Dstore1 => 0x48,
+            Self::Dstore2 => 0x49,
+            Self::Dstore3 => 0x4a,
+            Self::Astore0 => 0x4b,
+            Self::Astore1 => 0x4c,
+            Self::Astore2 => 0x4d,
+            Self::Astore3 => 0x4e,
+            Self::Iastore => 0x4f,
+            Self::Lastore => 0x50,
+            Self::Fastore => 0x51,
+            Self::Dastore => 0x52,
+            Self::Aastore => 0x53,
+            Self::Bastore => 0x54,
+            Self::Castore => 0x55,
+            Self::Sastore => 0x56,
+            Self::Pop => 0x57,
+            Self::Pop2 => 0x58,
+            Self::Dup => 0x59,
+            Self::DupX1 => 0x5a,
+            Self::DupX2 => 0x5b,
+            Self::Dup2 => 0x5c,
+            Self::Dup2X1 => 0x5d,
+            Self::Dup2X2 => 0x5e,
+            Self::Swap => 0x5f,
+            Self::Iadd => 0x60,
+            Self::Ladd => 0x61,
+            Self::Fadd => 0x62,
+            Self::Dadd => 0x63,
+            Self::Isub => 0x64,
+            Self::Lsub => 0x65,
+            Self::Fsub => 0x66,
+            Self::Dsub => 0x67,
+            Self::Imul => 0x68,
+            Self::Lmul => 0x69,
+            Self::Fmul => 0x6a,
+            Self::Dmul => 0x6b,
+            Self::Idiv => 0x6c,
+            Self::Ldiv => 0x6d,
+            Self::Fdiv => 0x6e,
+            Self::Ddiv => 0x6f,
+            Self::Irem => 0x70,
+            Self::Lrem => 0x71,
+            Self::Frem => 0x72,
+            Self::Drem => 0x73,
+            Self::Ineg => 0x74,
+            Self::Lneg => 0x75,
+            Self::Fneg => 0x76,
+            Self::Dneg => 0x77,
+            Self::Ishl => 0x78,
+            Self::Lshl => 0x79,
+            Self::Ishr => 0x7a,
+            Self::Lshr => 0x7b,
+            Self::Iushr => 0x7c,
+            Self::Lushr => 0x7d,
+            Self::Iand => 0x7e,
+            Self::Land => 0x7f,
+            Self::Ior => 0x80,
+            Self::Lor => 0x81,
+            Self::Ixor => 0x82,
+            Self::Lxor => 0x83,
+            Self::Iinc { .. } => 0x84,
+            Self::I2l => 0x85,
+            Self::I2f => 0x86,
+            Self::I2d => 0x87,
+            Self::L2i => 0x88,
+            Self::L2f => 0x89,
+            Self::L2d => 0x8a,
+            Self::F2i => 0x8b,
+            Self::F2l => 0x8c,
+            Self::F2d => 0x8d,
+            Self::D2i => 0x8e,
+            Self::D2l => 0x8f,
+            Self::D2f => 0x90,
+            Self::I2b => 0x91,
+            Self::I2c => 0x92,
+            Self::I2s => 0x93,
+            Self::Lcmp => 0x94,
+            Self::Fcmpl => 0x95,
+            Self::Fcmpg => 0x96,
+            Self::Dcmpl => 0x97,
+            Self::Dcmpg => 0x98,
+            Self::Ifeq { .. } => 0x99,
+            Self::Ifne { .. } => 0x9a,
+            Self::Iflt { .. } => 0x9b,
+            Self::Ifge { .. } => 0x9c,
+            Self::Ifgt { .. } => 0x9d,
+            Self::Ifle { .. } => 0x9e,
+            Self::IfIcmpeq { .. } => 0x9f,
+            Self::IfIcmpne { .. } => 0xa0,
+            Self::IfIcmplt { .. } => 0xa1,
+            Self::IfIcmpge { .. } => 0xa2,
+            Self::IfIcmpgt { .. } => 0xa3,
+            Self::IfIcmple { .. } => 0xa4,
+            Self::IfAcmpeq { .. } => 0xa5,
+            Self::IfAcmpne { .. } => 0xa6,
+            Self::Goto { .. } => 0xa7,
+            Self::Jsr { .. } => 0xa8,
+            Self::Ret { .. } => 0xa9,
+            Self::Tableswitch { .. } => 0xaa,
+            Self::Lookupswitch { .. } => 0xab,
+            Self::Ireturn => 0xac,
+            Self::Lreturn => 0xad,
+            Self::Freturn => 0xae,
+            Self::Dreturn => 0xaf,
+            Self::Areturn => 0xb0,
+            Self::Return => 0xb1,
+            Self::Getstatic { .. } => 0xb2,
+            Self::Putstatic { .. } => 0xb3,
+            Self::Getfield { .. } => 0xb4,
+            Self::Putfield { .. } => 0xb5,
+            Self::Invokevirtual { .. } => 0xb6,
+            Self::Invokespecial { .. } => 0xb7,
+            Self::Invokestatic { .. } => 0xb8,
+            Self::Invokeinterface { .. } => 0xb9,
+            Self::Invokedynamic { .. } => 0xba,
+            Self::New { .. } => 0xbb,
+            Self::Newarray { .. } => 0xbc,
+            Self::Anewarray { .. } => 0xbd,
+            Self::Arraylength => 0xbe,
+            Self::Athrow => 0xbf,
+            Self::Checkcast { .. } => 0xc0,
+            Self::Instanceof { .. } => 0xc1,
+            Self::Monitorenter => 0xc2,
+            Self::Monitorexit => 0xc3,
+            Self::Wide { .. } => 0xc4,
+            Self::Multianewarray { .. } => 0xc5,
+            Self::Ifnull { .. } => 0xc6,
+            Self::Ifnonnull { .. } => 0xc7,
+            Self::GotoW { .. } => 0xc8,
+            Self::JsrW { .. } => 0xc9,
+        }
+    }
+
+    /// The name of the instruction as written in the JVMS, e.g. `if_icmpeq`
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Self::Nop => "nop",
+            Self::AconstNull => "aconst_null",
+            Self::IconstM1 => "iconst_m1",
+            Self::Iconst0 => "iconst_0",
+            Self::Iconst1 => "iconst_1",
+            Self::Iconst2 => "iconst_2",
+            Self::Iconst3 => "iconst_3",
+            Self::Iconst4 => "iconst_4",
+            Self::Iconst5 => "iconst_5",
+            Self::Lconst0 => "lconst_0",
+            Self::Lconst1 => "lconst_1",
+            Self::Fconst0 => "fconst_0",
+            Self::Fconst1 => "fconst_1",
+            Self::Fconst2 => "fconst_2",
+            Self::Dconst0 => "dconst_0",
+            Self::Dconst1 => "dconst_1",
+            Self::Bipush { .. } => "bipush",
+            Self::Sipush { .. } => "sipush",
+            Self::Ldc { .. } => "ldc",
+            Self::LdcW { .. } => "ldc_w",
+            Self::Ldc2W { .. } => "ldc2_w",
+            Self::Iload { .. } => "iload",
+            Self::Lload { .. } => "lload",
+            Self::Fload { .. } => "fload",
+            Self::Dload { .. } => "dload",
+            Self::Aload { .. } => "aload",
+            Self::Iload0 => "iload_0",
+            Self::Iload1 => "iload_1",
+            Self::Iload2 => "iload_2",
+            Self::Iload3 => "iload_3",
+            Self::Lload0 => "lload_0",
+            Self::Lload1 => "lload_1",
+            Self::Lload2 => "lload_2",
+            Self::Lload3 => "lload_3",
+            Self::Fload0 => "fload_0",
+            Self::Fload1 => "fload_1",
+            Self::Fload2 => "fload_2",
+            Self::Fload3 => "fload_3",
+            Self::Dload0 => "dload_0",
+            Self::Dload1 => "dload_1",
+            Self::Dload2 => "dload_2",
+            Self::Dload3 => "dload_3",
+            Self::Aload0 => "aload_0",
+            Self::Aload1 => "aload_1",
+            Self::Aload2 => "aload_2",
+            Self::Aload3 => "aload_3",
+            Self::Iaload => "iaload",
+            Self::Laload => "laload",
+            Self::Faload => "faload",
+            Self::Daload => "daload",
+            Self::Aaload => "aaload",
+            Self::Baload => "baload",
+            Self::Caload => "caload",
+            Self::Saload => "saload",
+            Self::Istore { .. } => "istore",
+            Self::Lstore { .. } => "lstore",
+            Self::Fstore { .. } => "fstore",
+            Self::Dstore { .. } => "dstore",
+            Self::Astore { .. } => "astore",
+            Self::Istore0 => "istore_0",
+            Self::Istore1 => "istore_1",
+            Self::Istore2 => "istore_2",
+            Self::Istore3 => "istore_3",
+            Self::Lstore0 => "lstore_0",
+            Self::Lstore1 => "lstore_1",
+            Self::Lstore2 => "lstore_2",
+            Self::Lstore3 => "lstore_3",
+            Self::Fstore0 => "fstore_0",
+            Self::Fstore1 => "fstore_1",
+            Self::Fstore2 => "fstore_2",
+            Self::Fstore3 => "fstore_3",
+            Self::Dstore0 => "dstore_0",
+            Self::Dstore1 => "dstore_1",
+            Self::Dstore2 => "dstore_2",
+            Self::Dstore3 => "dstore_3",
+            Self::Astore0 => "astore_0",
+            Self::Astore1 => "astore_1",
+            Self::Astore2 => "astore_2",
+            Self::Astore3 => "astore_3",
+            Self::Iastore => "iastore",
+            Self::Lastore => "lastore",
+            Self::Fastore => "fastore",
+            Self::Dastore => "dastore",
+            Self::Aastore => "aastore",
+            Self::Bastore => "bastore",
+            Self::Castore => "castore",
+            Self::Sastore => "sastore",
+            Self::Pop => "pop",
+            Self::Pop2 => "pop2",
+            Self::Dup => "dup",
+            Self::DupX1 => "dup_x1",
+            Self::DupX2 => "dup_x2",
+            Self::Dup2 => "dup2",
+            Self::Dup2X1 => "dup2_x1",
+            Self::Dup2X2 => "dup2_x2",
+            Self::Swap => "swap",
+            Self::Iadd => "iadd",
+            Self::Ladd => "ladd",
+            Self::Fadd => "fadd",
+            Self::Dadd => "dadd",
+            Self::Isub => "isub",
+            Self::Lsub => "lsub",
+            Self::Fsub => "fsub",
+            Self::Dsub => "dsub",
+            Self::Imul => "imul",
+            Self::Lmul => "lmul",
+            Self::Fmul => "fmul",
+            Self::Dmul => "dmul",
+            Self::Idiv => "idiv",
+            Self::Ldiv => "ldiv",
+            Self::Fdiv => "fdiv",
+            Self::Ddiv => "ddiv",
+            Self::Irem => "irem",
+            Self::Lrem => "lrem",
+            Self::Frem => "frem",
+            Self::Drem => "drem",
+            Self::Ineg => "ineg",
+            Self::Lneg => "lneg",
+            Self::Fneg => "fneg",
+            Self::Dneg => "dneg",
+            Self::Ishl => "ishl",
+            Self::Lshl => "lshl",
+            Self::Ishr => "ishr",
+            Self::Lshr => "lshr",
+            Self::Iushr => "iushr",
+            Self::Lushr => "lushr",
+            Self::Iand => "iand",
+            Self::Land => "land",
+            Self::Ior => "ior",
+            Self::Lor => "lor",
+            Self::Ixor => "ixor",
+            Self::Lxor => "lxor",
+            Self::Iinc { .. } => "iinc",
+            Self::I2l => "i2l",
+            Self::I2f => "i2f",
+            Self::I2d => "i2d",
+            Self::L2i => "l2i",
+            Self::L2f => "l2f",
+            Self::L2d => "l2d",
+            Self::F2i => "f2i",
+            Self::F2l => "f2l",
+            Self::F2d => "f2d",
+            Self::D2i => "d2i",
+            Self::D2l => "d2l",
+            Self::D2f => "d2f",
+            Self::I2b => "i2b",
+            Self::I2c => "i2c",
+            Self::I2s => "i2s",
+            Self::Lcmp => "lcmp",
+            Self::Fcmpl => "fcmpl",
+            Self::Fcmpg => "fcmpg",
+            Self::Dcmpl => "dcmpl",
+            Self::Dcmpg => "dcmpg",
+            Self::Ifeq { .. } => "ifeq",
+            Self::Ifne { .. } => "ifne",
+            Self::Iflt { .. } => "iflt",
+            Self::Ifge { .. } => "ifge",
+            Self::Ifgt { .. } => "ifgt",
+            Self::Ifle { .. } => "ifle",
+            Self::IfIcmpeq { .. } => "if_icmpeq",
+            Self::IfIcmpne { .. } => "if_icmpne",
+            Self::IfIcmplt { .. } => "if_icmplt",
+            Self::IfIcmpge { .. } => "if_icmpge",
+            Self::IfIcmpgt { .. } => "if_icmpgt",
+            Self::IfIcmple { .. } => "if_icmple",
+            Self::IfAcmpeq { .. } => "if_acmpeq",
+            Self::IfAcmpne { .. } => "if_acmpne",
+            Self::Goto { .. } => "goto",
+            Self::Jsr { .. } => "jsr",
+            Self::Ret { .. } => "ret",
+            Self::Tableswitch { .. } => "tableswitch",
+            Self::Lookupswitch { .. } => "lookupswitch",
+            Self::Ireturn => "ireturn",
+            Self::Lreturn => "lreturn",
+            Self::Freturn => "freturn",
+            Self::Dreturn => "dreturn",
+            Self::Areturn => "areturn",
+            Self::Return => "return",
+            Self::Getstatic { .. } => "getstatic",
+            Self::Putstatic { .. } => "putstatic",
+            Self::Getfield { .. } => "getfield",
+            Self::Putfield { .. } => "putfield",
+            Self::Invokevirtual { .. } => "invokevirtual",
+            Self::Invokespecial { .. } => "invokespecial",
+            Self::Invokestatic { .. } => "invokestatic",
+            Self::Invokeinterface { .. } => "invokeinterface",
+            Self::Invokedynamic { .. } => "invokedynamic",
+            Self::New { .. } => "new",
+            Self::Newarray { .. } => "newarray",
+            Self::Anewarray { .. } => "anewarray",
+            Self::Arraylength => "arraylength",
+            Self::Athrow => "athrow",
+            Self::Checkcast { .. } => "checkcast",
+            Self::Instanceof { .. } => "instanceof",
+            Self::Monitorenter => "monitorenter",
+            Self::Monitorexit => "monitorexit",
+            Self::Wide { .. } => "wide",
+            Self::Multianewarray { .. } => "multianewarray",
+            Self::Ifnull { .. } => "ifnull",
+            Self::Ifnonnull { .. } => "ifnonnull",
+            Self::GotoW { .. } => "goto_w",
+            Self::JsrW { .. } => "jsr_w",
+        }
+    }
+
+    /// The number of bytes the instruction takes up when it is at `pc`
+    pub fn len(&self, pc: usize) -> usize {
+        let padding = (4 - (pc + 1) % 4) % 4;
+        match self {
+            Self::Tableswitch { offsets, .. } => 1 + padding + 12 + 4 * offsets.len(),
+            Self::Lookupswitch { pairs, .. } => 1 + padding + 8 + 8 * pairs.len(),
+            Self::Wide(Wide::Iinc { .. }) => 6,
+            Self::Wide(_) => 4,
+            Self::Nop
+            | Self::AconstNull
+            | Self::IconstM1
+            | Self::Iconst0
+            | Self::Iconst1
+            | Self::Iconst2
+            | Self::Iconst3
+            | Self::Iconst4
+            | Self::Iconst5
+            | Self::Lconst0
+            | Self::Lconst1
+            | Self::Fconst0
+            | Self::Fconst1
+            | Self::Fconst2
+            | Self::Dconst0
+            | Self::Dconst1
+            | Self::Iload0
+            | Self::Iload1
+            | Self::Iload2
+            | Self::Iload3
+            | Self::Lload0
+            | Self::Lload1
+            | Self::Lload2
+            | Self::Lload3
+            | Self::Fload0
+            | Self::Fload1
+            | Self::Fload2
+            | Self::Fload3
+            | Self::Dload0
+            | Self::Dload1
+            | Self::Dload2
+            | Self::Dload3
+            | Self::Aload0
+            | Self::Aload1
+            | Self::Aload2
+            | Self::Aload3
+            | Self::Iaload
+            | Self::Laload
+            | Self::Faload
+            | Self::Daload
+            | Self::Aaload
+            | Self::Baload
+            | Self::Caload
+            | Self::Saload
+            | Self::Istore0
+            | Self::Istore1
+            | Self::Istore2
+            | Self::Istore3
+            | Self::Lstore0
+            | Self::Lstore1
+            | Self::Lstore2
+            | Self::Lstore3
+            | Self::Fstore0
+            | Self::Fstore1
+            | Self::Fstore2
+            | Self::Fstore3
+            | Self::Dstore0
+            | Self::Dstore1
+            | Self::Dstore2
+            | Self::Dstore3
+            | Self::Astore0
+            | Self::Astore1
+            | Self::Astore2
+            | Self::Astore3
+            | Self::Iastore
+            | Self::Lastore
+            | Self::Fastore
+            | Self::Dastore
+            | Self::Aastore
+            | Self::Bastore
+            | Self::Castore
+            | Self::Sastore
+            | Self::Pop
+            | Self::Pop2
+            | Self::Dup
+            | Self::DupX1
+            | Self::DupX2
+            | Self::Dup2
+            | Self::Dup2X1
+            | Self::Dup2X2
+            | Self::Swap
+            | Self::Iadd
+            | Self::Ladd
+            | Self::Fadd
+            | Self::Dadd
+            | Self::Isub
+            | Self::Lsub
+            | Self::Fsub
+            | Self::Dsub
+            | Self::Imul
+            | Self::Lmul
+            | Self::Fmul
+            | Self::Dmul
+            | Self::Idiv
+            | Self::Ldiv
+            | Self::Fdiv
+            | Self::Ddiv
+            | Self::Irem
+            | Self::Lrem
+            | Self::Frem
+            | Self::Drem
+            | Self::Ineg
+            | Self::Lneg
+            | Self::Fneg
+            | Self::Dneg
+            | Self::Ishl
+            | Self::Lshl
+            | Self::Ishr
+            | Self::Lshr
+            | Self::Iushr
+            | Self::Lushr
+            | Self::Iand
+            | Self::Land
+            | Self::Ior
+            | Self::Lor
+            | Self::Ixor
+            | Self::Lxor
+            | Self::I2l
+            | Self::I2f
+            | Self::I2d
+            | Self::L2i
+            | Self::L2f
+            | Self::L2d
+            | Self::F2i
+            | Self::F2l
+            | Self::F2d
+            | Self::D2i
+            | Self::D2l
+            | Self::D2f
+            | Self::I2b
+            | Self::I2c
+            | Self::I2s
+            | Self::Lcmp
+            | Self::Fcmpl
+            | Self::Fcmpg
+            | Self::Dcmpl
+            | Self::Dcmpg
+            | Self::Ireturn
+            | Self::Lreturn
+            | Self::Freturn
+            | Self::Dreturn
+            | Self::Areturn
+            | Self::Return
+            | Self::Arraylength
+            | Self::Athrow
+            | Self::Monitorenter
+            | Self::Monitorexit => 1,
+            Self::Bipush { .. }
+            | Self::Ldc { .. }
+            | Self::Iload { .. }
+            | Self::Lload { .. }
+            | Self::Fload { .. }
+            | Self::Dload { .. }
+            | Self::Aload { .. }
+            | Self::Istore { .. }
+            | Self::Lstore { .. }
+            | Self::Fstore { .. }
+            | Self::Dstore { .. }
+            | Self::Astore { .. }
+            | Self::Ret { .. }
+            | Self::Newarray { .. } => 2,
+            Self::Sipush { .. }
+            | Self::LdcW { .. }
+            | Self::Ldc2W { .. }
+            | Self::Iinc { .. }
+            | Self::Ifeq { .. }
+            | Self::Ifne { .. }
+            | Self::Iflt { .. }
+            | Self::Ifge { .. }
+            | Self::Ifgt { .. }
+            | Self::Ifle { .. }
+            | Self::IfIcmpeq { .. }
+            | Self::IfIcmpne { .. }
+            | Self::IfIcmplt { .. }
+            | Self::IfIcmpge { .. }
+            | Self::IfIcmpgt { .. }
+            | Self::IfIcmple { .. }
+            | Self::IfAcmpeq { .. }
+            | Self::IfAcmpne { .. }
+            | Self::Goto { .. }
+            | Self::Jsr { .. }
+            | Self::Getstatic { .. }
+            | Self::Putstatic { .. }
+            | Self::Getfield { .. }
+            | Self::Putfield { .. }
+            | Self::Invokevirtual { .. }
+            | Self::Invokespecial { .. }
+            | Self::Invokestatic { .. }
+            | Self::New { .. }
+            | Self::Anewarray { .. }
+            | Self::Checkcast { .. }
+            | Self::Instanceof { .. }
+            | Self::Ifnull { .. }
+            | Self::Ifnonnull { .. } => 3,
+            Self::Multianewarray { .. } => 4,
+            Self::Invokeinterface { .. }
+            | Self::Invokedynamic { .. }
+            | Self::GotoW { .. }
+            | Self::JsrW { .. } => 5,
+        }
+    }
+
+    /// Encode the instruction, which is at `pc` in the code
+    pub fn write_to<W>(&self, w: &mut W, pc: usize) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        w.write_u8(self.opcode())?;
+        match self {
+            Self::Bipush(value) => w.write_i8(*value)?,
+            Self::Sipush(value) => w.write_i16(*value)?,
+            Self::Ldc(index) => {
+                w.write_u8(u8::try_from(*index).context("ldc index out of range")?)?
+            }
+            Self::LdcW(index) => write_index(w, *index)?,
+            Self::Ldc2W(index) => write_index(w, *index)?,
+            Self::Iload(index) => w.write_u8(*index)?,
+            Self::Lload(index) => w.write_u8(*index)?,
+            Self::Fload(index) => w.write_u8(*index)?,
+            Self::Dload(index) => w.write_u8(*index)?,
+            Self::Aload(index) => w.write_u8(*index)?,
+            Self::Istore(index) => w.write_u8(*index)?,
+            Self::Lstore(index) => w.write_u8(*index)?,
+            Self::Fstore(index) => w.write_u8(*index)?,
+            Self::Dstore(index) => w.write_u8(*index)?,
+            Self::Astore(index) => w.write_u8(*index)?,
+            Self::Iinc { index, value } => {
+                w.write_u8(*index)?;
+                w.write_i8(*value)?;
+            }
+            Self::Ifeq(offset) => w.write_i16(*offset)?,
+            Self::Ifne(offset) => w.write_i16(*offset)?,
+            Self::Iflt(offset) => w.write_i16(*offset)?,
+            Self::Ifge(offset) => w.write_i16(*offset)?,
+            Self::Ifgt(offset) => w.write_i16(*offset)?,
+            Self::Ifle(offset) => w.write_i16(*offset)?,
+            Self::IfIcmpeq(offset) => w.write_i16(*offset)?,
+            Self::IfIcmpne(offset) => w.write_i16(*offset)?,
+            Self::IfIcmplt(offset) => w.write_i16(*offset)?,
+            Self::IfIcmpge(offset) => w.write_i16(*offset)?,
+            Self::IfIcmpgt(offset) => w.write_i16(*offset)?,
+            Self::IfIcmple(offset) => w.write_i16(*offset)?,
+            Self::IfAcmpeq(offset) => w.write_i16(*offset)?,
+            Self::IfAcmpne(offset) => w.write_i16(*offset)?,
+            Self::Goto(offset) => w.write_i16(*offset)?,
+            Self::Jsr(offset) => w.write_i16(*offset)?,
+            Self::Ret(index) => w.write_u8(*index)?,
+            Self::Tableswitch {
+                default,
+                low,
+                high,
+                offsets,
+            } => {
+                w.write_all(&[0; 3][..(4 - (pc + 1) % 4) % 4])?;
+                ensure!(
+                    i64::from(*high) - i64::from(*low) + 1 == offsets.len() as i64,
+                    "tableswitch has {} offsets for {}..={}",
+                    offsets.len(),
+                    low,
+                    high
+                );
+                w.write_i32(*default)?;
+                w.write_i32(*low)?;
+                w.write_i32(*high)?;
+                for offset in offsets {
+                    w.write_i32(*offset)?;
+                }
+            }
+            Self::Lookupswitch { default, pairs } => {
+                w.write_all(&[0; 3][..(4 - (pc + 1) % 4) % 4])?;
+                w.write_i32(*default)?;
+                w.write_i32(i32::try_from(pairs.len()).context("too many lookupswitch pairs")?)?;
+                for (key, offset) in pairs {
+                    w.write_i32(*key)?;
+                    w.write_i32(*offset)?;
+                }
+            }
+            Self::Getstatic(index) => write_index(w, *index)?,
+            Self::Putstatic(index) => write_index(w, *index)?,
+            Self::Getfield(index) => write_index(w, *index)?,
+            Self::Putfield(index) => write_index(w, *index)?,
+            Self::Invokevirtual(index) => write_index(w, *index)?,
+            Self::Invokespecial(index) => write_index(w, *index)?,
+            Self::Invokestatic(index) => write_index(w, *index)?,
+            Self::Invokeinterface { index, count } => {
+                write_index(w, *index)?;
+                w.write_u8(*count)?;
+                w.write_u8(0)?;
+            }
+            Self::Invokedynamic(index) => {
+                write_index(w, *index)?;
+                w.write_u16(0)?;
+            }
+            Self::New(index) => write_index(w, *index)?,
+            Self::Newarray(atype) => w.write_u8(*atype as u8)?,
+            Self::Anewarray(index) => write_index(w, *index)?,
+            Self::Checkcast(index) => write_index(w, *index)?,
+            Self::Instanceof(index) => write_index(w, *index)?,
+            Self::Wide(wide) => match wide {
+                Wide::Iload(index) => write_wide(w, 0x15, *index)?,
+                Wide::Lload(index) => write_wide(w, 0x16, *index)?,
+                Wide::Fload(index) => write_wide(w, 0x17, *index)?,
+                Wide::Dload(index) => write_wide(w, 0x18, *index)?,
+                Wide::Aload(index) => write_wide(w, 0x19, *index)?,
+                Wide::Istore(index) => write_wide(w, 0x36, *index)?,
+                Wide::Lstore(index) => write_wide(w, 0x37, *index)?,
+                Wide::Fstore(index) => write_wide(w, 0x38, *index)?,
+                Wide::Dstore(index) => write_wide(w, 0x39, *index)?,
+                Wide::Astore(index) => write_wide(w, 0x3a, *index)?,
+                Wide::Ret(index) => write_wide(w, 0xa9, *index)?,
+                Wide::Iinc { index, value } => {
+                    write_wide(w, 0x84, *index)?;
+                    w.write_i16(*value)?;
+                }
+            },
+            Self::Multianewarray { index, dimensions } => {
+                write_index(w, *index)?;
+                w.write_u8(*dimensions)?;
+            }
+            Self::Ifnull(offset) => w.write_i16(*offset)?,
+            Self::Ifnonnull(offset) => w.write_i16(*offset)?,
+            Self::GotoW(offset) => w.write_i32(*offset)?,
+            Self::JsrW(offset) => w.write_i32(*offset)?,
+            Self::Nop
+            | Self::AconstNull
+            | Self::IconstM1
+            | Self::Iconst0
+            | Self::Iconst1
+            | Self::Iconst2
+            | Self::Iconst3
+            | Self::Iconst4
+            | Self::Iconst5
+            | Self::Lconst0
+            | Self::Lconst1
+            | Self::Fconst0
+            | Self::Fconst1
+            | Self::Fconst2
+            | Self::Dconst0
+            | Self::Dconst1
+            | Self::Iload0
+            | Self::Iload1
+            | Self::Iload2
+            | Self::Iload3
+            | Self::Lload0
+            | Self::Lload1
+            | Self::Lload2
+            | Self::Lload3
+            | Self::Fload0
+            | Self::Fload1
+            | Self::Fload2
+            | Self::Fload3
+            | Self::Dload0
+            | Self::Dload1
+            | Self::Dload2
+            | Self::Dload3
+            | Self::Aload0
+            | Self::Aload1
+            | Self::Aload2
+            | Self::Aload3
+            | Self::Iaload
+            | Self::Laload
+            | Self::Faload
+            | Self::Daload
+            | Self::Aaload
+            | Self::Baload
+            | Self::Caload
+            | Self::Saload
+            | Self::Istore0
+            | Self::Istore1
+            | Self::Istore2
+            | Self::Istore3
+            | Self::Lstore0
+            | Self::Lstore1
+            | Self::Lstore2
+            | Self::Lstore3
+            | Self::Fstore0
+            | Self::Fstore1
+            | Self::Fstore2
+            | Self::Fstore3
+            | Self::Dstore0
+            | Self::Dstore1
+            | Self::Dstore2
+            | Self::Dstore3
+            | Self::Astore0
+            | Self::Astore1
+            | Self::Astore2
+            | Self::Astore3
+            | Self::Iastore
+            | Self::Lastore
+            | Self::Fastore
+            | Self::Dastore
+            | Self::Aastore
+            | Self::Bastore
+            | Self::Castore
+            | Self::Sastore
+            | Self::Pop
+            | Self::Pop2
+            | Self::Dup
+            | Self::DupX1
+            | Self::DupX2
+            | Self::Dup2
+            | Self::Dup2X1
+            | Self::Dup2X2
+            | Self::Swap
+            | Self::Iadd
+            | Self::Ladd
+            | Self::Fadd
+            | Self::Dadd
+            | Self::Isub
+            | Self::Lsub
+            | Self::Fsub
+            | Self::Dsub
+            | Self::Imul
+            | Self::Lmul
+            | Self::Fmul
+            | Self::Dmul
+            | Self::Idiv
+            | Self::Ldiv
+            | Self::Fdiv
+            | Self::Ddiv
+            | Self::Irem
+            | Self::Lrem
+            | Self::Frem
+            | Self::Drem
+            | Self::Ineg
+            | Self::Lneg
+            | Self::Fneg
+            | Self::Dneg
+            | Self::Ishl
+            | Self::Lshl
+            | Self::Ishr
+            | Self::Lshr
+            | Self::Iushr
+            | Self::Lushr
+            | Self::Iand
+            | Self::Land
+            | Self::Ior
+            | Self::Lor
+            | Self::Ixor
+            | Self::Lxor
+            | Self::I2l
+            | Self::I2f
+            | Self::I2d
+            | Self::L2i
+            | Self::L2f
+            | Self::L2d
+            | Self::F2i
+            | Self::F2l
+            | Self::F2d
+            | Self::D2i
+            | Self::D2l
+            | Self::D2f
+            | Self::I2b
+            | Self::I2c
+            | Self::I2s
+            | Self::Lcmp
+            | Self::Fcmpl
+            | Self::Fcmpg
+            | Self::Dcmpl
+            | Self::Dcmpg
+            | Self::Ireturn
+            | Self::Lreturn
+            | Self::Freturn
+            | Self::Dreturn
+            | Self::Areturn
+            | Self::Return
+            | Self::Arraylength
+            | Self::Athrow
+            | Self::Monitorenter
+            | Self::Monitorexit => {}
+        }
+        Ok(())
+    }
+
+    /// The `pc` of every instruction that this one can branch to, not including the next
+    /// instruction if it can fall through to it
+    pub fn branch_targets(&self, pc: usize) -> Vec<usize> {
+        let target = |offset: i32| (pc as i64 + i64::from(offset)) as usize;
+        match self {
+            Self::Ifeq(offset)
+            | Self::Ifne(offset)
+            | Self::Iflt(offset)
+            | Self::Ifge(offset)
+            | Self::Ifgt(offset)
+            | Self::Ifle(offset)
+            | Self::IfIcmpeq(offset)
+            | Self::IfIcmpne(offset)
+            | Self::IfIcmplt(offset)
+            | Self::IfIcmpge(offset)
+            | Self::IfIcmpgt(offset)
+            | Self::IfIcmple(offset)
+            | Self::IfAcmpeq(offset)
+            | Self::IfAcmpne(offset)
+            | Self::Goto(offset)
+            | Self::Jsr(offset)
+            | Self::Ifnull(offset)
+            | Self::Ifnonnull(offset) => vec![target((*offset).into())],
+            Self::GotoW(offset) | Self::JsrW(offset) => vec![target(*offset)],
+            Self::Tableswitch {
+                default, offsets, ..
+            } => std::iter::once(default)
+                .chain(offsets)
+                .map(|o| target(*o))
+                .collect(),
+            Self::Lookupswitch { default, pairs } => std::iter::once(default)
+                .chain(pairs.iter().map(|(_, o)| o))
+                .map(|o| target(*o))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether execution can continue with the next instruction after this one. `jsr` counts as
+    /// falling through since its subroutine returns to the next instruction.
+    pub fn falls_through(&self) -> bool {
+        !matches!(
+            self,
+            Self::Goto(_)
+                | Self::GotoW(_)
+                | Self::Tableswitch { .. }
+                | Self::Lookupswitch { .. }
+                | Self::Ireturn
+                | Self::Lreturn
+                | Self::Freturn
+                | Self::Dreturn
+                | Self::Areturn
+                | Self::Return
+                | Self::Athrow
+                | Self::Ret(_)
+                | Self::Wide(Wide::Ret(_))
+        )
+    }
+
+    /// The number of operand stack slots that the instruction pops and then pushes, where `long`
+    /// and `double` take up two. Field and method instructions look up their descriptor in
+    /// `constant_pool`.
+    pub fn stack_effect(&self, constant_pool: &ConstantPool) -> anyhow::Result<(usize, usize)> {
+        let descriptor = |index: usize| -> anyhow::Result<&str> {
+            Ok(match constant_pool.constant(index)? {
+                Constant::MemberRef(member) => member.descriptor,
+                Constant::InvokeDynamic(dynamic) => dynamic.descriptor,
+                c => bail!("Expected member or InvokeDynamic constant, found {:?}", c),
+            })
+        };
+        let field_slots = |index: usize| -> anyhow::Result<usize> {
+            let descriptor = descriptor(index)?;
+            let mut chars = descriptor.chars();
+            let id = chars.next().context("Empty field descriptor")?;
+            Ok(FieldType::from_chars(id, &mut chars)?.slots())
+        };
+        // (argument slots, return slots)
+        let method_slots = |index: usize| -> anyhow::Result<(usize, usize)> {
+            let descriptor: MethodDescriptor = descriptor(index)?.parse()?;
+            Ok((
+                descriptor.params.iter().map(FieldType::slots).sum(),
+                match descriptor.return_value {
+                    ReturnDescriptor::Void => 0,
+                    ReturnDescriptor::FieldType(ft) => ft.slots(),
+                },
+            ))
+        };
+
+        Ok(match self {
+            Self::Nop
+            | Self::Iinc { .. }
+            | Self::Wide(Wide::Iinc { .. } | Wide::Ret(_))
+            | Self::Goto(_)
+            | Self::GotoW(_)
+            | Self::Ret(_)
+            | Self::Return => (0, 0),
+            Self::AconstNull
+            | Self::IconstM1
+            | Self::Iconst0
+            | Self::Iconst1
+            | Self::Iconst2
+            | Self::Iconst3
+            | Self::Iconst4
+            | Self::Iconst5
+            | Self::Fconst0
+            | Self::Fconst1
+            | Self::Fconst2
+            | Self::Bipush(_)
+            | Self::Sipush(_)
+            | Self::Ldc(_)
+            | Self::LdcW(_)
+            | Self::Iload(_)
+            | Self::Fload(_)
+            | Self::Aload(_)
+            | Self::Wide(Wide::Iload(_) | Wide::Fload(_) | Wide::Aload(_))
+            | Self::Iload0
+            | Self::Iload1
+            | Self::Iload2
+            | Self::Iload3
+            | Self::Fload0
+            | Self::Fload1
+            | Self::Fload2
+            | Self::Fload3
+            | Self::Aload0
+            | Self::Aload1
+            | Self::Aload2
+            | Self::Aload3
+            | Self::Jsr(_)
+            | Self::JsrW(_)
+            | Self::New(_) => (0, 1),
+            Self::Lconst0
+            | Self::Lconst1
+            | Self::Dconst0
+            | Self::Dconst1
+            | Self::Ldc2W(_)
+            | Self::Lload(_)
+            | Self::Dload(_)
+            | Self::Wide(Wide::Lload(_) | Wide::Dload(_))
+            | Self::Lload0
+            | Self::Lload1
+            | Self::Lload2
+            | Self::Lload3
+            | Self::Dload0
+            | Self::Dload1
+            | Self::Dload2
+            | Self::Dload3 => (0, 2),
+            Self::Iaload
+            | Self::Faload
+            | Self::Aaload
+            | Self::Baload
+            | Self::Caload
+            | Self::Saload
+            | Self::Iadd
+            | Self::Fadd
+            | Self::Isub
+            | Self::Fsub
+            | Self::Imul
+            | Self::Fmul
+            | Self::Idiv
+            | Self::Fdiv
+            | Self::Irem
+            | Self::Frem
+            | Self::Ishl
+            | Self::Ishr
+            | Self::Iushr
+            | Self::Iand
+            | Self::Ior
+            | Self::Ixor
+            | Self::Fcmpl
+            | Self::Fcmpg => (2, 1),
+            Self::Laload | Self::Daload => (2, 2),
+            Self::Istore(_)
+            | Self::Fstore(_)
+            | Self::Astore(_)
+            | Self::Wide(Wide::Istore(_) | Wide::Fstore(_) | Wide::Astore(_))
+            | Self::Istore0
+            | Self::Istore1
+            | Self::Istore2
+            | Self::Istore3
+            | Self::Fstore0
+            | Self::Fstore1
+            | Self::Fstore2
+            | Self::Fstore3
+            | Self::Astore0
+            | Self::Astore1
+            | Self::Astore2
+            | Self::Astore3
+            | Self::Pop
+            | Self::Ifeq(_)
+            | Self::Ifne(_)
+            | Self::Iflt(_)
+            | Self::Ifge(_)
+            | Self::Ifgt(_)
+            | Self::Ifle(_)
+            | Self::Ifnull(_)
+            | Self::Ifnonnull(_)
+            | Self::Tableswitch { .. }
+            | Self::Lookupswitch { .. }
+            | Self::Ireturn
+            | Self::Freturn
+            | Self::Areturn
+            | Self::Athrow
+            | Self::Monitorenter
+            | Self::Monitorexit => (1, 0),
+            Self::Lstore(_)
+            | Self::Dstore(_)
+            | Self::Wide(Wide::Lstore(_) | Wide::Dstore(_))
+            | Self::Lstore0
+            | Self::Lstore1
+            | Self::Lstore2
+            | Self::Lstore3
+            | Self::Dstore0
+            | Self::Dstore1
+            | Self::Dstore2
+            | Self::Dstore3
+            | Self::Pop2
+            | Self::IfIcmpeq(_)
+            | Self::IfIcmpne(_)
+            | Self::IfIcmplt(_)
+            | Self::IfIcmpge(_)
+            | Self::IfIcmpgt(_)
+            | Self::IfIcmple(_)
+            | Self::IfAcmpeq(_)
+            | Self::IfAcmpne(_)
+            | Self::Lreturn
+            | Self::Dreturn => (2, 0),
+            Self::Iastore
+            | Self::Fastore
+            | Self::Aastore
+            | Self::Bastore
+            | Self::Castore
+            | Self::Sastore => (3, 0),
+            Self::Lastore | Self::Dastore => (4, 0),
+            Self::Dup => (1, 2),
+            Self::DupX1 => (2, 3),
+            Self::DupX2 => (3, 4),
+            Self::Dup2 => (2, 4),
+            Self::Dup2X1 => (3, 5),
+            Self::Dup2X2 => (4, 6),
+            Self::Swap => (2, 2),
+            Self::Ladd
+            | Self::Dadd
+            | Self::Lsub
+            | Self::Dsub
+            | Self::Lmul
+            | Self::Dmul
+            | Self::Ldiv
+            | Self::Ddiv
+            | Self::Lrem
+            | Self::Drem
+            | Self::Land
+            | Self::Lor
+            | Self::Lxor => (4, 2),
+            Self::Lshl | Self::Lshr | Self::Lushr => (3, 2),
+            Self::Ineg
+            | Self::Fneg
+            | Self::I2f
+            | Self::F2i
+            | Self::I2b
+            | Self::I2c
+            | Self::I2s
+            | Self::Newarray(_)
+            | Self::Anewarray(_)
+            | Self::Arraylength
+            | Self::Checkcast(_)
+            | Self::Instanceof(_) => (1, 1),
+            Self::Lneg | Self::Dneg | Self::L2d | Self::D2l => (2, 2),
+            Self::I2l | Self::I2d | Self::F2l | Self::F2d => (1, 2),
+            Self::L2i | Self::L2f | Self::D2i | Self::D2f => (2, 1),
+            Self::Lcmp | Self::Dcmpl | Self::Dcmpg => (4, 1),
+            Self::Getstatic(index) => (0, field_slots(*index)?),
+            Self::Putstatic(index) => (field_slots(*index)?, 0),
+            Self::Getfield(index) => (1, field_slots(*index)?),
+            Self::Putfield(index) => (1 + field_slots(*index)?, 0),
+            Self::Invokevirtual(index)
+            | Self::Invokespecial(index)
+            | Self::Invokeinterface { index, .. } => {
+                let (args, ret) = method_slots(*index)?;
+                (1 + args, ret)
+            }
+            Self::Invokestatic(index) | Self::Invokedynamic(index) => method_slots(*index)?,
+            Self::Multianewarray { dimensions, .. } => ((*dimensions).into(), 1),
+        })
+    }
+
+    /// The local variable that the instruction reads or writes, if any
+    pub fn local_access(&self) -> Option<LocalAccess> {
+        let (index, slots, reads, writes) = match self {
+            Self::Iload(i) | Self::Fload(i) | Self::Aload(i) => (usize::from(*i), 1, true, false),
+            Self::Istore(i) | Self::Fstore(i) | Self::Astore(i) => {
+                (usize::from(*i), 1, false, true)
+            }
+            Self::Lload(i) | Self::Dload(i) => (usize::from(*i), 2, true, false),
+            Self::Lstore(i) | Self::Dstore(i) => (usize::from(*i), 2, false, true),
+            Self::Ret(i) => (usize::from(*i), 1, true, false),
+            Self::Iinc { index: i, .. } => (usize::from(*i), 1, true, true),
+            Self::Wide(Wide::Iload(i) | Wide::Fload(i) | Wide::Aload(i) | Wide::Ret(i)) => {
+                (usize::from(*i), 1, true, false)
+            }
+            Self::Wide(Wide::Istore(i) | Wide::Fstore(i) | Wide::Astore(i)) => {
+                (usize::from(*i), 1, false, true)
+            }
+            Self::Wide(Wide::Lload(i) | Wide::Dload(i)) => (usize::from(*i), 2, true, false),
+            Self::Wide(Wide::Lstore(i) | Wide::Dstore(i)) => (usize::from(*i), 2, false, true),
+            Self::Wide(Wide::Iinc { index: i, .. }) => (usize::from(*i), 1, true, true),
+            Self::Iload0 | Self::Fload0 | Self::Aload0 => (0, 1, true, false),
+            Self::Istore0 | Self::Fstore0 | Self::Astore0 => (0, 1, false, true),
+            Self::Iload1 | Self::Fload1 | Self::Aload1 => (1, 1, true, false),
+            Self::Istore1 | Self::Fstore1 | Self::Astore1 => (1, 1, false, true),
+            Self::Iload2 | Self::Fload2 | Self::Aload2 => (2, 1, true, false),
+            Self::Istore2 | Self::Fstore2 | Self::Astore2 => (2, 1, false, true),
+            Self::Iload3 | Self::Fload3 | Self::Aload3 => (3, 1, true, false),
+            Self::Istore3 | Self::Fstore3 | Self::Astore3 => (3, 1, false, true),
+            Self::Lload0 | Self::Dload0 => (0, 2, true, false),
+            Self::Lstore0 | Self::Dstore0 => (0, 2, false, true),
+            Self::Lload1 | Self::Dload1 => (1, 2, true, false),
+            Self::Lstore1 | Self::Dstore1 => (1, 2, false, true),
+            Self::Lload2 | Self::Dload2 => (2, 2, true, false),
+            Self::Lstore2 | Self::Dstore2 => (2, 2, false, true),
+            Self::Lload3 | Self::Dload3 => (3, 2, true, false),
+            Self::Lstore3 | Self::Dstore3 => (3, 2, false, true),
+            _ => return None,
+        };
+        Some(LocalAccess {
+            index,
+            slots,
+            reads,
+            writes,
+        })
+    }
+
+    /// The number of local variable slots needed for the instruction to access the local
+    /// variable it uses, if any
+    pub fn locals_used(&self) -> Option<usize> {
+        self.local_access()
+            .map(|access| access.index + access.slots)
+    }
+}
+
+/// How an instruction uses a local variable, `long` and `double` take up two slots starting at
+/// `index`. `iinc` both reads and writes its local.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalAccess {
+    pub index: usize,
+    pub slots: usize,
+    pub reads: bool,
+    pub writes: bool,
+}
+
+/// An iterator over the instructions of a `code` array, which yields each along with its `pc`.
+/// Stops after the first instruction that fails to decode.
+#[derive(Debug, Clone)]
+pub struct Instructions<'a> {
+    r: ByteReader<'a>,
+    failed: bool,
+}
+
+impl<'a> Instructions<'a> {
+    pub fn new(code: &'a [u8]) -> Self {
+        Self {
+            r: ByteReader::new(code),
+            failed: false,
+        }
+    }
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = Result<(usize, Instruction)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.r.remaining() == 0 {
+            return None;
+        }
+
+        let pc = self.r.offset();
+        match Instruction::read(&mut self.r) {
+            Ok(instruction) => Some(Ok((pc, instruction))),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{test::stdlib_classes, types::resolved::Attribute, ClassFile};
+    use std::{fs, io::Cursor};
+
+    #[test]
+    fn switches_and_wide() {
+        #[rustfmt::skip]
+        let code = [
+            0x10, 0xfe,                     // 0: bipush -2
+            0xaa, 0x00,                     // 2: tableswitch, padded to 4
+            0x00, 0x00, 0x00, 0x20,         //    default
+            0x00, 0x00, 0x00, 0x01,         //    low
+            0x00, 0x00, 0x00, 0x02,         //    high
+            0x00, 0x00, 0x00, 0x18,
+            0x00, 0x00, 0x00, 0x1c,
+            0xab, 0x00, 0x00, 0x00,         // 24: lookupswitch, padded to 28
+            0xff, 0xff, 0xff, 0xe8,         //     default
+            0x00, 0x00, 0x00, 0x01,         //     npairs
+            0x00, 0x00, 0x00, 0x07,
+            0x00, 0x00, 0x00, 0x0c,
+            0xc4, 0x84, 0x01, 0x00, 0xff, 0xff, // 44: wide iinc 256, -1
+            0xb9, 0x00, 0x05, 0x02, 0x00,   // 50: invokeinterface #5, 2
+            0xb1,                           // 55: return
+        ];
+
+        let instructions: Vec<_> = Instructions::new(&code).collect::<Result<_>>().unwrap();
+        assert_eq!(
+            instructions,
+            [
+                (0, Instruction::Bipush(-2)),
+                (
+                    2,
+                    Instruction::Tableswitch {
+                        default: 0x20,
+                        low: 1,
+                        high: 2,
+                        offsets: vec![0x18, 0x1c],
+                    }
+                ),
+                (
+                    24,
+                    Instruction::Lookupswitch {
+                        default: -24,
+                        pairs: vec![(7, 12)],
+                    }
+                ),
+                (
+                    44,
+                    Instruction::Wide(Wide::Iinc {
+                        index: 256,
+                        value: -1
+                    })
+                ),
+                (50, Instruction::Invokeinterface { index: 5, count: 2 }),
+                (55, Instruction::Return),
+            ]
+        );
+        assert_eq!(instructions[1].1.mnemonic(), "tableswitch");
+        assert_eq!(instructions[3].1.opcode(), 0xc4);
+
+        let mut truncated = Instructions::new(&code[..20]);
+        assert_eq!(truncated.next(), Some(Ok((0, Instruction::Bipush(-2)))));
+        assert!(matches!(
+            truncated.next(),
+            Some(Err(Error::Truncated { .. }))
+        ));
+        assert_eq!(truncated.next(), None);
+
+        assert_eq!(
+            Instructions::new(&[0xcb]).next(),
+            Some(Err(Error::InvalidTag {
+                kind: "opcode",
+                tag: 0xcb
+            }))
+        );
+    }
+
+    #[test]
+    fn decode_stdlib() {
+        let Some(paths) = stdlib_classes("java.base") else {
+            return;
+        };
+
+        for path in paths {
+            let bytes = fs::read(&path).unwrap();
+            let class = ClassFile::read_from(&mut Cursor::new(bytes)).unwrap();
+            for method in class.methods() {
+                let method = method.unwrap();
+                let Some(instructions) = method.instructions().unwrap() else {
+                    continue;
+                };
+                // decoding then encoding each instruction should give back the same bytes
+                let mut encoded = Vec::new();
+                for instruction in instructions {
+                    let (pc, instruction) = instruction
+                        .unwrap_or_else(|e| panic!("{}: {}: {}", path.display(), method.name, e));
+                    instruction.write_to(&mut encoded, pc).unwrap();
+                    assert_eq!(encoded.len(), pc + instruction.len(pc));
+                }
+                let Some(Attribute::Code { code, .. }) = method.code().unwrap() else {
+                    unreachable!();
+                };
+                assert_eq!(encoded, code);
+            }
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use anyhow::Context;
-use std::io::Read;
+use std::io::{Read, Write};
+
+use crate::error::{Error, Result};
 
 macro_rules! read_num_dec {
     ($name: ident -> $ty: ty) => {
@@ -56,3 +58,126 @@ where
     read_num_impl!(read_f32 -> f32);
     read_num_impl!(read_f64 -> f64);
 }
+
+macro_rules! write_num_dec {
+    ($name: ident -> $ty: ty) => {
+        fn $name(&mut self, n: $ty) -> anyhow::Result<()>;
+    };
+}
+
+pub trait WriteNum: Write {
+    write_num_dec!(write_u8 -> u8);
+    write_num_dec!(write_i8 -> i8);
+    write_num_dec!(write_u16 -> u16);
+    write_num_dec!(write_i16 -> i16);
+    write_num_dec!(write_u32 -> u32);
+    write_num_dec!(write_i32 -> i32);
+    write_num_dec!(write_u64 -> u64);
+    write_num_dec!(write_i64 -> i64);
+    write_num_dec!(write_u128 -> u128);
+    write_num_dec!(write_i128 -> i128);
+
+    write_num_dec!(write_f32 -> f32);
+    write_num_dec!(write_f64 -> f64);
+}
+
+macro_rules! write_num_impl {
+    ($name: ident -> $ty: ty) => {
+        fn $name(&mut self, n: $ty) -> anyhow::Result<()> {
+            self.write_all(&n.to_be_bytes())
+                .context(concat!("writing ", stringify!($ty)))?;
+            Ok(())
+        }
+    };
+}
+
+impl<W> WriteNum for W
+where
+    W: Write,
+{
+    write_num_impl!(write_u8 -> u8);
+    write_num_impl!(write_i8 -> i8);
+
+    write_num_impl!(write_u16 -> u16);
+    write_num_impl!(write_i16 -> i16);
+
+    write_num_impl!(write_u32 -> u32);
+    write_num_impl!(write_i32 -> i32);
+
+    write_num_impl!(write_u64 -> u64);
+    write_num_impl!(write_i64 -> i64);
+
+    write_num_impl!(write_u128 -> u128);
+    write_num_impl!(write_i128 -> i128);
+
+    write_num_impl!(write_f32 -> f32);
+    write_num_impl!(write_f64 -> f64);
+}
+
+macro_rules! byte_reader_impl {
+    ($name: ident -> $ty: ty) => {
+        pub fn $name(&mut self) -> Result<$ty> {
+            let bytes = self.read_bytes(std::mem::size_of::<$ty>())?;
+            Ok(<$ty>::from_be_bytes(bytes.try_into().unwrap()))
+        }
+    };
+}
+
+/// Reads big-endian values from a byte slice, keeping track of the offset so that errors can say
+/// where they happened.
+#[derive(Debug, Clone)]
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    /// The number of bytes that have been read so far
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The number of bytes that are left to be read
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.remaining() < len {
+            return Err(Error::Truncated {
+                offset: self.offset,
+            });
+        }
+        let bytes = &self.bytes[self.offset..][..len];
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    byte_reader_impl!(read_u8 -> u8);
+    byte_reader_impl!(read_i8 -> i8);
+
+    byte_reader_impl!(read_u16 -> u16);
+    byte_reader_impl!(read_i16 -> i16);
+
+    byte_reader_impl!(read_u32 -> u32);
+    byte_reader_impl!(read_i32 -> i32);
+
+    byte_reader_impl!(read_u64 -> u64);
+    byte_reader_impl!(read_i64 -> i64);
+
+    byte_reader_impl!(read_f32 -> f32);
+    byte_reader_impl!(read_f64 -> f64);
+}
+
+/// Read a `u2` count followed by that many items, each read using `f`
+pub(crate) fn read_table<'a, T>(
+    r: &mut ByteReader<'a>,
+    mut f: impl FnMut(&mut ByteReader<'a>) -> Result<T>,
+) -> Result<Vec<T>> {
+    let count = r.read_u16()?;
+    (0..count).map(|_| f(r)).collect()
+}
@@ -0,0 +1,511 @@
+use std::io::Write;
+
+use anyhow::Context;
+
+use crate::{
+    bytes::{ByteReader, WriteNum},
+    error::{Error, Result},
+    types::raw::RawConstant,
+};
+
+/// The constant pool of a class file, indexed from one as in the class file itself. See
+/// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.4>
+///
+/// Utf8 constants may borrow from the bytes the class was parsed from, see [`ClassFile::parse`].
+///
+/// [`ClassFile::parse`]: crate::ClassFile::parse
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
+pub struct ConstantPool<'a> {
+    /// Longs and Doubles are followed by a [`RawConstant::Unused`] so that `constants[index - 1]`
+    /// is the constant at `index`
+    pub(crate) constants: Vec<RawConstant<'a>>,
+}
+
+/// What kind of member a [`MemberRef`] refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum MemberKind {
+    Field,
+    Method,
+    InterfaceMethod,
+}
+
+/// A `Fieldref`, `Methodref` or `InterfaceMethodref` constant with all of its indices resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MemberRef<'a> {
+    /// The class or interface which has the member
+    pub class: &'a str,
+    pub name: &'a str,
+    pub descriptor: &'a str,
+    pub kind: MemberKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NameAndType<'a> {
+    pub name: &'a str,
+    pub descriptor: &'a str,
+}
+
+/// The kind of a [`MethodHandle`], see
+/// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-5.html#jvms-5.4.3.5>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ReferenceKind {
+    GetField = 1,
+    GetStatic = 2,
+    PutField = 3,
+    PutStatic = 4,
+    InvokeVirtual = 5,
+    InvokeStatic = 6,
+    InvokeSpecial = 7,
+    NewInvokeSpecial = 8,
+    InvokeInterface = 9,
+}
+
+impl TryFrom<u8> for ReferenceKind {
+    type Error = Error;
+
+    fn try_from(kind: u8) -> Result<Self> {
+        Ok(match kind {
+            1 => Self::GetField,
+            2 => Self::GetStatic,
+            3 => Self::PutField,
+            4 => Self::PutStatic,
+            5 => Self::InvokeVirtual,
+            6 => Self::InvokeStatic,
+            7 => Self::InvokeSpecial,
+            8 => Self::NewInvokeSpecial,
+            9 => Self::InvokeInterface,
+            tag => {
+                return Err(Error::InvalidTag {
+                    kind: "reference kind",
+                    tag,
+                })
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MethodHandle<'a> {
+    pub kind: ReferenceKind,
+    pub reference: MemberRef<'a>,
+}
+
+/// A `Dynamic` or `InvokeDynamic` constant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DynamicRef<'a> {
+    /// Index into the methods of the `BootstrapMethods` attribute
+    pub bootstrap_method_attr_index: usize,
+    pub name: &'a str,
+    pub descriptor: &'a str,
+}
+
+/// A constant which can be pushed onto the stack by `ldc`, or be a static argument to a bootstrap
+/// method, see <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.4-310>
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Loadable<'a> {
+    Integer(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    /// The name of the class
+    Class(&'a str),
+    String(&'a str),
+    /// A String constant which is not valid unicode, e.g. one containing an unpaired surrogate.
+    /// The modified UTF-8 bytes are kept as they are.
+    RawString(&'a [u8]),
+    MethodHandle(MethodHandle<'a>),
+    /// The method descriptor
+    MethodType(&'a str),
+    Dynamic(DynamicRef<'a>),
+}
+
+/// Any constant from the pool with its indices resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Constant<'a> {
+    Utf8(&'a str),
+    /// A `Utf8` constant which is not valid unicode, see [`ConstantPool::utf8`]
+    RawUtf8(&'a [u8]),
+    NameAndType(NameAndType<'a>),
+    MemberRef(MemberRef<'a>),
+    InvokeDynamic(DynamicRef<'a>),
+    Module(&'a str),
+    Package(&'a str),
+    Loadable(Loadable<'a>),
+}
+
+impl<'a> ConstantPool<'a> {
+    /// Parse the `constant_pool_count` and the constants that follow it
+    pub(crate) fn parse(r: &mut ByteReader<'a>) -> Result<Self> {
+        let constant_pool_count = r.read_u16()?;
+        let mut constants = Vec::with_capacity((constant_pool_count as usize).saturating_sub(1));
+
+        // idk why this counts from one, but java is gonna java...
+        let mut i = 1;
+        while i < constant_pool_count {
+            let (c, skip_next) = RawConstant::parse(r)?;
+            constants.push(c);
+            if skip_next {
+                i += 1;
+                // push an empty value so indexing still works
+                constants.push(RawConstant::Unused);
+            }
+            i += 1;
+        }
+
+        Ok(Self { constants })
+    }
+
+    /// Copy any borrowed strings so that the pool no longer borrows from the input
+    pub fn into_owned(self) -> ConstantPool<'static> {
+        ConstantPool {
+            constants: self
+                .constants
+                .into_iter()
+                .map(RawConstant::into_owned)
+                .collect(),
+        }
+    }
+
+    pub(crate) fn write_to<W>(&self, w: &mut W) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        let constant_pool_count =
+            u16::try_from(self.constants.len() + 1).context("constant pool too large")?;
+        w.write_u16(constant_pool_count)?;
+        for constant in &self.constants {
+            constant.write_to(w).context("writing raw constant")?;
+        }
+        Ok(())
+    }
+
+    /// The number of slots in the pool, where Longs and Doubles take up two. Valid indices are
+    /// `1..=len`.
+    pub fn len(&self) -> usize {
+        self.constants.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constants.is_empty()
+    }
+
+    /// Look up the constant at `index` without resolving it
+    pub(crate) fn get(&self, index: usize) -> Result<&RawConstant<'a>> {
+        match index.checked_sub(1).and_then(|i| self.constants.get(i)) {
+            None | Some(RawConstant::Unused) => Err(Error::BadConstantIndex { index }),
+            Some(c) => Ok(c),
+        }
+    }
+
+    /// Look up the constant at `index` and resolve any indices inside of it
+    pub fn constant(&self, index: usize) -> Result<Constant<'_>> {
+        Ok(match self.get(index)? {
+            c @ RawConstant::Utf8 { bytes, .. } => match c.as_str() {
+                Some(string) => Constant::Utf8(string),
+                None => Constant::RawUtf8(bytes),
+            },
+            RawConstant::NameAndType { .. } => Constant::NameAndType(self.name_and_type(index)?),
+            RawConstant::FieldRef { .. }
+            | RawConstant::MethodRef { .. }
+            | RawConstant::InterfaceMethodRef { .. } => {
+                Constant::MemberRef(self.member_ref(index)?)
+            }
+            RawConstant::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => Constant::InvokeDynamic(
+                self.dynamic_ref(*bootstrap_method_attr_index, *name_and_type_index)?,
+            ),
+            RawConstant::Module { .. } => Constant::Module(self.module_name(index)?),
+            RawConstant::Package { .. } => Constant::Package(self.package_name(index)?),
+            _ => Constant::Loadable(self.loadable(index)?),
+        })
+    }
+
+    /// The numbers stored in the constant at `index` as they are in the class file, such as the
+    /// class and name-and-type indices of a Methodref, or the reference kind and member index of a
+    /// MethodHandle. Utf8 constants and numbers don't have any.
+    pub fn operands(&self, index: usize) -> Result<Vec<usize>> {
+        Ok(match *self.get(index)? {
+            RawConstant::Class { name_index }
+            | RawConstant::Module { name_index }
+            | RawConstant::Package { name_index } => vec![name_index],
+            RawConstant::String { string_index } => vec![string_index],
+            RawConstant::MethodType { descriptor_index } => vec![descriptor_index],
+            RawConstant::FieldRef {
+                class_index,
+                name_and_type_index,
+            }
+            | RawConstant::MethodRef {
+                class_index,
+                name_and_type_index,
+            }
+            | RawConstant::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => vec![class_index, name_and_type_index],
+            RawConstant::NameAndType {
+                name_index,
+                descriptor_index,
+            } => vec![name_index, descriptor_index],
+            RawConstant::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => vec![reference_kind.into(), reference_index],
+            RawConstant::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            }
+            | RawConstant::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => vec![bootstrap_method_attr_index, name_and_type_index],
+            RawConstant::Unused
+            | RawConstant::Utf8 { .. }
+            | RawConstant::Integer { .. }
+            | RawConstant::Float { .. }
+            | RawConstant::Long { .. }
+            | RawConstant::Double { .. } => Vec::new(),
+        })
+    }
+
+    /// All constants in the pool along with their indices, skipping the unusable slot after each
+    /// Long and Double
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Result<Constant<'_>>)> {
+        self.constants
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !matches!(c, RawConstant::Unused))
+            .map(|(i, _)| (i + 1, self.constant(i + 1)))
+    }
+
+    fn wrong_kind<T>(&self, index: usize, expected: &'static str) -> Result<T> {
+        Err(Error::WrongConstantKind {
+            index,
+            expected,
+            found: self.get(index)?.kind(),
+        })
+    }
+
+    /// Look up the Utf8 constant at `index`. Strings are only decoded from modified UTF-8 the
+    /// first time they are looked up, and not at all if they are also valid UTF-8.
+    pub fn utf8(&self, index: usize) -> Result<&str> {
+        match self.get(index)? {
+            c @ RawConstant::Utf8 { .. } => c.as_str().ok_or(Error::InvalidUtf8 { index }),
+            _ => self.wrong_kind(index, "Utf8"),
+        }
+    }
+
+    /// Look up the name of the Class constant at `index`
+    pub fn class_name(&self, index: usize) -> Result<&str> {
+        match self.get(index)? {
+            RawConstant::Class { name_index } => self.utf8(*name_index),
+            _ => self.wrong_kind(index, "Class"),
+        }
+    }
+
+    /// Look up the name of the Module constant at `index`
+    pub fn module_name(&self, index: usize) -> Result<&str> {
+        match self.get(index)? {
+            RawConstant::Module { name_index } => self.utf8(*name_index),
+            _ => self.wrong_kind(index, "Module"),
+        }
+    }
+
+    /// Look up the name of the Package constant at `index`
+    pub fn package_name(&self, index: usize) -> Result<&str> {
+        match self.get(index)? {
+            RawConstant::Package { name_index } => self.utf8(*name_index),
+            _ => self.wrong_kind(index, "Package"),
+        }
+    }
+
+    /// Look up the contents of the String constant at `index`
+    pub fn string(&self, index: usize) -> Result<&str> {
+        match self.get(index)? {
+            RawConstant::String { string_index } => self.utf8(*string_index),
+            _ => self.wrong_kind(index, "String"),
+        }
+    }
+
+    /// Look up the NameAndType constant at `index`
+    pub fn name_and_type(&self, index: usize) -> Result<NameAndType<'_>> {
+        match self.get(index)? {
+            RawConstant::NameAndType {
+                name_index,
+                descriptor_index,
+            } => Ok(NameAndType {
+                name: self.utf8(*name_index)?,
+                descriptor: self.utf8(*descriptor_index)?,
+            }),
+            _ => self.wrong_kind(index, "NameAndType"),
+        }
+    }
+
+    /// Look up the FieldRef, MethodRef or InterfaceMethodRef constant at `index`
+    pub fn member_ref(&self, index: usize) -> Result<MemberRef<'_>> {
+        let (kind, class_index, name_and_type_index) = match self.get(index)? {
+            RawConstant::FieldRef {
+                class_index,
+                name_and_type_index,
+            } => (MemberKind::Field, class_index, name_and_type_index),
+            RawConstant::MethodRef {
+                class_index,
+                name_and_type_index,
+            } => (MemberKind::Method, class_index, name_and_type_index),
+            RawConstant::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => (
+                MemberKind::InterfaceMethod,
+                class_index,
+                name_and_type_index,
+            ),
+            _ => return self.wrong_kind(index, "FieldRef, MethodRef or InterfaceMethodRef"),
+        };
+        let NameAndType { name, descriptor } = self.name_and_type(*name_and_type_index)?;
+
+        Ok(MemberRef {
+            class: self.class_name(*class_index)?,
+            name,
+            descriptor,
+            kind,
+        })
+    }
+
+    /// Look up the MethodHandle constant at `index`
+    pub fn method_handle(&self, index: usize) -> Result<MethodHandle<'_>> {
+        match self.get(index)? {
+            RawConstant::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => Ok(MethodHandle {
+                kind: ReferenceKind::try_from(*reference_kind)?,
+                reference: self.member_ref(*reference_index)?,
+            }),
+            _ => self.wrong_kind(index, "MethodHandle"),
+        }
+    }
+
+    fn dynamic_ref(
+        &self,
+        bootstrap_method_attr_index: usize,
+        name_and_type_index: usize,
+    ) -> Result<DynamicRef<'_>> {
+        let NameAndType { name, descriptor } = self.name_and_type(name_and_type_index)?;
+        Ok(DynamicRef {
+            bootstrap_method_attr_index,
+            name,
+            descriptor,
+        })
+    }
+
+    /// Look up the InvokeDynamic constant at `index`
+    pub fn invoke_dynamic(&self, index: usize) -> Result<DynamicRef<'_>> {
+        match self.get(index)? {
+            RawConstant::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => self.dynamic_ref(*bootstrap_method_attr_index, *name_and_type_index),
+            _ => self.wrong_kind(index, "InvokeDynamic"),
+        }
+    }
+
+    /// Look up the constant at `index`, which must be one that `ldc` can load
+    pub fn loadable(&self, index: usize) -> Result<Loadable<'_>> {
+        Ok(match self.get(index)? {
+            RawConstant::Integer { num } => Loadable::Integer(*num),
+            RawConstant::Float { num } => Loadable::Float(*num),
+            RawConstant::Long { num } => Loadable::Long(*num),
+            RawConstant::Double { num } => Loadable::Double(*num),
+            RawConstant::Class { .. } => Loadable::Class(self.class_name(index)?),
+            RawConstant::String { string_index } => match self.get(*string_index)? {
+                c @ RawConstant::Utf8 { bytes, .. } if c.as_str().is_none() => {
+                    Loadable::RawString(bytes)
+                }
+                _ => Loadable::String(self.string(index)?),
+            },
+            RawConstant::MethodHandle { .. } => Loadable::MethodHandle(self.method_handle(index)?),
+            RawConstant::MethodType { descriptor_index } => {
+                Loadable::MethodType(self.utf8(*descriptor_index)?)
+            }
+            RawConstant::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => Loadable::Dynamic(
+                self.dynamic_ref(*bootstrap_method_attr_index, *name_and_type_index)?,
+            ),
+            _ => return self.wrong_kind(index, "loadable"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{test::stdlib_classes, ClassFile};
+    use std::{fs, io::Cursor};
+
+    #[test]
+    fn object_member_refs() {
+        let Some(paths) = stdlib_classes("java.base/java/lang/Object.class") else {
+            return;
+        };
+        let class = ClassFile::read_from(&mut Cursor::new(fs::read(&paths[0]).unwrap())).unwrap();
+        let pool = &class.constant_pool;
+
+        // `toString` calls `Integer.toHexString(hashCode())`
+        let (index, to_hex_string) = pool
+            .iter()
+            .find_map(|(i, c)| match c.unwrap() {
+                Constant::MemberRef(m) if m.name == "toHexString" => Some((i, m)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            to_hex_string,
+            MemberRef {
+                class: "java/lang/Integer",
+                name: "toHexString",
+                descriptor: "(I)Ljava/lang/String;",
+                kind: MemberKind::Method,
+            }
+        );
+        assert_eq!(pool.member_ref(index), Ok(to_hex_string));
+
+        let operands = pool.operands(index).unwrap();
+        assert_eq!(operands.len(), 2);
+        assert_eq!(pool.class_name(operands[0]), Ok("java/lang/Integer"));
+        assert_eq!(pool.name_and_type(operands[1]).unwrap().name, "toHexString");
+        assert_eq!(pool.operands(operands[0]).map(|o| o.len()), Ok(1));
+
+        assert_eq!(
+            pool.class_name(index),
+            Err(Error::WrongConstantKind {
+                index,
+                expected: "Class",
+                found: "MethodRef",
+            })
+        );
+        assert!(matches!(
+            pool.loadable(index),
+            Err(Error::WrongConstantKind { .. })
+        ));
+        assert_eq!(pool.utf8(0), Err(Error::BadConstantIndex { index: 0 }));
+        assert_eq!(
+            pool.utf8(pool.len() + 1),
+            Err(Error::BadConstantIndex {
+                index: pool.len() + 1
+            })
+        );
+    }
+}
@@ -0,0 +1,776 @@
+//! The control-flow graph of a method's code and a generic dataflow solver over it.
+//!
+//! ```
+//! # use class_files::{assembler::{Assembler, Jump}, bytecode::Instruction, constant_pool::ConstantPool};
+//! use class_files::control_flow::{solve, ControlFlowGraph, Liveness};
+//!
+//! // static int f(int x) { while (x > 0) x--; return x; }
+//! let mut asm = Assembler::new(1);
+//! let (head, exit) = (asm.new_label(), asm.new_label());
+//! asm.bind(head).push(Instruction::Iload0).jump(Jump::Ifle, exit);
+//! asm.push(Instruction::Iinc { index: 0, value: -1 }).jump(Jump::Goto, head);
+//! asm.bind(exit).push(Instruction::Iload0).push(Instruction::Ireturn);
+//! let code = asm.assemble(&ConstantPool::default())?;
+//!
+//! let cfg = ControlFlowGraph::new(&code.code, &code.exception_table)?;
+//! assert_eq!(cfg.blocks().len(), 3);
+//! let live = solve(&cfg, &Liveness);
+//! assert!(live.entry(0).contains(&0));
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use crate::{
+    bytecode::{Instruction, Instructions},
+    constant_pool::ConstantPool,
+    error::{Error, Result},
+    types::resolved::{Attribute, Exception, Method},
+};
+
+/// A straight-line run of instructions which is only entered at its first instruction and only
+/// left after its last
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    /// The `pc` of the first instruction
+    pub start: usize,
+    /// The `pc` just after the last instruction
+    pub end: usize,
+    /// Each instruction along with its `pc`
+    pub instructions: Vec<(usize, Instruction)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// Execution continues with the next instruction
+    FallThrough,
+    /// A jump by a conditional branch, `goto` or `jsr`
+    Branch,
+    /// One of the targets of a `tableswitch` or `lookupswitch`
+    Switch,
+    /// An exception thrown inside the block is caught, `catch_type` is the index of a Class
+    /// constant or zero for any exception
+    Exception { catch_type: u16 },
+}
+
+/// An edge between two blocks, given by their index in [`ControlFlowGraph::blocks`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// The basic blocks of a method's code and the edges between them. Block `0` is the entry.
+///
+/// Subroutines are not resolved, so `ret` has no successors and the instruction after a `jsr` is
+/// reached by falling through.
+#[derive(Debug, Clone, Default)]
+pub struct ControlFlowGraph {
+    blocks: Vec<BasicBlock>,
+    edges: Vec<Edge>,
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+}
+
+impl ControlFlowGraph {
+    /// Build the graph of `code` with exception edges from `exception_table`
+    pub fn new(code: &[u8], exception_table: &[Exception]) -> Result<Self> {
+        let instructions = Instructions::new(code).collect::<Result<Vec<_>>>()?;
+        let starts: BTreeSet<usize> = instructions.iter().map(|(pc, _)| *pc).collect();
+        let is_start = |pc: usize| starts.contains(&pc);
+
+        let mut leaders = BTreeSet::from([0]);
+        for (i, (pc, instruction)) in instructions.iter().enumerate() {
+            let targets = instruction.branch_targets(*pc);
+            if let Some(target) = targets.iter().find(|t| !is_start(**t)) {
+                return Err(Error::InvalidInstruction {
+                    pc: *pc,
+                    reason: if *target < code.len() {
+                        "branch target is not the start of an instruction"
+                    } else {
+                        "branch target is outside of the code"
+                    },
+                });
+            }
+            leaders.extend(targets.iter().copied());
+            if !targets.is_empty() || !instruction.falls_through() {
+                if let Some((next, _)) = instructions.get(i + 1) {
+                    leaders.insert(*next);
+                }
+            }
+        }
+        for exception in exception_table {
+            let (start, end, handler) = (
+                exception.start_pc.into(),
+                exception.end_pc.into(),
+                exception.handler_pc.into(),
+            );
+            if !is_start(start) || !is_start(handler) || (!is_start(end) && end != code.len()) {
+                return Err(Error::InvalidInstruction {
+                    pc: start,
+                    reason: "exception table entry is not on instruction boundaries",
+                });
+            }
+            leaders.extend([start, handler]);
+            if end < code.len() {
+                leaders.insert(end);
+            }
+        }
+
+        let mut blocks: Vec<BasicBlock> = Vec::with_capacity(leaders.len());
+        for (pc, instruction) in instructions {
+            let len = instruction.len(pc);
+            match blocks.last_mut() {
+                Some(block) if !leaders.contains(&pc) => {
+                    block.end = pc + len;
+                    block.instructions.push((pc, instruction));
+                }
+                _ => blocks.push(BasicBlock {
+                    start: pc,
+                    end: pc + len,
+                    instructions: vec![(pc, instruction)],
+                }),
+            }
+        }
+
+        let mut cfg = Self {
+            successors: vec![Vec::new(); blocks.len()],
+            predecessors: vec![Vec::new(); blocks.len()],
+            blocks,
+            edges: Vec::new(),
+        };
+        for from in 0..cfg.blocks.len() {
+            let block = &cfg.blocks[from];
+            let (pc, last) = block.instructions.last().expect("blocks are never empty");
+            let kind = match last {
+                Instruction::Tableswitch { .. } | Instruction::Lookupswitch { .. } => {
+                    EdgeKind::Switch
+                }
+                _ => EdgeKind::Branch,
+            };
+            let mut edges: Vec<_> = last
+                .branch_targets(*pc)
+                .into_iter()
+                .map(|target| (target, kind))
+                .collect();
+            if last.falls_through() && block.end < code.len() {
+                edges.push((block.end, EdgeKind::FallThrough));
+            }
+            for exception in exception_table {
+                if (usize::from(exception.start_pc)..usize::from(exception.end_pc))
+                    .contains(&block.start)
+                {
+                    edges.push((
+                        exception.handler_pc.into(),
+                        EdgeKind::Exception {
+                            catch_type: exception.catch_type,
+                        },
+                    ));
+                }
+            }
+            for (target, kind) in edges {
+                let to = cfg.block_at(target).expect("targets are leaders");
+                cfg.add_edge(Edge { from, to, kind });
+            }
+        }
+        Ok(cfg)
+    }
+
+    /// Build the graph of the method's `Code` attribute, `None` if it doesn't have one
+    pub fn from_method(method: &Method) -> Result<Option<Self>> {
+        match method.code()? {
+            Some(Attribute::Code {
+                code,
+                exception_table,
+                ..
+            }) => Self::new(code, &exception_table).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn add_edge(&mut self, edge: Edge) {
+        // a switch can have several cases with the same target
+        if self.successors[edge.from]
+            .iter()
+            .any(|i| self.edges[*i] == edge)
+        {
+            return;
+        }
+        self.successors[edge.from].push(self.edges.len());
+        self.predecessors[edge.to].push(self.edges.len());
+        self.edges.push(edge);
+    }
+
+    /// The blocks in order of their `pc`
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// The edges leaving `block`
+    pub fn successors(&self, block: usize) -> impl Iterator<Item = &Edge> {
+        self.successors[block].iter().map(|i| &self.edges[*i])
+    }
+
+    /// The edges entering `block`
+    pub fn predecessors(&self, block: usize) -> impl Iterator<Item = &Edge> {
+        self.predecessors[block].iter().map(|i| &self.edges[*i])
+    }
+
+    /// The index of the block containing the instruction at `pc`
+    pub fn block_at(&self, pc: usize) -> Option<usize> {
+        let i = self.blocks.partition_point(|block| block.start <= pc);
+        (i > 0 && pc < self.blocks[i - 1].end).then_some(i - 1)
+    }
+
+    /// Whether an exception thrown by an instruction in `block` can be caught in this method
+    fn is_protected(&self, block: usize) -> bool {
+        self.successors(block)
+            .any(|edge| matches!(edge.kind, EdgeKind::Exception { .. }))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Facts flow from the entry of the method along the edges
+    Forward,
+    /// Facts flow from the exits of the method against the edges
+    Backward,
+}
+
+/// A dataflow problem, where facts form a lattice which is combined with [`Analysis::join`] where
+/// control flow meets and changed by [`Analysis::transfer`] for each instruction. `join` and
+/// `transfer` must be monotone for [`solve`] to terminate.
+pub trait Analysis {
+    type Fact: Clone + PartialEq;
+
+    fn direction(&self) -> Direction;
+
+    /// The fact at the entry of the method when forward, or after each instruction that leaves
+    /// the method when backward
+    fn boundary(&self) -> Self::Fact;
+
+    /// The fact for code which hasn't been reached yet, joining with it changes nothing
+    fn bottom(&self) -> Self::Fact;
+
+    /// Combine `other` into `fact`
+    fn join(&self, fact: &mut Self::Fact, other: &Self::Fact);
+
+    /// Apply the instruction at `pc` to `fact`. Forward this turns the fact before the instruction
+    /// into the one after it, backward the other way around.
+    fn transfer(&self, pc: usize, instruction: &Instruction, fact: &mut Self::Fact);
+
+    /// The fact passed along an exception edge. Forward this is given the fact before an
+    /// instruction which throws and returns the one at the start of the handler, backward the
+    /// other way around.
+    fn exception(&self, fact: &Self::Fact) -> Self::Fact {
+        fact.clone()
+    }
+}
+
+/// The facts found by [`solve`], always in execution order so `entry` is before a block or
+/// instruction and `exit` is after it whichever the direction of the analysis
+#[derive(Debug, Clone)]
+pub struct Solution<F> {
+    entry: Vec<F>,
+    exit: Vec<F>,
+    before: HashMap<usize, F>,
+    after: HashMap<usize, F>,
+}
+
+impl<F> Solution<F> {
+    /// The fact at the start of `block`
+    pub fn entry(&self, block: usize) -> &F {
+        &self.entry[block]
+    }
+
+    /// The fact at the end of `block`
+    pub fn exit(&self, block: usize) -> &F {
+        &self.exit[block]
+    }
+
+    /// The fact just before the instruction at `pc` runs
+    pub fn before(&self, pc: usize) -> Option<&F> {
+        self.before.get(&pc)
+    }
+
+    /// The fact just after the instruction at `pc` runs, if it completes normally
+    pub fn after(&self, pc: usize) -> Option<&F> {
+        self.after.get(&pc)
+    }
+}
+
+/// Find the fixed point of `analysis` over `cfg` using a worklist of blocks
+pub fn solve<A: Analysis>(cfg: &ControlFlowGraph, analysis: &A) -> Solution<A::Fact> {
+    let len = cfg.blocks.len();
+    let mut solution = Solution {
+        entry: vec![analysis.bottom(); len],
+        exit: vec![analysis.bottom(); len],
+        before: HashMap::new(),
+        after: HashMap::new(),
+    };
+    if len == 0 {
+        return solution;
+    }
+
+    let mut queued = vec![false; len];
+    let mut worklist = VecDeque::new();
+    match analysis.direction() {
+        Direction::Forward => {
+            solution.entry[0] = analysis.boundary();
+            worklist.push_back(0);
+            queued[0] = true;
+        }
+        Direction::Backward => {
+            worklist.extend((0..len).rev());
+            queued.fill(true);
+        }
+    }
+
+    while let Some(block) = worklist.pop_front() {
+        queued[block] = false;
+        let changed = match analysis.direction() {
+            Direction::Forward => {
+                let (exit, thrown) = forward(cfg, analysis, block, &solution, None);
+                let mut changed = Vec::new();
+                for edge in cfg.successors(block) {
+                    let fact = match edge.kind {
+                        EdgeKind::Exception { .. } => analysis.exception(&thrown),
+                        _ => exit.clone(),
+                    };
+                    let entry = &mut solution.entry[edge.to];
+                    let old = entry.clone();
+                    analysis.join(entry, &fact);
+                    if *entry != old {
+                        changed.push(edge.to);
+                    }
+                }
+                solution.exit[block] = exit;
+                changed
+            }
+            Direction::Backward => {
+                solution.exit[block] = backward_exit(cfg, analysis, block, &solution);
+                let entry = backward(cfg, analysis, block, &solution, None);
+                if entry == solution.entry[block] {
+                    Vec::new()
+                } else {
+                    solution.entry[block] = entry;
+                    cfg.predecessors(block).map(|edge| edge.from).collect()
+                }
+            }
+        };
+        for block in changed {
+            if !queued[block] {
+                queued[block] = true;
+                worklist.push_back(block);
+            }
+        }
+    }
+
+    let mut before = HashMap::new();
+    let mut after = HashMap::new();
+    for block in 0..len {
+        let facts = Some((&mut before, &mut after));
+        match analysis.direction() {
+            Direction::Forward => {
+                forward(cfg, analysis, block, &solution, facts);
+            }
+            Direction::Backward => {
+                backward(cfg, analysis, block, &solution, facts);
+            }
+        }
+    }
+    solution.before = before;
+    solution.after = after;
+    solution
+}
+
+type InstructionFacts<'a, F> = Option<(&'a mut HashMap<usize, F>, &'a mut HashMap<usize, F>)>;
+
+/// Run `block` forwards from its entry, returning the fact at its exit and the join of the facts
+/// before each instruction, which is where an exception can be thrown from
+fn forward<A: Analysis>(
+    cfg: &ControlFlowGraph,
+    analysis: &A,
+    block: usize,
+    solution: &Solution<A::Fact>,
+    mut facts: InstructionFacts<A::Fact>,
+) -> (A::Fact, A::Fact) {
+    let protected = cfg.is_protected(block);
+    let mut fact = solution.entry[block].clone();
+    let mut thrown = analysis.bottom();
+    for (pc, instruction) in &cfg.blocks[block].instructions {
+        if protected {
+            analysis.join(&mut thrown, &fact);
+        }
+        if let Some((before, _)) = &mut facts {
+            before.insert(*pc, fact.clone());
+        }
+        analysis.transfer(*pc, instruction, &mut fact);
+        if let Some((_, after)) = &mut facts {
+            after.insert(*pc, fact.clone());
+        }
+    }
+    (fact, thrown)
+}
+
+/// The fact at the exit of `block` from the entries of the blocks it can continue to normally
+fn backward_exit<A: Analysis>(
+    cfg: &ControlFlowGraph,
+    analysis: &A,
+    block: usize,
+    solution: &Solution<A::Fact>,
+) -> A::Fact {
+    let mut exit = analysis.bottom();
+    let mut leaves = true;
+    for edge in cfg.successors(block) {
+        if !matches!(edge.kind, EdgeKind::Exception { .. }) {
+            leaves = false;
+            analysis.join(&mut exit, &solution.entry[edge.to]);
+        }
+    }
+    if leaves {
+        analysis.join(&mut exit, &analysis.boundary());
+    }
+    exit
+}
+
+/// Run `block` backwards from its exit, where each instruction that can throw also needs the
+/// facts at the start of its handlers, returning the fact at its entry
+fn backward<A: Analysis>(
+    cfg: &ControlFlowGraph,
+    analysis: &A,
+    block: usize,
+    solution: &Solution<A::Fact>,
+    mut facts: InstructionFacts<A::Fact>,
+) -> A::Fact {
+    let mut thrown = None;
+    for edge in cfg.successors(block) {
+        if let EdgeKind::Exception { .. } = edge.kind {
+            let thrown = thrown.get_or_insert_with(|| analysis.bottom());
+            analysis.join(thrown, &analysis.exception(&solution.entry[edge.to]));
+        }
+    }
+
+    let mut fact = solution.exit[block].clone();
+    for (pc, instruction) in cfg.blocks[block].instructions.iter().rev() {
+        if let Some((_, after)) = &mut facts {
+            after.insert(*pc, fact.clone());
+        }
+        analysis.transfer(*pc, instruction, &mut fact);
+        if let Some(thrown) = &thrown {
+            analysis.join(&mut fact, thrown);
+        }
+        if let Some((before, _)) = &mut facts {
+            before.insert(*pc, fact.clone());
+        }
+    }
+    fact
+}
+
+/// The local variable slots whose current value may still be read, found backwards
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Liveness;
+
+impl Analysis for Liveness {
+    type Fact = BTreeSet<usize>;
+
+    fn direction(&self) -> Direction {
+        Direction::Backward
+    }
+
+    fn boundary(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn bottom(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn join(&self, fact: &mut Self::Fact, other: &Self::Fact) {
+        fact.extend(other);
+    }
+
+    fn transfer(&self, _pc: usize, instruction: &Instruction, fact: &mut Self::Fact) {
+        if let Some(access) = instruction.local_access() {
+            let slots = access.index..access.index + access.slots;
+            if access.writes {
+                slots.clone().for_each(|slot| {
+                    fact.remove(&slot);
+                });
+            }
+            if access.reads {
+                fact.extend(slots);
+            }
+        }
+    }
+}
+
+/// A write to a local variable slot, `pc` is `None` for the parameters the method starts with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Definition {
+    pub slot: usize,
+    pub pc: Option<usize>,
+}
+
+/// The writes to local variable slots which may not have been overwritten yet, found forwards
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReachingDefinitions {
+    /// The number of slots taken by the parameters, including `this`
+    pub parameter_slots: usize,
+}
+
+impl ReachingDefinitions {
+    pub fn new(parameter_slots: usize) -> Self {
+        Self { parameter_slots }
+    }
+}
+
+impl Analysis for ReachingDefinitions {
+    type Fact = BTreeSet<Definition>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self) -> Self::Fact {
+        (0..self.parameter_slots)
+            .map(|slot| Definition { slot, pc: None })
+            .collect()
+    }
+
+    fn bottom(&self) -> Self::Fact {
+        BTreeSet::new()
+    }
+
+    fn join(&self, fact: &mut Self::Fact, other: &Self::Fact) {
+        fact.extend(other);
+    }
+
+    fn transfer(&self, pc: usize, instruction: &Instruction, fact: &mut Self::Fact) {
+        if let Some(access) = instruction.local_access().filter(|access| access.writes) {
+            let slots = access.index..access.index + access.slots;
+            fact.retain(|definition| !slots.contains(&definition.slot));
+            fact.extend(slots.map(|slot| Definition { slot, pc: Some(pc) }));
+        }
+    }
+}
+
+/// The height of the operand stack in slots, where `long` and `double` take up two
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackHeight {
+    /// No path reaches this point
+    Unreached,
+    Known(usize),
+    /// Paths with different heights meet, or the stack underflows
+    Conflict,
+}
+
+/// The height of the operand stack before and after each instruction, found forwards
+#[derive(Debug, Clone, Default)]
+pub struct StackHeights {
+    /// The slots popped and then pushed by each instruction, by `pc`
+    effects: HashMap<usize, (usize, usize)>,
+}
+
+impl StackHeights {
+    /// Look up the stack effect of every instruction in `cfg`, which needs the descriptors in
+    /// `constant_pool` for field and method instructions
+    pub fn new(cfg: &ControlFlowGraph, constant_pool: &ConstantPool) -> anyhow::Result<Self> {
+        let effects = cfg
+            .blocks()
+            .iter()
+            .flat_map(|block| &block.instructions)
+            .map(|(pc, instruction)| Ok((*pc, instruction.stack_effect(constant_pool)?)))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { effects })
+    }
+}
+
+impl Analysis for StackHeights {
+    type Fact = StackHeight;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self) -> Self::Fact {
+        StackHeight::Known(0)
+    }
+
+    fn bottom(&self) -> Self::Fact {
+        StackHeight::Unreached
+    }
+
+    fn join(&self, fact: &mut Self::Fact, other: &Self::Fact) {
+        *fact = match (*fact, *other) {
+            (StackHeight::Unreached, other) => other,
+            (fact, StackHeight::Unreached) => fact,
+            (StackHeight::Known(a), StackHeight::Known(b)) if a == b => StackHeight::Known(a),
+            _ => StackHeight::Conflict,
+        };
+    }
+
+    fn transfer(&self, pc: usize, _instruction: &Instruction, fact: &mut Self::Fact) {
+        if let StackHeight::Known(height) = *fact {
+            let (pops, pushes) = self.effects[&pc];
+            *fact = match height.checked_sub(pops) {
+                Some(height) => StackHeight::Known(height + pushes),
+                None => StackHeight::Conflict,
+            };
+        }
+    }
+
+    /// The stack is cleared and the exception pushed
+    fn exception(&self, fact: &Self::Fact) -> Self::Fact {
+        match fact {
+            StackHeight::Unreached => StackHeight::Unreached,
+            _ => StackHeight::Known(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        assembler::{Assembler, Jump},
+        builder::ConstantPoolBuilder,
+        test::stdlib_classes,
+        ClassFile,
+    };
+    use std::fs;
+
+    #[test]
+    fn switch_and_handler() {
+        let mut pool = ConstantPoolBuilder::new();
+        let exception = pool.class("java/lang/Exception").unwrap();
+        let pool = pool.build();
+
+        // static int f(int x) {
+        //     int y;
+        //     try { switch (x) { case 1: y = 10; break; default: y = x / 0; } }
+        //     catch (Exception e) { y = x; }
+        //     return y;
+        // }
+        let mut asm = Assembler::new(1);
+        let [start, one, default, end, handler, ret] = [(); 6].map(|_| asm.new_label());
+        asm.bind(start).push(Instruction::Iload0);
+        asm.lookupswitch(default, vec![(1, one)]);
+        asm.bind(one)
+            .push(Instruction::Bipush(10))
+            .push(Instruction::Istore1);
+        asm.jump(Jump::Goto, end);
+        asm.bind(default)
+            .push(Instruction::Iload0)
+            .push(Instruction::Iconst0);
+        asm.push(Instruction::Idiv).push(Instruction::Istore1);
+        asm.bind(end).jump(Jump::Goto, ret);
+        asm.bind(handler)
+            .push(Instruction::Pop)
+            .push(Instruction::Iload0);
+        asm.push(Instruction::Istore1);
+        asm.bind(ret)
+            .push(Instruction::Iload1)
+            .push(Instruction::Ireturn);
+        asm.exception_handler(start, end, handler, exception);
+        let code = asm.assemble(&pool).unwrap();
+
+        let cfg = ControlFlowGraph::new(&code.code, &code.exception_table).unwrap();
+        // entry, case 1, default, goto ret, handler, return
+        assert_eq!(cfg.blocks().len(), 6);
+        let successors = |b: usize| {
+            let mut edges: Vec<_> = cfg.successors(b).map(|e| (e.to, e.kind)).collect();
+            edges.sort_by_key(|(to, _)| *to);
+            edges
+        };
+        let catch = EdgeKind::Exception {
+            catch_type: exception as u16,
+        };
+        assert_eq!(
+            successors(0),
+            [(1, EdgeKind::Switch), (2, EdgeKind::Switch), (4, catch)]
+        );
+        assert_eq!(successors(1), [(3, EdgeKind::Branch), (4, catch)]);
+        assert_eq!(successors(2), [(3, EdgeKind::FallThrough), (4, catch)]);
+        assert_eq!(successors(3), [(5, EdgeKind::Branch)]);
+        assert_eq!(successors(4), [(5, EdgeKind::FallThrough)]);
+        assert!(successors(5).is_empty());
+        assert_eq!(cfg.predecessors(5).count(), 2);
+
+        // x is needed by the handler so it stays live through the try block, y is always written
+        // before it is read
+        let live = solve(&cfg, &Liveness);
+        assert_eq!(*live.entry(0), BTreeSet::from([0]));
+        assert_eq!(*live.entry(1), BTreeSet::from([0]));
+        assert_eq!(*live.entry(3), BTreeSet::from([1]));
+        assert_eq!(*live.entry(5), BTreeSet::from([1]));
+
+        let reaching = solve(&cfg, &ReachingDefinitions::new(1));
+        let ret_pc = cfg.blocks()[5].start;
+        let writes: BTreeSet<_> = reaching
+            .before(ret_pc)
+            .unwrap()
+            .iter()
+            .filter(|d| d.slot == 1)
+            .map(|d| d.pc.unwrap())
+            .collect();
+        assert_eq!(writes.len(), 3);
+        // only the write in case 1 can be followed by an instruction that throws
+        let case_one = cfg.blocks()[1].instructions[1].0;
+        assert_eq!(
+            *reaching.entry(4),
+            BTreeSet::from([
+                Definition { slot: 0, pc: None },
+                Definition {
+                    slot: 1,
+                    pc: Some(case_one)
+                }
+            ])
+        );
+        assert_eq!(cfg.block_at(case_one), Some(1));
+
+        let heights = solve(&cfg, &StackHeights::new(&cfg, &pool).unwrap());
+        assert_eq!(*heights.entry(4), StackHeight::Known(1));
+        assert_eq!(*heights.entry(5), StackHeight::Known(0));
+        assert_eq!(heights.after(ret_pc), Some(&StackHeight::Known(1)));
+    }
+
+    #[test]
+    fn stdlib() {
+        let Some(paths) = stdlib_classes("java.base") else {
+            return;
+        };
+        for path in paths {
+            let bytes = fs::read(&path).unwrap();
+            let class = ClassFile::parse(&bytes).unwrap();
+            for method in class.methods() {
+                let method = method.unwrap();
+                let Some(Attribute::Code { max_stack, .. }) = method.code().unwrap() else {
+                    continue;
+                };
+                let context = || format!("{} {}{}", path.display(), method.name, method.descriptor);
+                let cfg = ControlFlowGraph::from_method(&method)
+                    .unwrap_or_else(|e| panic!("{}: {}", context(), e))
+                    .unwrap();
+                let heights = StackHeights::new(&cfg, &class.constant_pool).unwrap();
+                let solution = solve(&cfg, &heights);
+                for block in 0..cfg.blocks().len() {
+                    match *solution.exit(block) {
+                        StackHeight::Conflict => panic!("{}: stack height conflict", context()),
+                        StackHeight::Known(height) => assert!(
+                            height <= usize::from(max_stack),
+                            "{}: exceeds max_stack",
+                            context()
+                        ),
+                        StackHeight::Unreached => {}
+                    }
+                }
+            }
+        }
+    }
+}
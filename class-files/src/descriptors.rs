@@ -1,8 +1,15 @@
-use std::str::{Chars, FromStr};
+use std::{
+    fmt,
+    str::{Chars, FromStr},
+};
 
 use anyhow::{bail, ensure, Context};
 
+/// The most dimensions an array type can have
+pub const MAX_DIMENSIONS: usize = 255;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum FieldType {
     /// B
     Byte,
@@ -46,42 +53,58 @@ impl FieldType {
             }
             'S' => Self::Short,
             'Z' => Self::Boolean,
-            '[' => Self::ArrReference(Box::new(Self::from_chars(
-                chars.next().context("Invalid format")?,
-                chars,
-            )?)),
+            '[' => {
+                // read the dimensions in a loop, so that a long run of them is rejected rather
+                // than overflowing the stack
+                let mut dimensions = 1;
+                let mut id = chars.next().context("Invalid format")?;
+                while id == '[' {
+                    dimensions += 1;
+                    ensure!(
+                        dimensions <= MAX_DIMENSIONS,
+                        "Invalid format -- more than {} array dimensions",
+                        MAX_DIMENSIONS
+                    );
+                    id = chars.next().context("Invalid format")?;
+                }
+                let mut ty = Self::from_chars(id, chars)?;
+                for _ in 0..dimensions {
+                    ty = Self::ArrReference(Box::new(ty));
+                }
+                ty
+            }
             c => bail!("Invalid type, found: '{}'", c),
         })
     }
+
+    /// The number of local variable or operand stack slots a value of this type takes up
+    pub fn slots(&self) -> usize {
+        match self {
+            Self::Long | Self::Double => 2,
+            _ => 1,
+        }
+    }
 }
 
-impl ToString for FieldType {
-    fn to_string(&self) -> String {
-        let mut s = String::new();
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            FieldType::Byte => s.push('B'),
-            FieldType::Char => s.push('C'),
-            FieldType::Double => s.push('D'),
-            FieldType::Float => s.push('F'),
-            FieldType::Int => s.push('I'),
-            FieldType::Long => s.push('J'),
-            FieldType::ObjReference(or) => {
-                s.push('L');
-                s.push_str(&or);
-                s.push(';');
-            }
-            FieldType::Short => s.push('S'),
-            FieldType::Boolean => s.push('Z'),
-            FieldType::ArrReference(ar) => {
-                s.push('[');
-                s.push_str(&ar.to_string());
-            }
+            FieldType::Byte => write!(f, "B"),
+            FieldType::Char => write!(f, "C"),
+            FieldType::Double => write!(f, "D"),
+            FieldType::Float => write!(f, "F"),
+            FieldType::Int => write!(f, "I"),
+            FieldType::Long => write!(f, "J"),
+            FieldType::ObjReference(or) => write!(f, "L{};", or),
+            FieldType::Short => write!(f, "S"),
+            FieldType::Boolean => write!(f, "Z"),
+            FieldType::ArrReference(ar) => write!(f, "[{}", ar),
         }
-        s
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ReturnDescriptor {
     FieldType(FieldType),
     Void,
@@ -95,16 +118,17 @@ impl ReturnDescriptor {
         })
     }
 }
-impl ToString for ReturnDescriptor {
-    fn to_string(&self) -> String {
+impl fmt::Display for ReturnDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            ReturnDescriptor::FieldType(ft) => return ft.to_string(),
-            ReturnDescriptor::Void => "V".into(),
+            ReturnDescriptor::FieldType(ft) => ft.fmt(f),
+            ReturnDescriptor::Void => write!(f, "V"),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MethodDescriptor {
     pub params: Vec<FieldType>,
     pub return_value: ReturnDescriptor,
@@ -127,7 +151,6 @@ impl FromStr for MethodDescriptor {
                 .next()
                 .context("Invalid format -- expected more chars")?;
         }
-        dbg!(&params);
         id = chars
             .next()
             .context("Invalid format -- expected more chars")?;
@@ -139,16 +162,13 @@ impl FromStr for MethodDescriptor {
     }
 }
 
-impl ToString for MethodDescriptor {
-    fn to_string(&self) -> String {
-        let mut s = String::new();
-        s.push('(');
+impl fmt::Display for MethodDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
         for param in &self.params {
-            s.push_str(&param.to_string());
+            write!(f, "{}", param)?;
         }
-        s.push(')');
-        s.push_str(&self.return_value.to_string());
-        s
+        write!(f, "){}", self.return_value)
     }
 }
 
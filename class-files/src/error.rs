@@ -0,0 +1,90 @@
+use std::fmt;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors produced while resolving the contents of a class file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The class file doesn't start with `0xCAFEBABE`
+    InvalidMagic { magic: u32 },
+    /// Tried to read past the end of the data, `offset` is where the read started
+    Truncated { offset: usize },
+    /// There are `remaining` bytes left over after the end of the class file
+    TrailingData { remaining: usize },
+    /// Constant pool index that is `0` or past the end of the constant pool
+    BadConstantIndex { index: usize },
+    /// Constant pool entry at `index` is not of the expected kind
+    WrongConstantKind {
+        index: usize,
+        expected: &'static str,
+        found: &'static str,
+    },
+    /// The length of an attribute does not match the length of its contents
+    BadAttributeLength { expected: usize, actual: usize },
+    /// Access flags that contain bits which are not valid in this position
+    InvalidAccessFlags { flags: u16 },
+    /// Utf8 constant at `index` could not be decoded into a string
+    InvalidUtf8 { index: usize },
+    /// A tag byte, which says how the data that follows it should be read, has an unknown value
+    InvalidTag { kind: &'static str, tag: u8 },
+    /// Structures are nested too deeply to be parsed
+    TooDeep,
+    /// The instruction at `pc` has operands which are not allowed
+    InvalidInstruction { pc: usize, reason: &'static str },
+    /// An error that occurred while parsing the attribute `name`, `offset` bytes into its info
+    Attribute {
+        name: String,
+        offset: usize,
+        source: Box<Error>,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidMagic { magic } => write!(f, "Invalid magic value: 0x{:x}", magic),
+            Error::Truncated { offset } => write!(f, "Unexpected end of data at offset {}", offset),
+            Error::TrailingData { remaining } => {
+                write!(f, "{} bytes remaining in file", remaining)
+            }
+            Error::BadConstantIndex { index } => {
+                write!(f, "Invalid constant pool index: {}", index)
+            }
+            Error::WrongConstantKind {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Expected {} constant at index {}, found {}",
+                expected, index, found
+            ),
+            Error::BadAttributeLength { expected, actual } => write!(
+                f,
+                "Attribute length is {} but its contents are {} bytes",
+                expected, actual
+            ),
+            Error::InvalidAccessFlags { flags } => write!(f, "Invalid access flags: 0x{:x}", flags),
+            Error::InvalidUtf8 { index } => {
+                write!(f, "Utf8 constant at index {} is not valid unicode", index)
+            }
+            Error::InvalidTag { kind, tag } => write!(f, "Invalid {} tag: {}", kind, tag),
+            Error::TooDeep => write!(f, "Structures are nested too deeply"),
+            Error::InvalidInstruction { pc, reason } => {
+                write!(f, "Invalid instruction at pc {}: {}", pc, reason)
+            }
+            Error::Attribute { name, offset, .. } => {
+                write!(f, "Invalid {} attribute at offset {}", name, offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Attribute { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,895 @@
+//! Format checking, see <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.8>
+//!
+//! Parsing a class file only checks what is needed to read it. [`ClassFile::check_format`] checks
+//! the rest of the static constraints that don't need to look at the code of methods: the
+//! version, access flags, the constants in the pool, names and descriptors, and which attributes
+//! may appear where. Every violation is reported rather than just the first.
+
+use std::{collections::HashSet, fmt, str::FromStr};
+
+use crate::{
+    constant_pool::{Constant, Loadable, MemberKind, ReferenceKind},
+    descriptors::{FieldType, MethodDescriptor, ReturnDescriptor, MAX_DIMENSIONS},
+    error::Error,
+    types::{
+        raw::RawAttribute, resolved::Attribute, ClassAccessFlags, FieldAccessFlags,
+        MethodAccessFlags,
+    },
+    ClassFile,
+};
+
+/// The oldest major version that can be loaded, from JDK 1.0.2
+pub const MIN_MAJOR_VERSION: u16 = 45;
+/// The newest major version that can be loaded, from Java SE 21
+pub const MAX_MAJOR_VERSION: u16 = 65;
+/// The minor version of class files which use preview features of [`MAX_MAJOR_VERSION`]
+pub const PREVIEW_MINOR_VERSION: u16 = 65535;
+
+/// A way in which a class file is malformed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatErrorKind {
+    /// The version isn't one that can be loaded, an `UnsupportedClassVersionError` rather than a
+    /// `ClassFormatError`
+    UnsupportedVersion { major: u16, minor: u16 },
+    /// The access flags are not allowed together, or not allowed here
+    Flags { flags: u16, reason: &'static str },
+    /// A constant can't be resolved
+    Constant(Error),
+    /// A constant of this kind is not allowed in this class file
+    ConstantNotAllowed { kind: &'static str },
+    /// A class, field or method name is not valid
+    Name(String),
+    /// A field or method descriptor is not valid
+    Descriptor(String),
+    /// There is more than one field or method with the same name and descriptor
+    Duplicate,
+    /// An attribute can't be parsed
+    Attribute(Error),
+    /// The attribute `name` was only added in class files of version `since`
+    AttributeVersion { name: String, since: u16 },
+    /// The attribute `name` is not allowed here
+    AttributeLocation { name: String },
+    /// Any other rule is broken
+    Invalid(&'static str),
+}
+
+impl fmt::Display for FormatErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatErrorKind::UnsupportedVersion { major, minor } => write!(
+                f,
+                "Unsupported class file version {}.{}, versions {}.0 to {}.0 are supported",
+                major, minor, MIN_MAJOR_VERSION, MAX_MAJOR_VERSION
+            ),
+            FormatErrorKind::Flags { flags, reason } => {
+                write!(f, "Invalid access flags 0x{:04x}: {}", flags, reason)
+            }
+            FormatErrorKind::Constant(e) => write!(f, "{}", e),
+            FormatErrorKind::ConstantNotAllowed { kind } => {
+                write!(f, "{} constants are not allowed here", kind)
+            }
+            FormatErrorKind::Name(name) => write!(f, "Invalid name: {:?}", name),
+            FormatErrorKind::Descriptor(descriptor) => {
+                write!(f, "Invalid descriptor: {:?}", descriptor)
+            }
+            FormatErrorKind::Duplicate => write!(f, "Duplicate name and descriptor"),
+            FormatErrorKind::Attribute(e) => match e {
+                Error::Attribute { source, .. } => write!(f, "{}: {}", e, source),
+                e => write!(f, "{}", e),
+            },
+            FormatErrorKind::AttributeVersion { name, since } => write!(
+                f,
+                "{} attributes are only allowed from version {}.0",
+                name, since
+            ),
+            FormatErrorKind::AttributeLocation { name } => {
+                write!(f, "{} attributes are not allowed here", name)
+            }
+            FormatErrorKind::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// A class file broke one of the rules checked by [`ClassFile::check_format`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatError {
+    /// Where the problem is, e.g. `class`, `constant #12` or `method main([Ljava/lang/String;)V`
+    pub location: String,
+    pub kind: FormatErrorKind,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.kind)
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// Where an attribute is found, see table 4.7-C of the JVMS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Location {
+    Class,
+    Field,
+    Method,
+    Code,
+}
+
+/// The first major version which defines the attribute `name` and where it may appear, or `None`
+/// for attributes which aren't defined by the JVMS
+fn attribute_rule(name: &str) -> Option<(u16, &'static [Location])> {
+    use Location::*;
+    Some(match name {
+        "ConstantValue" => (45, &[Field]),
+        "Code" => (45, &[Method]),
+        "StackMapTable" => (50, &[Code]),
+        "Exceptions" => (45, &[Method]),
+        "InnerClasses" => (45, &[Class]),
+        "EnclosingMethod" => (49, &[Class]),
+        "Synthetic" | "Deprecated" => (45, &[Class, Field, Method]),
+        "Signature" => (49, &[Class, Field, Method]),
+        "SourceFile" => (45, &[Class]),
+        "SourceDebugExtension" => (49, &[Class]),
+        "LineNumberTable" | "LocalVariableTable" => (45, &[Code]),
+        "LocalVariableTypeTable" => (49, &[Code]),
+        "RuntimeVisibleAnnotations" | "RuntimeInvisibleAnnotations" => {
+            (49, &[Class, Field, Method])
+        }
+        "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+            (49, &[Method])
+        }
+        "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+            (52, &[Class, Field, Method, Code])
+        }
+        "AnnotationDefault" => (49, &[Method]),
+        "BootstrapMethods" => (51, &[Class]),
+        "MethodParameters" => (52, &[Method]),
+        "Module" | "ModulePackages" | "ModuleMainClass" => (53, &[Class]),
+        "NestHost" | "NestMembers" => (55, &[Class]),
+        "Record" => (60, &[Class]),
+        "PermittedSubclasses" => (61, &[Class]),
+        _ => return None,
+    })
+}
+
+/// An unqualified name, such as a field or method name, see
+/// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.2.2>
+fn is_unqualified_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(['.', ';', '[', '/'])
+}
+
+fn is_method_name(name: &str) -> bool {
+    name == "<init>"
+        || name == "<clinit>"
+        || is_unqualified_name(name) && !name.contains(['<', '>'])
+}
+
+/// A class name in its internal form, e.g. `java/lang/Object`, see
+/// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.2.1>
+fn is_class_name(name: &str) -> bool {
+    name.split('/').all(is_unqualified_name)
+}
+
+/// A class name, or the descriptor of an array type which is how Class constants refer to arrays
+fn is_class_or_array_name(name: &str) -> bool {
+    if name.starts_with('[') {
+        field_descriptor(name).is_some()
+    } else {
+        is_class_name(name)
+    }
+}
+
+fn is_valid_type(ty: &FieldType, dimensions: usize) -> bool {
+    match ty {
+        FieldType::ArrReference(ty) => {
+            dimensions < MAX_DIMENSIONS && is_valid_type(ty, dimensions + 1)
+        }
+        FieldType::ObjReference(name) => is_class_name(name),
+        _ => true,
+    }
+}
+
+/// Parse `descriptor` if it is a valid field descriptor, unlike [`FieldType::from_chars`] this
+/// checks that there is nothing after it and that class names are valid
+fn field_descriptor(descriptor: &str) -> Option<FieldType> {
+    let mut chars = descriptor.chars();
+    let ty = FieldType::from_chars(chars.next()?, &mut chars).ok()?;
+    (chars.as_str().is_empty() && is_valid_type(&ty, 0)).then_some(ty)
+}
+
+/// Parse `descriptor` if it is a valid method descriptor, with no more than 255 slots of
+/// parameters
+fn method_descriptor(descriptor: &str) -> Option<MethodDescriptor> {
+    let parsed = MethodDescriptor::from_str(descriptor).ok()?;
+    let valid = parsed.to_string() == descriptor
+        && parsed.params.iter().all(|p| is_valid_type(p, 0))
+        && match &parsed.return_value {
+            ReturnDescriptor::FieldType(ty) => is_valid_type(ty, 0),
+            ReturnDescriptor::Void => true,
+        }
+        && parsed.params.iter().map(FieldType::slots).sum::<usize>() <= 255;
+    valid.then_some(parsed)
+}
+
+struct Checker<'c, 'a> {
+    class: &'c ClassFile<'a>,
+    major: u16,
+    is_interface: bool,
+    errors: Vec<FormatError>,
+}
+
+impl<'c, 'a> Checker<'c, 'a> {
+    fn error(&mut self, location: impl fmt::Display, kind: FormatErrorKind) {
+        self.errors.push(FormatError {
+            location: location.to_string(),
+            kind,
+        });
+    }
+
+    fn flags(&mut self, location: impl fmt::Display, flags: u16, reason: &'static str) {
+        self.error(location, FormatErrorKind::Flags { flags, reason });
+    }
+
+    fn check(mut self) -> Vec<FormatError> {
+        let (major, minor) = self.class.version;
+        // from version 56 the minor version is only used to mark class files which use preview
+        // features, and those can only be loaded by the release they are from
+        if !(MIN_MAJOR_VERSION..=MAX_MAJOR_VERSION).contains(&major)
+            || major >= 56
+                && minor != 0
+                && !(major == MAX_MAJOR_VERSION && minor == PREVIEW_MINOR_VERSION)
+        {
+            // the rules for other versions aren't known
+            self.error(
+                "class",
+                FormatErrorKind::UnsupportedVersion { major, minor },
+            );
+            return self.errors;
+        }
+
+        self.constant_pool();
+        self.class_flags();
+        self.class_names();
+        self.fields();
+        self.methods();
+        self.attributes("class", &self.class.attributes, Location::Class);
+        self.errors
+    }
+
+    fn constant_pool(&mut self) {
+        let pool = &self.class.constant_pool;
+        let is_module = self.class.access_flags.contains(ClassAccessFlags::MODULE);
+        for (index, constant) in pool.iter() {
+            let location = format!("constant #{}", index);
+            let kind = pool.get(index).map(|c| c.kind()).unwrap_or_default();
+            let since = match kind {
+                "MethodHandle" | "MethodType" | "InvokeDynamic" => 51,
+                "Module" | "Package" => 53,
+                "Dynamic" => 55,
+                _ => MIN_MAJOR_VERSION,
+            };
+            if self.major < since || matches!(kind, "Module" | "Package") && !is_module {
+                self.error(&location, FormatErrorKind::ConstantNotAllowed { kind });
+            }
+
+            let constant = match constant {
+                Ok(constant) => constant,
+                Err(e) => {
+                    self.error(location, FormatErrorKind::Constant(e));
+                    continue;
+                }
+            };
+            match constant {
+                Constant::Loadable(Loadable::Class(name)) if !is_class_or_array_name(name) => {
+                    self.error(location, FormatErrorKind::Name(name.into()));
+                }
+                Constant::Loadable(Loadable::MethodType(descriptor))
+                    if method_descriptor(descriptor).is_none() =>
+                {
+                    self.error(location, FormatErrorKind::Descriptor(descriptor.into()));
+                }
+                Constant::Loadable(Loadable::Dynamic(dynamic)) => {
+                    self.member(
+                        &location,
+                        MemberKind::Field,
+                        dynamic.name,
+                        dynamic.descriptor,
+                    );
+                }
+                Constant::InvokeDynamic(dynamic) => {
+                    self.member(
+                        &location,
+                        MemberKind::Method,
+                        dynamic.name,
+                        dynamic.descriptor,
+                    );
+                    if dynamic.name.starts_with('<') {
+                        self.error(location, FormatErrorKind::Name(dynamic.name.into()));
+                    }
+                }
+                Constant::MemberRef(member) => {
+                    if !is_class_or_array_name(member.class) {
+                        self.error(&location, FormatErrorKind::Name(member.class.into()));
+                    }
+                    self.member(&location, member.kind, member.name, member.descriptor);
+                    if member.name == "<clinit>" {
+                        self.error(location, FormatErrorKind::Name(member.name.into()));
+                    }
+                }
+                Constant::NameAndType(nt) => {
+                    if method_descriptor(nt.descriptor).is_none()
+                        && field_descriptor(nt.descriptor).is_none()
+                    {
+                        self.error(&location, FormatErrorKind::Descriptor(nt.descriptor.into()));
+                    }
+                    if !is_method_name(nt.name) {
+                        self.error(location, FormatErrorKind::Name(nt.name.into()));
+                    }
+                }
+                Constant::Loadable(Loadable::MethodHandle(handle)) => {
+                    let member = handle.reference;
+                    let kind_matches = match handle.kind {
+                        ReferenceKind::GetField
+                        | ReferenceKind::GetStatic
+                        | ReferenceKind::PutField
+                        | ReferenceKind::PutStatic => member.kind == MemberKind::Field,
+                        ReferenceKind::InvokeVirtual | ReferenceKind::NewInvokeSpecial => {
+                            member.kind == MemberKind::Method
+                        }
+                        ReferenceKind::InvokeStatic | ReferenceKind::InvokeSpecial => {
+                            member.kind == MemberKind::Method
+                                || member.kind == MemberKind::InterfaceMethod && self.major >= 52
+                        }
+                        ReferenceKind::InvokeInterface => {
+                            member.kind == MemberKind::InterfaceMethod
+                        }
+                    };
+                    if !kind_matches {
+                        self.error(
+                            &location,
+                            FormatErrorKind::Invalid(
+                                "Method handle refers to the wrong kind of member",
+                            ),
+                        );
+                    }
+                    let is_init = member.name == "<init>";
+                    if is_init != (handle.kind == ReferenceKind::NewInvokeSpecial)
+                        && member.kind != MemberKind::Field
+                    {
+                        self.error(
+                            location,
+                            FormatErrorKind::Invalid(
+                                "Only newInvokeSpecial method handles may refer to <init>",
+                            ),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Check the name and descriptor of a field or method which is referred to by a constant
+    fn member(&mut self, location: &str, kind: MemberKind, name: &str, descriptor: &str) {
+        match kind {
+            MemberKind::Field => {
+                if !is_unqualified_name(name) {
+                    self.error(location, FormatErrorKind::Name(name.into()));
+                }
+                if field_descriptor(descriptor).is_none() {
+                    self.error(location, FormatErrorKind::Descriptor(descriptor.into()));
+                }
+            }
+            MemberKind::Method | MemberKind::InterfaceMethod => {
+                if !is_method_name(name) {
+                    self.error(location, FormatErrorKind::Name(name.into()));
+                }
+                match method_descriptor(descriptor) {
+                    None => self.error(location, FormatErrorKind::Descriptor(descriptor.into())),
+                    Some(d)
+                        if name.starts_with('<') && d.return_value != ReturnDescriptor::Void =>
+                    {
+                        self.error(location, FormatErrorKind::Descriptor(descriptor.into()))
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    fn class_flags(&mut self) {
+        let flags = self.class.access_flags;
+        let bits = flags.bits();
+        if flags.contains(ClassAccessFlags::MODULE) && self.major >= 53 {
+            if flags.bits() != ClassAccessFlags::MODULE.bits() {
+                self.flags("class", bits, "Modules can't have any other flags");
+            }
+            if !self.class.interfaces.is_empty()
+                || !self.class.fields.is_empty()
+                || !self.class.methods.is_empty()
+            {
+                self.error(
+                    "class",
+                    FormatErrorKind::Invalid("Modules can't have interfaces, fields or methods"),
+                );
+            }
+            if self.class.super_class != 0 {
+                self.error(
+                    "class",
+                    FormatErrorKind::Invalid("Modules can't have a super class"),
+                );
+            }
+        } else if flags.contains(ClassAccessFlags::INTERFACE) {
+            if !flags.contains(ClassAccessFlags::ABSTRACT) {
+                self.flags("class", bits, "Interfaces must be abstract");
+            }
+            if flags.intersects(
+                ClassAccessFlags::FINAL
+                    | ClassAccessFlags::SUPER
+                    | ClassAccessFlags::ENUM
+                    | ClassAccessFlags::MODULE,
+            ) {
+                self.flags(
+                    "class",
+                    bits,
+                    "Interfaces can't be final, super, enums or modules",
+                );
+            }
+        } else {
+            if flags.contains(ClassAccessFlags::ANNOTATION) {
+                self.flags("class", bits, "Annotations must be interfaces");
+            }
+            if flags.contains(ClassAccessFlags::FINAL | ClassAccessFlags::ABSTRACT) {
+                self.flags("class", bits, "Classes can't be both final and abstract");
+            }
+        }
+    }
+
+    fn class_names(&mut self) {
+        let class = self.class;
+        let this_class = match class.this_class() {
+            Ok(name) => name,
+            Err(e) => {
+                self.error("this_class", FormatErrorKind::Constant(e));
+                return;
+            }
+        };
+        if class.access_flags.contains(ClassAccessFlags::MODULE) && self.major >= 53 {
+            if this_class != "module-info" {
+                self.error("this_class", FormatErrorKind::Name(this_class.into()));
+            }
+            return;
+        }
+        if !is_class_name(this_class) {
+            self.error("this_class", FormatErrorKind::Name(this_class.into()));
+        }
+
+        match class.super_class() {
+            Ok(None) if this_class != "java/lang/Object" => self.error(
+                "super_class",
+                FormatErrorKind::Invalid("Only java/lang/Object has no super class"),
+            ),
+            Ok(Some(name)) if !is_class_name(name) => {
+                self.error("super_class", FormatErrorKind::Name(name.into()))
+            }
+            Ok(Some(name)) if self.is_interface && name != "java/lang/Object" => self.error(
+                "super_class",
+                FormatErrorKind::Invalid(
+                    "The super class of an interface must be java/lang/Object",
+                ),
+            ),
+            Err(e) => self.error("super_class", FormatErrorKind::Constant(e)),
+            _ => {}
+        }
+
+        for interface in class.interfaces() {
+            match interface {
+                Ok(name) if !is_class_name(name) => {
+                    self.error("interfaces", FormatErrorKind::Name(name.into()))
+                }
+                Err(e) => self.error("interfaces", FormatErrorKind::Constant(e)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Look up the name and descriptor of a field or method, reporting an error if they can't be
+    fn name_and_descriptor(
+        &mut self,
+        kind: &str,
+        index: usize,
+        name_index: usize,
+        descriptor_index: usize,
+    ) -> Option<(&'c str, &'c str)> {
+        let pool = &self.class.constant_pool;
+        match (pool.utf8(name_index), pool.utf8(descriptor_index)) {
+            (Ok(name), Ok(descriptor)) => Some((name, descriptor)),
+            (Err(e), _) | (_, Err(e)) => {
+                self.error(format!("{} #{}", kind, index), FormatErrorKind::Constant(e));
+                None
+            }
+        }
+    }
+
+    fn fields(&mut self) {
+        let mut seen = HashSet::new();
+        for (i, field) in self.class.fields.iter().enumerate() {
+            let Some((name, descriptor)) =
+                self.name_and_descriptor("field", i, field.name_index, field.descriptor_index)
+            else {
+                continue;
+            };
+            let location = format!("field {}:{}", name, descriptor);
+
+            if !is_unqualified_name(name) {
+                self.error(&location, FormatErrorKind::Name(name.into()));
+            }
+            let ty = field_descriptor(descriptor);
+            if ty.is_none() {
+                self.error(&location, FormatErrorKind::Descriptor(descriptor.into()));
+            }
+            if !seen.insert((name, descriptor)) {
+                self.error(&location, FormatErrorKind::Duplicate);
+            }
+
+            let flags = field.access_flags;
+            let bits = flags.bits();
+            let access =
+                FieldAccessFlags::PUBLIC | FieldAccessFlags::PRIVATE | FieldAccessFlags::PROTECTED;
+            if (flags & access).bits().count_ones() > 1 {
+                self.flags(
+                    &location,
+                    bits,
+                    "At most one of public, private and protected",
+                );
+            }
+            if flags.contains(FieldAccessFlags::FINAL | FieldAccessFlags::VOLATILE) {
+                self.flags(&location, bits, "Fields can't be both final and volatile");
+            }
+            let interface_flags =
+                FieldAccessFlags::PUBLIC | FieldAccessFlags::STATIC | FieldAccessFlags::FINAL;
+            if self.is_interface
+                && (!flags.contains(interface_flags)
+                    || !(interface_flags | FieldAccessFlags::SYNTHETIC).contains(flags))
+            {
+                self.flags(
+                    &location,
+                    bits,
+                    "Interface fields must be public, static and final",
+                );
+            }
+
+            self.attributes(&location, &field.attributes, Location::Field);
+
+            // the type of a ConstantValue has to match the field
+            let constant_value = field.attributes.iter().find_map(|a| {
+                match Attribute::from_raw(a, &self.class.constant_pool) {
+                    Ok(Attribute::ConstantValue { value }) => Some(value),
+                    _ => None,
+                }
+            });
+            if let (Some(value), Some(ty)) = (constant_value, ty) {
+                let matches = match value {
+                    Loadable::Integer(_) => matches!(
+                        ty,
+                        FieldType::Int
+                            | FieldType::Short
+                            | FieldType::Char
+                            | FieldType::Byte
+                            | FieldType::Boolean
+                    ),
+                    Loadable::Float(_) => ty == FieldType::Float,
+                    Loadable::Long(_) => ty == FieldType::Long,
+                    Loadable::Double(_) => ty == FieldType::Double,
+                    Loadable::String(_) | Loadable::RawString(_) => {
+                        ty == FieldType::ObjReference("java/lang/String".into())
+                    }
+                    _ => false,
+                };
+                if !matches {
+                    self.error(
+                        location,
+                        FormatErrorKind::Invalid("ConstantValue doesn't match the field's type"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn methods(&mut self) {
+        let mut seen = HashSet::new();
+        for (i, method) in self.class.methods.iter().enumerate() {
+            let Some((name, descriptor)) =
+                self.name_and_descriptor("method", i, method.name_index, method.descriptor_index)
+            else {
+                continue;
+            };
+            let location = format!("method {}{}", name, descriptor);
+
+            if !is_method_name(name) {
+                self.error(&location, FormatErrorKind::Name(name.into()));
+            }
+            match method_descriptor(descriptor) {
+                Some(d) if !name.starts_with('<') || d.return_value == ReturnDescriptor::Void => {}
+                _ => self.error(&location, FormatErrorKind::Descriptor(descriptor.into())),
+            }
+            if !seen.insert((name, descriptor)) {
+                self.error(&location, FormatErrorKind::Duplicate);
+            }
+
+            let flags = method.access_flags;
+            // the flags of class initialisation methods are ignored
+            if name != "<clinit>" {
+                self.method_flags(&location, name, flags);
+            }
+
+            self.attributes(&location, &method.attributes, Location::Method);
+
+            let code_count = method
+                .attributes
+                .iter()
+                .filter(|a| self.class.constant_pool.utf8(a.attribute_name_index) == Ok("Code"))
+                .count();
+            let has_body =
+                !flags.intersects(MethodAccessFlags::ABSTRACT | MethodAccessFlags::NATIVE);
+            match (has_body, code_count) {
+                (true, 1) | (false, 0) => {}
+                (true, 0) => {
+                    self.error(location, FormatErrorKind::Invalid("Missing Code attribute"))
+                }
+                (true, _) => self.error(
+                    location,
+                    FormatErrorKind::Invalid("More than one Code attribute"),
+                ),
+                (false, _) => self.error(
+                    location,
+                    FormatErrorKind::Invalid("Abstract and native methods can't have code"),
+                ),
+            }
+        }
+    }
+
+    fn method_flags(&mut self, location: &str, name: &str, flags: MethodAccessFlags) {
+        let bits = flags.bits();
+        let access =
+            MethodAccessFlags::PUBLIC | MethodAccessFlags::PRIVATE | MethodAccessFlags::PROTECTED;
+        if (flags & access).bits().count_ones() > 1 {
+            self.flags(
+                location,
+                bits,
+                "At most one of public, private and protected",
+            );
+        }
+
+        if self.is_interface {
+            if self.major < 52 {
+                let allowed = MethodAccessFlags::PUBLIC
+                    | MethodAccessFlags::ABSTRACT
+                    | MethodAccessFlags::VARARGS
+                    | MethodAccessFlags::BRIDGE
+                    | MethodAccessFlags::SYNTHETIC;
+                if !flags.contains(MethodAccessFlags::PUBLIC | MethodAccessFlags::ABSTRACT)
+                    || !allowed.contains(flags)
+                {
+                    self.flags(
+                        location,
+                        bits,
+                        "Interface methods must be public and abstract",
+                    );
+                }
+            } else {
+                if flags.intersects(
+                    MethodAccessFlags::PROTECTED
+                        | MethodAccessFlags::FINAL
+                        | MethodAccessFlags::SYNCHRONIZED
+                        | MethodAccessFlags::NATIVE,
+                ) {
+                    self.flags(
+                        location,
+                        bits,
+                        "Interface methods can't be protected, final, synchronized or native",
+                    );
+                }
+                if (flags & (MethodAccessFlags::PUBLIC | MethodAccessFlags::PRIVATE)).is_empty() {
+                    self.flags(
+                        location,
+                        bits,
+                        "Interface methods must be public or private",
+                    );
+                }
+            }
+            if name == "<init>" {
+                self.error(
+                    location,
+                    FormatErrorKind::Invalid("Interfaces can't have constructors"),
+                );
+            }
+        }
+
+        // strictfp was only a flag from 46 until it became the default in 61
+        let strict_allowed = (46..61).contains(&self.major);
+        if flags.contains(MethodAccessFlags::ABSTRACT) {
+            let mut disallowed = MethodAccessFlags::PRIVATE
+                | MethodAccessFlags::STATIC
+                | MethodAccessFlags::FINAL
+                | MethodAccessFlags::SYNCHRONIZED
+                | MethodAccessFlags::NATIVE;
+            if strict_allowed {
+                disallowed |= MethodAccessFlags::STRICT;
+            }
+            if flags.intersects(disallowed) {
+                self.flags(
+                    location,
+                    bits,
+                    "Abstract methods can't be private, static, final, synchronized, native or strict",
+                );
+            }
+        }
+
+        if name == "<init>" {
+            let allowed = access
+                | MethodAccessFlags::VARARGS
+                | MethodAccessFlags::STRICT
+                | MethodAccessFlags::SYNTHETIC;
+            if !allowed.contains(flags) {
+                self.flags(location, bits, "Invalid flags for a constructor");
+            }
+        }
+    }
+
+    /// Check that each attribute can be parsed, and is allowed in this `location` and version
+    fn attributes(&mut self, location: &str, attributes: &'c [RawAttribute<'a>], kind: Location) {
+        let pool = &self.class.constant_pool;
+        for raw in attributes {
+            let attribute = match Attribute::from_raw(raw, pool) {
+                Ok(attribute) => attribute,
+                Err(e) => {
+                    self.error(location, FormatErrorKind::Attribute(e));
+                    continue;
+                }
+            };
+            self.attribute(location, attribute.name(), kind);
+            if let Attribute::Code { attributes, .. } = &attribute {
+                for nested in attributes {
+                    self.attribute(location, nested.name(), Location::Code);
+                }
+            }
+        }
+    }
+
+    fn attribute(&mut self, location: &str, name: &str, kind: Location) {
+        let Some((since, locations)) = attribute_rule(name) else {
+            return;
+        };
+        if self.major < since {
+            let name = name.into();
+            self.error(location, FormatErrorKind::AttributeVersion { name, since });
+        } else if !locations.contains(&kind) {
+            let name = name.into();
+            self.error(location, FormatErrorKind::AttributeLocation { name });
+        }
+    }
+}
+
+impl ClassFile<'_> {
+    /// Check that the class file is well formed, returning everything that is wrong with it if
+    /// not. See the [module docs](crate::format) for what is checked.
+    pub fn check_format(&self) -> Result<(), Vec<FormatError>> {
+        let checker = Checker {
+            class: self,
+            major: self.version.0,
+            is_interface: self.access_flags.contains(ClassAccessFlags::INTERFACE),
+            errors: Vec::new(),
+        };
+        let errors = checker.check();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::stdlib_classes;
+    use std::fs;
+
+    #[test]
+    fn stdlib() {
+        let Some(paths) = stdlib_classes("java.base") else {
+            return;
+        };
+        let failures: Vec<_> = paths
+            .iter()
+            .filter_map(|path| {
+                let bytes = fs::read(path).unwrap();
+                let class = ClassFile::parse(&bytes).unwrap();
+                class.check_format().err()
+            })
+            .flatten()
+            .collect();
+        assert!(
+            failures.is_empty(),
+            "{} errors, e.g.\n{}",
+            failures.len(),
+            failures
+                .iter()
+                .take(20)
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    #[test]
+    fn violations() {
+        let Some(paths) = stdlib_classes("java.base/java/lang/Object.class") else {
+            return;
+        };
+        let bytes = fs::read(&paths[0]).unwrap();
+        let object = ClassFile::parse(&bytes).unwrap();
+        object.check_format().unwrap();
+
+        let mut class = object.clone();
+        class.version = (MAX_MAJOR_VERSION + 1, 0);
+        assert_eq!(
+            class.check_format().unwrap_err(),
+            [FormatError {
+                location: "class".into(),
+                kind: FormatErrorKind::UnsupportedVersion {
+                    major: MAX_MAJOR_VERSION + 1,
+                    minor: 0
+                }
+            }]
+        );
+
+        // an interface which isn't abstract, whose methods aren't abstract either so it also
+        // reports each of those
+        let mut class = object.clone();
+        class.access_flags = ClassAccessFlags::INTERFACE;
+        class.version = (51, 0);
+        let errors = class.check_format().unwrap_err();
+        assert!(errors.iter().any(|e| e.location == "class"
+            && matches!(
+                e.kind,
+                FormatErrorKind::Flags {
+                    reason: "Interfaces must be abstract",
+                    ..
+                }
+            )));
+        assert!(errors.iter().any(|e| e.location == "method <init>()V"));
+        assert!(errors.iter().any(|e| e.location == "method hashCode()I"));
+
+        // a method which is both abstract and final, and a Code attribute in an old class file
+        let mut class = object.clone();
+        let index = class
+            .methods()
+            .position(|m| m.unwrap().name == "getClass")
+            .unwrap();
+        class.methods[index].access_flags |= MethodAccessFlags::ABSTRACT;
+        class.this_class = class.super_class;
+        class.version = (49, 0);
+        let errors = class.check_format().unwrap_err();
+        let locations: Vec<_> = errors.iter().map(|e| e.location.as_str()).collect();
+        assert!(locations.contains(&"method getClass()Ljava/lang/Class;"));
+        assert!(locations.contains(&"this_class"));
+        // StackMapTable attributes are from version 50
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e.kind, FormatErrorKind::AttributeVersion { since: 50, .. })));
+    }
+
+    #[test]
+    fn array_dimensions() {
+        let array = |dimensions| format!("{}I", "[".repeat(dimensions));
+        assert!(field_descriptor(&array(MAX_DIMENSIONS)).is_some());
+        assert!(field_descriptor(&array(MAX_DIMENSIONS + 1)).is_none());
+        assert!(method_descriptor(&format!("({})V", array(MAX_DIMENSIONS))).is_some());
+
+        // this many used to overflow the stack before they were counted
+        assert!(field_descriptor(&array(60000)).is_none());
+        assert!(method_descriptor(&format!("()[{}", array(60000))).is_none());
+    }
+}
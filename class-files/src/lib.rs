@@ -1,177 +1,643 @@
-use std::io::{self, Read, Seek};
+use std::io::{Read, Write};
 
+pub mod assembler;
+pub mod builder;
+pub mod bytecode;
 pub mod bytes;
+pub mod constant_pool;
+pub mod control_flow;
 pub mod descriptors;
+pub mod error;
+pub mod format;
+pub mod signatures;
 pub mod types;
+pub mod verifier;
 
-use anyhow::{bail, ensure, Context};
-use bytes::ReadNum;
+use anyhow::Context;
+use bytes::{read_table, ByteReader, WriteNum};
+use constant_pool::ConstantPool;
 use types::{
-    raw::{RawAttribute, RawConstant, RawField, RawMethod},
-    resolved::{Attribute, Field, Method},
+    raw::{RawAttribute, RawField, RawMethod},
+    resolved::{find_attribute, Attribute, Field, Method, ModuleDescriptor, RecordComponent},
     ClassAccessFlags, MethodAccessFlags,
 };
 
+/// A parsed class file. The lifetime is that of the bytes it was parsed from by
+/// [`ClassFile::parse`], or `'static` if it owns all of its data.
 #[derive(Debug, Clone, Default)]
-pub struct ClassFile {
+pub struct ClassFile<'a> {
     /// (major, minor)
     pub version: (u16, u16),
-    pub constant_pool: Vec<RawConstant>,
+    pub constant_pool: ConstantPool<'a>,
     pub access_flags: ClassAccessFlags,
     this_class: usize,
     super_class: usize,
     interfaces: Vec<usize>,
-    fields: Vec<RawField>,
-    methods: Vec<RawMethod>,
-    attributes: Vec<RawAttribute>,
+    fields: Vec<RawField<'a>>,
+    methods: Vec<RawMethod<'a>>,
+    attributes: Vec<RawAttribute<'a>>,
 }
 
-impl ClassFile {
-    pub fn this_class(&self) -> anyhow::Result<&'_ str> {
-        match &self.constant_pool[self.this_class - 1] {
-            RawConstant::Class { name_index } => {
-                Ok(&self.constant_pool[name_index - 1].unwrap_utf8())
-            }
-            c => bail!(
-                "Expected Class Constant, got {:?} at {}",
-                c,
-                self.this_class - 1
-            ),
-        }
+impl<'a> ClassFile<'a> {
+    pub fn this_class(&self) -> error::Result<&'_ str> {
+        self.constant_pool.class_name(self.this_class)
     }
 
-    pub fn super_class(&self) -> anyhow::Result<&'_ str> {
-        Ok(match &self.constant_pool[self.super_class - 1] {
-            RawConstant::Class { name_index } => &self.constant_pool[name_index - 1],
-            c => bail!(
-                "Expected Class Constant, got {:?} at {}",
-                c,
-                self.super_class - 1
-            ),
+    /// The constant pool index of the class's own name
+    pub fn this_class_index(&self) -> usize {
+        self.this_class
+    }
+
+    /// The constant pool index of the direct superclass, or 0 for `java/lang/Object`
+    pub fn super_class_index(&self) -> usize {
+        self.super_class
+    }
+
+    /// The name of the direct superclass, or `None` for `java/lang/Object`
+    pub fn super_class(&self) -> error::Result<Option<&'_ str>> {
+        match self.super_class {
+            0 => Ok(None),
+            index => self.constant_pool.class_name(index).map(Some),
         }
-        .unwrap_utf8())
     }
 
-    pub fn interfaces(&self) -> impl Iterator<Item = &RawConstant> {
-        self.interfaces.iter().map(|n| &self.constant_pool[*n])
+    pub fn interfaces(&self) -> impl Iterator<Item = error::Result<&'_ str>> {
+        self.interfaces
+            .iter()
+            .map(|n| self.constant_pool.class_name(*n))
     }
 
-    pub fn methods(&self) -> impl Iterator<Item = Method> {
+    pub fn methods(&self) -> impl Iterator<Item = error::Result<Method<'_>>> {
         self.methods
             .iter()
             .map(|m| Method::from_raw(m, &self.constant_pool))
     }
 
-    pub fn fields(&self) -> impl Iterator<Item = Field> {
+    pub fn fields(&self) -> impl Iterator<Item = error::Result<Field<'_>>> {
         self.fields
             .iter()
-            .map(|r| Field::from_raw(&r, &self.constant_pool))
+            .map(|r| Field::from_raw(r, &self.constant_pool))
     }
 
-    pub fn attributes(&self) -> impl Iterator<Item = Attribute<'_>> {
+    pub fn attributes(&self) -> impl Iterator<Item = error::Result<Attribute<'_>>> {
         self.attributes
             .iter()
             .map(|r| Attribute::from_raw(r, &self.constant_pool))
     }
 
-    pub fn find_entry_point(&self) -> Option<Method> {
-        let method = self
-            .methods()
-            .find(|m| m.name == "main" && m.descriptor == "([Ljava/lang/String;)V")?;
+    /// The description of the module if this is a `module-info.class`, built from its `Module`,
+    /// `ModulePackages` and `ModuleMainClass` attributes.
+    pub fn module_descriptor(&self) -> error::Result<Option<ModuleDescriptor<'_>>> {
+        let mut module = None;
+        let mut packages = Vec::new();
+        let mut main_class = None;
+        for attribute in self.attributes() {
+            match attribute? {
+                Attribute::Module { module: m } => module = Some(m),
+                Attribute::ModulePackages { packages: p } => packages = p,
+                Attribute::ModuleMainClass { main_class: m } => main_class = Some(m),
+                _ => {}
+            }
+        }
+
+        Ok(module.map(|module| ModuleDescriptor {
+            module,
+            packages,
+            main_class,
+        }))
+    }
+
+    /// The components of this class if it is a record, from the `Record` attribute
+    pub fn record_components(&self) -> error::Result<Option<Vec<RecordComponent<'_>>>> {
+        Ok(
+            match find_attribute(&self.attributes, &self.constant_pool, "Record")? {
+                Some(Attribute::Record { components }) => Some(components),
+                _ => None,
+            },
+        )
+    }
+
+    /// The classes which may extend or implement this class if it is sealed, from the
+    /// `PermittedSubclasses` attribute
+    pub fn permitted_subclasses(&self) -> error::Result<Option<Vec<&'_ str>>> {
+        Ok(
+            match find_attribute(&self.attributes, &self.constant_pool, "PermittedSubclasses")? {
+                Some(Attribute::PermittedSubclasses { classes }) => Some(classes),
+                _ => None,
+            },
+        )
+    }
+
+    /// The nest host of this class, from the `NestHost` attribute. Classes without one are the
+    /// host of their own nest.
+    pub fn nest_host(&self) -> error::Result<Option<&'_ str>> {
+        Ok(
+            match find_attribute(&self.attributes, &self.constant_pool, "NestHost")? {
+                Some(Attribute::NestHost { host_class }) => Some(host_class),
+                _ => None,
+            },
+        )
+    }
+
+    /// The other members of the nest hosted by this class, from the `NestMembers` attribute
+    pub fn nest_members(&self) -> error::Result<Vec<&'_ str>> {
+        Ok(
+            match find_attribute(&self.attributes, &self.constant_pool, "NestMembers")? {
+                Some(Attribute::NestMembers { classes }) => classes,
+                _ => Vec::new(),
+            },
+        )
+    }
+
+    /// Find the method with the given name and descriptor
+    pub fn find_method(&self, name: &str, descriptor: &str) -> error::Result<Option<Method<'_>>> {
+        for method in self.methods() {
+            let method = method?;
+            if method.name == name && method.descriptor == descriptor {
+                return Ok(Some(method));
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn find_entry_point(&self) -> error::Result<Option<Method<'_>>> {
+        let Some(method) = self.find_method("main", "([Ljava/lang/String;)V")? else {
+            return Ok(None);
+        };
 
         if (!method.access_flags).intersects(MethodAccessFlags::PUBLIC | MethodAccessFlags::STATIC)
         {
-            return None;
+            return Ok(None);
         }
 
-        Some(method)
+        Ok(Some(method))
     }
 
-    pub fn find_init_method(&self) -> Option<Method> {
-        let method = self
-            .methods()
-            .find(|m| m.name == "<clinit>" && m.descriptor == "()V")?;
+    pub fn find_init_method(&self) -> error::Result<Option<Method<'_>>> {
+        let Some(method) = self.find_method("<clinit>", "()V")? else {
+            return Ok(None);
+        };
 
         if (!method.access_flags).intersects(MethodAccessFlags::STATIC) {
-            return None;
+            return Ok(None);
+        }
+
+        Ok(Some(method))
+    }
+
+    /// Parse a class file without copying it. Attributes and strings are kept as slices into
+    /// `bytes`, and strings are only decoded when they are looked up. `bytes` can be anything that
+    /// derefs to `[u8]` such as a memory-mapped file:
+    ///
+    /// ```ignore
+    /// let file = File::open("Foo.class")?;
+    /// let map = unsafe { memmap2::Mmap::map(&file)? };
+    /// let class = ClassFile::parse(&map)?;
+    /// ```
+    pub fn parse(bytes: &'a [u8]) -> error::Result<Self> {
+        let mut r = ByteReader::new(bytes);
+
+        let magic = r.read_u32()?;
+        if magic != 0xcafe_babe {
+            return Err(error::Error::InvalidMagic { magic });
         }
 
-        Some(method)
+        let minor_version = r.read_u16()?;
+        let major_version = r.read_u16()?;
+
+        let out = Self {
+            version: (major_version, minor_version),
+            constant_pool: ConstantPool::parse(&mut r)?,
+            access_flags: ClassAccessFlags::from_bits_retain(r.read_u16()?),
+            this_class: r.read_u16()?.into(),
+            super_class: r.read_u16()?.into(),
+            interfaces: read_table(&mut r, |r| Ok(r.read_u16()?.into()))?,
+            fields: read_table(&mut r, RawField::parse)?,
+            methods: read_table(&mut r, RawMethod::parse)?,
+            attributes: read_table(&mut r, RawAttribute::parse)?,
+        };
+
+        // check that we've consumed all bytes
+        match r.remaining() {
+            0 => Ok(out),
+            remaining => Err(error::Error::TrailingData { remaining }),
+        }
     }
 
-    pub fn read_from<R>(r: &mut R) -> anyhow::Result<Self>
+    /// Copy everything that is borrowed from the input so that the class file can outlive it
+    pub fn into_owned(self) -> ClassFile<'static> {
+        ClassFile {
+            version: self.version,
+            constant_pool: self.constant_pool.into_owned(),
+            access_flags: self.access_flags,
+            this_class: self.this_class,
+            super_class: self.super_class,
+            interfaces: self.interfaces,
+            fields: self.fields.into_iter().map(RawField::into_owned).collect(),
+            methods: self
+                .methods
+                .into_iter()
+                .map(RawMethod::into_owned)
+                .collect(),
+            attributes: RawAttribute::all_into_owned(self.attributes),
+        }
+    }
+
+    /// Read a whole class file from `r` into a class file which owns its data, see
+    /// [`ClassFile::parse`] to avoid the copy
+    pub fn read_from<R>(r: &mut R) -> anyhow::Result<ClassFile<'static>>
     where
-        R: Read + Seek,
+        R: Read,
     {
-        let mut out: Self = Default::default();
+        let mut bytes = Vec::new();
+        r.read_to_end(&mut bytes).context("reading class file")?;
+        Ok(ClassFile::parse(&bytes)
+            .context("parsing class file")?
+            .into_owned())
+    }
 
-        let magic = r.read_u32().context("parsing magic")?;
-        if magic != 0xcafe_babe {
-            bail!("Invalid magic value: 0x{:x}", magic);
-        }
-        // eprintln!("magic = 0x{:X}", magic);
-
-        let minor_version = r.read_u16().context("parsing minor version")?;
-        let major_version = r.read_u16().context("parsing major version")?;
-        out.version = (major_version, minor_version);
-
-        let constant_pool_count = r.read_u16()?;
-        out.constant_pool
-            .reserve_exact(constant_pool_count as usize - 1);
-
-        // idk why this counts from one, but java is gonna java...
-        let mut i = 1;
-        while i < constant_pool_count {
-            let (c, skip_next) = RawConstant::read_from(r).context("parsing raw constant")?;
-            out.constant_pool.push(c);
-            if skip_next {
-                i += 1;
-                // push an empty value so indexing still works
-                out.constant_pool.push(RawConstant::Unused);
+    /// Serialise this class file in the format that [`ClassFile::read_from`] reads, such that
+    /// parsing and writing a class file yields the same bytes.
+    pub fn write_to<W>(&self, w: &mut W) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        w.write_u32(0xcafe_babe)?;
+
+        let (major_version, minor_version) = self.version;
+        w.write_u16(minor_version)?;
+        w.write_u16(major_version)?;
+
+        self.constant_pool
+            .write_to(w)
+            .context("writing constant pool")?;
+
+        w.write_u16(self.access_flags.bits())?;
+
+        macro_rules! write_u16 {
+            ($value: expr, $dbg: expr) => {
+                w.write_u16(u16::try_from($value).context(concat!($dbg, " out of range"))?)
+                    .context(concat!("writing ", $dbg))?;
+            };
+        }
+
+        write_u16!(self.this_class, "this_class");
+        write_u16!(self.super_class, "super_class");
+
+        write_u16!(self.interfaces.len(), "interfaces_count");
+        for interface in &self.interfaces {
+            write_u16!(*interface, "interface");
+        }
+
+        write_u16!(self.fields.len(), "fields_count");
+        for field in &self.fields {
+            field.write_to(w).context("writing RawField")?;
+        }
+
+        write_u16!(self.methods.len(), "methods_count");
+        for method in &self.methods {
+            method.write_to(w).context("writing RawMethod")?;
+        }
+
+        RawAttribute::write_all(w, &self.attributes).context("writing RawAttribute")?;
+
+        Ok(())
+    }
+
+    /// Serialise this class file into a new buffer, see [`ClassFile::write_to`]
+    pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        self.write_to(&mut out)?;
+        Ok(out)
+    }
+}
+
+/// Serialises the class with everything resolved except for the constant pool, which is kept
+/// as it is so that indices into it (e.g. from instructions) can be looked up.
+#[cfg(feature = "serde")]
+impl serde::Serialize for ClassFile<'_> {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{Error, SerializeStruct};
+
+        let this_class = self.this_class().map_err(S::Error::custom)?;
+        let super_class = self.super_class().map_err(S::Error::custom)?;
+        let interfaces = self
+            .interfaces()
+            .collect::<error::Result<Vec<_>>>()
+            .map_err(S::Error::custom)?;
+        let fields = self
+            .fields()
+            .collect::<error::Result<Vec<_>>>()
+            .map_err(S::Error::custom)?;
+        let methods = self
+            .methods()
+            .collect::<error::Result<Vec<_>>>()
+            .map_err(S::Error::custom)?;
+        let attributes = self
+            .attributes()
+            .collect::<error::Result<Vec<_>>>()
+            .map_err(S::Error::custom)?;
+
+        let mut state = s.serialize_struct("ClassFile", 9)?;
+        state.serialize_field("version", &self.version)?;
+        state.serialize_field("constant_pool", &self.constant_pool)?;
+        state.serialize_field("access_flags", &self.access_flags)?;
+        state.serialize_field("this_class", this_class)?;
+        state.serialize_field("super_class", &super_class)?;
+        state.serialize_field("interfaces", &interfaces)?;
+        state.serialize_field("fields", &fields)?;
+        state.serialize_field("methods", &methods)?;
+        state.serialize_field("attributes", &attributes)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use constant_pool::Constant;
+    use std::{
+        fs,
+        io::Cursor,
+        path::{Path, PathBuf},
+    };
+
+    /// All class files in the extracted JDK (see `build.sh`) at or below `subpath`, or `None` if
+    /// it has not been extracted.
+    pub(crate) fn stdlib_classes(subpath: &str) -> Option<Vec<PathBuf>> {
+        fn walk(path: &Path, out: &mut Vec<PathBuf>) {
+            for entry in fs::read_dir(path).unwrap().flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    walk(&path, out);
+                } else if path.extension().is_some_and(|e| e == "class") {
+                    out.push(path);
+                }
             }
-            i += 1;
         }
 
-        let access_flags = r.read_u16().context("parsing access_flags")?;
-        out.access_flags = ClassAccessFlags::from_bits_retain(access_flags);
-        out.this_class = r.read_u16().context("parsing this_class")?.into();
-        out.super_class = r.read_u16().context("parsing super_class")?.into();
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../stdlib")
+            .join(subpath);
+        if !root.exists() {
+            eprintln!("{} not found, run build.sh to extract it", root.display());
+            return None;
+        }
+
+        if root.is_file() {
+            return Some(vec![root]);
+        }
+
+        let mut out = Vec::new();
+        walk(&root, &mut out);
+        Some(out)
+    }
+
+    #[test]
+    fn round_trip_stdlib() {
+        let Some(paths) = stdlib_classes("") else {
+            return;
+        };
 
-        macro_rules! read_vec {
-            ($vec: expr, $code: block, $dbg: expr) => {
-                let count = r.read_u16().context(concat!("parsing count for ", $dbg))?;
-                $vec.reserve_exact(count.into());
-                for _ in 0..count {
-                    $vec.push($code);
+        for path in paths {
+            let bytes = fs::read(&path).unwrap();
+            let class = ClassFile::parse(&bytes)
+                .unwrap_or_else(|e| panic!("parsing {}: {:?}", path.display(), e));
+            let written = class.to_bytes().unwrap();
+            assert!(bytes == written, "{} did not round-trip", path.display());
+        }
+    }
+
+    /// Resolve everything in the class that can be resolved, returning the first error
+    fn resolve_all(class: &ClassFile) -> error::Result<()> {
+        for (_, constant) in class.constant_pool.iter() {
+            constant?;
+        }
+        class.this_class()?;
+        class.super_class()?;
+        for interface in class.interfaces() {
+            interface?;
+        }
+        for attribute in class.attributes() {
+            attribute?;
+        }
+        for field in class.fields() {
+            for attribute in field?.attributes() {
+                attribute?;
+            }
+        }
+        for method in class.methods() {
+            for attribute in method?.attributes() {
+                attribute?;
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_stdlib() {
+        let Some(paths) = stdlib_classes("java.base") else {
+            return;
+        };
+
+        for path in paths {
+            let class = ClassFile::read_from(&mut Cursor::new(fs::read(&path).unwrap())).unwrap();
+            if let Err(e) = resolve_all(&class) {
+                panic!("resolving {}: {:?}", path.display(), e);
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_does_not_panic() {
+        let Some(paths) = stdlib_classes("java.base/java/lang/Object.class") else {
+            return;
+        };
+        let bytes = fs::read(&paths[0]).unwrap();
+
+        for len in 0..bytes.len() {
+            assert!(ClassFile::read_from(&mut Cursor::new(&bytes[..len])).is_err());
+        }
+
+        for i in 0..bytes.len() {
+            for b in [0x00, 0xff] {
+                let mut bytes = bytes.clone();
+                bytes[i] = b;
+                if let Ok(class) = ClassFile::read_from(&mut Cursor::new(&bytes)) {
+                    let _ = resolve_all(&class);
                 }
-            };
-            ($vec: expr, $struct: ident) => {
-                read_vec!(
-                    $vec,
-                    { $struct::read_from(r).context(concat!("parsing ", stringify!($ty)))? },
-                    stringify!($ty)
-                );
-            };
+            }
+        }
+    }
+
+    #[test]
+    fn java_9_constants() {
+        // CONSTANT_Dynamic, CONSTANT_Module and CONSTANT_Package
+        let bytes = [17, 0, 1, 0, 2, 19, 0, 3, 20, 0, 4];
+        let mut r = ByteReader::new(&bytes);
+        let mut written = Vec::new();
+        for _ in 0..3 {
+            let (constant, skip_next) = types::raw::RawConstant::parse(&mut r).unwrap();
+            assert!(!skip_next);
+            constant.write_to(&mut written).unwrap();
+        }
+        assert_eq!(written, bytes);
+
+        let Some(paths) = stdlib_classes("java.base/module-info.class") else {
+            return;
+        };
+        let class = ClassFile::read_from(&mut Cursor::new(fs::read(&paths[0]).unwrap())).unwrap();
+        assert_eq!(class.this_class().unwrap(), "module-info");
+        assert!(class
+            .constant_pool
+            .iter()
+            .any(|(_, c)| matches!(c, Ok(Constant::Module("java.base")))));
+        assert!(class
+            .constant_pool
+            .iter()
+            .any(|(_, c)| matches!(c, Ok(Constant::Package(_)))));
+    }
+
+    #[test]
+    fn modified_utf8() {
+        let utf8 = |bytes: &[u8]| {
+            let mut constant = vec![1, 0, bytes.len() as u8];
+            constant.extend_from_slice(bytes);
+            let (constant, _) =
+                types::raw::RawConstant::parse(&mut ByteReader::new(&constant)).unwrap();
+            constant.as_str().map(String::from)
+        };
+
+        assert_eq!(utf8(b"abc").as_deref(), Some("abc"));
+        assert_eq!(utf8(&[b'a', 0xc0, 0x80]).as_deref(), Some("a\0"));
+        // U+1F600 as a surrogate pair
+        assert_eq!(
+            utf8(&[0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80]).as_deref(),
+            Some("\u{1f600}")
+        );
+        // valid UTF-8, but `\0` and characters outside of the BMP are written differently
+        assert_eq!(utf8(b"a\0"), None);
+        assert_eq!(utf8("\u{1f600}".as_bytes()), None);
+        // an unpaired surrogate
+        assert_eq!(utf8(&[0xed, 0xa0, 0xbd]), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_stdlib() {
+        let Some(paths) = stdlib_classes("java.base") else {
+            return;
+        };
+        for path in paths {
+            let bytes = fs::read(&path).unwrap();
+            let class = ClassFile::parse(&bytes).unwrap();
+            let json = serde_json::to_value(&class)
+                .unwrap_or_else(|e| panic!("serialising {}: {}", path.display(), e));
+            assert_eq!(json["this_class"], class.this_class().unwrap());
         }
 
-        read_vec!(
-            out.interfaces,
-            { r.read_u16().context("parsing interface")?.into() },
-            "Interface"
+        let Some(paths) = stdlib_classes("java.base/java/lang/Object.class") else {
+            return;
+        };
+        let bytes = fs::read(&paths[0]).unwrap();
+        let json = serde_json::to_value(ClassFile::parse(&bytes).unwrap()).unwrap();
+        assert_eq!(json["access_flags"], serde_json::json!(["PUBLIC", "SUPER"]));
+        assert_eq!(json["super_class"], serde_json::Value::Null);
+        let hash_code = json["methods"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|m| m["name"] == "hashCode")
+            .unwrap();
+        assert_eq!(
+            hash_code["access_flags"],
+            serde_json::json!(["PUBLIC", "NATIVE"])
         );
+    }
 
-        read_vec!(out.fields, RawField);
-        read_vec!(out.methods, RawMethod);
-        read_vec!(out.attributes, RawAttribute);
+    #[test]
+    fn java_base_module_descriptor() {
+        let Some(paths) = stdlib_classes("java.base/module-info.class") else {
+            return;
+        };
+        let class = ClassFile::read_from(&mut Cursor::new(fs::read(&paths[0]).unwrap())).unwrap();
+        let descriptor = class.module_descriptor().unwrap().unwrap();
 
-        // check that we've consumed all bytes
-        let remaining_bytes = r.bytes().count();
-        ensure!(
-            remaining_bytes == 0,
-            "{} bytes remaining in file",
-            remaining_bytes
+        assert_eq!(descriptor.module.name, "java.base");
+        assert!(descriptor.module.version.is_some());
+        assert!(descriptor.module.requires.is_empty());
+        let java_lang = descriptor
+            .module
+            .exports
+            .iter()
+            .find(|e| e.package == "java/lang")
+            .unwrap();
+        assert!(java_lang.to.is_empty());
+        assert!(descriptor
+            .module
+            .exports
+            .iter()
+            .any(|e| e.to.contains(&"jdk.internal.vm.ci")));
+        assert!(!descriptor.module.uses.is_empty());
+        assert!(descriptor.packages.contains(&"jdk/internal/misc"));
+        assert!(descriptor.main_class.is_none());
+
+        // only the packages listed in `ModulePackages` are reported
+        let mut class = class;
+        class
+            .attributes
+            .retain(|a| class.constant_pool.utf8(a.attribute_name_index) != Ok("ModulePackages"));
+        let descriptor = class.module_descriptor().unwrap().unwrap();
+        assert_eq!(descriptor.module.name, "java.base");
+        assert!(descriptor.packages.is_empty());
+
+        let Some(paths) = stdlib_classes("java.base/java/lang/Object.class") else {
+            return;
+        };
+        let class = ClassFile::read_from(&mut Cursor::new(fs::read(&paths[0]).unwrap())).unwrap();
+        assert!(class.module_descriptor().unwrap().is_none());
+    }
+
+    #[test]
+    fn records_and_nests() {
+        let Some(paths) = stdlib_classes("java.base/sun/nio/ch/IOUtil$LinkedRunnable.class") else {
+            return;
+        };
+        let class = ClassFile::read_from(&mut Cursor::new(fs::read(&paths[0]).unwrap())).unwrap();
+        let components = class.record_components().unwrap().unwrap();
+        let components: Vec<_> = components.iter().map(|c| (c.name, c.descriptor)).collect();
+        assert_eq!(
+            components,
+            [
+                ("node", "Ljava/lang/Runnable;"),
+                ("next", "Ljava/lang/Runnable;")
+            ]
         );
+        assert_eq!(class.nest_host().unwrap(), Some("sun/nio/ch/IOUtil"));
+        assert!(class.permitted_subclasses().unwrap().is_none());
 
-        Ok(out)
+        let init = class
+            .find_method("<init>", "(Ljava/lang/Runnable;Ljava/lang/Runnable;)V")
+            .unwrap()
+            .unwrap();
+        let names: Vec<_> = init
+            .parameters()
+            .unwrap()
+            .unwrap()
+            .iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, [Some("node"), Some("next")]);
+
+        let Some(paths) = stdlib_classes("java.base/sun/security/util/KnownOIDs.class") else {
+            return;
+        };
+        let class = ClassFile::read_from(&mut Cursor::new(fs::read(&paths[0]).unwrap())).unwrap();
+        let permitted = class.permitted_subclasses().unwrap().unwrap();
+        assert!(permitted.contains(&"sun/security/util/KnownOIDs$1"));
+        assert!(class.record_components().unwrap().is_none());
     }
 }
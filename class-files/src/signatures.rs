@@ -0,0 +1,679 @@
+//! Generic signatures, as found in the `Signature` attribute. See
+//! <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.9.1>
+
+use std::{
+    fmt,
+    iter::Peekable,
+    str::{Chars, FromStr},
+};
+
+use anyhow::{bail, ensure, Context};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum BaseType {
+    /// B
+    Byte,
+    /// C
+    Char,
+    /// D
+    Double,
+    /// F
+    Float,
+    /// I
+    Int,
+    /// J
+    Long,
+    /// S
+    Short,
+    /// Z
+    Boolean,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum JavaTypeSignature {
+    Base(BaseType),
+    Reference(ReferenceTypeSignature),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ReferenceTypeSignature {
+    /// L<ClassName><TypeArguments>;
+    Class(ClassTypeSignature),
+    /// T<Identifier>;
+    TypeVariable(String),
+    /// [
+    Array(Box<JavaTypeSignature>),
+}
+
+/// A class type such as `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClassTypeSignature {
+    /// The package of the class separated by `/`, empty for the unnamed package
+    pub package: String,
+    /// The outermost class followed by each inner class
+    pub classes: Vec<SimpleClassTypeSignature>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SimpleClassTypeSignature {
+    pub name: String,
+    pub type_arguments: Vec<TypeArgument>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TypeArgument {
+    /// *
+    Any,
+    Exact(ReferenceTypeSignature),
+    /// +
+    Extends(ReferenceTypeSignature),
+    /// -
+    Super(ReferenceTypeSignature),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TypeParameter {
+    pub name: String,
+    /// `None` if the parameter only has interface bounds, e.g. `T:Ljava/lang/Comparable<TT;>;`
+    pub class_bound: Option<ReferenceTypeSignature>,
+    pub interface_bounds: Vec<ReferenceTypeSignature>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub superclass: ClassTypeSignature,
+    pub superinterfaces: Vec<ClassTypeSignature>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ResultSignature {
+    Type(JavaTypeSignature),
+    Void,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub params: Vec<JavaTypeSignature>,
+    pub result: ResultSignature,
+    /// Each is either a class type or a type variable
+    pub throws: Vec<ReferenceTypeSignature>,
+}
+
+impl ClassTypeSignature {
+    /// The binary name of the class, e.g. `java/util/Map$Entry`
+    pub fn class_name(&self) -> String {
+        let mut name = self.package.clone();
+        for (i, class) in self.classes.iter().enumerate() {
+            match i {
+                0 if name.is_empty() => {}
+                0 => name.push('/'),
+                _ => name.push('$'),
+            }
+            name.push_str(&class.name);
+        }
+        name
+    }
+}
+
+/// Array and type argument types can be nested inside each other, this limits how far so that a
+/// malicious signature can't overflow the stack. Arrays have at most 255 dimensions, which leaves
+/// plenty for type arguments.
+const MAX_DEPTH: usize = 512;
+
+/// Reads a signature one character at a time
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// How many reference types are being read, each one inside of the last
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            chars: s.chars().peekable(),
+            depth: 0,
+        }
+    }
+
+    fn next(&mut self) -> anyhow::Result<char> {
+        self.chars
+            .next()
+            .context("Invalid format -- expected more chars")
+    }
+
+    fn expect(&mut self, expected: char) -> anyhow::Result<()> {
+        let c = self.next()?;
+        ensure!(
+            c == expected,
+            "Invalid format -- expected '{}', found '{}'",
+            expected,
+            c
+        );
+        Ok(())
+    }
+
+    /// Consume the next char if it is `c`
+    fn eat(&mut self, c: char) -> bool {
+        self.chars.next_if_eq(&c).is_some()
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        match self.chars.next() {
+            None => Ok(()),
+            Some(c) => bail!("Invalid format -- unexpected '{}' after signature", c),
+        }
+    }
+
+    fn identifier(&mut self) -> anyhow::Result<String> {
+        let mut s = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| !matches!(c, '.' | ';' | '[' | '/' | '<' | '>' | ':'))
+        {
+            s.push(c);
+        }
+        ensure!(!s.is_empty(), "Invalid format -- expected identifier");
+        Ok(s)
+    }
+
+    fn java_type(&mut self) -> anyhow::Result<JavaTypeSignature> {
+        let base = match self.chars.peek() {
+            Some('B') => BaseType::Byte,
+            Some('C') => BaseType::Char,
+            Some('D') => BaseType::Double,
+            Some('F') => BaseType::Float,
+            Some('I') => BaseType::Int,
+            Some('J') => BaseType::Long,
+            Some('S') => BaseType::Short,
+            Some('Z') => BaseType::Boolean,
+            _ => return Ok(JavaTypeSignature::Reference(self.reference_type()?)),
+        };
+        self.next()?;
+        Ok(JavaTypeSignature::Base(base))
+    }
+
+    /// Every nested type is read through here, so this is where the depth is kept track of
+    fn reference_type(&mut self) -> anyhow::Result<ReferenceTypeSignature> {
+        ensure!(
+            self.depth < MAX_DEPTH,
+            "Invalid format -- types are nested too deeply"
+        );
+        self.depth += 1;
+        let signature = self.nested_reference_type();
+        self.depth -= 1;
+        signature
+    }
+
+    fn nested_reference_type(&mut self) -> anyhow::Result<ReferenceTypeSignature> {
+        Ok(match self.chars.peek() {
+            Some('L') => ReferenceTypeSignature::Class(self.class_type()?),
+            Some('T') => {
+                self.next()?;
+                let name = self.identifier()?;
+                self.expect(';')?;
+                ReferenceTypeSignature::TypeVariable(name)
+            }
+            Some('[') => {
+                self.next()?;
+                ReferenceTypeSignature::Array(Box::new(self.java_type()?))
+            }
+            _ => bail!("Invalid type, found: '{}'", self.next()?),
+        })
+    }
+
+    fn class_type(&mut self) -> anyhow::Result<ClassTypeSignature> {
+        self.expect('L')?;
+
+        // the package is every identifier that is followed by a '/'
+        let mut package = String::new();
+        let mut name = self.identifier()?;
+        while self.eat('/') {
+            if !package.is_empty() {
+                package.push('/');
+            }
+            package.push_str(&name);
+            name = self.identifier()?;
+        }
+
+        let mut classes = vec![SimpleClassTypeSignature {
+            name,
+            type_arguments: self.type_arguments()?,
+        }];
+        while self.eat('.') {
+            classes.push(SimpleClassTypeSignature {
+                name: self.identifier()?,
+                type_arguments: self.type_arguments()?,
+            });
+        }
+        self.expect(';')?;
+
+        Ok(ClassTypeSignature { package, classes })
+    }
+
+    fn type_arguments(&mut self) -> anyhow::Result<Vec<TypeArgument>> {
+        let mut arguments = Vec::new();
+        if !self.eat('<') {
+            return Ok(arguments);
+        }
+        while !self.eat('>') {
+            arguments.push(if self.eat('*') {
+                TypeArgument::Any
+            } else if self.eat('+') {
+                TypeArgument::Extends(self.reference_type()?)
+            } else if self.eat('-') {
+                TypeArgument::Super(self.reference_type()?)
+            } else {
+                TypeArgument::Exact(self.reference_type()?)
+            });
+        }
+        ensure!(
+            !arguments.is_empty(),
+            "Invalid format -- empty type arguments"
+        );
+        Ok(arguments)
+    }
+
+    fn type_parameters(&mut self) -> anyhow::Result<Vec<TypeParameter>> {
+        let mut parameters = Vec::new();
+        if !self.eat('<') {
+            return Ok(parameters);
+        }
+        while !self.eat('>') {
+            let name = self.identifier()?;
+            self.expect(':')?;
+            let class_bound = match self.chars.peek() {
+                Some(':') => None,
+                _ => Some(self.reference_type()?),
+            };
+            let mut interface_bounds = Vec::new();
+            while self.eat(':') {
+                interface_bounds.push(self.reference_type()?);
+            }
+            parameters.push(TypeParameter {
+                name,
+                class_bound,
+                interface_bounds,
+            });
+        }
+        ensure!(
+            !parameters.is_empty(),
+            "Invalid format -- empty type parameters"
+        );
+        Ok(parameters)
+    }
+}
+
+impl FromStr for JavaTypeSignature {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let signature = parser.java_type()?;
+        parser.finish()?;
+        Ok(signature)
+    }
+}
+
+/// The signature of a field, which is a `FieldSignature` in the JVMS grammar
+impl FromStr for ReferenceTypeSignature {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let signature = parser.reference_type()?;
+        parser.finish()?;
+        Ok(signature)
+    }
+}
+
+impl FromStr for ClassSignature {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let type_parameters = parser.type_parameters()?;
+        let superclass = parser.class_type()?;
+        let mut superinterfaces = Vec::new();
+        while parser.chars.peek().is_some() {
+            superinterfaces.push(parser.class_type()?);
+        }
+
+        Ok(Self {
+            type_parameters,
+            superclass,
+            superinterfaces,
+        })
+    }
+}
+
+impl FromStr for MethodSignature {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::new(s);
+        let type_parameters = parser.type_parameters()?;
+
+        parser.expect('(')?;
+        let mut params = Vec::new();
+        while !parser.eat(')') {
+            params.push(parser.java_type()?);
+        }
+
+        let result = if parser.eat('V') {
+            ResultSignature::Void
+        } else {
+            ResultSignature::Type(parser.java_type()?)
+        };
+
+        let mut throws = Vec::new();
+        while parser.eat('^') {
+            let throw = parser.reference_type()?;
+            ensure!(
+                !matches!(throw, ReferenceTypeSignature::Array(_)),
+                "Invalid format -- array type in throws"
+            );
+            throws.push(throw);
+        }
+        parser.finish()?;
+
+        Ok(Self {
+            type_parameters,
+            params,
+            result,
+            throws,
+        })
+    }
+}
+
+impl fmt::Display for BaseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BaseType::Byte => write!(f, "B"),
+            BaseType::Char => write!(f, "C"),
+            BaseType::Double => write!(f, "D"),
+            BaseType::Float => write!(f, "F"),
+            BaseType::Int => write!(f, "I"),
+            BaseType::Long => write!(f, "J"),
+            BaseType::Short => write!(f, "S"),
+            BaseType::Boolean => write!(f, "Z"),
+        }
+    }
+}
+
+impl fmt::Display for JavaTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JavaTypeSignature::Base(b) => b.fmt(f),
+            JavaTypeSignature::Reference(r) => r.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for ReferenceTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReferenceTypeSignature::Class(c) => c.fmt(f),
+            ReferenceTypeSignature::TypeVariable(name) => write!(f, "T{};", name),
+            ReferenceTypeSignature::Array(component) => write!(f, "[{}", component),
+        }
+    }
+}
+
+impl fmt::Display for ClassTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "L")?;
+        if !self.package.is_empty() {
+            write!(f, "{}/", self.package)?;
+        }
+        for (i, class) in self.classes.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", class)?;
+        }
+        write!(f, ";")
+    }
+}
+
+impl fmt::Display for SimpleClassTypeSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.type_arguments.is_empty() {
+            write!(f, "<")?;
+            for argument in &self.type_arguments {
+                write!(f, "{}", argument)?;
+            }
+            write!(f, ">")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TypeArgument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeArgument::Any => write!(f, "*"),
+            TypeArgument::Exact(r) => r.fmt(f),
+            TypeArgument::Extends(r) => write!(f, "+{}", r),
+            TypeArgument::Super(r) => write!(f, "-{}", r),
+        }
+    }
+}
+
+impl fmt::Display for TypeParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.name)?;
+        if let Some(bound) = &self.class_bound {
+            write!(f, "{}", bound)?;
+        }
+        for bound in &self.interface_bounds {
+            write!(f, ":{}", bound)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write type parameters including the surrounding `<>`, or nothing if there are none
+fn write_type_parameters(f: &mut fmt::Formatter<'_>, parameters: &[TypeParameter]) -> fmt::Result {
+    if !parameters.is_empty() {
+        write!(f, "<")?;
+        for parameter in parameters {
+            write!(f, "{}", parameter)?;
+        }
+        write!(f, ">")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ClassSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_type_parameters(f, &self.type_parameters)?;
+        write!(f, "{}", self.superclass)?;
+        for interface in &self.superinterfaces {
+            write!(f, "{}", interface)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ResultSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResultSignature::Type(t) => t.fmt(f),
+            ResultSignature::Void => write!(f, "V"),
+        }
+    }
+}
+
+impl fmt::Display for MethodSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_type_parameters(f, &self.type_parameters)?;
+        write!(f, "(")?;
+        for param in &self.params {
+            write!(f, "{}", param)?;
+        }
+        write!(f, "){}", self.result)?;
+        for throw in &self.throws {
+            write!(f, "^{}", throw)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{test::stdlib_classes, types::resolved::Attribute, ClassFile};
+    use std::{fs, io::Cursor};
+
+    fn class(package: &str, classes: &[(&str, Vec<TypeArgument>)]) -> ClassTypeSignature {
+        ClassTypeSignature {
+            package: package.into(),
+            classes: classes
+                .iter()
+                .map(|(name, type_arguments)| SimpleClassTypeSignature {
+                    name: name.to_string(),
+                    type_arguments: type_arguments.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn type_variable(name: &str) -> ReferenceTypeSignature {
+        ReferenceTypeSignature::TypeVariable(name.into())
+    }
+
+    #[test]
+    fn hash_map_signatures() {
+        let signature = "<K:Ljava/lang/Object;V:Ljava/lang/Object;>Ljava/util/AbstractMap<TK;TV;>;Ljava/util/Map<TK;TV;>;Ljava/lang/Cloneable;";
+        let cs: ClassSignature = signature.parse().unwrap();
+        assert_eq!(cs.type_parameters.len(), 2);
+        assert_eq!(cs.type_parameters[1].name, "V");
+        assert_eq!(
+            cs.superclass,
+            class(
+                "java/util",
+                &[(
+                    "AbstractMap",
+                    vec![
+                        TypeArgument::Exact(type_variable("K")),
+                        TypeArgument::Exact(type_variable("V"))
+                    ]
+                )]
+            )
+        );
+        assert_eq!(cs.superinterfaces.len(), 2);
+        assert_eq!(cs.to_string(), signature);
+
+        let signature = "(Ljava/util/Map<+TK;+TV;>;)V";
+        let ms: MethodSignature = signature.parse().unwrap();
+        assert_eq!(ms.result, ResultSignature::Void);
+        assert_eq!(ms.to_string(), signature);
+
+        let signature = "Ljava/util/Set<Ljava/util/Map$Entry<TK;TV;>;>;";
+        let rs: ReferenceTypeSignature = signature.parse().unwrap();
+        assert_eq!(rs.to_string(), signature);
+    }
+
+    #[test]
+    fn inner_classes_and_bounds() {
+        let signature = "<T::Ljava/lang/Comparable<-TT;>;X:Ljava/lang/Throwable;>(Lpkg/Outer<TT;>.Inner<*>;[[I)[TT;^TX;^Ljava/io/IOException;";
+        let ms: MethodSignature = signature.parse().unwrap();
+
+        assert_eq!(ms.type_parameters[0].class_bound, None);
+        assert_eq!(ms.type_parameters[0].interface_bounds.len(), 1);
+        let JavaTypeSignature::Reference(ReferenceTypeSignature::Class(outer)) = &ms.params[0]
+        else {
+            panic!("expected class type, found {:?}", ms.params[0]);
+        };
+        assert_eq!(
+            *outer,
+            class(
+                "pkg",
+                &[
+                    ("Outer", vec![TypeArgument::Exact(type_variable("T"))]),
+                    ("Inner", vec![TypeArgument::Any]),
+                ]
+            )
+        );
+        assert_eq!(outer.class_name(), "pkg/Outer$Inner");
+        assert_eq!(
+            ms.result,
+            ResultSignature::Type(JavaTypeSignature::Reference(ReferenceTypeSignature::Array(
+                Box::new(JavaTypeSignature::Reference(type_variable("T")))
+            )))
+        );
+        assert_eq!(ms.throws.len(), 2);
+        assert_eq!(ms.to_string(), signature);
+
+        assert!("LNoSemicolon".parse::<ReferenceTypeSignature>().is_err());
+        assert!("()V^[I".parse::<MethodSignature>().is_err());
+        assert!("Ljava/util/List<>;"
+            .parse::<ReferenceTypeSignature>()
+            .is_err());
+        assert!("TT;TT;".parse::<ReferenceTypeSignature>().is_err());
+    }
+
+    #[test]
+    fn deeply_nested() {
+        let array = |dimensions| "[".repeat(dimensions) + "I";
+        assert!(array(255).parse::<JavaTypeSignature>().is_ok());
+        assert!(array(20000).parse::<JavaTypeSignature>().is_err());
+
+        let lists = |depth| "Ljava/util/List<".repeat(depth) + "TT;" + &">;".repeat(depth);
+        assert!(lists(100).parse::<ReferenceTypeSignature>().is_ok());
+        assert!(lists(20000).parse::<ReferenceTypeSignature>().is_err());
+        let method = format!("({})V", lists(20000));
+        assert!(method.parse::<MethodSignature>().is_err());
+    }
+
+    #[test]
+    fn stdlib_signatures_round_trip() {
+        let Some(paths) = stdlib_classes("java.base") else {
+            return;
+        };
+
+        for path in paths {
+            let bytes = fs::read(&path).unwrap();
+            let class = ClassFile::read_from(&mut Cursor::new(bytes)).unwrap();
+
+            fn check<T: FromStr<Err = anyhow::Error> + fmt::Display>(
+                attribute: Attribute,
+                path: &std::path::Path,
+            ) {
+                if let Attribute::Signature { signature } = attribute {
+                    let parsed = signature
+                        .parse::<T>()
+                        .unwrap_or_else(|e| panic!("{}: {}: {:?}", path.display(), signature, e));
+                    assert_eq!(parsed.to_string(), signature);
+                }
+            }
+
+            for attribute in class.attributes() {
+                check::<ClassSignature>(attribute.unwrap(), &path);
+            }
+            for field in class.fields() {
+                for attribute in field.unwrap().attributes() {
+                    check::<ReferenceTypeSignature>(attribute.unwrap(), &path);
+                }
+            }
+            for method in class.methods() {
+                for attribute in method.unwrap().attributes() {
+                    check::<MethodSignature>(attribute.unwrap(), &path);
+                }
+            }
+        }
+    }
+}
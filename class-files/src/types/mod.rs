@@ -83,6 +83,9 @@ bitflags::bitflags! {
 
         /// Declared as an enum type.
         const ENUM = 0x4000;
+
+        /// Is a module, not a class or interface.
+        const MODULE = 0x8000;
     }
 }
 
@@ -121,3 +124,101 @@ bitflags::bitflags! {
         const ENUM = 0x4000;
     }
 }
+
+bitflags::bitflags! {
+    /// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.25>
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct ModuleFlags: u16 {
+        /// Indicates that this module is open.
+        const OPEN = 0x0020;
+
+        /// Indicates that this module was not explicitly or implicitly declared.
+        const SYNTHETIC = 0x1000;
+
+        /// Indicates that this module was implicitly declared.
+        const MANDATED = 0x8000;
+    }
+}
+
+bitflags::bitflags! {
+    /// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.25>
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct ModuleRequiresFlags: u16 {
+        /// Indicates that any module which depends on the current module, implicitly declares a
+        /// dependence on the module indicated by this entry.
+        const TRANSITIVE = 0x0020;
+
+        /// Indicates that this dependence is mandatory in the static phase, i.e., at compile
+        /// time, but is optional in the dynamic phase, i.e., at run time.
+        const STATIC_PHASE = 0x0040;
+
+        /// Indicates that this dependence was not explicitly or implicitly declared in the source
+        /// of the module declaration.
+        const SYNTHETIC = 0x1000;
+
+        /// Indicates that this dependence was implicitly declared in the source of the module
+        /// declaration.
+        const MANDATED = 0x8000;
+    }
+}
+
+bitflags::bitflags! {
+    /// Flags of the `exports` and `opens` entries of a module.
+    ///
+    /// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.25>
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct ModuleExportsFlags: u16 {
+        /// Indicates that this export/opening was not explicitly or implicitly declared in the
+        /// source of the module declaration.
+        const SYNTHETIC = 0x1000;
+
+        /// Indicates that this export/opening was implicitly declared in the source of the module
+        /// declaration.
+        const MANDATED = 0x8000;
+    }
+}
+
+bitflags::bitflags! {
+    /// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.24>
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct MethodParameterFlags: u16 {
+        /// Indicates that the formal parameter was declared final.
+        const FINAL = 0x0010;
+
+        /// Indicates that the formal parameter was not explicitly or implicitly declared in
+        /// source code.
+        const SYNTHETIC = 0x1000;
+
+        /// Indicates that the formal parameter was implicitly declared in source code.
+        const MANDATED = 0x8000;
+    }
+}
+
+/// Serialise flags as an array of the names of the flags which are set, e.g. `["PUBLIC", "STATIC"]`
+#[cfg(feature = "serde")]
+macro_rules! serialize_flags {
+    ($($flags: ty),*) => {
+        $(
+            impl serde::Serialize for $flags {
+                fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+                where
+                    S: serde::Serializer,
+                {
+                    s.collect_seq(self.iter_names().map(|(name, _)| name))
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "serde")]
+serialize_flags!(
+    MethodAccessFlags,
+    FieldAccessFlags,
+    ClassAccessFlags,
+    NestedClassAccessFlags,
+    ModuleFlags,
+    ModuleRequiresFlags,
+    ModuleExportsFlags,
+    MethodParameterFlags
+);
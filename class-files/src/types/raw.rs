@@ -1,10 +1,24 @@
-use anyhow::bail;
+use anyhow::Context;
 
-use super::{super::bytes::ReadNum, FieldAccessFlags, MethodAccessFlags};
-use std::io::{self, Read};
+use super::{FieldAccessFlags, MethodAccessFlags};
+use crate::{
+    bytes::{read_table, ByteReader, WriteNum},
+    error::{Error, Result},
+};
+use std::{borrow::Cow, io::Write, sync::OnceLock};
+
+/// Write a constant pool index (or any other `usize` that is stored as a `u2`)
+fn write_index<W>(w: &mut W, index: usize) -> anyhow::Result<()>
+where
+    W: Write,
+{
+    let index = u16::try_from(index).with_context(|| format!("index {} out of range", index))?;
+    w.write_u16(index)
+}
 
 #[derive(Debug, Clone)]
-pub enum RawConstant {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub(crate) enum RawConstant<'a> {
     /// Doubles and Longs are two entries into the constant table, so we place this into the vec to
     /// allow lookups to stay O(1)
     Unused,
@@ -42,8 +56,14 @@ pub enum RawConstant {
         name_index: usize,
         descriptor_index: usize,
     },
+    /// The modified UTF-8 bytes as they are in the class file. They are checked the first time
+    /// they are looked up and the outcome is kept in `converted`: most strings are also valid
+    /// UTF-8 so can be used directly, the rest (those containing `\0` or characters outside of
+    /// the BMP) are decoded into it.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_utf8"))]
     Utf8 {
-        string: String,
+        bytes: Cow<'a, [u8]>,
+        converted: OnceLock<Utf8Str>,
     },
     MethodHandle {
         reference_kind: u8,
@@ -56,13 +76,100 @@ pub enum RawConstant {
         bootstrap_method_attr_index: usize,
         name_and_type_index: usize,
     },
+    /// A dynamically-computed constant, produced by invoking a bootstrap method
+    Dynamic {
+        bootstrap_method_attr_index: usize,
+        name_and_type_index: usize,
+    },
+    /// Only allowed in `module-info.class`
+    Module {
+        name_index: usize,
+    },
+    /// Only allowed in `module-info.class`
+    Package {
+        name_index: usize,
+    },
 }
 
-impl RawConstant {
-    pub fn read_from<R>(r: &mut R) -> anyhow::Result<(Self, bool)>
-    where
-        R: Read,
-    {
+/// How the bytes of a Utf8 constant are read as a string
+#[derive(Debug, Clone)]
+pub(crate) enum Utf8Str {
+    /// The bytes are valid UTF-8 as well as modified UTF-8, so they are the string
+    Same,
+    /// The bytes decoded from modified UTF-8
+    Converted(Box<str>),
+    /// The bytes aren't valid modified UTF-8 (e.g. they contain a `\0` byte or a four byte
+    /// sequence), or aren't valid unicode (e.g. they contain unpaired surrogates)
+    Invalid,
+}
+
+impl Utf8Str {
+    fn decode(bytes: &[u8]) -> Self {
+        // modified UTF-8 writes `\0` as two bytes and characters outside of the BMP as a surrogate
+        // pair of three bytes each, so these are valid UTF-8 but not valid here. Four byte
+        // sequences all start with 0xf0 to 0xf4 and 0xf5 to 0xff never appear in either.
+        if bytes.iter().any(|&b| b == 0 || b >= 0xf0) {
+            return Self::Invalid;
+        }
+        if std::str::from_utf8(bytes).is_ok() {
+            return Self::Same;
+        }
+        match cesu8::from_java_cesu8(bytes) {
+            Ok(string) => Self::Converted(string.into()),
+            Err(_) => Self::Invalid,
+        }
+    }
+}
+
+/// The string in the modified UTF-8 `bytes` of a Utf8 constant, checking and, if it isn't also
+/// valid UTF-8, decoding it into `converted` the first time
+fn utf8_str<'s>(bytes: &'s [u8], converted: &'s OnceLock<Utf8Str>) -> Option<&'s str> {
+    match converted.get_or_init(|| Utf8Str::decode(bytes)) {
+        // the bytes were already found to be valid, so this can't fail
+        Utf8Str::Same => std::str::from_utf8(bytes).ok(),
+        Utf8Str::Converted(string) => Some(string),
+        Utf8Str::Invalid => None,
+    }
+}
+
+/// Serialise a Utf8 constant as a string, or as its bytes if it isn't valid unicode
+#[cfg(feature = "serde")]
+#[allow(clippy::ptr_arg)] // serde passes a reference to each field
+fn serialize_utf8<S>(
+    bytes: &Cow<[u8]>,
+    converted: &OnceLock<Utf8Str>,
+    s: S,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match utf8_str(bytes, converted) {
+        Some(string) => s.serialize_str(string),
+        None => s.serialize_bytes(bytes),
+    }
+}
+
+impl RawConstant<'_> {
+    /// A Utf8 constant containing `string`
+    pub fn utf8(string: &str) -> RawConstant<'static> {
+        RawConstant::Utf8 {
+            bytes: cesu8::to_java_cesu8(string).into_owned().into(),
+            converted: OnceLock::new(),
+        }
+    }
+
+    /// The string in a Utf8 constant, or `None` if this isn't one or it isn't valid unicode
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Utf8 { bytes, converted } => utf8_str(bytes, converted),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> RawConstant<'a> {
+    /// Parse a constant, and whether it takes up two slots in the constant pool
+    pub fn parse(r: &mut ByteReader<'a>) -> Result<(Self, bool)> {
         let mut skip_next = false;
         let constant = match r.read_u8()? {
             7 => Self::Class {
@@ -101,12 +208,9 @@ impl RawConstant {
             },
             1 => {
                 let count = r.read_u16()?;
-                let mut bytes = vec![0u8; count.into()];
-                r.read_exact(&mut bytes)?;
-                // string: String::from_utf8(bytes.clone())
-                //     .with_context(|| format!("bytes: {:x?}", &bytes))?,
                 Self::Utf8 {
-                    string: cesu8::from_java_cesu8(&bytes)?.to_string(),
+                    bytes: Cow::Borrowed(r.read_bytes(count.into())?),
+                    converted: OnceLock::new(),
                 }
             }
             15 => Self::MethodHandle {
@@ -116,113 +220,369 @@ impl RawConstant {
             16 => Self::MethodType {
                 descriptor_index: r.read_u16()?.into(),
             },
+            17 => Self::Dynamic {
+                bootstrap_method_attr_index: r.read_u16()?.into(),
+                name_and_type_index: r.read_u16()?.into(),
+            },
             18 => Self::InvokeDynamic {
                 bootstrap_method_attr_index: r.read_u16()?.into(),
                 name_and_type_index: r.read_u16()?.into(),
             },
+            19 => Self::Module {
+                name_index: r.read_u16()?.into(),
+            },
+            20 => Self::Package {
+                name_index: r.read_u16()?.into(),
+            },
             tag => {
-                bail!("Invalid constant tag: {}", tag);
+                return Err(Error::InvalidTag {
+                    kind: "constant",
+                    tag,
+                })
             }
         };
 
         Ok((constant, skip_next))
     }
 
-    pub fn unwrap_utf8(&self) -> &str {
+    /// Copy any borrowed bytes so that this no longer borrows from the input
+    pub fn into_owned(self) -> RawConstant<'static> {
         match self {
-            RawConstant::Utf8 { string } => string,
-            _ => unreachable!("unwrap_utf8 on non-utf8 value. was: {:?}", self),
+            Self::Unused => RawConstant::Unused,
+            Self::Class { name_index } => RawConstant::Class { name_index },
+            Self::FieldRef {
+                class_index,
+                name_and_type_index,
+            } => RawConstant::FieldRef {
+                class_index,
+                name_and_type_index,
+            },
+            Self::MethodRef {
+                class_index,
+                name_and_type_index,
+            } => RawConstant::MethodRef {
+                class_index,
+                name_and_type_index,
+            },
+            Self::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => RawConstant::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            },
+            Self::String { string_index } => RawConstant::String { string_index },
+            Self::Integer { num } => RawConstant::Integer { num },
+            Self::Float { num } => RawConstant::Float { num },
+            Self::Long { num } => RawConstant::Long { num },
+            Self::Double { num } => RawConstant::Double { num },
+            Self::NameAndType {
+                name_index,
+                descriptor_index,
+            } => RawConstant::NameAndType {
+                name_index,
+                descriptor_index,
+            },
+            Self::Utf8 { bytes, converted } => RawConstant::Utf8 {
+                bytes: Cow::Owned(bytes.into_owned()),
+                converted,
+            },
+            Self::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => RawConstant::MethodHandle {
+                reference_kind,
+                reference_index,
+            },
+            Self::MethodType { descriptor_index } => RawConstant::MethodType { descriptor_index },
+            Self::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => RawConstant::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            },
+            Self::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => RawConstant::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            },
+            Self::Module { name_index } => RawConstant::Module { name_index },
+            Self::Package { name_index } => RawConstant::Package { name_index },
+        }
+    }
+
+    pub fn write_to<W>(&self, w: &mut W) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        match self {
+            // the slot after a Long or Double is not written at all
+            Self::Unused => {}
+            Self::Class { name_index } => {
+                w.write_u8(7)?;
+                write_index(w, *name_index)?;
+            }
+            Self::FieldRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                w.write_u8(9)?;
+                write_index(w, *class_index)?;
+                write_index(w, *name_and_type_index)?;
+            }
+            Self::MethodRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                w.write_u8(10)?;
+                write_index(w, *class_index)?;
+                write_index(w, *name_and_type_index)?;
+            }
+            Self::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                w.write_u8(11)?;
+                write_index(w, *class_index)?;
+                write_index(w, *name_and_type_index)?;
+            }
+            Self::String { string_index } => {
+                w.write_u8(8)?;
+                write_index(w, *string_index)?;
+            }
+            Self::Integer { num } => {
+                w.write_u8(3)?;
+                w.write_i32(*num)?;
+            }
+            Self::Float { num } => {
+                w.write_u8(4)?;
+                w.write_f32(*num)?;
+            }
+            Self::Long { num } => {
+                w.write_u8(5)?;
+                w.write_i64(*num)?;
+            }
+            Self::Double { num } => {
+                w.write_u8(6)?;
+                w.write_f64(*num)?;
+            }
+            Self::NameAndType {
+                name_index,
+                descriptor_index,
+            } => {
+                w.write_u8(12)?;
+                write_index(w, *name_index)?;
+                write_index(w, *descriptor_index)?;
+            }
+            Self::Utf8 { bytes, .. } => {
+                w.write_u8(1)?;
+                write_index(w, bytes.len()).context("Utf8 constant too long")?;
+                w.write_all(bytes)?;
+            }
+            Self::MethodHandle {
+                reference_kind,
+                reference_index,
+            } => {
+                w.write_u8(15)?;
+                w.write_u8(*reference_kind)?;
+                write_index(w, *reference_index)?;
+            }
+            Self::MethodType { descriptor_index } => {
+                w.write_u8(16)?;
+                write_index(w, *descriptor_index)?;
+            }
+            Self::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => {
+                w.write_u8(18)?;
+                write_index(w, *bootstrap_method_attr_index)?;
+                write_index(w, *name_and_type_index)?;
+            }
+            Self::Dynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => {
+                w.write_u8(17)?;
+                write_index(w, *bootstrap_method_attr_index)?;
+                write_index(w, *name_and_type_index)?;
+            }
+            Self::Module { name_index } => {
+                w.write_u8(19)?;
+                write_index(w, *name_index)?;
+            }
+            Self::Package { name_index } => {
+                w.write_u8(20)?;
+                write_index(w, *name_index)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The name of this kind of constant, as used in error messages
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Unused => "Unused",
+            Self::Class { .. } => "Class",
+            Self::FieldRef { .. } => "FieldRef",
+            Self::MethodRef { .. } => "MethodRef",
+            Self::InterfaceMethodRef { .. } => "InterfaceMethodRef",
+            Self::String { .. } => "String",
+            Self::Integer { .. } => "Integer",
+            Self::Float { .. } => "Float",
+            Self::Long { .. } => "Long",
+            Self::Double { .. } => "Double",
+            Self::NameAndType { .. } => "NameAndType",
+            Self::Utf8 { .. } => "Utf8",
+            Self::MethodHandle { .. } => "MethodHandle",
+            Self::MethodType { .. } => "MethodType",
+            Self::InvokeDynamic { .. } => "InvokeDynamic",
+            Self::Dynamic { .. } => "Dynamic",
+            Self::Module { .. } => "Module",
+            Self::Package { .. } => "Package",
         }
     }
 }
 
 #[derive(Debug, Clone, Default)]
-pub struct RawField {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RawField<'a> {
     pub(crate) access_flags: FieldAccessFlags,
     pub(crate) name_index: usize,
     pub(crate) descriptor_index: usize,
-    pub(crate) attributes: Vec<RawAttribute>,
+    pub(crate) attributes: Vec<RawAttribute<'a>>,
 }
 
-impl RawField {
-    pub fn read_from<R>(r: &mut R) -> anyhow::Result<Self>
-    where
-        R: Read,
-    {
-        let mut out: Self = Default::default();
-
-        let access_flags = r.read_u16()?;
-        out.access_flags = FieldAccessFlags::from_bits(access_flags).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Invalid Field Access Flags: 0x{:x}", access_flags),
-            )
-        })?;
-
-        out.name_index = r.read_u16()?.into();
-        out.descriptor_index = r.read_u16()?.into();
-        let attribute_count = r.read_u16()?;
-        out.attributes.reserve_exact(attribute_count.into());
-        for _ in 0..attribute_count {
-            out.attributes.push(RawAttribute::read_from(r)?);
+impl<'a> RawField<'a> {
+    pub fn parse(r: &mut ByteReader<'a>) -> Result<Self> {
+        let flags = r.read_u16()?;
+        Ok(Self {
+            access_flags: FieldAccessFlags::from_bits(flags)
+                .ok_or(Error::InvalidAccessFlags { flags })?,
+            name_index: r.read_u16()?.into(),
+            descriptor_index: r.read_u16()?.into(),
+            attributes: read_table(r, RawAttribute::parse)?,
+        })
+    }
+
+    /// Copy any borrowed bytes so that this no longer borrows from the input
+    pub fn into_owned(self) -> RawField<'static> {
+        RawField {
+            access_flags: self.access_flags,
+            name_index: self.name_index,
+            descriptor_index: self.descriptor_index,
+            attributes: RawAttribute::all_into_owned(self.attributes),
         }
+    }
 
-        Ok(out)
+    pub fn write_to<W>(&self, w: &mut W) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        w.write_u16(self.access_flags.bits())?;
+        write_index(w, self.name_index)?;
+        write_index(w, self.descriptor_index)?;
+        RawAttribute::write_all(w, &self.attributes)
     }
 }
 
 #[derive(Debug, Clone, Default)]
-pub struct RawAttribute {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RawAttribute<'a> {
     pub(crate) attribute_name_index: usize,
-    pub(crate) info: Vec<u8>,
+    pub(crate) info: Cow<'a, [u8]>,
 }
 
-impl RawAttribute {
-    pub fn read_from<R>(r: &mut R) -> anyhow::Result<Self>
+impl<'a> RawAttribute<'a> {
+    pub fn parse(r: &mut ByteReader<'a>) -> Result<Self> {
+        let attribute_name_index = r.read_u16()?.into();
+        let len = r.read_u32()?;
+        // a length that doesn't fit in a usize can't fit in the input either
+        let len = usize::try_from(len).map_err(|_| Error::Truncated { offset: r.offset() })?;
+        Ok(Self {
+            attribute_name_index,
+            info: Cow::Borrowed(r.read_bytes(len)?),
+        })
+    }
+
+    /// Copy any borrowed bytes so that this no longer borrows from the input
+    pub fn into_owned(self) -> RawAttribute<'static> {
+        RawAttribute {
+            attribute_name_index: self.attribute_name_index,
+            info: Cow::Owned(self.info.into_owned()),
+        }
+    }
+
+    pub(crate) fn all_into_owned(attributes: Vec<Self>) -> Vec<RawAttribute<'static>> {
+        attributes.into_iter().map(Self::into_owned).collect()
+    }
+
+    pub fn write_to<W>(&self, w: &mut W) -> anyhow::Result<()>
     where
-        R: Read,
+        W: Write,
     {
-        let mut out: Self = Default::default();
-        out.attribute_name_index = r.read_u16()?.into();
-        let len = r.read_u32()?;
-        out.info = (0..len).map(|_| 0).collect();
-        r.read_exact(&mut out.info)?;
-        Ok(out)
+        write_index(w, self.attribute_name_index)?;
+        let len = u32::try_from(self.info.len()).context("attribute too long")?;
+        w.write_u32(len)?;
+        w.write_all(&self.info)?;
+        Ok(())
+    }
+
+    /// Write `attributes_count` followed by each of the attributes
+    pub(crate) fn write_all<W>(w: &mut W, attributes: &[RawAttribute]) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        write_index(w, attributes.len()).context("too many attributes")?;
+        for attribute in attributes {
+            attribute.write_to(w)?;
+        }
+        Ok(())
     }
 }
 
 #[derive(Debug, Clone, Default)]
-pub struct RawMethod {
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RawMethod<'a> {
     pub(crate) access_flags: MethodAccessFlags,
     pub(crate) name_index: usize,
     pub(crate) descriptor_index: usize,
-    pub(crate) attributes: Vec<RawAttribute>,
+    pub(crate) attributes: Vec<RawAttribute<'a>>,
 }
 
-impl RawMethod {
-    pub fn read_from<R>(r: &mut R) -> anyhow::Result<Self>
-    where
-        R: Read,
-    {
-        let mut out: Self = Default::default();
-
-        let access_flags = r.read_u16()?;
-        out.access_flags = MethodAccessFlags::from_bits(access_flags).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Invalid Method Access Flags: 0x{:x}", access_flags),
-            )
-        })?;
-
-        out.name_index = r.read_u16()?.into();
-        out.descriptor_index = r.read_u16()?.into();
-
-        let attribute_count = r.read_u16()?;
-        out.attributes.reserve_exact(attribute_count.into());
-        for _ in 0..attribute_count {
-            out.attributes.push(RawAttribute::read_from(r)?);
+impl<'a> RawMethod<'a> {
+    pub fn parse(r: &mut ByteReader<'a>) -> Result<Self> {
+        let flags = r.read_u16()?;
+        Ok(Self {
+            access_flags: MethodAccessFlags::from_bits(flags)
+                .ok_or(Error::InvalidAccessFlags { flags })?,
+            name_index: r.read_u16()?.into(),
+            descriptor_index: r.read_u16()?.into(),
+            attributes: read_table(r, RawAttribute::parse)?,
+        })
+    }
+
+    /// Copy any borrowed bytes so that this no longer borrows from the input
+    pub fn into_owned(self) -> RawMethod<'static> {
+        RawMethod {
+            access_flags: self.access_flags,
+            name_index: self.name_index,
+            descriptor_index: self.descriptor_index,
+            attributes: RawAttribute::all_into_owned(self.attributes),
         }
+    }
 
-        Ok(out)
+    pub fn write_to<W>(&self, w: &mut W) -> anyhow::Result<()>
+    where
+        W: Write,
+    {
+        w.write_u16(self.access_flags.bits())?;
+        write_index(w, self.name_index)?;
+        write_index(w, self.descriptor_index)?;
+        RawAttribute::write_all(w, &self.attributes)
     }
 }
@@ -1,72 +1,27 @@
-use std::io::{self, Cursor, Seek};
+use crate::{
+    bytecode::Instructions,
+    bytes::{read_table, ByteReader},
+    constant_pool::{ConstantPool, Loadable, MethodHandle, NameAndType},
+    error::{Error, Result},
+};
 
-use crate::bytes::ReadNum;
-
-use super::{raw::*, NestedClassAccessFlags};
+use super::{
+    raw::*, MethodParameterFlags, ModuleExportsFlags, ModuleFlags, ModuleRequiresFlags,
+    NestedClassAccessFlags,
+};
 use super::{FieldAccessFlags, MethodAccessFlags};
 
-#[derive(Debug, Clone)]
-pub enum Constant<'a> {
-    Class {
-        name: &'a str,
-    },
-    FieldRef {
-        class: &'a Constant<'a>,
-        name_and_type: &'a Constant<'a>,
-    },
-    MethodRef {
-        class_index: usize,
-        name_and_type_index: usize,
-    },
-    InterfaceMethodRef {
-        class_index: usize,
-        name_and_type_index: usize,
-    },
-    String {
-        string_index: usize,
-    },
-    Integer {
-        num: i32,
-    },
-    Float {
-        num: f32,
-    },
-    Long {
-        num: i64,
-    },
-    Double {
-        num: f64,
-    },
-    NameAndType {
-        name_index: usize,
-        descriptor_index: usize,
-    },
-    Utf8 {
-        string: String,
-    },
-    MethodHandle {
-        reference_kind: u8,
-        reference_index: usize,
-    },
-    MethodType {
-        descriptor_index: usize,
-    },
-    InvokeDynamic {
-        bootstrap_method_attr_index: usize,
-        name_and_type_index: usize,
-    },
-}
-
 #[derive(Debug, Clone)]
 pub struct Method<'a> {
     pub access_flags: MethodAccessFlags,
     pub name: &'a str,
     pub descriptor: &'a str,
-    pub attributes: &'a [RawAttribute],
-    pub constant_pool: &'a [RawConstant],
+    pub attributes: &'a [RawAttribute<'a>],
+    pub constant_pool: &'a ConstantPool<'a>,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Exception {
     pub start_pc: u16,
     pub end_pc: u16,
@@ -75,92 +30,351 @@ pub struct Exception {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct InnerClassInfo<'a> {
-    inner_class_info: &'a RawConstant,
-    outer_class_info: &'a RawConstant,
-    inner_name: &'a str,
-    inner_class_access_flags: NestedClassAccessFlags,
+    pub inner_class: &'a str,
+    /// `None` if the class is not a member of a class or interface
+    pub outer_class: Option<&'a str>,
+    /// `None` if the class is anonymous
+    pub inner_name: Option<&'a str>,
+    pub inner_class_access_flags: NestedClassAccessFlags,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LineNumber {
-    start_pc: usize,
-    line_number: usize,
+    pub start_pc: usize,
+    pub line_number: usize,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LocalVariable<'a> {
-    start_pc: usize,
-    length: usize,
-    name: &'a str,
-    descriptor: &'a str,
-    index: usize,
+    pub start_pc: usize,
+    pub length: usize,
+    pub name: &'a str,
+    pub descriptor: &'a str,
+    pub index: usize,
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LocalVariableType<'a> {
-    start_pc: usize,
-    length: usize,
-    name: &'a str,
-    signature: &'a str,
-    index: usize,
+    pub start_pc: usize,
+    pub length: usize,
+    pub name: &'a str,
+    pub signature: &'a str,
+    pub index: usize,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// See <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.16.1>
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ElementValue<'a> {
+    /// `B`
+    Byte(i8),
+    /// `C`
+    Char(u16),
+    /// `D`
+    Double(f64),
+    /// `F`
+    Float(f32),
+    /// `I`
+    Int(i32),
+    /// `J`
+    Long(i64),
+    /// `S`
+    Short(i16),
+    /// `Z`
+    Boolean(bool),
+    /// `s`
+    String(&'a str),
+    /// `e` -- an enum constant
+    Enum {
+        /// Field descriptor of the enum type
+        type_name: &'a str,
+        /// Simple name of the enum constant
+        const_name: &'a str,
+    },
+    /// `c` -- a class literal, such as `String.class` or `void.class`
+    Class {
+        /// Return descriptor of the class, i.e. `V` for `void.class`
+        return_descriptor: &'a str,
+    },
+    /// `@` -- a nested annotation
+    Annotation(Annotation<'a>),
+    /// `[`
+    Array(Vec<ElementValue<'a>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AnnotationElement<'a> {
-    name: &'a str,
-    // TODO:
-    // value: AnnotationElementValue<'a>,
-    // See <https://docs.oracle.com/javase/specs/jvms/se7/html/jvms-4.html#jvms-4.7.16.1>
+    pub name: &'a str,
+    pub value: ElementValue<'a>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Annotation<'a> {
     /// Field descriptor representing the annotation type corresponding to the annotation
     /// represented by this annotation structure
-    ty: &'a str,
+    pub ty: &'a str,
     /// Each value of the `elements` table represents a single element-value pair in this
     /// `annotation`.
-    elements: Vec<AnnotationElement<'a>>,
+    pub elements: Vec<AnnotationElement<'a>>,
+}
+
+/// The kind of type that a type annotation applies to, and where that type is.
+///
+/// See <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.20.1>
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TargetInfo {
+    /// Type parameter declaration of a generic class, interface, method or constructor
+    TypeParameter { type_parameter_index: u8 },
+    /// Type in the `extends` or `implements` clause of a class or interface. An index of `65535`
+    /// refers to the superclass, otherwise it is an index into `interfaces`.
+    Supertype { supertype_index: u16 },
+    /// Bound of a type parameter declaration
+    TypeParameterBound {
+        type_parameter_index: u8,
+        bound_index: u8,
+    },
+    /// Type in a field declaration, return type of a method or receiver type of a method
+    Empty,
+    /// Type in a formal parameter declaration
+    FormalParameter { formal_parameter_index: u8 },
+    /// Type in the `throws` clause of a method, as an index into the `Exceptions` attribute
+    Throws { throws_type_index: u16 },
+    /// Type in a local variable declaration
+    LocalVar { table: Vec<LocalVarTarget> },
+    /// Type in an exception parameter declaration, as an index into the exception table
+    Catch { exception_table_index: u16 },
+    /// Type in an `instanceof`, `new` or method reference expression
+    Offset { offset: u16 },
+    /// Type argument in a cast, constructor invocation or method invocation
+    TypeArgument {
+        offset: u16,
+        type_argument_index: u8,
+    },
+}
+
+/// A range of code in which a local variable has a value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LocalVarTarget {
+    pub start_pc: u16,
+    pub length: u16,
+    pub index: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TypePathKind {
+    /// Annotation is deeper in an array type
+    Array,
+    /// Annotation is deeper in a nested type
+    Nested,
+    /// Annotation is on the bound of a wildcard type argument of a parameterized type
+    WildcardBound,
+    /// Annotation is on a type argument of a parameterized type
+    TypeArgument,
+}
+
+/// One step of the path to the annotated part of a type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TypePathEntry {
+    pub kind: TypePathKind,
+    /// Which type argument is annotated, only meaningful for [`TypePathKind::TypeArgument`]
+    pub type_argument_index: u8,
+}
+
+/// See <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.20>
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TypeAnnotation<'a> {
+    /// The `target_type` value from the class file, which says what kind of type is annotated
+    pub target_type: u8,
+    pub target_info: TargetInfo,
+    pub target_path: Vec<TypePathEntry>,
+    pub annotation: Annotation<'a>,
+}
+
+/// The type of a local variable or operand stack entry in a [`StackMapFrame`]
+///
+/// See <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.4>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum VerificationType<'a> {
+    Top,
+    Integer,
+    Float,
+    /// Takes up two local variables, but only one entry in a frame
+    Long,
+    /// Takes up two local variables, but only one entry in a frame
+    Double,
+    Null,
+    UninitializedThis,
+    /// Instance of the class (or array type) with this name
+    Object(&'a str),
+    /// Result of the `new` instruction at this offset, which has not had its constructor called
+    Uninitialized(u16),
+}
+
+/// See <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.4>
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum StackMapFrameKind<'a> {
+    /// `same_frame`: same locals as the previous frame and an empty stack
+    Same,
+    /// `same_frame_extended`: [`StackMapFrameKind::Same`] with a larger offset delta
+    SameExtended,
+    /// `same_locals_1_stack_item_frame`: same locals as the previous frame and one stack entry
+    SameLocals1StackItem { stack: VerificationType<'a> },
+    /// `same_locals_1_stack_item_frame_extended`: [`StackMapFrameKind::SameLocals1StackItem`]
+    /// with a larger offset delta
+    SameLocals1StackItemExtended { stack: VerificationType<'a> },
+    /// `chop_frame`: the previous frame without its last `absent_locals` locals and an empty
+    /// stack
+    Chop { absent_locals: u8 },
+    /// `append_frame`: the previous frame with extra locals and an empty stack
+    Append { locals: Vec<VerificationType<'a>> },
+    /// `full_frame`
+    Full {
+        locals: Vec<VerificationType<'a>>,
+        stack: Vec<VerificationType<'a>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StackMapFrame<'a> {
+    /// Bytecode offset that this frame applies to, already resolved from the `offset_delta`s
+    pub offset: usize,
+    pub kind: StackMapFrameKind<'a>,
+}
+
+/// A dependence of a module on another module
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ModuleRequires<'a> {
+    pub module: &'a str,
+    pub flags: ModuleRequiresFlags,
+    /// Version of the module when the current module was compiled
+    pub version: Option<&'a str>,
+}
+
+/// A package that is exported or opened by a module
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ModuleExports<'a> {
+    pub package: &'a str,
+    pub flags: ModuleExportsFlags,
+    /// Modules that the package is exported or opened to, everyone if empty
+    pub to: Vec<&'a str>,
+}
+
+/// A service interface and the implementations of it that are provided by a module
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ModuleProvides<'a> {
+    pub service: &'a str,
+    pub with: Vec<&'a str>,
+}
+
+/// The contents of the `Module` attribute
+///
+/// See <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.25>
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Module<'a> {
+    pub name: &'a str,
+    pub flags: ModuleFlags,
+    pub version: Option<&'a str>,
+    pub requires: Vec<ModuleRequires<'a>>,
+    pub exports: Vec<ModuleExports<'a>>,
+    pub opens: Vec<ModuleExports<'a>>,
+    /// Service interfaces which may be discovered by the module via `ServiceLoader`
+    pub uses: Vec<&'a str>,
+    pub provides: Vec<ModuleProvides<'a>>,
+}
+
+/// Everything a `module-info.class` says about the module it describes
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ModuleDescriptor<'a> {
+    pub module: Module<'a>,
+    /// All packages of the module, from the `ModulePackages` attribute, or empty if it is missing
+    pub packages: Vec<&'a str>,
+    /// From the `ModuleMainClass` attribute
+    pub main_class: Option<&'a str>,
 }
 
+/// A component of a record class, see
+/// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.30>
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RecordComponent<'a> {
+    pub name: &'a str,
+    pub descriptor: &'a str,
+    /// Such as `Signature` and annotations on the component
+    pub attributes: Vec<Attribute<'a>>,
+}
+
+/// See <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.7.24>
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MethodParameter<'a> {
+    /// `None` if the parameter has no name
+    pub name: Option<&'a str>,
+    pub access_flags: MethodParameterFlags,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BootstrapMethod<'a> {
-    method_ref: &'a RawConstant,
-    arguments: Vec<&'a RawConstant>,
+    pub method_ref: MethodHandle<'a>,
+    pub arguments: Vec<Loadable<'a>>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Attribute<'a> {
     ConstantValue {
-        value: &'a RawConstant,
+        value: Loadable<'a>,
     },
     Code {
         max_stack: u16,
         max_locals: u16,
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_code"))]
         code: &'a [u8],
         exception_table: Vec<Exception>,
-        // TODO: recursively parse this so we can use Attribute here
-        attributes: Vec<RawAttribute>,
+        /// Such as [`Attribute::LineNumberTable`] and [`Attribute::StackMapTable`]
+        attributes: Vec<Attribute<'a>>,
+    },
+    /// Used during verification by type checking, see [`StackMapFrame`]
+    StackMapTable {
+        entries: Vec<StackMapFrame<'a>>,
     },
-    // TODO: Impl these:
-    StackMapTable, // TODO
     Exceptions {
-        /// Each value in the `exception_index_table` array must be a valid index into the
-        /// `constant_pool` table. The constant_pool entry referenced by each table item must be a
-        /// Class_info representing a class type that this method is declared to throw.
-        exception_index_table: Vec<u16>,
+        /// The names of the classes that this method is declared to throw
+        exceptions: Vec<&'a str>,
     },
     InnerClasses {
         classes: Vec<InnerClassInfo<'a>>,
     },
     EnclosingMethod {
-        class: &'a RawConstant,
-        method_index: &'a RawConstant,
+        class: &'a str,
+        /// `None` if the class is not immediately enclosed by a method or constructor
+        method: Option<NameAndType<'a>>,
     },
     Synthetic,
     Signature {
-        signature: &'a RawConstant,
+        /// Parse with [`crate::signatures::ClassSignature`], [`crate::signatures::MethodSignature`]
+        /// or [`crate::signatures::ReferenceTypeSignature`] depending on where the attribute is
+        signature: &'a str,
     },
     SourceFile {
         sourcefile: &'a str,
@@ -179,19 +393,58 @@ pub enum Attribute<'a> {
     },
     Deprecated,
     RuntimeVisibleAnnotations {
-        // TODO: annotations: Vec<Annotation<'a>>,
+        annotations: Vec<Annotation<'a>>,
     },
     RuntimeInvisibleAnnotations {
-        // TODO
+        annotations: Vec<Annotation<'a>>,
     },
     RuntimeVisibleParameterAnnotations {
-        // TODO
+        /// The annotations on each of the parameters of the method, in order
+        parameter_annotations: Vec<Vec<Annotation<'a>>>,
     },
     RuntimeInvisibleParameterAnnotations {
-        // TODO
+        /// The annotations on each of the parameters of the method, in order
+        parameter_annotations: Vec<Vec<Annotation<'a>>>,
     },
+    RuntimeVisibleTypeAnnotations {
+        annotations: Vec<TypeAnnotation<'a>>,
+    },
+    RuntimeInvisibleTypeAnnotations {
+        annotations: Vec<TypeAnnotation<'a>>,
+    },
+    /// The default value of the annotation interface element represented by this method
     AnnotationDefault {
-        // TODO
+        default_value: ElementValue<'a>,
+    },
+    Module {
+        module: Module<'a>,
+    },
+    /// All the packages of a module, including those which are not exported or opened
+    ModulePackages {
+        packages: Vec<&'a str>,
+    },
+    ModuleMainClass {
+        main_class: &'a str,
+    },
+    /// The class which is the nest host of this class
+    NestHost {
+        host_class: &'a str,
+    },
+    /// The classes which are members of the nest hosted by this class
+    NestMembers {
+        classes: Vec<&'a str>,
+    },
+    /// The classes which are allowed to extend or implement this sealed class
+    PermittedSubclasses {
+        classes: Vec<&'a str>,
+    },
+    /// The components of a record class
+    Record {
+        components: Vec<RecordComponent<'a>>,
+    },
+    /// Names and flags of the formal parameters of a method
+    MethodParameters {
+        parameters: Vec<MethodParameter<'a>>,
     },
     /// The `BootstrapMethods` attribute records bootstrap method specifiers referenced by `invokedynamic` instructions
     BootstrapMethods {
@@ -203,41 +456,441 @@ pub enum Attribute<'a> {
     },
 }
 
+/// Read a `u2` constant pool index and look up the name of the Class constant it refers to
+fn read_class_name<'a>(r: &mut ByteReader, const_pool: &'a ConstantPool<'a>) -> Result<&'a str> {
+    const_pool.class_name(r.read_u16()?.into())
+}
+
+/// Read a `u2` constant pool index and look up the loadable constant it refers to
+fn read_loadable<'a>(r: &mut ByteReader, const_pool: &'a ConstantPool<'a>) -> Result<Loadable<'a>> {
+    const_pool.loadable(r.read_u16()?.into())
+}
+
+/// Read a `u2` constant pool index and look up the Utf8 constant it refers to
+fn read_utf8<'a>(r: &mut ByteReader, const_pool: &'a ConstantPool<'a>) -> Result<&'a str> {
+    const_pool.utf8(r.read_u16()?.into())
+}
+
+/// Annotations can be nested inside each other, this limits how far so that a malicious class
+/// file can't overflow the stack
+const MAX_ANNOTATION_DEPTH: usize = 256;
+
+impl<'a> ElementValue<'a> {
+    fn read(
+        r: &mut ByteReader<'a>,
+        const_pool: &'a ConstantPool<'a>,
+        depth: usize,
+    ) -> Result<Self> {
+        if depth > MAX_ANNOTATION_DEPTH {
+            return Err(Error::TooDeep);
+        }
+
+        let tag = r.read_u8()?;
+
+        // `B`, `C`, `I`, `S` and `Z` all point to an Integer constant
+        let mut read_int = || {
+            let index = r.read_u16()?.into();
+            match const_pool.get(index)? {
+                RawConstant::Integer { num } => Ok(*num),
+                c => Err(Error::WrongConstantKind {
+                    index,
+                    expected: "Integer",
+                    found: c.kind(),
+                }),
+            }
+        };
+
+        Ok(match tag {
+            b'B' => Self::Byte(read_int()? as i8),
+            b'C' => Self::Char(read_int()? as u16),
+            b'I' => Self::Int(read_int()?),
+            b'S' => Self::Short(read_int()? as i16),
+            b'Z' => Self::Boolean(read_int()? != 0),
+            b'D' | b'F' | b'J' => {
+                let index = r.read_u16()?.into();
+                match (tag, const_pool.get(index)?) {
+                    (b'D', RawConstant::Double { num }) => Self::Double(*num),
+                    (b'F', RawConstant::Float { num }) => Self::Float(*num),
+                    (b'J', RawConstant::Long { num }) => Self::Long(*num),
+                    (_, c) => {
+                        return Err(Error::WrongConstantKind {
+                            index,
+                            expected: match tag {
+                                b'D' => "Double",
+                                b'F' => "Float",
+                                _ => "Long",
+                            },
+                            found: c.kind(),
+                        })
+                    }
+                }
+            }
+            b's' => Self::String(read_utf8(r, const_pool)?),
+            b'e' => Self::Enum {
+                type_name: read_utf8(r, const_pool)?,
+                const_name: read_utf8(r, const_pool)?,
+            },
+            b'c' => Self::Class {
+                return_descriptor: read_utf8(r, const_pool)?,
+            },
+            b'@' => Self::Annotation(Annotation::read(r, const_pool, depth + 1)?),
+            b'[' => Self::Array(read_table(r, |r| {
+                ElementValue::read(r, const_pool, depth + 1)
+            })?),
+            tag => {
+                return Err(Error::InvalidTag {
+                    kind: "element_value",
+                    tag,
+                })
+            }
+        })
+    }
+}
+
+impl<'a> Annotation<'a> {
+    fn read(
+        r: &mut ByteReader<'a>,
+        const_pool: &'a ConstantPool<'a>,
+        depth: usize,
+    ) -> Result<Self> {
+        Ok(Self {
+            ty: read_utf8(r, const_pool)?,
+            elements: read_table(r, |r| {
+                Ok(AnnotationElement {
+                    name: read_utf8(r, const_pool)?,
+                    value: ElementValue::read(r, const_pool, depth)?,
+                })
+            })?,
+        })
+    }
+}
+
+/// `parameter_annotations` of the `Runtime*ParameterAnnotations` attributes
+fn read_parameter_annotations<'a>(
+    r: &mut ByteReader<'a>,
+    const_pool: &'a ConstantPool<'a>,
+) -> Result<Vec<Vec<Annotation<'a>>>> {
+    let num_parameters = r.read_u8()?;
+    (0..num_parameters)
+        .map(|_| read_table(r, |r| Annotation::read(r, const_pool, 0)))
+        .collect()
+}
+
+impl TargetInfo {
+    fn read(r: &mut ByteReader, target_type: u8) -> Result<Self> {
+        Ok(match target_type {
+            0x00 | 0x01 => Self::TypeParameter {
+                type_parameter_index: r.read_u8()?,
+            },
+            0x10 => Self::Supertype {
+                supertype_index: r.read_u16()?,
+            },
+            0x11 | 0x12 => Self::TypeParameterBound {
+                type_parameter_index: r.read_u8()?,
+                bound_index: r.read_u8()?,
+            },
+            0x13..=0x15 => Self::Empty,
+            0x16 => Self::FormalParameter {
+                formal_parameter_index: r.read_u8()?,
+            },
+            0x17 => Self::Throws {
+                throws_type_index: r.read_u16()?,
+            },
+            0x40 | 0x41 => Self::LocalVar {
+                table: read_table(r, |r| {
+                    Ok(LocalVarTarget {
+                        start_pc: r.read_u16()?,
+                        length: r.read_u16()?,
+                        index: r.read_u16()?,
+                    })
+                })?,
+            },
+            0x42 => Self::Catch {
+                exception_table_index: r.read_u16()?,
+            },
+            0x43..=0x46 => Self::Offset {
+                offset: r.read_u16()?,
+            },
+            0x47..=0x4b => Self::TypeArgument {
+                offset: r.read_u16()?,
+                type_argument_index: r.read_u8()?,
+            },
+            tag => {
+                return Err(Error::InvalidTag {
+                    kind: "target_type",
+                    tag,
+                })
+            }
+        })
+    }
+}
+
+impl<'a> TypeAnnotation<'a> {
+    fn read(r: &mut ByteReader<'a>, const_pool: &'a ConstantPool<'a>) -> Result<Self> {
+        let target_type = r.read_u8()?;
+        let target_info = TargetInfo::read(r, target_type)?;
+
+        let path_length = r.read_u8()?;
+        let target_path = (0..path_length)
+            .map(|_| {
+                let kind = match r.read_u8()? {
+                    0 => TypePathKind::Array,
+                    1 => TypePathKind::Nested,
+                    2 => TypePathKind::WildcardBound,
+                    3 => TypePathKind::TypeArgument,
+                    tag => {
+                        return Err(Error::InvalidTag {
+                            kind: "type_path_kind",
+                            tag,
+                        })
+                    }
+                };
+                Ok(TypePathEntry {
+                    kind,
+                    type_argument_index: r.read_u8()?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            target_type,
+            target_info,
+            target_path,
+            annotation: Annotation::read(r, const_pool, 0)?,
+        })
+    }
+}
+
+impl<'a> VerificationType<'a> {
+    fn read(r: &mut ByteReader, const_pool: &'a ConstantPool<'a>) -> Result<Self> {
+        Ok(match r.read_u8()? {
+            0 => Self::Top,
+            1 => Self::Integer,
+            2 => Self::Float,
+            3 => Self::Double,
+            4 => Self::Long,
+            5 => Self::Null,
+            6 => Self::UninitializedThis,
+            7 => Self::Object(read_class_name(r, const_pool)?),
+            8 => Self::Uninitialized(r.read_u16()?),
+            tag => {
+                return Err(Error::InvalidTag {
+                    kind: "verification_type_info",
+                    tag,
+                })
+            }
+        })
+    }
+}
+
+impl<'a> StackMapFrame<'a> {
+    /// Read the `entries` of a `StackMapTable`, resolving their offsets
+    fn read_table(r: &mut ByteReader, const_pool: &'a ConstantPool<'a>) -> Result<Vec<Self>> {
+        let count = r.read_u16()?;
+        let mut frames = Vec::with_capacity(count.into());
+        let mut offset = None;
+        for _ in 0..count {
+            let frame_type = r.read_u8()?;
+            let (offset_delta, kind) = match frame_type {
+                0..=63 => (frame_type.into(), StackMapFrameKind::Same),
+                64..=127 => (
+                    u16::from(frame_type - 64),
+                    StackMapFrameKind::SameLocals1StackItem {
+                        stack: VerificationType::read(r, const_pool)?,
+                    },
+                ),
+                247 => (
+                    r.read_u16()?,
+                    StackMapFrameKind::SameLocals1StackItemExtended {
+                        stack: VerificationType::read(r, const_pool)?,
+                    },
+                ),
+                248..=250 => (
+                    r.read_u16()?,
+                    StackMapFrameKind::Chop {
+                        absent_locals: 251 - frame_type,
+                    },
+                ),
+                251 => (r.read_u16()?, StackMapFrameKind::SameExtended),
+                252..=254 => {
+                    let offset_delta = r.read_u16()?;
+                    let locals = (0..frame_type - 251)
+                        .map(|_| VerificationType::read(r, const_pool))
+                        .collect::<Result<_>>()?;
+                    (offset_delta, StackMapFrameKind::Append { locals })
+                }
+                255 => (
+                    r.read_u16()?,
+                    StackMapFrameKind::Full {
+                        locals: read_table(r, |r| VerificationType::read(r, const_pool))?,
+                        stack: read_table(r, |r| VerificationType::read(r, const_pool))?,
+                    },
+                ),
+                tag => {
+                    return Err(Error::InvalidTag {
+                        kind: "stack_map_frame",
+                        tag,
+                    })
+                }
+            };
+
+            // every frame after the first is at least one byte after the previous one, so that
+            // there can't be two frames at the same offset
+            let frame_offset = match offset {
+                None => usize::from(offset_delta),
+                Some(prev) => prev + usize::from(offset_delta) + 1,
+            };
+            offset = Some(frame_offset);
+            frames.push(StackMapFrame {
+                offset: frame_offset,
+                kind,
+            });
+        }
+        Ok(frames)
+    }
+}
+
+impl<'a> Module<'a> {
+    fn read(r: &mut ByteReader, const_pool: &'a ConstantPool<'a>) -> Result<Self> {
+        let read_version = |r: &mut ByteReader| match r.read_u16()? {
+            0 => Ok(None),
+            index => const_pool.utf8(index.into()).map(Some),
+        };
+        let read_module = |r: &mut ByteReader| const_pool.module_name(r.read_u16()?.into());
+        let read_class = |r: &mut ByteReader| read_class_name(r, const_pool);
+        let read_exports = |r: &mut ByteReader| {
+            Ok(ModuleExports {
+                package: const_pool.package_name(r.read_u16()?.into())?,
+                flags: ModuleExportsFlags::from_bits_retain(r.read_u16()?),
+                to: read_table(r, read_module)?,
+            })
+        };
+
+        Ok(Self {
+            name: read_module(r)?,
+            flags: ModuleFlags::from_bits_retain(r.read_u16()?),
+            version: read_version(r)?,
+            requires: read_table(r, |r| {
+                Ok(ModuleRequires {
+                    module: read_module(r)?,
+                    flags: ModuleRequiresFlags::from_bits_retain(r.read_u16()?),
+                    version: read_version(r)?,
+                })
+            })?,
+            exports: read_table(r, read_exports)?,
+            opens: read_table(r, read_exports)?,
+            uses: read_table(r, read_class)?,
+            provides: read_table(r, |r| {
+                Ok(ModuleProvides {
+                    service: read_class(r)?,
+                    with: read_table(r, read_class)?,
+                })
+            })?,
+        })
+    }
+}
+
 impl<'a> Attribute<'a> {
-    pub fn from_raw(raw: &'a RawAttribute, const_pool: &'a [RawConstant]) -> Self {
-        let name = const_pool[raw.attribute_name_index - 1].unwrap_utf8();
-        let mut cursor = Cursor::new(&raw.info);
-        // TODO: Remove these unwraps
-        match name {
+    /// The name of the attribute as it appears in the class file
+    pub fn name(&self) -> &'a str {
+        match self {
+            Self::ConstantValue { .. } => "ConstantValue",
+            Self::Code { .. } => "Code",
+            Self::StackMapTable { .. } => "StackMapTable",
+            Self::Exceptions { .. } => "Exceptions",
+            Self::InnerClasses { .. } => "InnerClasses",
+            Self::EnclosingMethod { .. } => "EnclosingMethod",
+            Self::Synthetic => "Synthetic",
+            Self::Signature { .. } => "Signature",
+            Self::SourceFile { .. } => "SourceFile",
+            Self::SourceDebugExtension { .. } => "SourceDebugExtension",
+            Self::LineNumberTable { .. } => "LineNumberTable",
+            Self::LocalVariableTable { .. } => "LocalVariableTable",
+            Self::LocalVariableTypeTable { .. } => "LocalVariableTypeTable",
+            Self::Deprecated => "Deprecated",
+            Self::RuntimeVisibleAnnotations { .. } => "RuntimeVisibleAnnotations",
+            Self::RuntimeInvisibleAnnotations { .. } => "RuntimeInvisibleAnnotations",
+            Self::RuntimeVisibleParameterAnnotations { .. } => "RuntimeVisibleParameterAnnotations",
+            Self::RuntimeInvisibleParameterAnnotations { .. } => {
+                "RuntimeInvisibleParameterAnnotations"
+            }
+            Self::RuntimeVisibleTypeAnnotations { .. } => "RuntimeVisibleTypeAnnotations",
+            Self::RuntimeInvisibleTypeAnnotations { .. } => "RuntimeInvisibleTypeAnnotations",
+            Self::AnnotationDefault { .. } => "AnnotationDefault",
+            Self::Module { .. } => "Module",
+            Self::ModulePackages { .. } => "ModulePackages",
+            Self::ModuleMainClass { .. } => "ModuleMainClass",
+            Self::NestHost { .. } => "NestHost",
+            Self::NestMembers { .. } => "NestMembers",
+            Self::PermittedSubclasses { .. } => "PermittedSubclasses",
+            Self::Record { .. } => "Record",
+            Self::MethodParameters { .. } => "MethodParameters",
+            Self::BootstrapMethods { .. } => "BootstrapMethods",
+            Self::Other { name, .. } => name,
+        }
+    }
+
+    pub fn from_raw(raw: &'a RawAttribute<'a>, const_pool: &'a ConstantPool<'a>) -> Result<Self> {
+        Self::from_info(raw.attribute_name_index, &raw.info, const_pool, 0)
+    }
+
+    /// Resolve an attribute from the index of its name and its `info`, `depth` being how many
+    /// attributes it is nested inside of
+    fn from_info(
+        name_index: usize,
+        info: &'a [u8],
+        const_pool: &'a ConstantPool<'a>,
+        depth: usize,
+    ) -> Result<Self> {
+        let name = const_pool.utf8(name_index)?;
+        let mut r = ByteReader::new(info);
+
+        Self::parse(name, info, &mut r, const_pool, depth)
+            .and_then(|attribute| {
+                // every byte of the attribute should belong to its contents
+                if r.remaining() == 0 {
+                    Ok(attribute)
+                } else {
+                    Err(Error::BadAttributeLength {
+                        expected: info.len(),
+                        actual: r.offset(),
+                    })
+                }
+            })
+            .map_err(|e| Error::Attribute {
+                name: name.into(),
+                offset: r.offset(),
+                source: Box::new(e),
+            })
+    }
+
+    fn parse(
+        name: &'a str,
+        info: &'a [u8],
+        r: &mut ByteReader<'a>,
+        const_pool: &'a ConstantPool<'a>,
+        depth: usize,
+    ) -> Result<Self> {
+        Ok(match name {
             "ConstantValue" => Self::ConstantValue {
-                value: &const_pool[cursor.read_u16().unwrap() as usize],
+                value: read_loadable(r, const_pool)?,
             },
             "Code" => {
-                let max_stack = cursor.read_u16().unwrap();
-                let max_locals = cursor.read_u16().unwrap();
-                let code_length = cursor.read_u32().unwrap();
-                let code = &raw.info[cursor.position() as usize..][..code_length as usize];
-                cursor
-                    .seek(io::SeekFrom::Current(code_length as i64))
-                    .unwrap();
-
-                let exception_table_len = cursor.read_u16().unwrap();
-                let mut exception_table = Vec::with_capacity(exception_table_len.into());
-                for _ in 0..exception_table_len {
-                    exception_table.push(Exception {
-                        start_pc: cursor.read_u16().unwrap(),
-                        end_pc: cursor.read_u16().unwrap(),
-                        handler_pc: cursor.read_u16().unwrap(),
-                        catch_type: cursor.read_u16().unwrap(),
-                    });
-                }
+                let max_stack = r.read_u16()?;
+                let max_locals = r.read_u16()?;
+                let code_length = r.read_u32()?;
+                let code = r.read_bytes(code_length as usize)?;
 
-                let attributes_count = cursor.read_u16().unwrap();
-                let mut attributes = Vec::with_capacity(attributes_count.into());
-                for _ in 0..attributes_count {
-                    let raw = RawAttribute::read_from(&mut cursor).unwrap();
-                    attributes.push(raw);
-                }
+                let exception_table = read_table(r, |r| {
+                    Ok(Exception {
+                        start_pc: r.read_u16()?,
+                        end_pc: r.read_u16()?,
+                        handler_pc: r.read_u16()?,
+                        catch_type: r.read_u16()?,
+                    })
+                })?;
+
+                let attributes = read_attributes(r, const_pool, depth + 1)?;
 
                 Self::Code {
                     max_stack,
@@ -247,160 +900,688 @@ impl<'a> Attribute<'a> {
                     attributes,
                 }
             }
-            // TODO: Impl these:
-            "StackMapTable" => Self::StackMapTable {},
+            "StackMapTable" => Self::StackMapTable {
+                entries: StackMapFrame::read_table(r, const_pool)?,
+            },
             "Exceptions" => Self::Exceptions {
-                exception_index_table: (0..cursor.read_u16().unwrap())
-                    .map(|_| cursor.read_u16().unwrap())
-                    .collect(),
-            },
-            "InnerClasses" => {
-                let classes = (0..cursor.read_u16().unwrap())
-                    .map(|_| {
-                        let inner_class_info = &const_pool[cursor.read_u16().unwrap() as usize - 1];
-                        let outer_class_info = &const_pool[cursor.read_u16().unwrap() as usize - 1];
-                        let inner_name =
-                            &const_pool[cursor.read_u16().unwrap() as usize - 1].unwrap_utf8();
-                        let access_flags = cursor.read_u16().unwrap();
-                        InnerClassInfo {
-                            inner_class_info,
-                            outer_class_info,
-                            inner_name,
-                            inner_class_access_flags: NestedClassAccessFlags::from_bits(
-                                access_flags,
-                            )
-                            .unwrap_or_else(|| {
-                                panic!("Invalid Class Access Flags: 0x{:x}", access_flags)
-                            }),
-                        }
+                exceptions: read_table(r, |r| read_class_name(r, const_pool))?,
+            },
+            "InnerClasses" => Self::InnerClasses {
+                classes: read_table(r, |r| {
+                    let inner_class = read_class_name(r, const_pool)?;
+                    let outer_class = match r.read_u16()? {
+                        0 => None,
+                        index => Some(const_pool.class_name(index.into())?),
+                    };
+                    let inner_name = match r.read_u16()? {
+                        0 => None,
+                        index => Some(const_pool.utf8(index.into())?),
+                    };
+                    let flags = r.read_u16()?;
+                    Ok(InnerClassInfo {
+                        inner_class,
+                        outer_class,
+                        inner_name,
+                        inner_class_access_flags: NestedClassAccessFlags::from_bits_retain(flags),
                     })
-                    .collect();
-                Self::InnerClasses { classes }
-                //Self::InnerClasses { info: &raw.info }
-            }
+                })?,
+            },
             "EnclosingMethod" => Self::EnclosingMethod {
-                class: &const_pool[cursor.read_u16().unwrap() as usize - 1],
-                method_index: &const_pool[cursor.read_u16().unwrap() as usize - 1],
+                class: read_class_name(r, const_pool)?,
+                method: match r.read_u16()? {
+                    0 => None,
+                    index => Some(const_pool.name_and_type(index.into())?),
+                },
             },
             "Synthetic" => Self::Synthetic,
             "Signature" => Self::Signature {
-                signature: &const_pool[cursor.read_u16().unwrap() as usize - 1],
+                signature: read_utf8(r, const_pool)?,
             },
             "SourceFile" => Self::SourceFile {
-                sourcefile: &const_pool[cursor.read_u16().unwrap() as usize - 1].unwrap_utf8(),
+                sourcefile: read_utf8(r, const_pool)?,
             },
             "SourceDebugExtension" => Self::SourceDebugExtension {
-                debug_extension: &raw.info,
+                debug_extension: r.read_bytes(r.remaining())?,
             },
             "LineNumberTable" => Self::LineNumberTable {
-                table: (0..cursor.read_u16().unwrap())
-                    .map(|_| LineNumber {
-                        start_pc: cursor.read_u16().unwrap().into(),
-                        line_number: cursor.read_u16().unwrap().into(),
+                table: read_table(r, |r| {
+                    Ok(LineNumber {
+                        start_pc: r.read_u16()?.into(),
+                        line_number: r.read_u16()?.into(),
                     })
-                    .collect(),
+                })?,
             },
             "LocalVariableTable" => Self::LocalVariableTable {
-                table: (0..cursor.read_u16().unwrap())
-                    .map(|_| LocalVariable {
-                        start_pc: cursor.read_u16().unwrap().into(),
-                        length: cursor.read_u16().unwrap().into(),
-                        name: &const_pool[cursor.read_u16().unwrap() as usize - 1].unwrap_utf8(),
-                        descriptor: &const_pool[cursor.read_u16().unwrap() as usize - 1]
-                            .unwrap_utf8(),
-                        index: cursor.read_u16().unwrap().into(),
+                table: read_table(r, |r| {
+                    Ok(LocalVariable {
+                        start_pc: r.read_u16()?.into(),
+                        length: r.read_u16()?.into(),
+                        name: read_utf8(r, const_pool)?,
+                        descriptor: read_utf8(r, const_pool)?,
+                        index: r.read_u16()?.into(),
                     })
-                    .collect(),
+                })?,
             },
             "LocalVariableTypeTable" => Self::LocalVariableTypeTable {
-                table: (0..cursor.read_u16().unwrap())
-                    .map(|_| LocalVariableType {
-                        start_pc: cursor.read_u16().unwrap().into(),
-                        length: cursor.read_u16().unwrap().into(),
-                        name: &const_pool[cursor.read_u16().unwrap() as usize - 1].unwrap_utf8(),
-                        signature: &const_pool[cursor.read_u16().unwrap() as usize - 1]
-                            .unwrap_utf8(),
-                        index: cursor.read_u16().unwrap().into(),
+                table: read_table(r, |r| {
+                    Ok(LocalVariableType {
+                        start_pc: r.read_u16()?.into(),
+                        length: r.read_u16()?.into(),
+                        name: read_utf8(r, const_pool)?,
+                        signature: read_utf8(r, const_pool)?,
+                        index: r.read_u16()?.into(),
                     })
-                    .collect(),
+                })?,
             },
             "Deprecated" => Self::Deprecated,
 
-            // TODO: Annotations
-            "RuntimeVisibleAnnotations" => Self::RuntimeVisibleAnnotations {},
-            "RuntimeInvisibleAnnotations" => Self::RuntimeInvisibleAnnotations {},
-            "RuntimeVisibleParameterAnnotations" => Self::RuntimeVisibleParameterAnnotations {},
-            "RuntimeInvisibleParameterAnnotations" => Self::RuntimeInvisibleParameterAnnotations {},
-            "AnnotationDefault" => Self::AnnotationDefault {},
+            "RuntimeVisibleAnnotations" => Self::RuntimeVisibleAnnotations {
+                annotations: read_table(r, |r| Annotation::read(r, const_pool, 0))?,
+            },
+            "RuntimeInvisibleAnnotations" => Self::RuntimeInvisibleAnnotations {
+                annotations: read_table(r, |r| Annotation::read(r, const_pool, 0))?,
+            },
+            "RuntimeVisibleParameterAnnotations" => Self::RuntimeVisibleParameterAnnotations {
+                parameter_annotations: read_parameter_annotations(r, const_pool)?,
+            },
+            "RuntimeInvisibleParameterAnnotations" => Self::RuntimeInvisibleParameterAnnotations {
+                parameter_annotations: read_parameter_annotations(r, const_pool)?,
+            },
+            "RuntimeVisibleTypeAnnotations" => Self::RuntimeVisibleTypeAnnotations {
+                annotations: read_table(r, |r| TypeAnnotation::read(r, const_pool))?,
+            },
+            "RuntimeInvisibleTypeAnnotations" => Self::RuntimeInvisibleTypeAnnotations {
+                annotations: read_table(r, |r| TypeAnnotation::read(r, const_pool))?,
+            },
+            "AnnotationDefault" => Self::AnnotationDefault {
+                default_value: ElementValue::read(r, const_pool, 0)?,
+            },
 
+            "Module" => Self::Module {
+                module: Module::read(r, const_pool)?,
+            },
+            "ModulePackages" => Self::ModulePackages {
+                packages: read_table(r, |r| const_pool.package_name(r.read_u16()?.into()))?,
+            },
+            "ModuleMainClass" => Self::ModuleMainClass {
+                main_class: read_class_name(r, const_pool)?,
+            },
+            "NestHost" => Self::NestHost {
+                host_class: read_class_name(r, const_pool)?,
+            },
+            "NestMembers" => Self::NestMembers {
+                classes: read_table(r, |r| read_class_name(r, const_pool))?,
+            },
+            "PermittedSubclasses" => Self::PermittedSubclasses {
+                classes: read_table(r, |r| read_class_name(r, const_pool))?,
+            },
+            "Record" => Self::Record {
+                components: read_table(r, |r| {
+                    Ok(RecordComponent {
+                        name: read_utf8(r, const_pool)?,
+                        descriptor: read_utf8(r, const_pool)?,
+                        attributes: read_attributes(r, const_pool, depth + 1)?,
+                    })
+                })?,
+            },
+            "MethodParameters" => {
+                let parameters_count = r.read_u8()?;
+                Self::MethodParameters {
+                    parameters: (0..parameters_count)
+                        .map(|_| {
+                            let name = match r.read_u16()? {
+                                0 => None,
+                                index => Some(const_pool.utf8(index.into())?),
+                            };
+                            let flags = r.read_u16()?;
+                            Ok(MethodParameter {
+                                name,
+                                access_flags: MethodParameterFlags::from_bits_retain(flags),
+                            })
+                        })
+                        .collect::<Result<_>>()?,
+                }
+            }
             "BootstrapMethods" => Self::BootstrapMethods {
-                methods: (0..cursor.read_u16().unwrap())
-                    .map(|_| BootstrapMethod {
-                        method_ref: &const_pool[cursor.read_u16().unwrap() as usize - 1],
-                        arguments: (0..cursor.read_u16().unwrap())
-                            .map(|_| &const_pool[cursor.read_u16().unwrap() as usize - 1])
-                            .collect(),
+                methods: read_table(r, |r| {
+                    Ok(BootstrapMethod {
+                        method_ref: const_pool.method_handle(r.read_u16()?.into())?,
+                        arguments: read_table(r, |r| read_loadable(r, const_pool))?,
                     })
-                    .collect(),
+                })?,
             },
             a => {
                 eprintln!("Unknown attribute {}", a);
-                Self::Other {
-                    name: a,
-                    info: &raw.info,
-                }
+                r.read_bytes(r.remaining())?;
+                Self::Other { name: a, info }
             }
+        })
+    }
+}
+
+/// Attributes can be nested inside each other (e.g. a `Code` attribute inside of a `Code`
+/// attribute, or a `Record` inside of one of its components), this limits how far so that a malicious class file can't overflow the stack
+const MAX_ATTRIBUTE_DEPTH: usize = 16;
+
+/// Read a `u2` count followed by that many attributes, as nested inside some attributes, `depth`
+/// being how many attributes they are nested inside of
+fn read_attributes<'a>(
+    r: &mut ByteReader<'a>,
+    const_pool: &'a ConstantPool<'a>,
+    depth: usize,
+) -> Result<Vec<Attribute<'a>>> {
+    if depth > MAX_ATTRIBUTE_DEPTH {
+        return Err(Error::TooDeep);
+    }
+    read_table(r, |r| {
+        let name_index = r.read_u16()?.into();
+        let len = r.read_u32()?;
+        Attribute::from_info(name_index, r.read_bytes(len as usize)?, const_pool, depth)
+    })
+}
+
+/// Find the attribute called `name` and resolve it
+pub(crate) fn find_attribute<'a>(
+    attributes: &'a [RawAttribute<'a>],
+    const_pool: &'a ConstantPool<'a>,
+    name: &str,
+) -> Result<Option<Attribute<'a>>> {
+    for raw in attributes {
+        if const_pool.utf8(raw.attribute_name_index)? == name {
+            return Attribute::from_raw(raw, const_pool).map(Some);
         }
     }
+    Ok(None)
 }
 
 impl<'a> Method<'a> {
-    pub(crate) fn from_raw(raw: &'a RawMethod, constant_pool: &'a [RawConstant]) -> Self {
-        Self {
+    pub(crate) fn from_raw(
+        raw: &'a RawMethod<'a>,
+        constant_pool: &'a ConstantPool<'a>,
+    ) -> Result<Self> {
+        Ok(Self {
             access_flags: raw.access_flags,
-            name: constant_pool[raw.name_index - 1].unwrap_utf8(),
-            descriptor: constant_pool[raw.descriptor_index - 1].unwrap_utf8(),
+            name: constant_pool.utf8(raw.name_index)?,
+            descriptor: constant_pool.utf8(raw.descriptor_index)?,
             attributes: &raw.attributes,
             constant_pool,
-        }
+        })
     }
-    pub fn attributes(&self) -> impl Iterator<Item = Attribute<'_>> {
+
+    pub fn attributes(&self) -> impl Iterator<Item = Result<Attribute<'a>>> + 'a {
+        let constant_pool = self.constant_pool;
         self.attributes
             .iter()
-            .map(|r| Attribute::from_raw(r, self.constant_pool))
+            .map(move |r| Attribute::from_raw(r, constant_pool))
     }
 
-    pub fn code(&self) -> Option<Attribute> {
-        self.attributes
-            .iter()
-            .map(|r| Attribute::from_raw(r, self.constant_pool))
-            .find(|a| matches!(a, Attribute::Code { .. }))
+    pub fn code(&self) -> Result<Option<Attribute<'a>>> {
+        find_attribute(self.attributes, self.constant_pool, "Code")
+    }
+
+    /// The instructions in the method's code, `None` if there is no `Code` attribute
+    pub fn instructions(&self) -> Result<Option<Instructions<'a>>> {
+        Ok(match self.code()? {
+            Some(Attribute::Code { code, .. }) => Some(Instructions::new(code)),
+            _ => None,
+        })
+    }
+
+    /// The attributes nested inside the `Code` attribute, empty if there is no code
+    fn code_attributes(&self) -> Result<Vec<Attribute<'a>>> {
+        Ok(match self.code()? {
+            Some(Attribute::Code { attributes, .. }) => attributes,
+            _ => Vec::new(),
+        })
+    }
+
+    /// The source line of the instruction at `pc`, from the `LineNumberTable` attributes of the
+    /// method's code
+    pub fn line_number_at(&self, pc: usize) -> Result<Option<usize>> {
+        let mut closest: Option<LineNumber> = None;
+        for attribute in self.code_attributes()? {
+            let Attribute::LineNumberTable { table } = attribute else {
+                continue;
+            };
+            // the entry which starts nearest before `pc` is the one that covers it
+            for entry in table {
+                if entry.start_pc <= pc && closest.is_none_or(|c| entry.start_pc >= c.start_pc) {
+                    closest = Some(entry);
+                }
+            }
+        }
+        Ok(closest.map(|c| c.line_number))
+    }
+
+    /// The local variables which are in scope at `pc`, from the `LocalVariableTable` attributes
+    /// of the method's code
+    pub fn local_variables_at(&self, pc: usize) -> Result<Vec<LocalVariable<'a>>> {
+        let mut variables = Vec::new();
+        for attribute in self.code_attributes()? {
+            if let Attribute::LocalVariableTable { table } = attribute {
+                variables.extend(
+                    table
+                        .into_iter()
+                        .filter(|v| (v.start_pc..v.start_pc + v.length).contains(&pc)),
+                );
+            }
+        }
+        Ok(variables)
+    }
+
+    /// The names and flags of the parameters from the `MethodParameters` attribute, if present
+    pub fn parameters(&self) -> Result<Option<Vec<MethodParameter<'a>>>> {
+        Ok(
+            match find_attribute(self.attributes, self.constant_pool, "MethodParameters")? {
+                Some(Attribute::MethodParameters { parameters }) => Some(parameters),
+                _ => None,
+            },
+        )
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Field<'a> {
     pub access_flags: FieldAccessFlags,
     pub name: &'a str,
     pub descriptor: &'a str,
-    attributes: &'a [RawAttribute],
-    constant_pool: &'a [RawConstant],
+    attributes: &'a [RawAttribute<'a>],
+    constant_pool: &'a ConstantPool<'a>,
 }
 
 impl<'a> Field<'a> {
-    pub(crate) fn from_raw(raw: &'a RawField, const_pool: &'a [RawConstant]) -> Self {
-        Self {
+    pub(crate) fn from_raw(
+        raw: &'a RawField<'a>,
+        const_pool: &'a ConstantPool<'a>,
+    ) -> Result<Self> {
+        Ok(Self {
             access_flags: raw.access_flags,
-            name: const_pool[raw.name_index - 1].unwrap_utf8(),
-            descriptor: const_pool[raw.descriptor_index - 1].unwrap_utf8(),
+            name: const_pool.utf8(raw.name_index)?,
+            descriptor: const_pool.utf8(raw.descriptor_index)?,
             attributes: &raw.attributes,
             constant_pool: const_pool,
-        }
+        })
     }
 
-    pub fn attributes(&self) -> impl Iterator<Item = Attribute<'_>> {
+    pub fn attributes(&self) -> impl Iterator<Item = Result<Attribute<'a>>> + 'a {
+        let constant_pool = self.constant_pool;
         self.attributes
             .iter()
-            .map(|r| Attribute::from_raw(r, self.constant_pool))
+            .map(move |r| Attribute::from_raw(r, constant_pool))
+    }
+}
+
+/// Serialise code as a map from the `pc` of each instruction to the instruction
+#[cfg(feature = "serde")]
+fn serialize_code<S>(code: &&[u8], s: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    let instructions = Instructions::new(code)
+        .collect::<Result<Vec<_>>>()
+        .map_err(serde::ser::Error::custom)?;
+    s.collect_map(instructions)
+}
+
+/// Serialise a field or method with its attributes resolved
+#[cfg(feature = "serde")]
+fn serialize_member<S, F>(
+    s: S,
+    name: &'static str,
+    access_flags: F,
+    member_name: &str,
+    descriptor: &str,
+    attributes: &[RawAttribute],
+    constant_pool: &ConstantPool,
+) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+    F: serde::Serialize,
+{
+    use serde::ser::SerializeStruct;
+
+    let attributes = attributes
+        .iter()
+        .map(|a| Attribute::from_raw(a, constant_pool))
+        .collect::<Result<Vec<_>>>()
+        .map_err(serde::ser::Error::custom)?;
+    let mut state = s.serialize_struct(name, 4)?;
+    state.serialize_field("access_flags", &access_flags)?;
+    state.serialize_field("name", member_name)?;
+    state.serialize_field("descriptor", descriptor)?;
+    state.serialize_field("attributes", &attributes)?;
+    state.end()
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Method<'_> {
+    fn serialize<S>(&self, s: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serialize_member(
+            s,
+            "Method",
+            self.access_flags,
+            self.name,
+            self.descriptor,
+            self.attributes,
+            self.constant_pool,
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Field<'_> {
+    fn serialize<S>(&self, s: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serialize_member(
+            s,
+            "Field",
+            self.access_flags,
+            self.name,
+            self.descriptor,
+            self.attributes,
+            self.constant_pool,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{builder::ConstantPoolBuilder, test::stdlib_classes, ClassFile};
+    use std::{fs, io::Cursor};
+
+    fn read_stdlib_class(path: &str) -> Option<ClassFile<'static>> {
+        let paths = stdlib_classes(path)?;
+        let bytes = fs::read(&paths[0]).unwrap();
+        Some(ClassFile::read_from(&mut Cursor::new(bytes)).unwrap())
+    }
+
+    #[test]
+    fn deprecated_annotations() {
+        let Some(class) = read_stdlib_class("java.base/java/lang/Deprecated.class") else {
+            return;
+        };
+
+        let annotations = class
+            .attributes()
+            .find_map(|a| match a.unwrap() {
+                Attribute::RuntimeVisibleAnnotations { annotations } => Some(annotations),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(annotations[0].ty, "Ljava/lang/annotation/Documented;");
+        assert!(annotations[0].elements.is_empty());
+        assert_eq!(
+            annotations[1],
+            Annotation {
+                ty: "Ljava/lang/annotation/Retention;",
+                elements: vec![AnnotationElement {
+                    name: "value",
+                    value: ElementValue::Enum {
+                        type_name: "Ljava/lang/annotation/RetentionPolicy;",
+                        const_name: "RUNTIME",
+                    },
+                }],
+            }
+        );
+        let ElementValue::Array(targets) = &annotations[2].elements[0].value else {
+            panic!("expected array, got {:?}", annotations[2]);
+        };
+        assert_eq!(targets.len(), 8);
+
+        let defaults: Vec<_> = class
+            .methods()
+            .map(|m| {
+                let m = m.unwrap();
+                let default = m.attributes().find_map(|a| match a.unwrap() {
+                    Attribute::AnnotationDefault { default_value } => Some(default_value),
+                    _ => None,
+                });
+                (m.name, default.unwrap())
+            })
+            .collect();
+        assert_eq!(
+            defaults,
+            [
+                ("since", ElementValue::String("")),
+                ("forRemoval", ElementValue::Boolean(false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_annotations() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/TypeAnnotations.class");
+        let Ok(bytes) = fs::read(path) else {
+            eprintln!("{} not found, run test/build.sh to compile it", path);
+            return;
+        };
+        let class = ClassFile::read_from(&mut Cursor::new(bytes)).unwrap();
+        fn type_annotations<'a>(
+            mut attributes: impl Iterator<Item = Result<Attribute<'a>>>,
+        ) -> Vec<TypeAnnotation<'a>> {
+            attributes
+                .find_map(|a| match a.unwrap() {
+                    Attribute::RuntimeVisibleTypeAnnotations { annotations } => Some(annotations),
+                    _ => None,
+                })
+                .unwrap()
+        }
+        let nullable = Annotation {
+            ty: "LTypeAnnotations$Nullable;",
+            elements: Vec::new(),
+        };
+
+        // List<@Nullable String> names
+        let field = class.fields().map(Result::unwrap).next().unwrap();
+        assert_eq!(
+            type_annotations(field.attributes()),
+            [TypeAnnotation {
+                target_type: 0x13,
+                target_info: TargetInfo::Empty,
+                target_path: vec![TypePathEntry {
+                    kind: TypePathKind::TypeArgument,
+                    type_argument_index: 0,
+                }],
+                annotation: nullable.clone(),
+            }]
+        );
+
+        // first(@Nullable String[] values)
+        let first = class
+            .find_method("first", "([Ljava/lang/String;)Ljava/lang/String;")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            type_annotations(first.attributes()),
+            [TypeAnnotation {
+                target_type: 0x16,
+                target_info: TargetInfo::FormalParameter {
+                    formal_parameter_index: 0
+                },
+                target_path: vec![TypePathEntry {
+                    kind: TypePathKind::Array,
+                    type_argument_index: 0,
+                }],
+                annotation: nullable,
+            }]
+        );
+    }
+
+    /// The error at the bottom of the attributes an error is nested in
+    fn innermost(mut error: Error) -> Error {
+        while let Error::Attribute { source, .. } = error {
+            error = *source;
+        }
+        error
+    }
+
+    #[test]
+    fn nested_code() {
+        let mut pool = ConstantPoolBuilder::new();
+        let name = pool.utf8("Code").unwrap();
+        let pool = pool.build();
+
+        // a `Code` attribute with no code, holding `info` as its only attribute
+        let code = |info: Vec<u8>| {
+            let mut code = vec![0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1];
+            code.extend((name as u16).to_be_bytes());
+            code.extend((info.len() as u32).to_be_bytes());
+            code.extend(info);
+            code
+        };
+        let mut info = vec![0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+        for _ in 0..2000 {
+            info = code(info);
+        }
+        let error = Attribute::from_info(name, &info, &pool, 0).unwrap_err();
+        assert_eq!(innermost(error), Error::TooDeep);
+
+        let info = code(code(vec![0, 1, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0]));
+        assert!(Attribute::from_info(name, &info, &pool, 0).is_ok());
+    }
+
+    #[test]
+    fn nested_record() {
+        let mut pool = ConstantPoolBuilder::new();
+        let name = pool.utf8("Record").unwrap();
+        let component = pool.utf8("x").unwrap() as u16;
+        let descriptor = pool.utf8("I").unwrap() as u16;
+        let pool = pool.build();
+
+        // a `Record` with one component, which has `info` as its only attribute
+        let record = |info: Vec<u8>| {
+            let mut record = vec![0, 1];
+            record.extend(component.to_be_bytes());
+            record.extend(descriptor.to_be_bytes());
+            record.extend([0, 1]);
+            record.extend((name as u16).to_be_bytes());
+            record.extend((info.len() as u32).to_be_bytes());
+            record.extend(info);
+            record
+        };
+        let mut info = vec![0, 0];
+        for _ in 0..2000 {
+            info = record(info);
+        }
+        let error = Attribute::from_info(name, &info, &pool, 0).unwrap_err();
+        assert_eq!(innermost(error), Error::TooDeep);
+
+        let info = record(record(vec![0, 0]));
+        assert!(Attribute::from_info(name, &info, &pool, 0).is_ok());
+    }
+
+    #[test]
+    fn unassigned_flags() {
+        let mut pool = ConstantPoolBuilder::new();
+        let name = pool.utf8("InnerClasses").unwrap();
+        let class = pool.class("A$B").unwrap() as u16;
+        let pool = pool.build();
+
+        // flags which aren't assigned in the spec are to be ignored, not rejected
+        let mut info = vec![0, 1];
+        info.extend(class.to_be_bytes());
+        info.extend([0, 0, 0, 0, 0x01, 0x01]);
+        let Attribute::InnerClasses { classes } =
+            Attribute::from_info(name, &info, &pool, 0).unwrap()
+        else {
+            panic!("Expected InnerClasses");
+        };
+        assert_eq!(classes[0].inner_class_access_flags.bits(), 0x0101);
+        assert!(classes[0]
+            .inner_class_access_flags
+            .contains(NestedClassAccessFlags::PUBLIC));
+
+        let mut pool = ConstantPoolBuilder::new();
+        let name = pool.utf8("MethodParameters").unwrap();
+        let pool = pool.build();
+        let info = [1, 0, 0, 0x10, 0x01];
+        let Attribute::MethodParameters { parameters } =
+            Attribute::from_info(name, &info, &pool, 0).unwrap()
+        else {
+            panic!("Expected MethodParameters");
+        };
+        assert_eq!(parameters[0].access_flags.bits(), 0x1001);
+        assert!(parameters[0]
+            .access_flags
+            .contains(MethodParameterFlags::SYNTHETIC));
+    }
+
+    #[test]
+    fn abs_stack_map() {
+        let Some(class) = read_stdlib_class("java.base/java/lang/Math.class") else {
+            return;
+        };
+
+        let method = class.find_method("abs", "(I)I").unwrap().unwrap();
+        let Some(Attribute::Code { attributes, .. }) = method.code().unwrap() else {
+            panic!("abs has no code");
+        };
+        let entries = attributes
+            .into_iter()
+            .find_map(|a| match a {
+                Attribute::StackMapTable { entries } => Some(entries),
+                _ => None,
+            })
+            .unwrap();
+
+        assert_eq!(
+            entries,
+            [
+                StackMapFrame {
+                    offset: 9,
+                    kind: StackMapFrameKind::Same,
+                },
+                StackMapFrame {
+                    offset: 10,
+                    kind: StackMapFrameKind::SameLocals1StackItem {
+                        stack: VerificationType::Integer,
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn debug_info_at_pc() {
+        let Some(class) = read_stdlib_class("java.base/sun/nio/ch/IOUtil$LinkedRunnable.class")
+        else {
+            return;
+        };
+
+        let init = class
+            .find_method("<init>", "(Ljava/lang/Runnable;Ljava/lang/Runnable;)V")
+            .unwrap()
+            .unwrap();
+        let lines: Vec<_> = [0, 3, 4, 9, 18, 19]
+            .into_iter()
+            .map(|pc| init.line_number_at(pc).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                Some(511),
+                Some(511),
+                Some(512),
+                Some(511),
+                Some(511),
+                Some(513)
+            ]
+        );
+
+        let names: Vec<_> = init
+            .local_variables_at(10)
+            .unwrap()
+            .iter()
+            .map(|v| v.name)
+            .collect();
+        assert_eq!(names, ["this", "node", "next"]);
+        assert!(init.local_variables_at(20).unwrap().is_empty());
     }
 }
@@ -0,0 +1,2040 @@
+//! Bytecode verification, see <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.10>
+//!
+//! Class files from version 50 are verified by type checking against the frames in each method's
+//! `StackMapTable` ([JVMS 4.10.1]). Older class files don't have stack maps, so the types at each
+//! instruction are inferred instead ([JVMS 4.10.2]). Version 50 class files fall back to
+//! inference if type checking fails, like HotSpot does.
+//!
+//! Checking whether one class is assignable to another needs to know about other classes, which
+//! is provided by a [`ClassHierarchy`]. Classes that it doesn't know about are assumed to be
+//! assignable.
+//!
+//! [JVMS 4.10.1]: https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.10.1
+//! [JVMS 4.10.2]: https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.10.2
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    bytecode::{ArrayType, Instruction, Instructions, Wide},
+    constant_pool::{ConstantPool, Loadable, MemberKind},
+    descriptors::{FieldType, MethodDescriptor, ReturnDescriptor},
+    error::Error,
+    types::{
+        resolved::{
+            Attribute, Exception, Method, StackMapFrame, StackMapFrameKind, VerificationType,
+        },
+        ClassAccessFlags, MethodAccessFlags,
+    },
+    ClassFile,
+};
+
+const OBJECT: &str = "java/lang/Object";
+const THROWABLE: &str = "java/lang/Throwable";
+
+/// What the verifier needs to know about a class other than the one being verified
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassInfo {
+    /// `None` for `java/lang/Object`
+    pub super_class: Option<String>,
+    pub is_interface: bool,
+}
+
+/// Looks up classes referenced by the class being verified
+pub trait ClassHierarchy {
+    /// Information about the class called `name`, or `None` if it isn't known
+    fn class_info(&self, name: &str) -> Option<ClassInfo>;
+}
+
+/// Knows about no classes, so all classes are assumed to be assignable to each other
+impl ClassHierarchy for () {
+    fn class_info(&self, _name: &str) -> Option<ClassInfo> {
+        None
+    }
+}
+
+impl ClassHierarchy for HashMap<String, ClassFile<'_>> {
+    fn class_info(&self, name: &str) -> Option<ClassInfo> {
+        let class = self.get(name)?;
+        Some(ClassInfo {
+            super_class: class.super_class().ok()?.map(String::from),
+            is_interface: class.access_flags.contains(ClassAccessFlags::INTERFACE),
+        })
+    }
+}
+
+/// The type of a local variable or operand stack entry, see
+/// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.10.1.2>
+///
+/// `long` and `double` take up two entries, the second of which is [`Type::Top`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Top,
+    /// Any type which takes up one entry
+    OneWord,
+    /// Any type which takes up two entries
+    TwoWord,
+    /// Also used for `boolean`, `byte`, `char` and `short`
+    Integer,
+    Float,
+    Long,
+    Double,
+    /// Any reference, including ones that haven't been initialised yet
+    Reference,
+    Null,
+    /// `this` in a constructor, before the super constructor has been called
+    UninitializedThis,
+    /// Result of the `new` instruction at this pc, which has not had its constructor called
+    Uninitialized(usize),
+    /// Instance of the class with this name, or of the array type with this descriptor
+    Object(String),
+    /// Stored by `jsr` to the subroutine starting at this pc, only used by the inference
+    /// verifier
+    ReturnAddress(usize),
+}
+
+impl Type {
+    fn object(name: &str) -> Self {
+        Self::Object(name.into())
+    }
+
+    fn from_field_type(field_type: &FieldType) -> Self {
+        match field_type {
+            FieldType::Byte
+            | FieldType::Char
+            | FieldType::Int
+            | FieldType::Short
+            | FieldType::Boolean => Self::Integer,
+            FieldType::Float => Self::Float,
+            FieldType::Long => Self::Long,
+            FieldType::Double => Self::Double,
+            FieldType::ObjReference(name) => Self::Object(name.clone()),
+            FieldType::ArrReference(_) => Self::Object(field_type.to_string()),
+        }
+    }
+
+    fn from_verification_type(verification_type: &VerificationType) -> Self {
+        match *verification_type {
+            VerificationType::Top => Self::Top,
+            VerificationType::Integer => Self::Integer,
+            VerificationType::Float => Self::Float,
+            VerificationType::Long => Self::Long,
+            VerificationType::Double => Self::Double,
+            VerificationType::Null => Self::Null,
+            VerificationType::UninitializedThis => Self::UninitializedThis,
+            VerificationType::Object(name) => Self::object(name),
+            VerificationType::Uninitialized(pc) => Self::Uninitialized(pc.into()),
+        }
+    }
+
+    /// Whether the type takes up two entries
+    fn is_wide(&self) -> bool {
+        matches!(self, Self::Long | Self::Double | Self::TwoWord)
+    }
+
+    /// The type of the elements if this is a reference to an array of references
+    fn component(&self) -> Option<Type> {
+        match self {
+            Self::Object(name) => Some(Self::object(reference_component(name.strip_prefix('[')?)?)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Top => write!(f, "top"),
+            Type::OneWord => write!(f, "oneWord"),
+            Type::TwoWord => write!(f, "twoWord"),
+            Type::Integer => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::Long => write!(f, "long"),
+            Type::Double => write!(f, "double"),
+            Type::Reference => write!(f, "reference"),
+            Type::Null => write!(f, "null"),
+            Type::UninitializedThis => write!(f, "uninitializedThis"),
+            Type::Uninitialized(pc) => write!(f, "uninitialized({})", pc),
+            Type::Object(name) => write!(f, "{}", name),
+            Type::ReturnAddress(pc) => write!(f, "returnAddress({})", pc),
+        }
+    }
+}
+
+/// Why verification failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyErrorKind {
+    /// A value of type `actual` was found where a value of type `expected` is needed
+    Mismatch {
+        expected: Type,
+        actual: Type,
+    },
+    /// An array was needed, but `actual` isn't one
+    NotArray {
+        actual: Type,
+    },
+    StackUnderflow,
+    /// The operand stack would grow past `max_stack`
+    StackOverflow,
+    /// The operand stack has a different height to the frame at a branch target
+    StackHeight {
+        expected: usize,
+        actual: usize,
+    },
+    /// The local variable at `index` is past `max_locals`
+    BadLocal {
+        index: usize,
+    },
+    /// A branch, exception handler or stack map frame at `target` isn't the start of an
+    /// instruction
+    BadTarget {
+        target: usize,
+    },
+    /// There is no stack map frame at `target`, which needs one
+    MissingFrame {
+        target: usize,
+    },
+    /// Execution can continue past the end of the code
+    FallsOffEnd,
+    /// A rule which isn't about the type of a value is broken
+    Invalid(&'static str),
+    /// A constant used by an instruction can't be resolved
+    Constant(Error),
+    /// A field or method descriptor can't be parsed
+    Descriptor(String),
+    /// Following the superclasses of `class` leads back to itself
+    Circular {
+        class: String,
+    },
+}
+
+impl fmt::Display for VerifyErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyErrorKind::Mismatch { expected, actual } => {
+                write!(f, "Expected {}, found {}", expected, actual)
+            }
+            VerifyErrorKind::NotArray { actual } => {
+                write!(f, "Expected an array, found {}", actual)
+            }
+            VerifyErrorKind::StackUnderflow => write!(f, "Operand stack underflow"),
+            VerifyErrorKind::StackOverflow => write!(f, "Operand stack overflow"),
+            VerifyErrorKind::StackHeight { expected, actual } => write!(
+                f,
+                "Expected operand stack height {}, found {}",
+                expected, actual
+            ),
+            VerifyErrorKind::BadLocal { index } => {
+                write!(f, "Local variable {} is out of bounds", index)
+            }
+            VerifyErrorKind::BadTarget { target } => {
+                write!(f, "{} is not the start of an instruction", target)
+            }
+            VerifyErrorKind::MissingFrame { target } => {
+                write!(f, "Expected stack map frame at {}", target)
+            }
+            VerifyErrorKind::FallsOffEnd => write!(f, "Execution falls off the end of the code"),
+            VerifyErrorKind::Invalid(reason) => write!(f, "{}", reason),
+            VerifyErrorKind::Constant(e) => write!(f, "{}", e),
+            VerifyErrorKind::Descriptor(e) => write!(f, "Invalid descriptor: {}", e),
+            VerifyErrorKind::Circular { class } => {
+                write!(f, "Class {} is its own superclass", class)
+            }
+        }
+    }
+}
+
+impl From<Error> for VerifyErrorKind {
+    fn from(value: Error) -> Self {
+        Self::Constant(value)
+    }
+}
+
+/// A method failed verification, see
+/// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.10>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub class: String,
+    /// Name and descriptor of the method, e.g. `main([Ljava/lang/String;)V`
+    pub method: String,
+    /// The instruction that failed, or `0` for problems with the method as a whole
+    pub pc: usize,
+    pub kind: VerifyErrorKind,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "VerifyError in {}.{} at pc {}: {}",
+            self.class, self.method, self.pc, self.kind
+        )
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+type Result<T> = std::result::Result<T, VerifyErrorKind>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Frame {
+    /// Always `max_locals` long
+    locals: Vec<Type>,
+    stack: Vec<Type>,
+    /// Whether the super constructor still needs to be called, `flagThisUninit` in the JVMS
+    this_uninit: bool,
+}
+
+impl Frame {
+    fn replace(&mut self, from: &Type, to: &Type) {
+        for t in self.locals.iter_mut().chain(self.stack.iter_mut()) {
+            if t == from {
+                *t = to.clone();
+            }
+        }
+    }
+}
+
+struct Verifier<'a, H> {
+    class: &'a ClassFile<'a>,
+    this_class: &'a str,
+    super_class: Option<&'a str>,
+    pool: &'a ConstantPool<'a>,
+    method: &'a Method<'a>,
+    hierarchy: &'a H,
+    max_stack: usize,
+    max_locals: usize,
+    code_len: usize,
+    instructions: BTreeMap<usize, Instruction>,
+    exception_table: Vec<Exception>,
+    stack_map: Option<Vec<StackMapFrame<'a>>>,
+    descriptor: MethodDescriptor,
+}
+
+/// Verify every method in `class`
+pub fn verify_class<H: ClassHierarchy>(
+    class: &ClassFile,
+    hierarchy: &H,
+) -> std::result::Result<(), VerifyError> {
+    let class_name = || class.this_class().unwrap_or_default().to_string();
+    for method in class.methods() {
+        let method = method.map_err(|e| VerifyError {
+            class: class_name(),
+            method: String::new(),
+            pc: 0,
+            kind: e.into(),
+        })?;
+        verify_method(class, &method, hierarchy)?;
+    }
+    Ok(())
+}
+
+/// Verify a single method of `class`, which doesn't need to be one of its own methods
+pub fn verify_method<H: ClassHierarchy>(
+    class: &ClassFile,
+    method: &Method,
+    hierarchy: &H,
+) -> std::result::Result<(), VerifyError> {
+    let error = |pc, kind| VerifyError {
+        class: class.this_class().unwrap_or_default().to_string(),
+        method: format!("{}{}", method.name, method.descriptor),
+        pc,
+        kind,
+    };
+    let Some(verifier) = Verifier::new(class, method, hierarchy).map_err(|e| error(0, e))? else {
+        return Ok(());
+    };
+
+    let (major, _) = class.version;
+    let has_subroutines = verifier
+        .instructions
+        .values()
+        .any(|i| matches!(i, Instruction::Jsr(_) | Instruction::JsrW(_)));
+    let result = if major < 50 || (major == 50 && has_subroutines) {
+        verifier.infer()
+    } else {
+        verifier.type_check().or_else(|e| {
+            if major == 50 {
+                verifier.infer()
+            } else {
+                Err(e)
+            }
+        })
+    };
+    result.map_err(|(pc, kind)| error(pc, kind))
+}
+
+impl<'a, H: ClassHierarchy> Verifier<'a, H> {
+    /// `None` if the method has no code to verify
+    fn new(class: &'a ClassFile, method: &'a Method<'a>, hierarchy: &'a H) -> Result<Option<Self>> {
+        let Some(Attribute::Code {
+            max_stack,
+            max_locals,
+            code,
+            exception_table,
+            attributes,
+        }) = method.code()?
+        else {
+            return Ok(None);
+        };
+
+        let instructions = Instructions::new(code).collect::<crate::error::Result<_>>()?;
+        let stack_map = attributes.into_iter().find_map(|a| match a {
+            Attribute::StackMapTable { entries } => Some(entries),
+            _ => None,
+        });
+
+        Ok(Some(Self {
+            class,
+            this_class: class.this_class()?,
+            super_class: class.super_class()?,
+            pool: &class.constant_pool,
+            method,
+            hierarchy,
+            max_stack: max_stack.into(),
+            max_locals: max_locals.into(),
+            code_len: code.len(),
+            instructions,
+            exception_table,
+            stack_map,
+            descriptor: method
+                .descriptor
+                .parse()
+                .map_err(|e: anyhow::Error| VerifyErrorKind::Descriptor(e.to_string()))?,
+        }))
+    }
+
+    fn is_static(&self) -> bool {
+        self.method.access_flags.contains(MethodAccessFlags::STATIC)
+    }
+
+    fn class_info(&self, name: &str) -> Option<ClassInfo> {
+        if name == self.this_class {
+            Some(ClassInfo {
+                super_class: self.super_class.map(String::from),
+                is_interface: self
+                    .class
+                    .access_flags
+                    .contains(ClassAccessFlags::INTERFACE),
+            })
+        } else {
+            self.hierarchy.class_info(name)
+        }
+    }
+
+    /// Whether a value of type `actual` can be used where `expected` is needed, see
+    /// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.10.1.2>
+    fn is_assignable(&self, actual: &Type, expected: &Type) -> Result<bool> {
+        Ok(match (actual, expected) {
+            (actual, expected) if actual == expected => true,
+            (_, Type::Top) => true,
+            (Type::Top, _) => false,
+            (actual, Type::OneWord) => !actual.is_wide(),
+            (actual, Type::TwoWord) => actual.is_wide(),
+            (
+                Type::Null | Type::Object(_) | Type::UninitializedThis | Type::Uninitialized(_),
+                Type::Reference,
+            ) => true,
+            (Type::Null, Type::Object(_)) => true,
+            (Type::Object(actual), Type::Object(expected)) => self.is_subclass(actual, expected)?,
+            _ => false,
+        })
+    }
+
+    /// Whether the class or array type `actual` is assignable to `expected`. Interfaces are
+    /// treated like `java/lang/Object`, as the JVMS does.
+    fn is_subclass(&self, actual: &str, expected: &str) -> Result<bool> {
+        if actual == expected || expected == OBJECT {
+            return Ok(true);
+        }
+        match (actual.strip_prefix('['), expected.strip_prefix('[')) {
+            (Some(actual), Some(expected)) => {
+                match (reference_component(actual), reference_component(expected)) {
+                    (Some(actual), Some(expected)) => self.is_subclass(actual, expected),
+                    _ => Ok(false),
+                }
+            }
+            (Some(_), None) => Ok(matches!(
+                expected,
+                "java/lang/Cloneable" | "java/io/Serializable"
+            )),
+            (None, Some(_)) => Ok(false),
+            (None, None) => {
+                match self.class_info(expected) {
+                    Some(info) if info.is_interface => return Ok(true),
+                    None => return Ok(true),
+                    _ => {}
+                }
+                let mut visited = HashSet::new();
+                let mut class = actual.to_string();
+                loop {
+                    if !visited.insert(class.clone()) {
+                        return Err(VerifyErrorKind::Circular { class });
+                    }
+                    match self.class_info(&class) {
+                        Some(ClassInfo {
+                            super_class: Some(super_class),
+                            ..
+                        }) if super_class == expected => return Ok(true),
+                        Some(ClassInfo {
+                            super_class: Some(super_class),
+                            ..
+                        }) => class = super_class,
+                        Some(ClassInfo {
+                            super_class: None, ..
+                        }) => return Ok(false),
+                        None => return Ok(true),
+                    }
+                }
+            }
+        }
+    }
+
+    /// The classes from `name` up to `java/lang/Object`, or `None` if one of them is unknown
+    fn superclasses(&self, name: &str) -> Result<Option<Vec<String>>> {
+        let mut classes = vec![name.to_string()];
+        let mut visited = HashSet::from([name.to_string()]);
+        loop {
+            let Some(info) = self.class_info(&classes[classes.len() - 1]) else {
+                return Ok(None);
+            };
+            let Some(super_class) = info.super_class else {
+                return Ok(Some(classes));
+            };
+            if !visited.insert(super_class.clone()) {
+                return Err(VerifyErrorKind::Circular { class: super_class });
+            }
+            classes.push(super_class);
+        }
+    }
+
+    /// The most specific class or array type that both `a` and `b` are assignable to
+    fn least_upper_bound(&self, a: &str, b: &str) -> Result<String> {
+        if a == b {
+            return Ok(a.into());
+        }
+        Ok(match (a.strip_prefix('['), b.strip_prefix('[')) {
+            (Some(a), Some(b)) => match (reference_component(a), reference_component(b)) {
+                (Some(a), Some(b)) => {
+                    let component = self.least_upper_bound(a, b)?;
+                    if component.starts_with('[') {
+                        format!("[{}", component)
+                    } else {
+                        format!("[L{};", component)
+                    }
+                }
+                _ => OBJECT.into(),
+            },
+            (None, None) => {
+                let is_interface = |name| self.class_info(name).is_some_and(|i| i.is_interface);
+                if is_interface(a) || is_interface(b) {
+                    return Ok(OBJECT.into());
+                }
+                match (self.superclasses(a)?, self.superclasses(b)?) {
+                    (Some(a), Some(b)) => a
+                        .into_iter()
+                        .find(|class| b.contains(class))
+                        .unwrap_or_else(|| OBJECT.into()),
+                    _ => OBJECT.into(),
+                }
+            }
+            _ => OBJECT.into(),
+        })
+    }
+
+    /// The type of a value that could be either `a` or `b`
+    fn merge(&self, a: &Type, b: &Type) -> Result<Type> {
+        Ok(match (a, b) {
+            (a, b) if a == b => a.clone(),
+            (Type::Null, Type::Object(_)) => b.clone(),
+            (Type::Object(_), Type::Null) => a.clone(),
+            (Type::Object(a), Type::Object(b)) => Type::Object(self.least_upper_bound(a, b)?),
+            _ => Type::Top,
+        })
+    }
+
+    fn push(&self, frame: &mut Frame, t: Type) -> Result<()> {
+        let wide = t.is_wide();
+        frame.stack.push(t);
+        if wide {
+            frame.stack.push(Type::Top);
+        }
+        if frame.stack.len() > self.max_stack {
+            return Err(VerifyErrorKind::StackOverflow);
+        }
+        Ok(())
+    }
+
+    /// Pop a value which must be assignable to `expected`, returning its actual type
+    fn pop(&self, frame: &mut Frame, expected: &Type) -> Result<Type> {
+        let len = frame.stack.len();
+        if expected.is_wide() {
+            if len < 2 {
+                return Err(VerifyErrorKind::StackUnderflow);
+            }
+            let actual = match &frame.stack[len - 2..] {
+                [actual, Type::Top] if actual.is_wide() => actual.clone(),
+                [_, actual] => actual.clone(),
+                _ => unreachable!(),
+            };
+            if !self.is_assignable(&actual, expected)? {
+                return Err(VerifyErrorKind::Mismatch {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+            frame.stack.truncate(len - 2);
+            Ok(actual)
+        } else {
+            let actual = frame.stack.pop().ok_or(VerifyErrorKind::StackUnderflow)?;
+            if !self.is_assignable(&actual, expected)? {
+                let actual = match frame.stack.last() {
+                    Some(wide) if actual == Type::Top && wide.is_wide() => wide.clone(),
+                    _ => actual,
+                };
+                return Err(VerifyErrorKind::Mismatch {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+            Ok(actual)
+        }
+    }
+
+    /// Make sure the top `depth` entries of the stack don't split a `long` or `double` in half
+    fn check_boundary(&self, frame: &Frame, depth: usize) -> Result<()> {
+        let len = frame.stack.len();
+        if depth > len {
+            return Err(VerifyErrorKind::StackUnderflow);
+        }
+        if len > depth
+            && frame.stack[len - depth] == Type::Top
+            && frame.stack[len - depth - 1].is_wide()
+        {
+            return Err(VerifyErrorKind::Invalid(
+                "Instruction splits a long or double on the operand stack",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Insert a copy of the top `count` entries of the stack `depth` entries down
+    fn dup(&self, frame: &mut Frame, count: usize, depth: usize) -> Result<()> {
+        self.check_boundary(frame, count)?;
+        self.check_boundary(frame, depth)?;
+        let len = frame.stack.len();
+        let copy: Vec<_> = frame.stack[len - count..].to_vec();
+        frame.stack.splice(len - depth..len - depth, copy);
+        if frame.stack.len() > self.max_stack {
+            return Err(VerifyErrorKind::StackOverflow);
+        }
+        Ok(())
+    }
+
+    /// Push the local at `index`, which must be assignable to `expected`
+    fn load(&self, frame: &mut Frame, index: usize, expected: Type) -> Result<()> {
+        let size = if expected.is_wide() { 2 } else { 1 };
+        if index + size > frame.locals.len() {
+            return Err(VerifyErrorKind::BadLocal {
+                index: index + size - 1,
+            });
+        }
+        let actual = frame.locals[index].clone();
+        if !self.is_assignable(&actual, &expected)? {
+            return Err(VerifyErrorKind::Mismatch { expected, actual });
+        }
+        if expected == Type::Reference {
+            self.push(frame, actual)
+        } else {
+            self.push(frame, expected)
+        }
+    }
+
+    /// Pop a value which must be assignable to `expected` into the local at `index`. `astore`
+    /// can also store return addresses.
+    fn store(&self, frame: &mut Frame, index: usize, expected: Type) -> Result<()> {
+        let actual = match frame.stack.last() {
+            Some(Type::ReturnAddress(_)) if expected == Type::Reference => {
+                frame.stack.pop().unwrap()
+            }
+            _ => self.pop(frame, &expected)?,
+        };
+        let size = if actual.is_wide() { 2 } else { 1 };
+        if index + size > frame.locals.len() {
+            return Err(VerifyErrorKind::BadLocal {
+                index: index + size - 1,
+            });
+        }
+        frame.locals[index] = actual;
+        if size == 2 {
+            frame.locals[index + 1] = Type::Top;
+        }
+        if index > 0 && frame.locals[index - 1].is_wide() {
+            frame.locals[index - 1] = Type::Top;
+        }
+        Ok(())
+    }
+
+    fn iinc(&self, frame: &Frame, index: usize) -> Result<()> {
+        match frame.locals.get(index) {
+            None => Err(VerifyErrorKind::BadLocal { index }),
+            Some(Type::Integer) => Ok(()),
+            Some(actual) => Err(VerifyErrorKind::Mismatch {
+                expected: Type::Integer,
+                actual: actual.clone(),
+            }),
+        }
+    }
+
+    /// Pop an array of one of the `arrays` types, or `null`
+    fn pop_array(&self, frame: &mut Frame, arrays: &[&str]) -> Result<()> {
+        let actual = self.pop(frame, &Type::OneWord)?;
+        match &actual {
+            Type::Null => Ok(()),
+            Type::Object(name) if arrays.contains(&name.as_str()) => Ok(()),
+            _ => Err(VerifyErrorKind::Mismatch {
+                expected: Type::object(arrays[0]),
+                actual,
+            }),
+        }
+    }
+
+    /// Pop an array of references or `null`, returning the type of its elements
+    fn pop_reference_array(&self, frame: &mut Frame) -> Result<Type> {
+        let actual = self.pop(frame, &Type::OneWord)?;
+        match &actual {
+            Type::Null => Ok(Type::Null),
+            array => array
+                .component()
+                .ok_or(VerifyErrorKind::NotArray { actual }),
+        }
+    }
+
+    fn array_load(&self, frame: &mut Frame, arrays: &[&str], element: Type) -> Result<()> {
+        self.pop(frame, &Type::Integer)?;
+        self.pop_array(frame, arrays)?;
+        self.push(frame, element)
+    }
+
+    fn array_store(&self, frame: &mut Frame, arrays: &[&str], element: Type) -> Result<()> {
+        self.pop(frame, &element)?;
+        self.pop(frame, &Type::Integer)?;
+        self.pop_array(frame, arrays)
+    }
+
+    fn unary(&self, frame: &mut Frame, operand: Type, result: Type) -> Result<()> {
+        self.pop(frame, &operand)?;
+        self.push(frame, result)
+    }
+
+    fn binary(&self, frame: &mut Frame, operand: Type, result: Type) -> Result<()> {
+        self.pop(frame, &operand)?;
+        self.pop(frame, &operand)?;
+        self.push(frame, result)
+    }
+
+    fn return_value(&self, frame: &mut Frame, t: Option<Type>) -> Result<()> {
+        let expected = match &self.descriptor.return_value {
+            ReturnDescriptor::Void => None,
+            ReturnDescriptor::FieldType(field_type) => Some(Type::from_field_type(field_type)),
+        };
+        match (expected, t) {
+            (None, None) => {
+                if frame.this_uninit {
+                    return Err(VerifyErrorKind::Invalid(
+                        "Constructor returns without calling another constructor",
+                    ));
+                }
+                Ok(())
+            }
+            (None, Some(_)) => Err(VerifyErrorKind::Invalid(
+                "Method returns a value but its return type is void",
+            )),
+            (Some(_), None) => Err(VerifyErrorKind::Invalid(
+                "Method returns void but its return type isn't",
+            )),
+            (Some(expected), Some(t)) => {
+                let matches = match &t {
+                    Type::Reference => matches!(expected, Type::Object(_)),
+                    t => *t == expected,
+                };
+                if !matches {
+                    return Err(VerifyErrorKind::Mismatch {
+                        expected,
+                        actual: t,
+                    });
+                }
+                self.pop(frame, &expected)?;
+                Ok(())
+            }
+        }
+    }
+
+    fn field_type(&self, descriptor: &str) -> Result<Type> {
+        let mut chars = descriptor.chars();
+        let id = chars
+            .next()
+            .ok_or_else(|| VerifyErrorKind::Descriptor("Empty field descriptor".into()))?;
+        FieldType::from_chars(id, &mut chars)
+            .map(|t| Type::from_field_type(&t))
+            .map_err(|e| VerifyErrorKind::Descriptor(e.to_string()))
+    }
+
+    fn ldc_type(&self, index: usize) -> Result<Type> {
+        Ok(match self.pool.loadable(index)? {
+            Loadable::Integer(_) => Type::Integer,
+            Loadable::Float(_) => Type::Float,
+            Loadable::Long(_) => Type::Long,
+            Loadable::Double(_) => Type::Double,
+            Loadable::Class(_) => Type::object("java/lang/Class"),
+            Loadable::String(_) | Loadable::RawString(_) => Type::object("java/lang/String"),
+            Loadable::MethodHandle(_) => Type::object("java/lang/invoke/MethodHandle"),
+            Loadable::MethodType(_) => Type::object("java/lang/invoke/MethodType"),
+            Loadable::Dynamic(dynamic) => self.field_type(dynamic.descriptor)?,
+        })
+    }
+
+    fn field(&self, frame: &mut Frame, index: usize, get: bool, is_static: bool) -> Result<()> {
+        let field = self.pool.member_ref(index)?;
+        if field.kind != MemberKind::Field {
+            return Err(VerifyErrorKind::Invalid(
+                "Field instruction refers to a method",
+            ));
+        }
+        let t = self.field_type(field.descriptor)?;
+        if get {
+            if !is_static {
+                self.pop(frame, &Type::object(field.class))?;
+            }
+            self.push(frame, t)
+        } else {
+            self.pop(frame, &t)?;
+            if !is_static {
+                // fields declared by this class can be set before the super constructor is
+                // called
+                let this_field = field.class == self.this_class
+                    && frame.stack.last() == Some(&Type::UninitializedThis);
+                if this_field {
+                    frame.stack.pop();
+                } else {
+                    self.pop(frame, &Type::object(field.class))?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn invoke(&self, frame: &mut Frame, instruction: &Instruction) -> Result<()> {
+        let (class, name, descriptor) = match *instruction {
+            Instruction::Invokedynamic(index) => {
+                let dynamic = self.pool.invoke_dynamic(index)?;
+                ("", dynamic.name, dynamic.descriptor)
+            }
+            Instruction::Invokevirtual(index)
+            | Instruction::Invokespecial(index)
+            | Instruction::Invokestatic(index)
+            | Instruction::Invokeinterface { index, .. } => {
+                let method = self.pool.member_ref(index)?;
+                let kind_matches = match instruction {
+                    Instruction::Invokevirtual(_) => method.kind == MemberKind::Method,
+                    Instruction::Invokeinterface { .. } => {
+                        method.kind == MemberKind::InterfaceMethod
+                    }
+                    _ => method.kind != MemberKind::Field,
+                };
+                if !kind_matches {
+                    return Err(VerifyErrorKind::Invalid(
+                        "Invoke instruction refers to the wrong kind of member",
+                    ));
+                }
+                (method.class, method.name, method.descriptor)
+            }
+            _ => unreachable!("not an invoke instruction"),
+        };
+
+        let is_init = name == "<init>" && matches!(instruction, Instruction::Invokespecial(_));
+        if name.starts_with('<') && !is_init {
+            return Err(VerifyErrorKind::Invalid(
+                "Initialization method can only be called by invokespecial",
+            ));
+        }
+
+        let descriptor: MethodDescriptor = descriptor
+            .parse()
+            .map_err(|e: anyhow::Error| VerifyErrorKind::Descriptor(e.to_string()))?;
+        if let Instruction::Invokeinterface { count, .. } = instruction {
+            let slots: usize = descriptor.params.iter().map(FieldType::slots).sum();
+            if usize::from(*count) != slots + 1 {
+                return Err(VerifyErrorKind::Invalid(
+                    "invokeinterface count doesn't match the method descriptor",
+                ));
+            }
+        }
+        for param in descriptor.params.iter().rev() {
+            self.pop(frame, &Type::from_field_type(param))?;
+        }
+
+        match instruction {
+            Instruction::Invokestatic(_) | Instruction::Invokedynamic(_) => {}
+            Instruction::Invokeinterface { .. } => {
+                self.pop(frame, &Type::object(OBJECT))?;
+            }
+            Instruction::Invokevirtual(_) => {
+                self.pop(frame, &Type::object(class))?;
+            }
+            Instruction::Invokespecial(_) if is_init => {
+                if descriptor.return_value != ReturnDescriptor::Void {
+                    return Err(VerifyErrorKind::Invalid("Constructor doesn't return void"));
+                }
+                match self.pop(frame, &Type::Reference)? {
+                    Type::UninitializedThis => {
+                        if class != self.this_class && Some(class) != self.super_class {
+                            return Err(VerifyErrorKind::Invalid(
+                                "Constructor of the wrong class called on uninitializedThis",
+                            ));
+                        }
+                        frame.replace(&Type::UninitializedThis, &Type::object(self.this_class));
+                        frame.this_uninit = false;
+                    }
+                    Type::Uninitialized(pc) => {
+                        match self.instructions.get(&pc) {
+                            Some(Instruction::New(index))
+                                if self.pool.class_name(*index)? == class => {}
+                            _ => {
+                                return Err(VerifyErrorKind::Invalid(
+                                    "Constructor of the wrong class called on uninitialized object",
+                                ))
+                            }
+                        }
+                        frame.replace(&Type::Uninitialized(pc), &Type::object(class));
+                    }
+                    actual => {
+                        return Err(VerifyErrorKind::Mismatch {
+                            expected: Type::UninitializedThis,
+                            actual,
+                        })
+                    }
+                }
+            }
+            _ => {
+                self.pop(frame, &Type::object(self.this_class))?;
+            }
+        }
+
+        if let ReturnDescriptor::FieldType(t) = &descriptor.return_value {
+            self.push(frame, Type::from_field_type(t))?;
+        }
+        Ok(())
+    }
+
+    /// Update `frame` to what it is after running `instruction`
+    fn execute(&self, frame: &mut Frame, pc: usize, instruction: &Instruction) -> Result<()> {
+        use Instruction as I;
+        use Type as T;
+
+        const INT_ARRAY: &[&str] = &["[I"];
+        const LONG_ARRAY: &[&str] = &["[J"];
+        const FLOAT_ARRAY: &[&str] = &["[F"];
+        const DOUBLE_ARRAY: &[&str] = &["[D"];
+        const BYTE_ARRAY: &[&str] = &["[B", "[Z"];
+        const CHAR_ARRAY: &[&str] = &["[C"];
+        const SHORT_ARRAY: &[&str] = &["[S"];
+
+        match instruction {
+            I::Nop | I::Goto(_) | I::GotoW(_) => {}
+            I::AconstNull => self.push(frame, T::Null)?,
+            I::IconstM1
+            | I::Iconst0
+            | I::Iconst1
+            | I::Iconst2
+            | I::Iconst3
+            | I::Iconst4
+            | I::Iconst5
+            | I::Bipush(_)
+            | I::Sipush(_) => self.push(frame, T::Integer)?,
+            I::Lconst0 | I::Lconst1 => self.push(frame, T::Long)?,
+            I::Fconst0 | I::Fconst1 | I::Fconst2 => self.push(frame, T::Float)?,
+            I::Dconst0 | I::Dconst1 => self.push(frame, T::Double)?,
+            I::Ldc(index) | I::LdcW(index) => {
+                let t = self.ldc_type(*index)?;
+                if t.is_wide() {
+                    return Err(VerifyErrorKind::Invalid("ldc of a long or double"));
+                }
+                self.push(frame, t)?;
+            }
+            I::Ldc2W(index) => {
+                let t = self.ldc_type(*index)?;
+                if !t.is_wide() {
+                    return Err(VerifyErrorKind::Invalid(
+                        "ldc2_w of a constant which isn't long or double",
+                    ));
+                }
+                self.push(frame, t)?;
+            }
+
+            I::Iload(i) => self.load(frame, (*i).into(), T::Integer)?,
+            I::Lload(i) => self.load(frame, (*i).into(), T::Long)?,
+            I::Fload(i) => self.load(frame, (*i).into(), T::Float)?,
+            I::Dload(i) => self.load(frame, (*i).into(), T::Double)?,
+            I::Aload(i) => self.load(frame, (*i).into(), T::Reference)?,
+            I::Iload0 => self.load(frame, 0, T::Integer)?,
+            I::Iload1 => self.load(frame, 1, T::Integer)?,
+            I::Iload2 => self.load(frame, 2, T::Integer)?,
+            I::Iload3 => self.load(frame, 3, T::Integer)?,
+            I::Lload0 => self.load(frame, 0, T::Long)?,
+            I::Lload1 => self.load(frame, 1, T::Long)?,
+            I::Lload2 => self.load(frame, 2, T::Long)?,
+            I::Lload3 => self.load(frame, 3, T::Long)?,
+            I::Fload0 => self.load(frame, 0, T::Float)?,
+            I::Fload1 => self.load(frame, 1, T::Float)?,
+            I::Fload2 => self.load(frame, 2, T::Float)?,
+            I::Fload3 => self.load(frame, 3, T::Float)?,
+            I::Dload0 => self.load(frame, 0, T::Double)?,
+            I::Dload1 => self.load(frame, 1, T::Double)?,
+            I::Dload2 => self.load(frame, 2, T::Double)?,
+            I::Dload3 => self.load(frame, 3, T::Double)?,
+            I::Aload0 => self.load(frame, 0, T::Reference)?,
+            I::Aload1 => self.load(frame, 1, T::Reference)?,
+            I::Aload2 => self.load(frame, 2, T::Reference)?,
+            I::Aload3 => self.load(frame, 3, T::Reference)?,
+
+            I::Iaload => self.array_load(frame, INT_ARRAY, T::Integer)?,
+            I::Laload => self.array_load(frame, LONG_ARRAY, T::Long)?,
+            I::Faload => self.array_load(frame, FLOAT_ARRAY, T::Float)?,
+            I::Daload => self.array_load(frame, DOUBLE_ARRAY, T::Double)?,
+            I::Baload => self.array_load(frame, BYTE_ARRAY, T::Integer)?,
+            I::Caload => self.array_load(frame, CHAR_ARRAY, T::Integer)?,
+            I::Saload => self.array_load(frame, SHORT_ARRAY, T::Integer)?,
+            I::Aaload => {
+                self.pop(frame, &T::Integer)?;
+                let element = self.pop_reference_array(frame)?;
+                self.push(frame, element)?;
+            }
+
+            I::Istore(i) => self.store(frame, (*i).into(), T::Integer)?,
+            I::Lstore(i) => self.store(frame, (*i).into(), T::Long)?,
+            I::Fstore(i) => self.store(frame, (*i).into(), T::Float)?,
+            I::Dstore(i) => self.store(frame, (*i).into(), T::Double)?,
+            I::Astore(i) => self.store(frame, (*i).into(), T::Reference)?,
+            I::Istore0 => self.store(frame, 0, T::Integer)?,
+            I::Istore1 => self.store(frame, 1, T::Integer)?,
+            I::Istore2 => self.store(frame, 2, T::Integer)?,
+            I::Istore3 => self.store(frame, 3, T::Integer)?,
+            I::Lstore0 => self.store(frame, 0, T::Long)?,
+            I::Lstore1 => self.store(frame, 1, T::Long)?,
+            I::Lstore2 => self.store(frame, 2, T::Long)?,
+            I::Lstore3 => self.store(frame, 3, T::Long)?,
+            I::Fstore0 => self.store(frame, 0, T::Float)?,
+            I::Fstore1 => self.store(frame, 1, T::Float)?,
+            I::Fstore2 => self.store(frame, 2, T::Float)?,
+            I::Fstore3 => self.store(frame, 3, T::Float)?,
+            I::Dstore0 => self.store(frame, 0, T::Double)?,
+            I::Dstore1 => self.store(frame, 1, T::Double)?,
+            I::Dstore2 => self.store(frame, 2, T::Double)?,
+            I::Dstore3 => self.store(frame, 3, T::Double)?,
+            I::Astore0 => self.store(frame, 0, T::Reference)?,
+            I::Astore1 => self.store(frame, 1, T::Reference)?,
+            I::Astore2 => self.store(frame, 2, T::Reference)?,
+            I::Astore3 => self.store(frame, 3, T::Reference)?,
+
+            I::Iastore => self.array_store(frame, INT_ARRAY, T::Integer)?,
+            I::Lastore => self.array_store(frame, LONG_ARRAY, T::Long)?,
+            I::Fastore => self.array_store(frame, FLOAT_ARRAY, T::Float)?,
+            I::Dastore => self.array_store(frame, DOUBLE_ARRAY, T::Double)?,
+            I::Bastore => self.array_store(frame, BYTE_ARRAY, T::Integer)?,
+            I::Castore => self.array_store(frame, CHAR_ARRAY, T::Integer)?,
+            I::Sastore => self.array_store(frame, SHORT_ARRAY, T::Integer)?,
+            I::Aastore => {
+                self.pop(frame, &T::object(OBJECT))?;
+                self.pop(frame, &T::Integer)?;
+                self.pop_reference_array(frame)?;
+            }
+
+            I::Pop => {
+                self.check_boundary(frame, 1)?;
+                frame.stack.pop();
+            }
+            I::Pop2 => {
+                self.check_boundary(frame, 2)?;
+                frame.stack.truncate(frame.stack.len() - 2);
+            }
+            I::Dup => self.dup(frame, 1, 1)?,
+            I::DupX1 => self.dup(frame, 1, 2)?,
+            I::DupX2 => self.dup(frame, 1, 3)?,
+            I::Dup2 => self.dup(frame, 2, 2)?,
+            I::Dup2X1 => self.dup(frame, 2, 3)?,
+            I::Dup2X2 => self.dup(frame, 2, 4)?,
+            I::Swap => {
+                self.check_boundary(frame, 1)?;
+                self.check_boundary(frame, 2)?;
+                let len = frame.stack.len();
+                frame.stack.swap(len - 1, len - 2);
+            }
+
+            I::Iadd
+            | I::Isub
+            | I::Imul
+            | I::Idiv
+            | I::Irem
+            | I::Ishl
+            | I::Ishr
+            | I::Iushr
+            | I::Iand
+            | I::Ior
+            | I::Ixor => self.binary(frame, T::Integer, T::Integer)?,
+            I::Ladd | I::Lsub | I::Lmul | I::Ldiv | I::Lrem | I::Land | I::Lor | I::Lxor => {
+                self.binary(frame, T::Long, T::Long)?
+            }
+            I::Fadd | I::Fsub | I::Fmul | I::Fdiv | I::Frem => {
+                self.binary(frame, T::Float, T::Float)?
+            }
+            I::Dadd | I::Dsub | I::Dmul | I::Ddiv | I::Drem => {
+                self.binary(frame, T::Double, T::Double)?
+            }
+            I::Lshl | I::Lshr | I::Lushr => {
+                self.pop(frame, &T::Integer)?;
+                self.unary(frame, T::Long, T::Long)?;
+            }
+            I::Ineg | I::I2b | I::I2c | I::I2s => self.unary(frame, T::Integer, T::Integer)?,
+            I::Lneg => self.unary(frame, T::Long, T::Long)?,
+            I::Fneg => self.unary(frame, T::Float, T::Float)?,
+            I::Dneg => self.unary(frame, T::Double, T::Double)?,
+            I::Iinc { index, .. } => self.iinc(frame, (*index).into())?,
+            I::I2l => self.unary(frame, T::Integer, T::Long)?,
+            I::I2f => self.unary(frame, T::Integer, T::Float)?,
+            I::I2d => self.unary(frame, T::Integer, T::Double)?,
+            I::L2i => self.unary(frame, T::Long, T::Integer)?,
+            I::L2f => self.unary(frame, T::Long, T::Float)?,
+            I::L2d => self.unary(frame, T::Long, T::Double)?,
+            I::F2i => self.unary(frame, T::Float, T::Integer)?,
+            I::F2l => self.unary(frame, T::Float, T::Long)?,
+            I::F2d => self.unary(frame, T::Float, T::Double)?,
+            I::D2i => self.unary(frame, T::Double, T::Integer)?,
+            I::D2l => self.unary(frame, T::Double, T::Long)?,
+            I::D2f => self.unary(frame, T::Double, T::Float)?,
+            I::Lcmp => self.binary(frame, T::Long, T::Integer)?,
+            I::Fcmpl | I::Fcmpg => self.binary(frame, T::Float, T::Integer)?,
+            I::Dcmpl | I::Dcmpg => self.binary(frame, T::Double, T::Integer)?,
+
+            I::Ifeq(_) | I::Ifne(_) | I::Iflt(_) | I::Ifge(_) | I::Ifgt(_) | I::Ifle(_) => {
+                self.pop(frame, &T::Integer)?;
+            }
+            I::IfIcmpeq(_)
+            | I::IfIcmpne(_)
+            | I::IfIcmplt(_)
+            | I::IfIcmpge(_)
+            | I::IfIcmpgt(_)
+            | I::IfIcmple(_) => {
+                self.pop(frame, &T::Integer)?;
+                self.pop(frame, &T::Integer)?;
+            }
+            I::IfAcmpeq(_) | I::IfAcmpne(_) => {
+                self.pop(frame, &T::Reference)?;
+                self.pop(frame, &T::Reference)?;
+            }
+            I::Ifnull(_) | I::Ifnonnull(_) => {
+                self.pop(frame, &T::Reference)?;
+            }
+            I::Jsr(_) | I::JsrW(_) => {
+                let target = instruction.branch_targets(pc)[0];
+                self.push(frame, T::ReturnAddress(target))?;
+            }
+            I::Ret(index) => {
+                self.ret(frame, (*index).into())?;
+            }
+            I::Tableswitch { .. } => {
+                self.pop(frame, &T::Integer)?;
+            }
+            I::Lookupswitch { pairs, .. } => {
+                if !pairs.windows(2).all(|w| w[0].0 < w[1].0) {
+                    return Err(VerifyErrorKind::Invalid("lookupswitch keys aren't sorted"));
+                }
+                self.pop(frame, &T::Integer)?;
+            }
+
+            I::Ireturn => self.return_value(frame, Some(T::Integer))?,
+            I::Lreturn => self.return_value(frame, Some(T::Long))?,
+            I::Freturn => self.return_value(frame, Some(T::Float))?,
+            I::Dreturn => self.return_value(frame, Some(T::Double))?,
+            I::Areturn => self.return_value(frame, Some(T::Reference))?,
+            I::Return => self.return_value(frame, None)?,
+
+            I::Getstatic(index) => self.field(frame, *index, true, true)?,
+            I::Putstatic(index) => self.field(frame, *index, false, true)?,
+            I::Getfield(index) => self.field(frame, *index, true, false)?,
+            I::Putfield(index) => self.field(frame, *index, false, false)?,
+            I::Invokevirtual(_)
+            | I::Invokespecial(_)
+            | I::Invokestatic(_)
+            | I::Invokeinterface { .. }
+            | I::Invokedynamic(_) => self.invoke(frame, instruction)?,
+
+            I::New(index) => {
+                if self.pool.class_name(*index)?.starts_with('[') {
+                    return Err(VerifyErrorKind::Invalid("new of an array type"));
+                }
+                let t = T::Uninitialized(pc);
+                if frame.stack.contains(&t) {
+                    return Err(VerifyErrorKind::Invalid(
+                        "Result of an earlier run of this new is still on the operand stack",
+                    ));
+                }
+                frame.replace(&t, &T::Top);
+                self.push(frame, t)?;
+            }
+            I::Newarray(atype) => {
+                let array = match atype {
+                    ArrayType::Boolean => "[Z",
+                    ArrayType::Char => "[C",
+                    ArrayType::Float => "[F",
+                    ArrayType::Double => "[D",
+                    ArrayType::Byte => "[B",
+                    ArrayType::Short => "[S",
+                    ArrayType::Int => "[I",
+                    ArrayType::Long => "[J",
+                };
+                self.unary(frame, T::Integer, T::object(array))?;
+            }
+            I::Anewarray(index) => {
+                let class = self.pool.class_name(*index)?;
+                let array = if class.starts_with('[') {
+                    format!("[{}", class)
+                } else {
+                    format!("[L{};", class)
+                };
+                self.unary(frame, T::Integer, T::Object(array))?;
+            }
+            I::Arraylength => {
+                let actual = self.pop(frame, &T::OneWord)?;
+                match &actual {
+                    T::Null => {}
+                    T::Object(name) if name.starts_with('[') => {}
+                    _ => return Err(VerifyErrorKind::NotArray { actual }),
+                }
+                self.push(frame, T::Integer)?;
+            }
+            I::Athrow => {
+                self.pop(frame, &T::object(THROWABLE))?;
+            }
+            I::Checkcast(index) => {
+                let class = self.pool.class_name(*index)?;
+                self.unary(frame, T::object(OBJECT), T::object(class))?;
+            }
+            I::Instanceof(index) => {
+                self.pool.class_name(*index)?;
+                self.unary(frame, T::object(OBJECT), T::Integer)?;
+            }
+            I::Monitorenter | I::Monitorexit => {
+                self.pop(frame, &T::Reference)?;
+            }
+            I::Multianewarray { index, dimensions } => {
+                let class = self.pool.class_name(*index)?;
+                let array_dimensions = class.chars().take_while(|c| *c == '[').count();
+                if *dimensions == 0 || array_dimensions < (*dimensions).into() {
+                    return Err(VerifyErrorKind::Invalid(
+                        "multianewarray has more dimensions than its type",
+                    ));
+                }
+                for _ in 0..*dimensions {
+                    self.pop(frame, &T::Integer)?;
+                }
+                self.push(frame, T::object(class))?;
+            }
+
+            I::Wide(wide) => match *wide {
+                Wide::Iload(i) => self.load(frame, i.into(), T::Integer)?,
+                Wide::Lload(i) => self.load(frame, i.into(), T::Long)?,
+                Wide::Fload(i) => self.load(frame, i.into(), T::Float)?,
+                Wide::Dload(i) => self.load(frame, i.into(), T::Double)?,
+                Wide::Aload(i) => self.load(frame, i.into(), T::Reference)?,
+                Wide::Istore(i) => self.store(frame, i.into(), T::Integer)?,
+                Wide::Lstore(i) => self.store(frame, i.into(), T::Long)?,
+                Wide::Fstore(i) => self.store(frame, i.into(), T::Float)?,
+                Wide::Dstore(i) => self.store(frame, i.into(), T::Double)?,
+                Wide::Astore(i) => self.store(frame, i.into(), T::Reference)?,
+                Wide::Ret(i) => {
+                    self.ret(frame, i.into())?;
+                }
+                Wide::Iinc { index, .. } => self.iinc(frame, index.into())?,
+            },
+        }
+        Ok(())
+    }
+
+    /// Check that `ret` uses a return address, returning the start of its subroutine
+    fn ret(&self, frame: &Frame, index: usize) -> Result<usize> {
+        match frame.locals.get(index) {
+            None => Err(VerifyErrorKind::BadLocal { index }),
+            Some(Type::ReturnAddress(subroutine)) => Ok(*subroutine),
+            Some(_) => Err(VerifyErrorKind::Invalid(
+                "ret uses a local which isn't a return address",
+            )),
+        }
+    }
+
+    /// Pad `types` with [`Type::Top`] to `max`, after the second halves of `long`s and
+    /// `double`s.
+    fn expand(types: &[Type], max: usize) -> Result<Vec<Type>> {
+        let mut expanded = Vec::with_capacity(max);
+        for t in types {
+            expanded.push(t.clone());
+            if t.is_wide() {
+                expanded.push(Type::Top);
+            }
+        }
+        if expanded.len() > max {
+            return Err(VerifyErrorKind::Invalid(
+                "Frame doesn't fit in max_locals or max_stack",
+            ));
+        }
+        Ok(expanded)
+    }
+
+    /// The types of the method's arguments, see
+    /// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.10.1.6>
+    fn argument_types(&self) -> Vec<Type> {
+        let mut types = Vec::new();
+        if !self.is_static() {
+            if self.method.name == "<init>" && self.this_class != OBJECT {
+                types.push(Type::UninitializedThis);
+            } else {
+                types.push(Type::object(self.this_class));
+            }
+        }
+        types.extend(self.descriptor.params.iter().map(Type::from_field_type));
+        types
+    }
+
+    fn initial_frame(&self) -> Result<Frame> {
+        let arguments = self.argument_types();
+        let mut locals = Self::expand(&arguments, self.max_locals)?;
+        locals.resize(self.max_locals, Type::Top);
+        Ok(Frame {
+            locals,
+            stack: vec![],
+            this_uninit: arguments.contains(&Type::UninitializedThis),
+        })
+    }
+
+    /// The frames in the `StackMapTable`, by pc
+    fn stack_map_frames(&self) -> Result<BTreeMap<usize, Frame>> {
+        let mut frames = BTreeMap::new();
+        let mut locals = self.argument_types();
+        for frame in self.stack_map.iter().flatten() {
+            let stack = match &frame.kind {
+                StackMapFrameKind::Same | StackMapFrameKind::SameExtended => vec![],
+                StackMapFrameKind::SameLocals1StackItem { stack }
+                | StackMapFrameKind::SameLocals1StackItemExtended { stack } => {
+                    vec![Type::from_verification_type(stack)]
+                }
+                StackMapFrameKind::Chop { absent_locals } => {
+                    let absent = usize::from(*absent_locals);
+                    if absent > locals.len() {
+                        return Err(VerifyErrorKind::Invalid(
+                            "chop_frame removes more locals than there are",
+                        ));
+                    }
+                    locals.truncate(locals.len() - absent);
+                    vec![]
+                }
+                StackMapFrameKind::Append { locals: appended } => {
+                    locals.extend(appended.iter().map(Type::from_verification_type));
+                    vec![]
+                }
+                StackMapFrameKind::Full {
+                    locals: full,
+                    stack,
+                } => {
+                    locals = full.iter().map(Type::from_verification_type).collect();
+                    stack.iter().map(Type::from_verification_type).collect()
+                }
+            };
+
+            if !self.instructions.contains_key(&frame.offset) {
+                return Err(VerifyErrorKind::BadTarget {
+                    target: frame.offset,
+                });
+            }
+            let mut expanded = Self::expand(&locals, self.max_locals)?;
+            expanded.resize(self.max_locals, Type::Top);
+            frames.insert(
+                frame.offset,
+                Frame {
+                    this_uninit: expanded.contains(&Type::UninitializedThis),
+                    locals: expanded,
+                    stack: Self::expand(&stack, self.max_stack)?,
+                },
+            );
+        }
+        Ok(frames)
+    }
+
+    /// Check that `frame` can be used where `target` is expected
+    fn check_frame(&self, frame: &Frame, target: &Frame) -> Result<()> {
+        for (actual, expected) in frame.locals.iter().zip(&target.locals) {
+            if !self.is_assignable(actual, expected)? {
+                return Err(VerifyErrorKind::Mismatch {
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+            }
+        }
+        if frame.stack.len() != target.stack.len() {
+            return Err(VerifyErrorKind::StackHeight {
+                expected: target.stack.len(),
+                actual: frame.stack.len(),
+            });
+        }
+        for (actual, expected) in frame.stack.iter().zip(&target.stack) {
+            if !self.is_assignable(actual, expected)? {
+                return Err(VerifyErrorKind::Mismatch {
+                    expected: expected.clone(),
+                    actual: actual.clone(),
+                });
+            }
+        }
+        if frame.this_uninit && !target.this_uninit {
+            return Err(VerifyErrorKind::Invalid(
+                "uninitializedThis is lost at a branch target",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The frame at the start of each exception handler that covers `pc`
+    fn handler_frames(&self, pc: usize, frame: &Frame) -> Result<Vec<(usize, Frame)>> {
+        let mut frames = Vec::new();
+        for exception in &self.exception_table {
+            let start = usize::from(exception.start_pc);
+            let end = usize::from(exception.end_pc);
+            if !(start..end).contains(&pc) {
+                continue;
+            }
+            let catch_type = match exception.catch_type {
+                0 => Type::object(THROWABLE),
+                index => Type::object(self.pool.class_name(index.into())?),
+            };
+            if !self.is_assignable(&catch_type, &Type::object(THROWABLE))? {
+                return Err(VerifyErrorKind::Mismatch {
+                    expected: Type::object(THROWABLE),
+                    actual: catch_type,
+                });
+            }
+            frames.push((
+                exception.handler_pc.into(),
+                Frame {
+                    locals: frame.locals.clone(),
+                    stack: vec![catch_type],
+                    this_uninit: frame.this_uninit,
+                },
+            ));
+        }
+        Ok(frames)
+    }
+
+    fn check_exception_table(&self) -> std::result::Result<(), (usize, VerifyErrorKind)> {
+        for exception in &self.exception_table {
+            let start = usize::from(exception.start_pc);
+            let end = usize::from(exception.end_pc);
+            let handler = usize::from(exception.handler_pc);
+            let is_boundary = |pc| self.instructions.contains_key(&pc);
+            if start >= end || !is_boundary(start) || !(is_boundary(end) || end == self.code_len) {
+                return Err((
+                    start,
+                    VerifyErrorKind::Invalid("Invalid exception handler range"),
+                ));
+            }
+            if !is_boundary(handler) {
+                return Err((start, VerifyErrorKind::BadTarget { target: handler }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Verification by type checking, see
+    /// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.10.1>
+    fn type_check(&self) -> std::result::Result<(), (usize, VerifyErrorKind)> {
+        self.check_exception_table()?;
+        let stack_map = self.stack_map_frames().map_err(|e| (0, e))?;
+        let mut current = Some(self.initial_frame().map_err(|e| (0, e))?);
+
+        for (&pc, instruction) in &self.instructions {
+            let result = (|| {
+                if let Some(target) = stack_map.get(&pc) {
+                    if let Some(frame) = &current {
+                        self.check_frame(frame, target)?;
+                    }
+                    current = Some(target.clone());
+                }
+                let Some(frame) = &current else {
+                    return Err(VerifyErrorKind::MissingFrame { target: pc });
+                };
+
+                if matches!(
+                    instruction,
+                    Instruction::Jsr(_)
+                        | Instruction::JsrW(_)
+                        | Instruction::Ret(_)
+                        | Instruction::Wide(Wide::Ret(_))
+                ) {
+                    return Err(VerifyErrorKind::Invalid(
+                        "jsr and ret aren't allowed in class files from version 50",
+                    ));
+                }
+
+                for (handler, exception_frame) in self.handler_frames(pc, frame)? {
+                    let target = stack_map
+                        .get(&handler)
+                        .ok_or(VerifyErrorKind::MissingFrame { target: handler })?;
+                    self.check_frame(&exception_frame, target)?;
+                }
+
+                let mut next = frame.clone();
+                self.execute(&mut next, pc, instruction)?;
+
+                for target in instruction.branch_targets(pc) {
+                    let frame = stack_map.get(&target).ok_or(
+                        if self.instructions.contains_key(&target) {
+                            VerifyErrorKind::MissingFrame { target }
+                        } else {
+                            VerifyErrorKind::BadTarget { target }
+                        },
+                    )?;
+                    self.check_frame(&next, frame)?;
+                }
+
+                current = if instruction.falls_through() {
+                    if pc + instruction.len(pc) == self.code_len {
+                        return Err(VerifyErrorKind::FallsOffEnd);
+                    }
+                    Some(next)
+                } else {
+                    None
+                };
+                Ok(())
+            })();
+            result.map_err(|e| (pc, e))?;
+        }
+        Ok(())
+    }
+
+    /// The locals that can be written by the subroutine starting at `start`, including by
+    /// subroutines that it calls
+    fn subroutine_writes(&self, start: usize) -> Vec<bool> {
+        let mut writes = vec![false; self.max_locals];
+        let mut visited = BTreeSet::new();
+        let mut worklist = vec![start];
+        while let Some(pc) = worklist.pop() {
+            if !visited.insert(pc) {
+                continue;
+            }
+            let Some(instruction) = self.instructions.get(&pc) else {
+                continue;
+            };
+            if let Some((index, wide)) = stored_local(instruction) {
+                let size = if wide { 2 } else { 1 };
+                for i in index..index + size {
+                    if let Some(write) = writes.get_mut(i) {
+                        *write = true;
+                    }
+                }
+            }
+            if matches!(
+                instruction,
+                Instruction::Ret(_) | Instruction::Wide(Wide::Ret(_))
+            ) {
+                continue;
+            }
+            worklist.extend(instruction.branch_targets(pc));
+            if instruction.falls_through() {
+                worklist.push(pc + instruction.len(pc));
+            }
+        }
+        writes
+    }
+
+    /// Verification by type inference, see
+    /// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-4.html#jvms-4.10.2>
+    fn infer(&self) -> std::result::Result<(), (usize, VerifyErrorKind)> {
+        self.check_exception_table()?;
+
+        let mut frames: HashMap<usize, Frame> = HashMap::new();
+        let mut worklist = BTreeSet::new();
+        // frames at the `ret`s of each subroutine, merged together
+        let mut ret_frames: HashMap<usize, Frame> = HashMap::new();
+        let mut writes: HashMap<usize, Vec<bool>> = HashMap::new();
+
+        let Some(&first) = self.instructions.keys().next() else {
+            return Err((0, VerifyErrorKind::FallsOffEnd));
+        };
+        frames.insert(first, self.initial_frame().map_err(|e| (0, e))?);
+        worklist.insert(first);
+
+        while let Some(pc) = worklist.pop_first() {
+            let instruction = &self.instructions[&pc];
+            let frame = frames[&pc].clone();
+
+            let mut flow_to = |frames: &mut HashMap<usize, Frame>, target, frame| {
+                self.flow(frames, &mut worklist, target, frame)
+            };
+
+            let result = (|| {
+                for (handler, exception_frame) in self.handler_frames(pc, &frame)? {
+                    flow_to(&mut frames, handler, exception_frame)?;
+                }
+
+                let mut next = frame.clone();
+                self.execute(&mut next, pc, instruction)?;
+
+                match instruction {
+                    Instruction::Jsr(_) | Instruction::JsrW(_) => {
+                        let subroutine = instruction.branch_targets(pc)[0];
+                        flow_to(&mut frames, subroutine, next)?;
+                        if let Some(ret_frame) = ret_frames.get(&subroutine) {
+                            let writes = writes
+                                .entry(subroutine)
+                                .or_insert_with(|| self.subroutine_writes(subroutine));
+                            flow_to(
+                                &mut frames,
+                                pc + instruction.len(pc),
+                                Self::after_subroutine(&frame, ret_frame, writes),
+                            )?;
+                        }
+                    }
+                    Instruction::Ret(_) | Instruction::Wide(Wide::Ret(_)) => {
+                        let index = match instruction {
+                            Instruction::Ret(index) => usize::from(*index),
+                            Instruction::Wide(Wide::Ret(index)) => usize::from(*index),
+                            _ => unreachable!(),
+                        };
+                        let subroutine = self.ret(&frame, index)?;
+                        let ret_frame = match ret_frames.get(&subroutine) {
+                            Some(old) => self.merge_frames(old, &next)?,
+                            None => next,
+                        };
+                        ret_frames.insert(subroutine, ret_frame.clone());
+                        let writes = writes
+                            .entry(subroutine)
+                            .or_insert_with(|| self.subroutine_writes(subroutine))
+                            .clone();
+                        let callers: Vec<_> = self
+                            .instructions
+                            .iter()
+                            .filter(|(&caller, i)| {
+                                matches!(i, Instruction::Jsr(_) | Instruction::JsrW(_))
+                                    && i.branch_targets(caller)[0] == subroutine
+                            })
+                            .map(|(&caller, i)| (caller, caller + i.len(caller)))
+                            .collect();
+                        for (caller, after) in callers {
+                            let Some(caller_frame) = frames.get(&caller).cloned() else {
+                                continue;
+                            };
+                            flow_to(
+                                &mut frames,
+                                after,
+                                Self::after_subroutine(&caller_frame, &ret_frame, &writes),
+                            )?;
+                        }
+                    }
+                    _ => {
+                        for target in instruction.branch_targets(pc) {
+                            flow_to(&mut frames, target, next.clone())?;
+                        }
+                        if instruction.falls_through() {
+                            flow_to(&mut frames, pc + instruction.len(pc), next)?;
+                        }
+                    }
+                }
+                Ok(())
+            })();
+            result.map_err(|e| (pc, e))?;
+        }
+        Ok(())
+    }
+
+    /// Merge `frame` into the frame at `target`, queueing `target` to be checked again if that
+    /// changes it
+    fn flow(
+        &self,
+        frames: &mut HashMap<usize, Frame>,
+        worklist: &mut BTreeSet<usize>,
+        target: usize,
+        frame: Frame,
+    ) -> Result<()> {
+        if target == self.code_len {
+            return Err(VerifyErrorKind::FallsOffEnd);
+        }
+        if !self.instructions.contains_key(&target) {
+            return Err(VerifyErrorKind::BadTarget { target });
+        }
+        match frames.get(&target) {
+            None => {
+                frames.insert(target, frame);
+                worklist.insert(target);
+            }
+            Some(old) => {
+                let merged = self.merge_frames(old, &frame)?;
+                if &merged != old {
+                    frames.insert(target, merged);
+                    worklist.insert(target);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The frame after returning from a subroutine, which has the caller's locals except for
+    /// those that the subroutine could have written
+    fn after_subroutine(caller: &Frame, ret: &Frame, writes: &[bool]) -> Frame {
+        Frame {
+            locals: (0..caller.locals.len())
+                .map(|i| {
+                    if writes[i] {
+                        ret.locals[i].clone()
+                    } else if caller.locals[i].is_wide() && writes.get(i + 1) == Some(&true) {
+                        // the second half was overwritten
+                        Type::Top
+                    } else {
+                        caller.locals[i].clone()
+                    }
+                })
+                .collect(),
+            stack: ret.stack.clone(),
+            this_uninit: caller.this_uninit,
+        }
+    }
+
+    fn merge_frames(&self, a: &Frame, b: &Frame) -> Result<Frame> {
+        if a.stack.len() != b.stack.len() {
+            return Err(VerifyErrorKind::StackHeight {
+                expected: a.stack.len(),
+                actual: b.stack.len(),
+            });
+        }
+        let mut stack = Vec::with_capacity(a.stack.len());
+        for (a, b) in a.stack.iter().zip(&b.stack) {
+            let merged = self.merge(a, b)?;
+            if merged == Type::Top && *a != Type::Top {
+                return Err(VerifyErrorKind::Mismatch {
+                    expected: a.clone(),
+                    actual: b.clone(),
+                });
+            }
+            stack.push(merged);
+        }
+        Ok(Frame {
+            locals: a
+                .locals
+                .iter()
+                .zip(&b.locals)
+                .map(|(a, b)| self.merge(a, b))
+                .collect::<Result<_>>()?,
+            stack,
+            this_uninit: a.this_uninit || b.this_uninit,
+        })
+    }
+}
+
+/// The class name or array descriptor of the component of an array type, or `None` if it's a
+/// primitive
+fn reference_component(component: &str) -> Option<&str> {
+    if component.starts_with('[') {
+        Some(component)
+    } else {
+        component.strip_prefix('L')?.strip_suffix(';')
+    }
+}
+
+/// The local variable that `instruction` stores into, and whether it's a `long` or `double`
+fn stored_local(instruction: &Instruction) -> Option<(usize, bool)> {
+    use Instruction as I;
+    Some(match instruction {
+        I::Istore(i) | I::Fstore(i) | I::Astore(i) => ((*i).into(), false),
+        I::Lstore(i) | I::Dstore(i) => ((*i).into(), true),
+        I::Istore0 | I::Fstore0 | I::Astore0 => (0, false),
+        I::Istore1 | I::Fstore1 | I::Astore1 => (1, false),
+        I::Istore2 | I::Fstore2 | I::Astore2 => (2, false),
+        I::Istore3 | I::Fstore3 | I::Astore3 => (3, false),
+        I::Lstore0 | I::Dstore0 => (0, true),
+        I::Lstore1 | I::Dstore1 => (1, true),
+        I::Lstore2 | I::Dstore2 => (2, true),
+        I::Lstore3 | I::Dstore3 => (3, true),
+        I::Wide(Wide::Istore(i) | Wide::Fstore(i) | Wide::Astore(i)) => ((*i).into(), false),
+        I::Wide(Wide::Lstore(i) | Wide::Dstore(i)) => ((*i).into(), true),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        assembler::{Assembler, Jump},
+        constant_pool::Constant,
+        test::stdlib_classes,
+    };
+    use std::{fs, io::Cursor};
+
+    /// Verify `asm` as a static method of `java/lang/Object`, with the class' version set to
+    /// `major`
+    fn verify(
+        asm: &Assembler,
+        descriptor: &str,
+        major: u16,
+    ) -> std::result::Result<(), VerifyError> {
+        verify_with(asm, descriptor, major, &())
+    }
+
+    /// [`verify`] with other classes looked up in `hierarchy`
+    fn verify_with<H: ClassHierarchy>(
+        asm: &Assembler,
+        descriptor: &str,
+        major: u16,
+        hierarchy: &H,
+    ) -> std::result::Result<(), VerifyError> {
+        let path = stdlib_classes("java.base/java/lang/Object.class").unwrap();
+        let mut class =
+            ClassFile::read_from(&mut Cursor::new(fs::read(&path[0]).unwrap())).unwrap();
+        class.version.0 = major;
+        let code_index = class
+            .constant_pool
+            .iter()
+            .find(|(_, c)| matches!(c, Ok(Constant::Utf8("Code"))))
+            .unwrap()
+            .0;
+        let code = asm.assemble(&class.constant_pool).unwrap();
+        let attributes = [code.to_raw(code_index).unwrap()];
+        let method = Method {
+            access_flags: MethodAccessFlags::STATIC,
+            name: "test",
+            descriptor,
+            attributes: &attributes,
+            constant_pool: &class.constant_pool,
+        };
+        verify_method(&class, &method, hierarchy)
+    }
+
+    #[test]
+    fn type_errors() {
+        let mut asm = Assembler::new(0);
+        asm.push(Instruction::Iconst0)
+            .push(Instruction::Fconst0)
+            .push(Instruction::Iadd)
+            .push(Instruction::Return);
+        let e = verify(&asm, "()V", 61).unwrap_err();
+        assert_eq!(e.method, "test()V");
+        assert_eq!(e.pc, 2);
+        assert_eq!(
+            e.kind,
+            VerifyErrorKind::Mismatch {
+                expected: Type::Integer,
+                actual: Type::Float
+            }
+        );
+
+        let mut asm = Assembler::new(0);
+        asm.push(Instruction::Lconst0).push(Instruction::Ireturn);
+        let e = verify(&asm, "()I", 61).unwrap_err();
+        assert_eq!(
+            (e.pc, e.kind),
+            (
+                1,
+                VerifyErrorKind::Mismatch {
+                    expected: Type::Integer,
+                    actual: Type::Long
+                }
+            )
+        );
+
+        let mut asm = Assembler::new(1);
+        asm.push(Instruction::Aload0).push(Instruction::Areturn);
+        assert!(verify(&asm, "(Ljava/lang/String;)Ljava/lang/Object;", 61).is_ok());
+        let e = verify(&asm, "(I)Ljava/lang/Object;", 61).unwrap_err();
+        assert_eq!(
+            (e.pc, e.kind),
+            (
+                0,
+                VerifyErrorKind::Mismatch {
+                    expected: Type::Reference,
+                    actual: Type::Integer
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn stack_map_frames() {
+        let mut asm = Assembler::new(1);
+        let end = asm.new_label();
+        asm.push(Instruction::Iload0)
+            .jump(Jump::Ifeq, end)
+            .push(Instruction::Iinc { index: 0, value: 1 })
+            .bind(end)
+            .push(Instruction::Return);
+
+        // the assembler doesn't write a StackMapTable, so there's no frame at the branch target
+        let e = verify(&asm, "(I)V", 61).unwrap_err();
+        assert_eq!(
+            (e.pc, e.kind),
+            (1, VerifyErrorKind::MissingFrame { target: 7 })
+        );
+
+        // older class files are verified by inference, and 50 falls back to it
+        assert!(verify(&asm, "(I)V", 49).is_ok());
+        assert!(verify(&asm, "(I)V", 50).is_ok());
+
+        // an int and a float can't be merged on the stack
+        let mut asm = Assembler::new(0);
+        let float = asm.new_label();
+        let end = asm.new_label();
+        asm.push(Instruction::Iconst0)
+            .jump(Jump::Ifeq, float)
+            .push(Instruction::Iconst1)
+            .jump(Jump::Goto, end)
+            .bind(float)
+            .push(Instruction::Fconst0)
+            .bind(end)
+            .push(Instruction::Pop)
+            .push(Instruction::Return);
+        let e = verify(&asm, "()V", 49).unwrap_err();
+        assert_eq!(
+            (e.pc, e.kind),
+            (
+                8,
+                VerifyErrorKind::Mismatch {
+                    expected: Type::Integer,
+                    actual: Type::Float
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn subroutines() {
+        // the subroutine is called with an int and then a float in local 0, which it doesn't
+        // touch, so each caller should get its own type back
+        let mut asm = Assembler::new(0);
+        let subroutine = asm.new_label();
+        asm.push(Instruction::Iconst1)
+            .push(Instruction::Istore0)
+            .jump(Jump::Jsr, subroutine)
+            .push(Instruction::Iload0)
+            .push(Instruction::Pop)
+            .push(Instruction::Fconst1)
+            .push(Instruction::Fstore0)
+            .jump(Jump::Jsr, subroutine)
+            .push(Instruction::Fload0)
+            .push(Instruction::Pop)
+            .push(Instruction::Return)
+            .bind(subroutine)
+            .push(Instruction::Astore1)
+            .push(Instruction::Ret(1));
+        verify(&asm, "()V", 49).unwrap();
+
+        let e = verify(&asm, "()V", 51).unwrap_err();
+        assert_eq!(
+            (e.pc, e.kind),
+            (
+                2,
+                VerifyErrorKind::Invalid(
+                    "jsr and ret aren't allowed in class files from version 50"
+                )
+            )
+        );
+    }
+
+    /// `A` extends `B`, which extends `A`
+    struct Circular;
+
+    impl ClassHierarchy for Circular {
+        fn class_info(&self, name: &str) -> Option<ClassInfo> {
+            let super_class = match name {
+                "A" => "B",
+                "B" => "A",
+                "C" => OBJECT,
+                _ => return None,
+            };
+            Some(ClassInfo {
+                super_class: Some(super_class.into()),
+                is_interface: false,
+            })
+        }
+    }
+
+    #[test]
+    fn circular_superclasses() {
+        let mut asm = Assembler::new(1);
+        asm.push(Instruction::Aload0).push(Instruction::Areturn);
+        let e = verify_with(&asm, "(LA;)LC;", 61, &Circular).unwrap_err();
+        assert_eq!(
+            (e.pc, e.kind),
+            (
+                1,
+                VerifyErrorKind::Circular {
+                    class: "A".to_string()
+                }
+            )
+        );
+
+        // merging an A with a C looks for the superclasses they have in common
+        let mut asm = Assembler::new(3);
+        let c = asm.new_label();
+        let end = asm.new_label();
+        asm.push(Instruction::Iload0)
+            .jump(Jump::Ifeq, c)
+            .push(Instruction::Aload1)
+            .jump(Jump::Goto, end)
+            .bind(c)
+            .push(Instruction::Aload2)
+            .bind(end)
+            .push(Instruction::Areturn);
+        let e = verify_with(&asm, "(ILA;LC;)Ljava/lang/Object;", 49, &Circular).unwrap_err();
+        assert_eq!(
+            e.kind,
+            VerifyErrorKind::Circular {
+                class: "A".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn stdlib() {
+        let Some(paths) = stdlib_classes("java.base") else {
+            return;
+        };
+        let classes: HashMap<_, _> = paths
+            .iter()
+            .map(|path| {
+                let bytes = fs::read(path).unwrap();
+                ClassFile::read_from(&mut Cursor::new(bytes)).unwrap()
+            })
+            .filter(|class| !class.access_flags.contains(ClassAccessFlags::MODULE))
+            .map(|class| (class.this_class().unwrap().to_string(), class))
+            .collect();
+
+        // and again by inference, pretending that they're older class files
+        let failures: Vec<_> = classes
+            .values()
+            .flat_map(|class| {
+                let mut old = class.clone();
+                old.version = (49, 0);
+                [verify_class(class, &classes), verify_class(&old, &classes)]
+            })
+            .filter_map(|result| result.err())
+            .collect();
+        assert!(
+            failures.is_empty(),
+            "{} classes failed, e.g.\n{}",
+            failures.len(),
+            failures
+                .iter()
+                .take(20)
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Class loaders, see <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-5.html#jvms-5.3>
+//!
+//! A class at run time is identified by its binary name together with the loader that defined
+//! it, so different loaders can define classes with the same name. The built-in loaders give their
+//! parent the first chance to load a class before looking at their own class path: the bootstrap
+//! loader reads the JDK's modules, the platform loader delegates to it, and the application loader
+//! reads the module path and the class path. User-defined loaders are objects of subclasses of
+//! `java.lang.ClassLoader`, which are asked for classes by calling their `loadClass`.
+
+use std::fmt;
+
+use crate::class_path::ClassPath;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ClassLoader {
+    Bootstrap,
+    Platform,
+    Application,
+    /// A `java.lang.ClassLoader` object, by its reference into the heap
+    User(usize),
+}
+
+/// The defining loader and binary name of a class
+pub(crate) type ClassKey = (ClassLoader, String);
+
+impl ClassLoader {
+    /// The loader that a built-in loader delegates to. User-defined loaders delegate however their
+    /// `loadClass` does.
+    pub fn parent(self) -> Option<Self> {
+        match self {
+            ClassLoader::Bootstrap | ClassLoader::User(_) => None,
+            ClassLoader::Platform => Some(ClassLoader::Bootstrap),
+            ClassLoader::Application => Some(ClassLoader::Platform),
+        }
+    }
+}
+
+impl fmt::Display for ClassLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClassLoader::Bootstrap => write!(f, "bootstrap"),
+            ClassLoader::Platform => write!(f, "platform"),
+            ClassLoader::Application => write!(f, "app"),
+            ClassLoader::User(reference) => write!(f, "loader@{}", reference),
+        }
+    }
+}
+
+/// Where each of the built-in loaders finds its classes
+#[derive(Debug, Default)]
+pub(crate) struct ClassPaths {
+    boot: ClassPath,
+    /// The JDK's platform modules are in the same image as `java.base`, so they're found by the
+    /// bootstrap loader and this is empty
+    platform: ClassPath,
+    app: ClassPath,
+}
+
+impl ClassPaths {
+    pub fn new(boot: ClassPath, app: ClassPath) -> Self {
+        Self {
+            boot,
+            platform: ClassPath::default(),
+            app,
+        }
+    }
+
+    /// The class path that `loader` defines classes from, if it's a built-in loader
+    pub fn get(&self, loader: ClassLoader) -> Option<&ClassPath> {
+        match loader {
+            ClassLoader::Bootstrap => Some(&self.boot),
+            ClassLoader::Platform => Some(&self.platform),
+            ClassLoader::Application => Some(&self.app),
+            ClassLoader::User(_) => None,
+        }
+    }
+
+    /// Read the class `name` that `loader` would define or get from its parents, without loading
+    /// it
+    pub fn find_class(&self, loader: ClassLoader, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        if let Some(parent) = loader.parent() {
+            if let Some(bytes) = self.find_class(parent, name)? {
+                return Ok(Some(bytes));
+            }
+        }
+        match self.get(loader) {
+            Some(class_path) => class_path.find_class(name),
+            None => Ok(None),
+        }
+    }
+}
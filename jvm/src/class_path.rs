@@ -0,0 +1,586 @@
+//! The class path, which is searched in order for the bytes of a class by its binary name.
+//!
+//! [^see]: <https://docs.oracle.com/en/java/javase/21/docs/specs/man/java.html#standard-options-for-java>
+
+use anyhow::{ensure, Context};
+use class_files::format::MAX_MAJOR_VERSION;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    jimage::JImage,
+    manifest::{Manifest, MANIFEST_NAME},
+    zip::ZipArchive,
+};
+
+const JMOD_HEADER: [u8; 4] = [b'J', b'M', 1, 0];
+/// Where the classes are in a jmod, the other directories are for native libraries, commands and
+/// configuration
+const JMOD_CLASSES: &str = "classes/";
+
+/// The Java release that this JVM runs, which picks the versioned entries of multi-release jars
+const RELEASE: u16 = MAX_MAJOR_VERSION - 44;
+
+#[derive(Debug)]
+pub(crate) enum ClassPathEntry {
+    /// A directory where `java/lang/String` is found at `java/lang/String.class`
+    Directory(PathBuf),
+    /// A jar or zip file where `java/lang/String` is the entry `java/lang/String.class`
+    Archive {
+        path: PathBuf,
+        archive: ZipArchive,
+        manifest: Option<Manifest>,
+        /// The releases with entries in `META-INF/versions/` that apply to [`RELEASE`], newest
+        /// first, only for multi-release jars
+        versions: Vec<u16>,
+    },
+    /// A `.jmod` file, where `java/lang/String` is the entry `classes/java/lang/String.class`
+    Jmod { path: PathBuf, archive: ZipArchive },
+    /// A JDK's `lib/modules` jimage, where `java/lang/String` is `/java.base/java/lang/String.class`
+    Image { path: PathBuf, image: JImage },
+}
+
+impl ClassPathEntry {
+    /// The bytes of the resource at `name`, e.g. `java/lang/String.class`
+    pub fn find(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match self {
+            Self::Directory(dir) => match fs::read(dir.join(name)) {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e).with_context(|| format!("reading {}", dir.join(name).display())),
+            },
+            Self::Archive {
+                path,
+                archive,
+                versions,
+                ..
+            } => {
+                let versioned = versions
+                    .iter()
+                    .map(|version| format!("META-INF/versions/{}/{}", version, name));
+                for name in versioned.chain([name.to_string()]) {
+                    if let Some(bytes) = archive
+                        .read(&name)
+                        .with_context(|| format!("reading {}", path.display()))?
+                    {
+                        return Ok(Some(bytes));
+                    }
+                }
+                Ok(None)
+            }
+            Self::Jmod { path, archive } => archive
+                .read(&format!("{}{}", JMOD_CLASSES, name))
+                .with_context(|| format!("reading {}", path.display())),
+            Self::Image { path, image } => {
+                let Some((package, _)) = name.rsplit_once('/') else {
+                    return Ok(None);
+                };
+                let Some(module) = image.module_of(package)? else {
+                    return Ok(None);
+                };
+                image
+                    .read(&format!("/{}/{}", module, name))
+                    .with_context(|| format!("reading {}", path.display()))
+            }
+        }
+    }
+
+    /// Open a `.jmod` file, which is a zip file after a four byte header of `JM` and the version
+    fn open_jmod(path: &Path) -> anyhow::Result<Self> {
+        let mut data = fs::read(path)?;
+        ensure!(
+            data.get(..4) == Some(&JMOD_HEADER),
+            "{} is not a jmod file",
+            path.display()
+        );
+        data.drain(..4);
+        Ok(Self::Jmod {
+            path: path.to_path_buf(),
+            archive: ZipArchive::new(data)
+                .with_context(|| format!("reading {}", path.display()))?,
+        })
+    }
+
+    fn open_archive(path: &Path) -> anyhow::Result<Self> {
+        let archive = ZipArchive::open(path)?;
+        let manifest = archive
+            .read(MANIFEST_NAME)
+            .with_context(|| format!("reading {}", path.display()))?
+            .map(|bytes| Manifest::parse(&bytes));
+
+        let mut versions = Vec::new();
+        if manifest.as_ref().is_some_and(Manifest::is_multi_release) {
+            versions = archive
+                .names()
+                .filter_map(|name| name.strip_prefix("META-INF/versions/")?.split_once('/'))
+                .filter_map(|(version, _)| version.parse().ok())
+                .filter(|version| (9..=RELEASE).contains(version))
+                .collect();
+            versions.sort_by(|a, b| b.cmp(a));
+            versions.dedup();
+        }
+
+        Ok(Self::Archive {
+            path: path.to_path_buf(),
+            archive,
+            manifest,
+            versions,
+        })
+    }
+}
+
+/// The JDK given by `JAVA_HOME`, or else the one that `java` on the `PATH` belongs to
+pub(crate) fn java_home() -> Option<PathBuf> {
+    if let Some(home) = env::var_os("JAVA_HOME") {
+        return Some(PathBuf::from(home));
+    }
+    let java = env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join("java"))
+        .find(|java| java.is_file())?;
+    // `java` is in `$JAVA_HOME/bin`, though usually through a symlink
+    Some(
+        fs::canonicalize(java)
+            .ok()?
+            .parent()?
+            .parent()?
+            .to_path_buf(),
+    )
+}
+
+/// Turn a relative URL from a `Class-Path` manifest attribute into a path
+fn url_to_path(url: &str) -> Option<PathBuf> {
+    let url = url.strip_prefix("file:").unwrap_or(url);
+    if url.contains("://") {
+        return None;
+    }
+    // undo percent-encoding, such as `%20` for spaces
+    let mut bytes = Vec::with_capacity(url.len());
+    let mut rest = url.as_bytes();
+    while let Some((byte, tail)) = rest.split_first() {
+        let escaped = (*byte == b'%')
+            .then(|| std::str::from_utf8(tail.get(..2)?).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(*byte);
+                rest = tail;
+            }
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+/// An ordered list of directories and archives, the first one that has a class is where it's
+/// loaded from
+#[derive(Debug, Default)]
+pub(crate) struct ClassPath {
+    entries: Vec<ClassPathEntry>,
+}
+
+fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jar") || ext.eq_ignore_ascii_case("zip"))
+}
+
+fn is_jmod(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jmod"))
+}
+
+impl ClassPath {
+    /// Parse a class path separated like `PATH`, with `:` on Unix and `;` on Windows
+    pub fn parse(class_path: &str) -> anyhow::Result<Self> {
+        let mut cp = Self::default();
+        for path in env::split_paths(class_path) {
+            cp.push(path)?;
+        }
+        Ok(cp)
+    }
+
+    /// Parse a module path, where each directory holds jmods, jars or exploded modules
+    pub fn parse_module_path(module_path: &str) -> anyhow::Result<Self> {
+        let mut cp = Self::default();
+        for path in env::split_paths(module_path) {
+            let Ok(entries) = fs::read_dir(&path) else {
+                // files can be given directly too
+                cp.push(path)?;
+                continue;
+            };
+            let mut modules: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+            modules.sort();
+            for module in modules {
+                if module.is_dir() || is_archive(&module) || is_jmod(&module) {
+                    cp.push(module)?;
+                }
+            }
+        }
+        Ok(cp)
+    }
+
+    /// The class path given by the `CLASSPATH` environment variable, or the current directory if
+    /// it isn't set
+    pub fn from_env() -> anyhow::Result<Self> {
+        match env::var("CLASSPATH") {
+            Ok(class_path) => Self::parse(&class_path).context("parsing CLASSPATH"),
+            Err(_) => Self::parse("."),
+        }
+    }
+
+    /// Where the JDK's own classes are loaded from, which is `$JAVA_HOME/lib/modules` or, if
+    /// there is no JDK, the classes extracted from it by `build.sh`
+    pub fn boot() -> anyhow::Result<Self> {
+        let mut cp = Self::default();
+        let image = java_home().map(|home| home.join("lib").join("modules"));
+        match image {
+            Some(path) if path.is_file() => cp.entries.push(ClassPathEntry::Image {
+                image: JImage::open(&path)?,
+                path,
+            }),
+            _ => cp.push("stdlib/java.base")?,
+        }
+        Ok(cp)
+    }
+
+    /// Add the entries of `other` after the ones in this class path
+    pub fn append(&mut self, other: Self) {
+        self.entries.extend(other.entries);
+    }
+
+    /// The class path used by `java -jar` for the jar at `path`, along with the binary name of
+    /// the main class given by its manifest
+    pub fn for_jar<P>(path: P) -> anyhow::Result<(Self, String)>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let jar = ClassPathEntry::open_archive(path)?;
+        let main_class = match &jar {
+            ClassPathEntry::Archive {
+                manifest: Some(manifest),
+                ..
+            } => manifest.main_class(),
+            _ => None,
+        }
+        .with_context(|| format!("no main manifest attribute, in {}", path.display()))?;
+
+        let mut cp = Self::default();
+        cp.push_archive(jar)?;
+        Ok((cp, main_class))
+    }
+
+    /// Add a directory, a jar, zip or jmod file, or every one of those in a directory given as
+    /// `dir/*`. Paths which don't exist are skipped, like in `java`. The `Class-Path` in the
+    /// manifest of a jar is followed too.
+    pub fn push<P>(&mut self, path: P) -> anyhow::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if path.file_name().is_some_and(|name| name == "*") {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            let Ok(entries) = fs::read_dir(dir) else {
+                return Ok(());
+            };
+            let mut jars: Vec<_> = entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_file() && (is_archive(path) || is_jmod(path)))
+                .collect();
+            jars.sort();
+            for jar in jars {
+                self.push(jar)?;
+            }
+        } else if path.is_dir() {
+            self.entries
+                .push(ClassPathEntry::Directory(path.to_path_buf()));
+        } else if path.is_file() && !self.contains_archive(path) {
+            if is_jmod(path) {
+                self.entries.push(ClassPathEntry::open_jmod(path)?);
+            } else {
+                self.push_archive(ClassPathEntry::open_archive(path)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn contains_archive(&self, path: &Path) -> bool {
+        let canonical = fs::canonicalize(path).ok();
+        self.entries.iter().any(|entry| match entry {
+            ClassPathEntry::Archive { path: p, .. } | ClassPathEntry::Jmod { path: p, .. } => {
+                p == path || fs::canonicalize(p).ok() == canonical
+            }
+            ClassPathEntry::Directory(_) | ClassPathEntry::Image { .. } => false,
+        })
+    }
+
+    /// Add an archive and then what its manifest's `Class-Path` refers to, relative to the
+    /// directory it is in
+    fn push_archive(&mut self, entry: ClassPathEntry) -> anyhow::Result<()> {
+        let ClassPathEntry::Archive { path, manifest, .. } = &entry else {
+            unreachable!("only archives have manifests");
+        };
+        let dir = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let dependencies: Vec<_> = manifest
+            .iter()
+            .flat_map(Manifest::class_path)
+            .filter_map(url_to_path)
+            .map(|path| dir.join(path))
+            .collect();
+
+        self.entries.push(entry);
+        for path in dependencies {
+            // `dir/*` only means every jar on the command line
+            if path.file_name().is_some_and(|name| name == "*") {
+                continue;
+            }
+            self.push(path)?;
+        }
+        Ok(())
+    }
+
+    /// The bytes of the class with the binary name `name`, e.g. `java/lang/String`
+    pub fn find_class(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        self.find(&format!("{}.class", name))
+    }
+
+    /// The bytes of the resource at `name` in the first entry which has it
+    pub fn find(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        for entry in &self.entries {
+            if let Some(bytes) = entry.find(name)? {
+                return Ok(Some(bytes));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::zip::crc32;
+    use std::process;
+
+    /// An empty directory for a test to make files in
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("jvm-class-path-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write a zip archive at `path` with `entries` stored uncompressed
+    fn write_jar(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut data = Vec::new();
+        let mut central = Vec::new();
+        for (name, contents) in entries {
+            let len = (contents.len() as u32).to_le_bytes();
+            // version needed, flags, method, time and date, then the CRC-32, sizes and name and
+            // extra field lengths
+            let fields = [
+                &[20, 0, 0, 0, 0, 0, 0, 0, 0, 0][..],
+                &crc32(contents).to_le_bytes(),
+                &len,
+                &len,
+                &(name.len() as u16).to_le_bytes(),
+                &[0, 0],
+            ]
+            .concat();
+            central.extend(0x02014b50u32.to_le_bytes());
+            central.extend([20, 0]);
+            central.extend(&fields);
+            central.extend([0; 10]);
+            central.extend((data.len() as u32).to_le_bytes());
+            central.extend(name.as_bytes());
+
+            data.extend(0x04034b50u32.to_le_bytes());
+            data.extend(&fields);
+            data.extend(name.as_bytes());
+            data.extend(*contents);
+        }
+        let count = (entries.len() as u16).to_le_bytes();
+        let end = [
+            &0x06054b50u32.to_le_bytes()[..],
+            &[0; 4],
+            &count,
+            &count,
+            &(central.len() as u32).to_le_bytes(),
+            &(data.len() as u32).to_le_bytes(),
+            &[0, 0],
+        ]
+        .concat();
+        data.extend(central);
+        data.extend(end);
+        fs::write(path, data).unwrap();
+    }
+
+    fn class_path(paths: &[PathBuf]) -> ClassPath {
+        let joined = env::join_paths(paths).unwrap();
+        ClassPath::parse(joined.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn lookup_order() {
+        let dir = temp_dir("lookup_order");
+        let classes = dir.join("classes");
+        fs::create_dir_all(classes.join("a")).unwrap();
+        fs::write(classes.join("a/Both.class"), b"dir").unwrap();
+        fs::write(classes.join("a/Dir.class"), b"dir").unwrap();
+        let jar = dir.join("lib.jar");
+        write_jar(&jar, &[("a/Both.class", b"jar"), ("a/Jar.class", b"jar")]);
+
+        // the first entry with the class wins, and entries which don't exist are skipped
+        let cp = class_path(&[dir.join("missing"), classes.clone(), jar.clone()]);
+        assert_eq!(cp.find_class("a/Both").unwrap().unwrap(), b"dir");
+        assert_eq!(cp.find_class("a/Dir").unwrap().unwrap(), b"dir");
+        assert_eq!(cp.find_class("a/Jar").unwrap().unwrap(), b"jar");
+        assert_eq!(cp.find("a/Jar.class").unwrap().unwrap(), b"jar");
+        assert!(cp.find_class("a/Missing").unwrap().is_none());
+        assert!(cp.find_class("Both").unwrap().is_none());
+
+        let cp = class_path(&[jar, classes, dir.join("missing.jar")]);
+        assert_eq!(cp.entries.len(), 2);
+        assert_eq!(cp.find_class("a/Both").unwrap().unwrap(), b"jar");
+        assert!(cp.find_class("a/Missing").unwrap().is_none());
+
+        assert!(ClassPath::default().find_class("a/Both").unwrap().is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn wildcard() {
+        let dir = temp_dir("wildcard");
+        let lib = dir.join("lib");
+        fs::create_dir_all(lib.join("classes.jar")).unwrap();
+        write_jar(&lib.join("b.jar"), &[("X.class", b"b"), ("B.class", b"b")]);
+        write_jar(&lib.join("a.JAR"), &[("X.class", b"a")]);
+        write_jar(&lib.join("c.zip"), &[("C.class", b"c")]);
+        fs::write(lib.join("notes.txt"), b"not a jar").unwrap();
+        fs::write(lib.join("classes.jar/D.class"), b"d").unwrap();
+
+        // every jar and zip in the directory, sorted by name, but not its subdirectories or
+        // other files
+        let cp = class_path(&[lib.join("*")]);
+        assert_eq!(cp.entries.len(), 3);
+        assert_eq!(cp.find_class("X").unwrap().unwrap(), b"a");
+        assert_eq!(cp.find_class("B").unwrap().unwrap(), b"b");
+        assert_eq!(cp.find_class("C").unwrap().unwrap(), b"c");
+        assert!(cp.find_class("D").unwrap().is_none());
+        assert!(cp.find("notes.txt").unwrap().is_none());
+
+        // a jar given explicitly as well isn't added twice
+        let cp = class_path(&[lib.join("b.jar"), lib.join("*")]);
+        assert_eq!(cp.entries.len(), 3);
+        assert_eq!(cp.find_class("X").unwrap().unwrap(), b"b");
+
+        assert_eq!(class_path(&[dir.join("missing/*")]).entries.len(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn manifest_class_path() {
+        let dir = temp_dir("manifest_class_path");
+        fs::create_dir_all(dir.join("lib/classes/c")).unwrap();
+        fs::write(dir.join("lib/classes/c/C.class"), b"c").unwrap();
+        // with a percent-encoded space, and carrying on over a continuation line
+        let manifest = b"Manifest-Version: 1.0\r\nMain-Class: app.Main\r\n\
+              Class-Path: lib/dep%20one.jar \r\n lib/classes/ missing.jar lib/*\r\n\r\n";
+        write_jar(
+            &dir.join("app.jar"),
+            &[(MANIFEST_NAME, manifest), ("app/Main.class", b"main")],
+        );
+        // which refers back to the first jar, which isn't added again
+        write_jar(
+            &dir.join("lib/dep one.jar"),
+            &[
+                (MANIFEST_NAME, b"Class-Path: ../app.jar\r\n"),
+                ("dep/Dep.class", b"dep"),
+            ],
+        );
+        // only found through `lib/*`, which doesn't mean anything in a manifest
+        write_jar(&dir.join("lib/other.jar"), &[("o/O.class", b"o")]);
+
+        let (cp, main_class) = ClassPath::for_jar(dir.join("app.jar")).unwrap();
+        assert_eq!(main_class, "app/Main");
+        assert_eq!(cp.entries.len(), 3);
+        assert_eq!(cp.find_class("app/Main").unwrap().unwrap(), b"main");
+        assert_eq!(cp.find_class("dep/Dep").unwrap().unwrap(), b"dep");
+        assert_eq!(cp.find_class("c/C").unwrap().unwrap(), b"c");
+        assert!(cp.find_class("o/O").unwrap().is_none());
+
+        // the dependencies are followed when the jar is on the class path too
+        let cp = class_path(&[dir.join("app.jar")]);
+        assert_eq!(cp.find_class("dep/Dep").unwrap().unwrap(), b"dep");
+
+        write_jar(&dir.join("no-main.jar"), &[("app/Main.class", b"main")]);
+        let e = ClassPath::for_jar(dir.join("no-main.jar")).unwrap_err();
+        assert!(e.to_string().starts_with("no main manifest attribute"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn multi_release() {
+        let dir = temp_dir("multi_release");
+        let newer = format!("META-INF/versions/{}/m/M.class", RELEASE + 1);
+        let entries: [(&str, &[u8]); 5] = [
+            ("m/M.class", b"base"),
+            ("META-INF/versions/9/m/M.class", b"9"),
+            ("META-INF/versions/11/m/M.class", b"11"),
+            (&newer, b"newer"),
+            ("META-INF/versions/9/m/Nine.class", b"9"),
+        ];
+        let manifest: (&str, &[u8]) = (MANIFEST_NAME, b"Multi-Release: true\r\n");
+        let jar = dir.join("mr.jar");
+        write_jar(&jar, &[&[manifest][..], &entries].concat());
+
+        // the newest version up to the one this JVM runs, falling back to older ones
+        let cp = class_path(&[jar]);
+        assert_eq!(cp.find_class("m/M").unwrap().unwrap(), b"11");
+        assert_eq!(cp.find_class("m/Nine").unwrap().unwrap(), b"9");
+        assert!(cp.find_class("m/Missing").unwrap().is_none());
+
+        // without the manifest attribute the versioned entries are ignored
+        let jar = dir.join("plain.jar");
+        write_jar(&jar, &entries);
+        let cp = class_path(&[jar]);
+        assert_eq!(cp.find_class("m/M").unwrap().unwrap(), b"base");
+        assert!(cp.find_class("m/Nine").unwrap().is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn urls() {
+        let path = |url| url_to_path(url).map(|path| path.to_str().unwrap().to_string());
+        assert_eq!(path("lib/a.jar").as_deref(), Some("lib/a.jar"));
+        assert_eq!(path("file:lib/a.jar").as_deref(), Some("lib/a.jar"));
+        assert_eq!(path("lib/a%20b.jar").as_deref(), Some("lib/a b.jar"));
+        assert_eq!(path("%e2%82%AC.jar").as_deref(), Some("\u{20ac}.jar"));
+        // percent signs which aren't followed by two hex digits are kept
+        assert_eq!(path("100%.jar").as_deref(), Some("100%.jar"));
+        assert_eq!(path("a%zz%2").as_deref(), Some("a%zz%2"));
+        // other schemes can't be loaded from, and paths must be UTF-8
+        assert_eq!(path("http://example.com/a.jar"), None);
+        assert_eq!(path("%ff.jar"), None);
+    }
+
+    #[test]
+    fn jdk_jmods() {
+        let Some(jmods) = java_home().map(|home| home.join("jmods")) else {
+            return;
+        };
+        if !jmods.is_dir() {
+            return;
+        }
+        let cp = ClassPath::parse_module_path(jmods.to_str().unwrap()).unwrap();
+        let bytes = cp.find_class("java/sql/Array").unwrap().unwrap();
+        let class = class_files::ClassFile::parse(&bytes).unwrap();
+        assert_eq!(class.this_class().unwrap(), "java/sql/Array");
+        assert!(cp.find_class("java/sql/Nope").unwrap().is_none());
+    }
+}
@@ -0,0 +1,638 @@
+//! A reader for jimage files, the container format of a JDK's `lib/modules`. Only the index is
+//! held in memory, resources are read from the file when they're looked up.
+//!
+//! [^see]: `jdk.internal.jimage.BasicImageReader` in the JDK sources
+
+use anyhow::{bail, ensure, Context};
+use std::{
+    fs,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    sync::Mutex,
+};
+
+use crate::zip::inflate;
+use class_files::bytes::ByteReader;
+
+const MAGIC: u32 = 0xcafedada;
+const MAJOR_VERSION: u32 = 1;
+const HEADER_LEN: usize = 7 * 4;
+
+const COMPRESSED_MAGIC: u32 = 0xcafefafa;
+const COMPRESSED_HEADER_LEN: usize = 4 + 8 + 8 + 4 + 4 + 1;
+const CONSTANT_UTF8: u8 = 1;
+
+/// Multiplier of the FNV-1a style hash used for the perfect hash table, and its initial seed
+const HASH_MULTIPLIER: u32 = 0x01000193;
+
+// the kinds of location attribute
+const ATTRIBUTE_END: u8 = 0;
+const ATTRIBUTE_MODULE: u8 = 1;
+const ATTRIBUTE_PARENT: u8 = 2;
+const ATTRIBUTE_BASE: u8 = 3;
+const ATTRIBUTE_EXTENSION: u8 = 4;
+const ATTRIBUTE_OFFSET: u8 = 5;
+const ATTRIBUTE_COMPRESSED: u8 = 6;
+const ATTRIBUTE_UNCOMPRESSED: u8 = 7;
+const ATTRIBUTE_COUNT: usize = 8;
+
+fn hash(name: &str, seed: u32) -> u32 {
+    name.bytes().fold(seed, |hash, byte| {
+        hash.wrapping_mul(HASH_MULTIPLIER) ^ u32::from(byte)
+    }) & 0x7fffffff
+}
+
+/// Where a resource is, as the values of its attributes indexed by kind
+#[derive(Debug, Clone, Copy)]
+struct Location([u64; ATTRIBUTE_COUNT]);
+
+#[derive(Debug)]
+pub(crate) struct JImage {
+    file: Mutex<fs::File>,
+    big_endian: bool,
+    /// Indexed by hash, negative values are the index in `offsets` directly and positive ones
+    /// are the seed to hash again with
+    redirect: Vec<i32>,
+    /// The offset of each location in `locations`
+    offsets: Vec<u32>,
+    locations: Vec<u8>,
+    strings: Vec<u8>,
+    /// Where the resources start in the file, which is right after the index
+    resources_start: u64,
+}
+
+impl JImage {
+    pub fn open<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::read_index(fs::File::open(&path)?)
+            .with_context(|| format!("reading jimage {}", path.as_ref().display()))
+    }
+
+    fn read_index(mut file: fs::File) -> anyhow::Result<Self> {
+        let mut header = [0; HEADER_LEN];
+        file.read_exact(&mut header)?;
+        let big_endian = match header[..4].try_into().unwrap() {
+            magic if u32::from_le_bytes(magic) == MAGIC => false,
+            magic if u32::from_be_bytes(magic) == MAGIC => true,
+            _ => bail!("Not a jimage file"),
+        };
+        let u32_at = |offset: usize| {
+            let bytes = header[offset..offset + 4].try_into().unwrap();
+            if big_endian {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            }
+        };
+
+        let version = u32_at(4);
+        ensure!(
+            version >> 16 == MAJOR_VERSION,
+            "Unsupported jimage version {}.{}",
+            version >> 16,
+            version & 0xffff
+        );
+        let table_len = u32_at(16) as usize;
+        let locations_len = u32_at(20) as usize;
+        let strings_len = u32_at(24) as usize;
+
+        let mut index = vec![0; table_len * 8 + locations_len + strings_len];
+        file.read_exact(&mut index)
+            .context("jimage index is truncated")?;
+        let strings = index.split_off(table_len * 8 + locations_len);
+        let locations = index.split_off(table_len * 8);
+        let words: Vec<u32> = index
+            .chunks_exact(4)
+            .map(|word| {
+                let word = word.try_into().unwrap();
+                if big_endian {
+                    u32::from_be_bytes(word)
+                } else {
+                    u32::from_le_bytes(word)
+                }
+            })
+            .collect();
+        let (redirect, offsets) = words.split_at(table_len);
+
+        Ok(Self {
+            file: Mutex::new(file),
+            big_endian,
+            redirect: redirect.iter().map(|r| *r as i32).collect(),
+            offsets: offsets.to_vec(),
+            locations,
+            strings,
+            resources_start: (HEADER_LEN + table_len * 8 + locations_len + strings_len) as u64,
+        })
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = bytes.try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    fn u64(&self, bytes: &[u8]) -> u64 {
+        let bytes = bytes.try_into().unwrap();
+        if self.big_endian {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        }
+    }
+
+    /// The nul-terminated modified UTF-8 string at `offset` in the strings table
+    fn string_bytes(&self, offset: u64) -> anyhow::Result<&[u8]> {
+        let bytes = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| self.strings.get(offset..))
+            .context("jimage string offset is out of bounds")?;
+        let len = bytes
+            .iter()
+            .position(|b| *b == 0)
+            .context("jimage string is not terminated")?;
+        Ok(&bytes[..len])
+    }
+
+    fn string(&self, offset: u64) -> anyhow::Result<&str> {
+        std::str::from_utf8(self.string_bytes(offset)?).context("jimage string is not valid UTF-8")
+    }
+
+    /// Decode the attributes of the location at `offset` in the locations table, each is a byte
+    /// with the kind in the top five bits and the length minus one in the bottom three, followed
+    /// by a big-endian value
+    fn location(&self, offset: u32) -> anyhow::Result<Location> {
+        let mut attributes = [0; ATTRIBUTE_COUNT];
+        let mut bytes = self
+            .locations
+            .get(offset as usize..)
+            .context("jimage location offset is out of bounds")?;
+        loop {
+            let (byte, rest) = bytes
+                .split_first()
+                .context("jimage location is truncated")?;
+            let kind = byte >> 3;
+            if kind == ATTRIBUTE_END {
+                return Ok(Location(attributes));
+            }
+            ensure!(
+                usize::from(kind) < ATTRIBUTE_COUNT,
+                "Invalid jimage location attribute {}",
+                kind
+            );
+            let len = usize::from(byte & 7) + 1;
+            let value = rest.get(..len).context("jimage location is truncated")?;
+            attributes[usize::from(kind)] = value
+                .iter()
+                .fold(0, |value, byte| value << 8 | u64::from(*byte));
+            bytes = &rest[len..];
+        }
+    }
+
+    /// The full name of a location, like `/java.base/java/lang/Object.class`
+    fn location_name(&self, location: &Location) -> anyhow::Result<String> {
+        let string = |kind: u8| self.string(location.0[usize::from(kind)]);
+        let (module, parent) = (string(ATTRIBUTE_MODULE)?, string(ATTRIBUTE_PARENT)?);
+        let (base, extension) = (string(ATTRIBUTE_BASE)?, string(ATTRIBUTE_EXTENSION)?);
+        let mut name = String::new();
+        if !module.is_empty() {
+            name.push_str(&format!("/{}/", module));
+        }
+        if !parent.is_empty() {
+            name.push_str(&format!("{}/", parent));
+        }
+        name.push_str(base);
+        if !extension.is_empty() {
+            name.push_str(&format!(".{}", extension));
+        }
+        Ok(name)
+    }
+
+    /// Find the location of `name` using the perfect hash table
+    fn find_location(&self, name: &str) -> anyhow::Result<Option<Location>> {
+        let len = self.redirect.len() as u32;
+        if len == 0 {
+            return Ok(None);
+        }
+        let index = match self.redirect[(hash(name, HASH_MULTIPLIER) % len) as usize] {
+            0 => return Ok(None),
+            seed @ 1.. => hash(name, seed as u32) % len,
+            index => (-index - 1) as u32,
+        };
+        let offset = *self
+            .offsets
+            .get(index as usize)
+            .context("jimage redirect is out of bounds")?;
+        // names which aren't in the image still hash to some location
+        let location = self.location(offset)?;
+        Ok((self.location_name(&location)? == name).then_some(location))
+    }
+
+    /// The contents of the resource with the full name `name`, `None` if there isn't one
+    pub fn read(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(location) = self.find_location(name)? else {
+            return Ok(None);
+        };
+        self.read_location(&location)
+            .with_context(|| format!("reading {} from jimage", name))
+            .map(Some)
+    }
+
+    fn read_location(&self, location: &Location) -> anyhow::Result<Vec<u8>> {
+        let offset = location.0[usize::from(ATTRIBUTE_OFFSET)];
+        let compressed = location.0[usize::from(ATTRIBUTE_COMPRESSED)];
+        let uncompressed = location.0[usize::from(ATTRIBUTE_UNCOMPRESSED)];
+        let len = if compressed != 0 {
+            compressed
+        } else {
+            uncompressed
+        };
+
+        let mut bytes = vec![0; usize::try_from(len)?];
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(self.resources_start + offset))?;
+            file.read_exact(&mut bytes)
+                .context("jimage resource is truncated")?;
+        }
+        if compressed != 0 {
+            bytes = self.decompress(bytes)?;
+        }
+        ensure!(
+            bytes.len() as u64 == uncompressed,
+            "Expected {} bytes, found {}",
+            uncompressed,
+            bytes.len()
+        );
+        Ok(bytes)
+    }
+
+    /// Undo each compression applied by the `jlink --compress` plugins, which each add a header
+    fn decompress(&self, mut bytes: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        while bytes.len() >= COMPRESSED_HEADER_LEN && self.u32(&bytes[..4]) == COMPRESSED_MAGIC {
+            let compressed_len = usize::try_from(self.u64(&bytes[4..12]))?;
+            let uncompressed_len = usize::try_from(self.u64(&bytes[12..20]))?;
+            let decompressor = self.string(self.u32(&bytes[20..24]).into())?;
+            let content = bytes
+                .get(COMPRESSED_HEADER_LEN..COMPRESSED_HEADER_LEN + compressed_len)
+                .context("Compressed jimage resource is truncated")?;
+            bytes = match decompressor {
+                "zip" => inflate_zlib(content, uncompressed_len)?,
+                "compact-cp" => self.unshare_strings(content)?,
+                name => bail!("Unsupported jimage decompressor '{}'", name),
+            };
+        }
+        Ok(bytes)
+    }
+
+    /// Undo the `compact-cp` compression of a class file, which moves Utf8 constants into the
+    /// image's strings table and splits descriptors into their package and class names
+    ///
+    /// [^see]: `jdk.internal.jimage.decompressor.StringSharingDecompressor`
+    fn unshare_strings(&self, content: &[u8]) -> anyhow::Result<Vec<u8>> {
+        /// A reference to a string in the strings table
+        const EXTERNALIZED_STRING: u8 = 23;
+        /// A descriptor whose class names are replaced by references to their package and name
+        const EXTERNALIZED_STRING_DESCRIPTOR: u8 = 25;
+
+        let mut r = ByteReader::new(content);
+        let mut out = Vec::with_capacity(content.len() * 2);
+        // magic, minor and major version
+        out.extend_from_slice(r.read_bytes(8)?);
+        let count = r.read_u16()?;
+        out.extend_from_slice(&count.to_be_bytes());
+
+        let write_utf8 = |out: &mut Vec<u8>, bytes: &[u8]| -> anyhow::Result<()> {
+            out.push(CONSTANT_UTF8);
+            out.extend_from_slice(&u16::try_from(bytes.len())?.to_be_bytes());
+            out.extend_from_slice(bytes);
+            Ok(())
+        };
+        let mut i = 1;
+        while i < count {
+            let tag = r.read_u8()?;
+            match tag {
+                EXTERNALIZED_STRING => {
+                    let string = self.string_bytes(read_compressed_int(&mut r)?.into())?;
+                    write_utf8(&mut out, string)?;
+                }
+                EXTERNALIZED_STRING_DESCRIPTOR => {
+                    let descriptor = self.string_bytes(read_compressed_int(&mut r)?.into())?;
+                    let len = read_compressed_int(&mut r)?;
+                    let mut indices = ByteReader::new(r.read_bytes(len as usize)?);
+                    // each `L` is followed by the package and then the simple name of the class
+                    let mut string = Vec::with_capacity(descriptor.len() * 2);
+                    for byte in descriptor {
+                        string.push(*byte);
+                        if *byte == b'L' {
+                            let package =
+                                self.string_bytes(read_compressed_int(&mut indices)?.into())?;
+                            if !package.is_empty() {
+                                string.extend_from_slice(package);
+                                string.push(b'/');
+                            }
+                            let class =
+                                self.string_bytes(read_compressed_int(&mut indices)?.into())?;
+                            string.extend_from_slice(class);
+                        }
+                    }
+                    write_utf8(&mut out, &string)?;
+                }
+                CONSTANT_UTF8 => {
+                    let len = r.read_u16()?;
+                    write_utf8(&mut out, r.read_bytes(len.into())?)?;
+                }
+                tag => {
+                    let len = match tag {
+                        // long and double take up two entries
+                        5 | 6 => {
+                            i += 1;
+                            8
+                        }
+                        3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => 4,
+                        15 => 3,
+                        7 | 8 | 16 | 19 | 20 => 2,
+                        tag => bail!("Invalid constant pool tag {} in compact-cp resource", tag),
+                    };
+                    out.push(tag);
+                    out.extend_from_slice(r.read_bytes(len)?);
+                }
+            }
+            i += 1;
+        }
+
+        // everything after the constant pool is unchanged
+        out.extend_from_slice(r.read_bytes(r.remaining())?);
+        Ok(out)
+    }
+
+    /// The module which contains the package `package`, given with `/` like `java/lang`
+    pub fn module_of(&self, package: &str) -> anyhow::Result<Option<String>> {
+        // `/packages/java.lang` is a list of (is empty, module name) for the modules with it
+        let name = format!("/packages/{}", package.replace('/', "."));
+        let Some(location) = self.find_location(&name)? else {
+            return Ok(None);
+        };
+        let modules = self.read_location(&location)?;
+        for pair in modules.chunks_exact(8) {
+            if self.u32(&pair[..4]) == 0 {
+                return Ok(Some(self.string(self.u32(&pair[4..]).into())?.to_string()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Read an integer as written by `jdk.internal.jimage.decompressor.CompressIndexes`. If the top bit
+/// of the first byte is set, the next two bits are the length in bytes and the bottom five are the
+/// top of the value, otherwise it is four bytes long.
+fn read_compressed_int(r: &mut ByteReader) -> anyhow::Result<u32> {
+    let header = r.read_u8()?;
+    let (len, value) = if header & 0x80 != 0 {
+        ((header >> 5) & 3, u32::from(header & 0x1f))
+    } else {
+        (4, u32::from(header))
+    };
+    let mut value = value;
+    for _ in 1..len {
+        value = value << 8 | u32::from(r.read_u8()?);
+    }
+    Ok(value)
+}
+
+/// Decompress zlib data, which is deflate data with a two byte header and a checksum after it
+fn inflate_zlib(data: &[u8], size: usize) -> anyhow::Result<Vec<u8>> {
+    ensure!(
+        data.len() >= 2 && data[0] & 0x0f == 8 && data[1] & 0x20 == 0,
+        "Invalid zlib header"
+    );
+    inflate(&data[2..], size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::class_path::java_home;
+
+    #[test]
+    fn jdk_modules() {
+        let Some(path) = java_home().map(|home| home.join("lib/modules")) else {
+            return;
+        };
+        if !path.exists() {
+            return;
+        }
+        let image = JImage::open(path).unwrap();
+        assert_eq!(
+            image.module_of("java/lang").unwrap().as_deref(),
+            Some("java.base")
+        );
+        assert_eq!(image.module_of("not/a/package").unwrap(), None);
+
+        let object = image.read("/java.base/java/lang/Object.class").unwrap();
+        let object = class_files::ClassFile::parse(object.as_deref().unwrap()).unwrap();
+        assert_eq!(object.this_class().unwrap(), "java/lang/Object");
+        assert!(image
+            .read("/java.base/java/lang/Nope.class")
+            .unwrap()
+            .is_none());
+    }
+
+    /// The inverse of [`read_compressed_int`]
+    fn write_compressed_int(out: &mut Vec<u8>, value: u32) {
+        match value {
+            0..=0x1f => out.push(0xa0 | value as u8),
+            0x20..=0x1fff => out.extend_from_slice(&(0xc000 | value as u16).to_be_bytes()),
+            0x2000..=0x1f_ffff => out.extend_from_slice(&(0xe0_0000 | value).to_be_bytes()[1..]),
+            _ => out.extend_from_slice(&value.to_be_bytes()),
+        }
+    }
+
+    #[test]
+    fn compressed_ints() {
+        for value in [
+            0,
+            0x1f,
+            0x20,
+            0x1fff,
+            0x2000,
+            0x1f_ffff,
+            0x20_0000,
+            0x7fff_ffff,
+        ] {
+            let mut bytes = Vec::new();
+            write_compressed_int(&mut bytes, value);
+            let mut r = ByteReader::new(&bytes);
+            assert_eq!(read_compressed_int(&mut r).unwrap(), value);
+            assert_eq!(r.remaining(), 0);
+        }
+        assert_eq!(
+            read_compressed_int(&mut ByteReader::new(&[0xc1, 0x02])).unwrap(),
+            0x102
+        );
+    }
+
+    /// Move the Utf8 constants of `class` into `strings` the way the `compact-cp` plugin of
+    /// `jlink` does, splitting the class names out of descriptors
+    fn share_strings(class: &[u8], strings: &mut Vec<u8>) -> Vec<u8> {
+        let mut add_string = |string: &[u8]| {
+            let offset = strings.len() as u32;
+            strings.extend_from_slice(string);
+            strings.push(0);
+            offset
+        };
+
+        let mut r = ByteReader::new(class);
+        let mut out = r.read_bytes(8).unwrap().to_vec();
+        let count = r.read_u16().unwrap();
+        out.extend_from_slice(&count.to_be_bytes());
+        let mut i = 1;
+        while i < count {
+            let tag = r.read_u8().unwrap();
+            let len = match tag {
+                CONSTANT_UTF8 => {
+                    let len = r.read_u16().unwrap();
+                    let string = r.read_bytes(len.into()).unwrap();
+                    if string.starts_with(b"(") && string.contains(&b';') {
+                        // `(Ljava/lang/String;I)V` becomes `(L;I)V` with the package and name of
+                        // each class
+                        let mut descriptor = Vec::new();
+                        let mut indices = Vec::new();
+                        let mut rest = string;
+                        while let Some(start) = rest.iter().position(|b| *b == b'L') {
+                            descriptor.extend_from_slice(&rest[..=start]);
+                            let end =
+                                start + rest[start..].iter().position(|b| *b == b';').unwrap();
+                            let name = &rest[start + 1..end];
+                            let slash = name.iter().rposition(|b| *b == b'/');
+                            let (package, class) = match slash {
+                                Some(slash) => (&name[..slash], &name[slash + 1..]),
+                                None => (&name[..0], name),
+                            };
+                            write_compressed_int(&mut indices, add_string(package));
+                            write_compressed_int(&mut indices, add_string(class));
+                            rest = &rest[end..];
+                        }
+                        descriptor.extend_from_slice(rest);
+                        out.push(25);
+                        write_compressed_int(&mut out, add_string(&descriptor));
+                        write_compressed_int(&mut out, indices.len() as u32);
+                        out.extend_from_slice(&indices);
+                    } else {
+                        out.push(23);
+                        write_compressed_int(&mut out, add_string(string));
+                    }
+                    i += 1;
+                    continue;
+                }
+                5 | 6 => {
+                    i += 1;
+                    8
+                }
+                3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => 4,
+                15 => 3,
+                _ => 2,
+            };
+            out.push(tag);
+            out.extend_from_slice(r.read_bytes(len).unwrap());
+            i += 1;
+        }
+        out.extend_from_slice(r.read_bytes(r.remaining()).unwrap());
+        out
+    }
+
+    /// Add the header that each `jlink --compress` plugin puts before the resource it compressed
+    fn compressed_header(
+        strings: &mut Vec<u8>,
+        decompressor: &str,
+        content: &[u8],
+        uncompressed_len: usize,
+    ) -> Vec<u8> {
+        let name = strings.len() as u32;
+        strings.extend_from_slice(decompressor.as_bytes());
+        strings.push(0);
+
+        let mut out = COMPRESSED_MAGIC.to_le_bytes().to_vec();
+        out.extend_from_slice(&(content.len() as u64).to_le_bytes());
+        out.extend_from_slice(&(uncompressed_len as u64).to_le_bytes());
+        out.extend_from_slice(&name.to_le_bytes());
+        // the name of the resource, which isn't used
+        out.extend_from_slice(&0u32.to_le_bytes());
+        // whether this is the last header
+        out.push(1);
+        out.extend_from_slice(content);
+        out
+    }
+
+    #[test]
+    fn compressed_resources() {
+        use class_files::{
+            builder::ClassFileBuilder,
+            constant_pool::Loadable,
+            types::{FieldAccessFlags, MethodAccessFlags},
+        };
+
+        let mut builder = ClassFileBuilder::new("p/Demo", Some("java/lang/Object")).unwrap();
+        builder
+            .add_field(
+                FieldAccessFlags::STATIC,
+                "big",
+                "J",
+                Some(Loadable::Long(1 << 40)),
+            )
+            .unwrap()
+            .add_field(
+                FieldAccessFlags::empty(),
+                "name",
+                "Ljava/lang/String;",
+                None,
+            )
+            .unwrap()
+            .add_method(
+                MethodAccessFlags::ABSTRACT,
+                "run",
+                "(Ljava/util/List;ILDemo;[Ljava/lang/Object;)V",
+                None,
+            )
+            .unwrap();
+        let class = builder.build().to_bytes().unwrap();
+
+        let mut strings = vec![0];
+        let shared = share_strings(&class, &mut strings);
+        let shared = compressed_header(&mut strings, "compact-cp", &shared, class.len());
+        // zlib holding a single stored deflate block, then the checksum which isn't checked
+        let mut zlib = vec![0x78, 0x01, 0x01];
+        zlib.extend_from_slice(&(shared.len() as u16).to_le_bytes());
+        zlib.extend_from_slice(&(!(shared.len() as u16)).to_le_bytes());
+        zlib.extend_from_slice(&shared);
+        zlib.extend_from_slice(&[0; 4]);
+        let zipped = compressed_header(&mut strings, "zip", &zlib, shared.len());
+
+        let image = JImage {
+            // the resource is decompressed from memory, so any file will do
+            file: Mutex::new(
+                fs::File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml")).unwrap(),
+            ),
+            big_endian: false,
+            redirect: Vec::new(),
+            offsets: Vec::new(),
+            locations: Vec::new(),
+            strings,
+            resources_start: 0,
+        };
+        let bytes = image.decompress(zipped).unwrap();
+        assert_eq!(bytes, class);
+
+        let class = class_files::ClassFile::parse(&bytes).unwrap();
+        assert_eq!(class.this_class().unwrap(), "p/Demo");
+        let run = class.methods().next().unwrap().unwrap();
+        assert_eq!(
+            run.descriptor,
+            "(Ljava/util/List;ILDemo;[Ljava/lang/Object;)V"
+        );
+    }
+}
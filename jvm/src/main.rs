@@ -1,22 +1,39 @@
 use anyhow::{bail, Context};
 use class_files::{
     bytes::ReadNum,
+    constant_pool::ConstantPool,
     descriptors::MethodDescriptor,
-    types::resolved::{Attribute, Method},
+    format::FormatErrorKind,
+    types::{
+        raw::RawAttribute,
+        resolved::{Attribute, Method},
+        ClassAccessFlags, MethodAccessFlags,
+    },
+    verifier::{self, ClassHierarchy, ClassInfo},
     ClassFile,
 };
+use class_loader::{ClassKey, ClassLoader, ClassPaths};
+use class_path::ClassPath;
 use op_code::handle_op_code;
 use std::{
+    cell::RefCell,
     collections::HashMap,
     fs,
     io::{BufReader, Cursor, Seek},
     ops::{Deref, Index, IndexMut},
-    path::{Path, PathBuf},
+    path::Path,
 };
+use throwable::Throwable;
 use types::{java, DataType, StackFrame};
 
+pub mod class_loader;
+pub mod class_path;
+pub mod jimage;
+pub mod manifest;
 pub mod op_code;
+pub mod throwable;
 pub mod types;
+pub mod zip;
 
 #[derive(Debug, Clone)]
 pub(crate) enum Array {
@@ -51,6 +68,19 @@ impl Array {
         })
     }
 
+    fn len(&self) -> usize {
+        match self {
+            Array::Boolean(a) => a.len(),
+            Array::Char(a) => a.len(),
+            Array::Float(a) => a.len(),
+            Array::Double(a) => a.len(),
+            Array::Byte(a) => a.len(),
+            Array::Short(a) => a.len(),
+            Array::Int(a) => a.len(),
+            Array::Long(a) => a.len(),
+        }
+    }
+
     fn get(&self, index: usize) -> DataType {
         match self {
             Array::Boolean(a) => a[index].into(),
@@ -105,21 +135,18 @@ impl Array {
 
 #[derive(Debug, Clone)]
 pub(crate) enum HeapItem {
+    /// An object of the class, with the fields that have been set by name. The rest still have
+    /// their initial value.
     Object {
-        // TODO
+        class: ClassKey,
+        fields: HashMap<String, DataType>,
     },
-    Primitive(
-        // TODO
-    ),
     Array(Array),
-    Null,
-    Empty,
-}
-
-impl HeapItem {
-    pub fn is_empty(&self) -> bool {
-        matches!(self, HeapItem::Empty)
-    }
+    /// A `java.lang.String`, which is kept as a Rust string until the JDK's `String` class can be
+    /// initialised
+    String(String),
+    /// The `java.lang.Class` object of a loaded class
+    Class(ClassKey),
 }
 
 #[derive(Debug, Clone)]
@@ -137,46 +164,15 @@ impl Default for Heap {
     }
 }
 
+// TODO: collect garbage by looking at all stack frames for references into the heap (op stack &
+//       variables), reusing the slots of items which aren't referenced
 impl Heap {
-    pub fn collect_garbage(&mut self) -> anyhow::Result<()> {
-        // TODO: May require JVM to be passed
-        //       Look at all stack frames for references into here (op stack & variables)
-        //       Referenes are indexes to a non-empty value within bounds
-        todo!()
-    }
-
-    pub fn is_valid_reference(&self, index: usize) -> bool {
-        index < self.inner.len() && !matches!(self.inner.get(index).unwrap(), HeapItem::Empty)
-    }
-
-    pub fn remove(&mut self, index: usize) -> anyhow::Result<()> {
-        let len = self.inner.len();
-        if !self.is_valid_reference(index) {
-            bail!("Tried to remove item at invalid index: {}", index);
-        }
-
-        let item = self.inner.get_mut(index).unwrap();
-        if index == len - 1 {
-            self.inner.pop();
-        } else {
-            _ = std::mem::replace(item, HeapItem::Empty);
-        }
-
-        Ok(())
-    }
-
     pub fn create_array(&mut self, atype: u8, size: usize) -> anyhow::Result<usize> {
         let array = HeapItem::Array(Array::create(atype, size)?);
         self.try_append(array)
     }
 
     fn try_append(&mut self, item: HeapItem) -> anyhow::Result<usize> {
-        for (i, it) in self.inner.iter_mut().enumerate() {
-            if it.is_empty() {
-                _ = std::mem::replace(it, item);
-                return Ok(i);
-            }
-        }
         if self.inner.len() < self.max_size {
             self.inner.push(item);
             Ok(self.inner.len() - 1)
@@ -185,6 +181,71 @@ impl Heap {
         }
     }
 
+    /// Allocate an object of the class with all of its fields at their initial value
+    pub fn create_object(&mut self, class: ClassKey) -> anyhow::Result<usize> {
+        self.try_append(HeapItem::Object {
+            class,
+            fields: HashMap::new(),
+        })
+    }
+
+    pub fn create_string(&mut self, string: &str) -> anyhow::Result<usize> {
+        self.try_append(HeapItem::String(string.to_string()))
+    }
+
+    fn get(&self, index: usize) -> anyhow::Result<&HeapItem> {
+        self.inner.get(index).with_context(|| {
+            format!(
+                "Index {} out of bounds for length {}",
+                index,
+                self.inner.len()
+            )
+        })
+    }
+
+    pub fn get_string(&self, index: usize) -> anyhow::Result<&str> {
+        match self.get(index)? {
+            HeapItem::String(string) => Ok(string),
+            item => bail!("Heap item is not a string: {:?}", item),
+        }
+    }
+
+    /// The class of the object at `index`, which is where its methods are looked up
+    pub fn class_of(&self, index: usize) -> anyhow::Result<ClassKey> {
+        Ok(match self.get(index)? {
+            HeapItem::Object { class, .. } => class.clone(),
+            HeapItem::String(_) => (ClassLoader::Bootstrap, "java/lang/String".to_string()),
+            HeapItem::Class(_) => (ClassLoader::Bootstrap, "java/lang/Class".to_string()),
+            item => bail!("Heap item is not an object: {:?}", item),
+        })
+    }
+
+    /// The value of the field `name` of the object at `index`, with the type `descriptor`. Fields
+    /// are told apart only by name, so one which hides a field of a super class replaces it.
+    pub fn get_field(
+        &self,
+        index: usize,
+        name: &str,
+        descriptor: &str,
+    ) -> anyhow::Result<DataType> {
+        let HeapItem::Object { fields, .. } = self.get(index)? else {
+            bail!("Heap item is not an object: {:?}", self.inner[index]);
+        };
+        Ok(fields
+            .get(name)
+            .copied()
+            .unwrap_or_else(|| DataType::default_for(descriptor)))
+    }
+
+    pub fn set_field(&mut self, index: usize, name: &str, value: DataType) -> anyhow::Result<()> {
+        self.get(index)?;
+        let HeapItem::Object { fields, .. } = &mut self.inner[index] else {
+            bail!("Heap item is not an object: {:?}", self.inner[index]);
+        };
+        fields.insert(name.to_string(), value);
+        Ok(())
+    }
+
     fn get_array(&self, index: usize) -> anyhow::Result<&Array> {
         let Some(item) = self.inner.get(index) else {
             bail!(
@@ -231,21 +292,55 @@ impl IndexMut<usize> for Heap {
 
 #[derive(Debug, Clone)]
 pub(crate) struct Class {
-    pub(crate) file: ClassFile,
+    pub(crate) file: ClassFile<'static>,
+    /// The loader that defined this class
+    pub(crate) loader: ClassLoader,
+    /// The reference to its `java.lang.Class` object, once there is one
+    pub(crate) object: Option<usize>,
+    pub(crate) linked: bool,
     pub(crate) initialised: bool,
 }
 
 impl Class {
-    pub fn new(file: ClassFile) -> Self {
+    pub fn new(loader: ClassLoader, file: ClassFile<'static>) -> Self {
         Class {
             file,
+            loader,
+            object: None,
+            linked: false,
             initialised: false,
         }
     }
+
+    pub fn key(&self) -> anyhow::Result<ClassKey> {
+        Ok((self.loader, self.this_class()?.to_string()))
+    }
+}
+
+/// Check the format of a class as it is loaded, throwing a `ClassFormatError` or an
+/// `UnsupportedClassVersionError` listing everything that is wrong with it, see
+/// <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-5.html#jvms-5.3.5>
+fn check_class_format(class: &ClassFile) -> anyhow::Result<()> {
+    let Err(errors) = class.check_format() else {
+        return Ok(());
+    };
+    let error = if errors
+        .iter()
+        .any(|e| matches!(e.kind, FormatErrorKind::UnsupportedVersion { .. }))
+    {
+        throwable::UNSUPPORTED_CLASS_VERSION_ERROR
+    } else {
+        throwable::CLASS_FORMAT_ERROR
+    };
+    let mut message = class.this_class().unwrap_or("<unknown class>").to_string();
+    for e in errors {
+        message.push_str(&format!("\n    {}", e));
+    }
+    Err(Throwable::new(error, message).into())
 }
 
 impl Deref for Class {
-    type Target = ClassFile;
+    type Target = ClassFile<'static>;
 
     fn deref(&self) -> &Self::Target {
         &self.file
@@ -262,7 +357,18 @@ pub(crate) struct Jvm<'a> {
     pub(crate) stack: Vec<StackFrame>,
     /// [^see]: <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-2.html#jvms-2.5.3>
     pub(crate) heap: Heap,
-    pub(crate) classes: HashMap<String, Class>,
+    /// By defining loader and name
+    pub(crate) classes: HashMap<ClassKey, Class>,
+    /// The defining loader of each class by the loaders that have loaded it, which includes the
+    /// loaders that delegated to the defining loader
+    pub(crate) loaded: HashMap<ClassKey, ClassLoader>,
+    pub(crate) class_paths: ClassPaths,
+    /// What the verifier has looked up about classes that aren't loaded
+    class_infos: RefCell<HashMap<ClassKey, Option<ClassInfo>>>,
+    /// The classes whose super classes are being loaded, to catch a class which is its own super
+    /// class. This is kept across calls to the `loadClass` of user-defined loaders, which define
+    /// the classes they load with `defineClass1`.
+    loading: Vec<ClassKey>,
     pub(crate) entry_class: Option<&'a str>,
 }
 
@@ -272,10 +378,15 @@ impl<'a> Jvm<'a> {
             stack: Default::default(),
             heap: Default::default(),
             classes: Default::default(),
+            loaded: Default::default(),
+            class_paths: Default::default(),
+            class_infos: Default::default(),
+            loading: Default::default(),
             entry_class: None,
         }
     }
 
+    /// Define the class in the file at `path` with the application loader
     pub fn load_class_from_file<P>(&mut self, path: P) -> anyhow::Result<String>
     where
         P: AsRef<Path>,
@@ -283,8 +394,7 @@ impl<'a> Jvm<'a> {
         let file = fs::File::open(path)?;
         let mut file = BufReader::new(file);
         let class = ClassFile::read_from(&mut file)?;
-        let name = class.this_class()?.to_string();
-        self.classes.insert(name.clone(), Class::new(class));
+        let (_, name) = self.define_class(ClassLoader::Application, class)?;
 
         Ok(name)
     }
@@ -294,56 +404,187 @@ impl<'a> Jvm<'a> {
         P: AsRef<Path>,
     {
         for path in paths {
-            let file = fs::File::open(&path)
+            let file = fs::File::open(path)
                 .with_context(|| format!("opening {}", path.as_ref().display()))?;
             let mut file = BufReader::new(file);
             let class = ClassFile::read_from(&mut file)
                 .with_context(|| format!("parsing {}", path.as_ref().display()))?;
-            self.classes
-                .insert(class.this_class()?.to_string(), Class::new(class));
+            // module descriptors (`module-info.class`) can't be loaded as classes
+            if class.access_flags.contains(ClassAccessFlags::MODULE) {
+                continue;
+            }
+            self.define_class(ClassLoader::Application, class)
+                .with_context(|| format!("loading {}", path.as_ref().display()))?;
         }
         Ok(())
     }
 
-    fn read_dir_recursive<P>(path: P) -> Vec<PathBuf>
-    where
-        P: AsRef<Path>,
-    {
-        let Ok(entries) = fs::read_dir(path) else {
-            return vec![];
+    /// Load the class with the binary name `name` using `loader`, unless it has loaded it already,
+    /// returning the loader that defined it
+    ///
+    /// [^see]: <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-5.html#jvms-5.3>
+    pub fn load_class(&mut self, loader: ClassLoader, name: &str) -> anyhow::Result<ClassLoader> {
+        self.load_class_or_none(loader, name)?
+            .ok_or_else(|| Throwable::new(throwable::NO_CLASS_DEF_FOUND_ERROR, name).into())
+    }
+
+    /// A built-in loader asks its parent for the class first, and defines it from its own class
+    /// path if the parent can't find it
+    fn load_class_or_none(
+        &mut self,
+        loader: ClassLoader,
+        name: &str,
+    ) -> anyhow::Result<Option<ClassLoader>> {
+        let key = (loader, name.to_string());
+        if let Some(&defining) = self.loaded.get(&key) {
+            return Ok(Some(defining));
+        }
+        if self.loading.contains(&key) {
+            bail!(Throwable::new(throwable::CLASS_CIRCULARITY_ERROR, name));
+        }
+
+        let defining = if let ClassLoader::User(reference) = loader {
+            self.call_load_class(reference, name)?
+        } else if let Some(defining) = match loader.parent() {
+            Some(parent) => self.load_class_or_none(parent, name)?,
+            None => None,
+        } {
+            Some(defining)
+        } else if let Some(bytes) = self.class_paths.get(loader).unwrap().find_class(name)? {
+            let class = ClassFile::parse(&bytes)
+                .with_context(|| format!("parsing {}", name))?
+                .into_owned();
+            if class.this_class()? != name {
+                bail!(Throwable::new(
+                    throwable::NO_CLASS_DEF_FOUND_ERROR,
+                    format!("{} (wrong name: {})", name, class.this_class()?),
+                ));
+            }
+            self.define_class(loader, class)?;
+            Some(loader)
+        } else {
+            None
         };
-        entries
-            .flatten()
-            .flat_map(|entry| {
-                if let Ok(ft) = entry.file_type() {
-                    if ft.is_dir() {
-                        return Self::read_dir_recursive(&entry.path());
-                    } else if ft.is_file() {
-                        return vec![entry.path()];
-                    }
-                }
-                vec![]
-            })
-            .collect()
+
+        if let Some(defining) = defining {
+            self.loaded.insert(key, defining);
+        }
+        Ok(defining)
     }
 
-    pub fn load_classes_from_dir<P>(&mut self, path: P) -> anyhow::Result<()>
-    where
-        P: AsRef<Path>,
-    {
-        let files = Self::read_dir_recursive(path);
-        let files: Vec<_> = files
-            .iter()
-            .filter(|f| {
-                if let Some(file_name) = f.file_name() {
-                    let file_name = file_name.to_str().unwrap();
-                    file_name != "module-info.class" && file_name.ends_with(".class")
-                } else {
-                    false
-                }
-            })
-            .collect();
-        self.load_classes_from_files(&files)
+    /// Ask a user-defined loader for a class by calling its `loadClass(String)`, which delegates to
+    /// its parent however it likes and calls `defineClass1` for the classes that it defines itself
+    fn call_load_class(
+        &mut self,
+        reference: usize,
+        name: &str,
+    ) -> anyhow::Result<Option<ClassLoader>> {
+        const DESCRIPTOR: &str = "(Ljava/lang/String;)Ljava/lang/Class;";
+        let class = self.heap.class_of(reference)?;
+        let class = self.select_method(&class, "loadClass", DESCRIPTOR)?;
+        // Java code sees binary names with `.` between the packages
+        let string = self.heap.create_string(&name.replace('/', "."))?;
+
+        // a frame to hold the arguments and what's returned
+        self.stack.push(StackFrame::new(2, 0));
+        self.stack.last_mut().unwrap().op_stack.extend([
+            DataType::ClassReference(reference),
+            DataType::ClassReference(string),
+        ]);
+        let result = self.invoke_method(&class, "loadClass", DESCRIPTOR);
+        let frame = self.stack.pop().context("No stack frame")?;
+        result?;
+
+        let key = match frame.op_stack.last() {
+            Some(DataType::Null) => return Ok(None),
+            Some(DataType::ClassReference(object)) => match &self.heap[*object] {
+                HeapItem::Class(key) => key.clone(),
+                item => bail!("loadClass returned {:?} instead of a Class", item),
+            },
+            value => bail!("loadClass returned {:?} instead of a Class", value),
+        };
+        if key.1 != name {
+            bail!(Throwable::new(
+                throwable::NO_CLASS_DEF_FOUND_ERROR,
+                format!("{} (wrong name: {})", name, key.1),
+            ));
+        }
+        Ok(Some(key.0))
+    }
+
+    /// Define `class` with `loader` as its defining loader, after loading its super class and
+    /// interfaces with the same loader
+    ///
+    /// [^see]: <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-5.html#jvms-5.3.5>
+    pub fn define_class(
+        &mut self,
+        loader: ClassLoader,
+        class: ClassFile<'static>,
+    ) -> anyhow::Result<ClassKey> {
+        check_class_format(&class)?;
+        let key = (loader, class.this_class()?.to_string());
+        if self.classes.contains_key(&key) {
+            bail!(Throwable::new(
+                throwable::LINKAGE_ERROR,
+                format!(
+                    "{} attempted duplicate class definition for {}",
+                    loader, key.1
+                ),
+            ));
+        }
+
+        let supers = class
+            .super_class()?
+            .into_iter()
+            .map(Ok)
+            .chain(class.interfaces())
+            .map(|name| name.map(String::from))
+            .collect::<Result<Vec<_>, _>>()?;
+        // the class comes off `loading` even when one of its supers fails to load
+        let depth = self.loading.len();
+        self.loading.push(key.clone());
+        let result = supers.iter().try_for_each(|super_class| {
+            self.load_class(loader, super_class)
+                .map(drop)
+                .with_context(|| format!("loading {}", key.1))
+        });
+        self.loading.truncate(depth);
+        result?;
+
+        self.loaded.insert(key.clone(), loader);
+        self.classes.insert(key.clone(), Class::new(loader, class));
+        Ok(key)
+    }
+
+    /// Load and link the class `name` using `loader` unless that has been done already, which
+    /// happens the first time that it is referenced
+    ///
+    /// A class that can't be found or defined fails with a [`Throwable`], such as a
+    /// `NoClassDefFoundError`, which is what the code referring to it would see thrown. Anything
+    /// else is the JVM failing, e.g. a class path that can't be read.
+    pub fn resolve_class(&mut self, loader: ClassLoader, name: &str) -> anyhow::Result<&Class> {
+        let key = (self.load_class(loader, name)?, name.to_string());
+        self.link_class(&key)?;
+        Ok(&self.classes[&key])
+    }
+
+    /// The `java.lang.Class` object for a loaded class, which is created the first time it's
+    /// asked for
+    pub fn class_object(&mut self, key: &ClassKey) -> anyhow::Result<usize> {
+        let class = self
+            .classes
+            .get(key)
+            .with_context(|| format!("Class '{}' not found", key.1))?;
+        if let Some(object) = class.object {
+            return Ok(object);
+        }
+        let object = self.heap.try_append(HeapItem::Class(key.clone()))?;
+        self.classes.get_mut(key).unwrap().object = Some(object);
+        Ok(object)
+    }
+
+    pub fn set_class_paths(&mut self, class_paths: ClassPaths) {
+        self.class_paths = class_paths;
     }
 
     pub fn set_entry_class(&mut self, class: &'a str) {
@@ -355,14 +596,12 @@ impl<'a> Jvm<'a> {
         let Some(entry_class) = self.entry_class else {
             bail!("Entry class not set");
         };
-        let Some(entry_class) = self.classes.get(entry_class) else {
-            bail!("Entry class '{}' not found", entry_class);
-        };
-
-        let entry_class = entry_class.clone();
+        let entry_class = self
+            .resolve_class(ClassLoader::Application, entry_class)?
+            .clone();
 
         // find entry point
-        let Some(entry_point) = entry_class.find_entry_point() else {
+        let Some(entry_point) = entry_class.find_entry_point()? else {
             bail!(
                 "No entry point found in class '{}'",
                 entry_class.this_class()?
@@ -385,7 +624,7 @@ impl<'a> Jvm<'a> {
             code,
             exception_table,
             attributes,
-        }) = method.code()
+        }) = method.code()?
         else {
             bail!("No code attribute for method '{}'", method.name);
         };
@@ -394,19 +633,14 @@ impl<'a> Jvm<'a> {
 
         self.stack.push(StackFrame::new(max_stack, max_locals));
 
-        dbg!(attributes
-            .iter()
-            .map(|a| Attribute::from_raw(&a, &class.constant_pool))
-            .collect::<Vec<_>>());
-
         dbg!(max_stack, max_locals, code, exception_table, attributes);
 
-        self.run_code(class.this_class()?, code)?;
+        self.run_code(&class.key()?, code)?;
 
         Ok(())
     }
 
-    fn run_code(&mut self, curr_class: &str, code: &[u8]) -> anyhow::Result<()> {
+    fn run_code(&mut self, curr_class: &ClassKey, code: &[u8]) -> anyhow::Result<()> {
         let stack_frame = self.stack.len() - 1;
 
         let mut cursor = Cursor::new(code);
@@ -419,11 +653,10 @@ impl<'a> Jvm<'a> {
             // do things
             handle_op_code(instruction, self, curr_class, &mut cursor, stack_frame)?;
 
-            let dpc = (cursor.seek(std::io::SeekFrom::Current(0))? - start) as usize;
-            dbg!(dpc);
-            //self.pc += dpc;
+            // branches can move the cursor backwards as well as forwards
+            let pc = cursor.stream_position()? as usize;
             if stack_frame < self.stack.len() {
-                self.stack[stack_frame].pc += dpc;
+                self.stack[stack_frame].pc = pc;
 
                 if self.stack[stack_frame].pc >= code.len() {
                     eprintln!("Out of code (no more code)");
@@ -437,21 +670,154 @@ impl<'a> Jvm<'a> {
         Ok(())
     }
 
+    /// The class which has the method that is run when `name` with `descriptor` is invoked on an
+    /// object of `class`, which is the closest of its super classes to declare it
+    ///
+    /// [^see]: <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-5.html#jvms-5.4.6>
+    pub fn select_method(
+        &mut self,
+        class: &ClassKey,
+        name: &str,
+        descriptor: &str,
+    ) -> anyhow::Result<ClassKey> {
+        let mut key = class.clone();
+        loop {
+            let class = self
+                .classes
+                .get(&key)
+                .with_context(|| format!("Class '{}' not found", key.1))?;
+            if let Some(method) = class.find_method(name, descriptor)? {
+                if !method.access_flags.contains(MethodAccessFlags::STATIC) {
+                    return Ok(key);
+                }
+            }
+            let Some(super_class) = class.super_class()? else {
+                bail!(Throwable::new(
+                    throwable::ABSTRACT_METHOD_ERROR,
+                    format!("{}.{}{}", class.this_class()?, name, descriptor),
+                ));
+            };
+            let super_class = super_class.to_string();
+            key = self.resolve_class(key.0, &super_class)?.key()?;
+        }
+    }
+
+    /// Run the method `name` with `descriptor` of `class`, taking its arguments off the operand
+    /// stack of the current frame with the object it's invoked on first, unless it's static.
+    /// Whatever it returns is pushed onto that stack.
+    pub fn invoke_method(
+        &mut self,
+        class_key: &ClassKey,
+        name: &str,
+        descriptor: &str,
+    ) -> anyhow::Result<()> {
+        self.link_class(class_key)?;
+        let class = &self.classes[class_key];
+        let method = class
+            .find_method(name, descriptor)?
+            .context("Expected method")?;
+
+        if method.access_flags.intersects(MethodAccessFlags::NATIVE) {
+            // TODO: FIND A BETTER WAY THAN THIS:
+            let method_name = method.name.to_string();
+            let method_descriptor = method.descriptor.to_string();
+            let method_attributes: Vec<_> = method
+                .attributes
+                .iter()
+                .cloned()
+                .map(RawAttribute::into_owned)
+                .collect();
+            let method = Method {
+                access_flags: method.access_flags,
+                name: &method_name,
+                descriptor: &method_descriptor,
+                attributes: &method_attributes,
+                constant_pool: &ConstantPool::default(), // easier than cloning the entire pool
+            };
+            let name = class.this_class()?.to_string();
+            return self.handle_native_method(&name, &method);
+        }
+        if method.access_flags.contains(MethodAccessFlags::ABSTRACT) {
+            bail!(Throwable::new(
+                throwable::ABSTRACT_METHOD_ERROR,
+                format!("{}.{}{}", class.this_class()?, name, descriptor),
+            ));
+        }
+
+        let Attribute::Code { code, .. } = method.code()?.context("Code attribute not present")?
+        else {
+            bail!(
+                "Expected a Code attribute for {}.{}{}",
+                class.this_class()?,
+                name,
+                descriptor
+            );
+        };
+
+        let md: MethodDescriptor = method.descriptor.parse()?;
+        let args =
+            md.params.len() + usize::from(!method.access_flags.contains(MethodAccessFlags::STATIC));
+
+        let mut new_stack_frame = StackFrame::for_method(&method)?;
+
+        let stack_frame = self.stack.last_mut().context("No stack frame")?;
+        for i in 0..args {
+            let v = stack_frame.op_stack.pop().context("")?;
+            new_stack_frame.variables[args - i - 1] = v;
+        }
+
+        self.stack.push(new_stack_frame);
+
+        let code = code.to_vec().into_boxed_slice();
+
+        self.run_code(class_key, &code)
+    }
+
+    /// Link the class if it has not been linked already, which verifies it and links its super
+    /// class first.
+    ///
+    /// [^see]: <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-5.html#jvms-5.4>
+    pub fn link_class(&mut self, key: &ClassKey) -> anyhow::Result<()> {
+        let class = self
+            .classes
+            .get(key)
+            .with_context(|| format!("Class '{}' not found", key.1))?;
+        if class.linked {
+            return Ok(());
+        }
+
+        if let Some(super_class) = class.super_class()? {
+            let super_class = super_class.to_string();
+            self.resolve_class(key.0, &super_class)?;
+        }
+
+        let class = &self.classes[key];
+        verifier::verify_class(
+            class,
+            &LoaderHierarchy {
+                jvm: self,
+                loader: key.0,
+            },
+        )?;
+
+        self.classes.get_mut(key).unwrap().linked = true;
+        Ok(())
+    }
+
     /// Initialise the class if it has not been initialised already
     /// Returns whether it was initialised by the calling of this function.
-    pub fn init_class(&mut self, class_name: &str) -> anyhow::Result<bool> {
-        let class = self.classes.get_mut(class_name).context("")?;
-
+    pub fn init_class(&mut self, loader: ClassLoader, class_name: &str) -> anyhow::Result<bool> {
+        let class = self.resolve_class(loader, class_name)?;
         if class.initialised {
             return Ok(false);
         }
 
         let class = class.clone();
-        let method = class.find_init_method().context("")?;
+        let method = class.find_init_method()?.context("")?;
 
         self.run_method(&class, &method)?;
 
-        self.classes.get_mut(class_name).unwrap().initialised = true;
+        self.classes.get_mut(&class.key()?).unwrap().initialised = true;
 
         Ok(true)
     }
@@ -461,21 +827,195 @@ impl<'a> Jvm<'a> {
             "Handle native method: class={} method={}",
             class, method.name
         );
-        todo!()
+        match (class, method.name) {
+            ("java/lang/ClassLoader", "defineClass1") => self.define_class1(),
+            ("java/lang/ClassLoader", "findBootstrapClass") => self.find_bootstrap_class(),
+            _ => bail!(Throwable::new(
+                throwable::UNSATISFIED_LINK_ERROR,
+                format!("{}.{}{}", class, method.name, method.descriptor),
+            )),
+        }
+    }
+
+    /// `private static native Class<?> findBootstrapClass(String name)`, which loads a class with
+    /// the bootstrap loader, or returns `null` if it can't find it
+    fn find_bootstrap_class(&mut self) -> anyhow::Result<()> {
+        let stack_frame = self.stack.last_mut().context("No stack frame")?;
+        let Some(&DataType::ClassReference(name)) = stack_frame.op_stack.last() else {
+            bail!("Invalid stack args")
+        };
+        let name = self.heap.get_string(name)?.replace('.', "/");
+        self.stack.last_mut().unwrap().op_stack.pop();
+
+        let class = match self.load_class_or_none(ClassLoader::Bootstrap, &name)? {
+            Some(loader) => DataType::ClassReference(self.class_object(&(loader, name))?),
+            None => DataType::Null,
+        };
+        self.stack.last_mut().unwrap().op_stack.push(class);
+        Ok(())
+    }
+
+    /// `static native Class<?> defineClass1(ClassLoader loader, String name, byte[] b, int off,
+    /// int len, ProtectionDomain pd, String source)`, which defines a class with `loader`
+    fn define_class1(&mut self) -> anyhow::Result<()> {
+        let stack_frame = self.stack.last().context("No stack frame")?;
+        let Some(args) = stack_frame.op_stack.len().checked_sub(7) else {
+            bail!("Invalid stack args")
+        };
+        // checked before they're popped so that the frame is left alone on an error
+        let [loader, name, bytes, off, len, _pd, _source] = stack_frame.op_stack[args..] else {
+            unreachable!()
+        };
+        let (DataType::ArrayReference(bytes), DataType::Int(off), DataType::Int(len)) =
+            (bytes, off, len)
+        else {
+            bail!("Invalid stack args")
+        };
+        let loader = match loader {
+            DataType::ClassReference(reference) => ClassLoader::User(reference),
+            DataType::Null => ClassLoader::Bootstrap,
+            _ => bail!("Invalid stack args"),
+        };
+        let name = match name {
+            DataType::ClassReference(reference) => Some(self.heap.get_string(reference)?),
+            DataType::Null => None,
+            _ => bail!("Invalid stack args"),
+        };
+
+        let Array::Byte(bytes) = self.heap.get_array(bytes)? else {
+            bail!("Expected a byte array");
+        };
+        let bytes: Vec<u8> = usize::try_from(off)
+            .ok()
+            .zip(usize::try_from(len).ok())
+            .and_then(|(off, len)| bytes.get(off..off.checked_add(len)?))
+            .ok_or_else(|| {
+                Throwable::new(
+                    throwable::ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION,
+                    format!(
+                        "{}..{} out of bounds for length {}",
+                        off,
+                        off.wrapping_add(len),
+                        bytes.len()
+                    ),
+                )
+            })?
+            .iter()
+            .map(|&b| b as u8)
+            .collect();
+        let class = ClassFile::read_from(&mut bytes.as_slice()).map_err(|e| {
+            Throwable::new(
+                throwable::CLASS_FORMAT_ERROR,
+                format!("{:#} defining a class with {}", e, loader),
+            )
+        })?;
+        // `name` is the binary name, with dots
+        if let Some(name) = name.map(|name| name.replace('.', "/")) {
+            let this_class = class.this_class()?;
+            if name != this_class {
+                bail!(Throwable::new(
+                    throwable::NO_CLASS_DEF_FOUND_ERROR,
+                    format!("{} (wrong name: {})", name, this_class),
+                ));
+            }
+        }
+        self.stack.last_mut().unwrap().op_stack.truncate(args);
+
+        let key = self.define_class(loader, class)?;
+        let object = self.class_object(&key)?;
+        self.stack
+            .last_mut()
+            .unwrap()
+            .op_stack
+            .push(DataType::ClassReference(object));
+        Ok(())
     }
 }
 
-fn main() -> anyhow::Result<()> {
-    let mut jvm = Jvm::new();
+/// What a loader sees of the class hierarchy, for verifying the classes it defines. Classes which
+/// it hasn't loaded yet are read from the class paths without loading them, so that verifying
+/// doesn't load every class it sees.
+struct LoaderHierarchy<'a, 'b> {
+    jvm: &'a Jvm<'b>,
+    loader: ClassLoader,
+}
 
-    jvm.load_classes_from_dir("stdlib/java.base/java/lang")
-        .context("loading std lib")?;
+impl ClassHierarchy for LoaderHierarchy<'_, '_> {
+    fn class_info(&self, name: &str) -> Option<ClassInfo> {
+        let info = |class: &ClassFile| {
+            Some(ClassInfo {
+                super_class: class.super_class().ok()?.map(String::from),
+                is_interface: class.access_flags.contains(ClassAccessFlags::INTERFACE),
+            })
+        };
+        let key = (self.loader, name.to_string());
+        if let Some(&defining) = self.jvm.loaded.get(&key) {
+            return info(&self.jvm.classes[&(defining, key.1)]);
+        }
+        self.jvm
+            .class_infos
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| {
+                let bytes = self.jvm.class_paths.find_class(self.loader, name).ok()??;
+                info(&ClassFile::parse(&bytes).ok()?)
+            })
+            .clone()
+    }
+}
 
+fn main() -> anyhow::Result<()> {
     // TODO: Proper CLI
-    jvm.load_classes_from_files(&std::env::args().skip(2).collect::<Vec<_>>())?;
+    let mut args = std::env::args().skip(1).peekable();
+    let usage = "Usage: jvm [options] <class file | class name> [class files...]\n       \
+                 jvm [options] -jar <jar file> [args...]\n\
+                 Options: -cp <class path>, -p <module path>";
+    let mut class_path = None;
+    let mut module_path = ClassPath::default();
+    let mut jar = None;
+    while let Some(option) = args.next_if(|arg| arg.starts_with('-')) {
+        let value = args
+            .next()
+            .with_context(|| format!("Missing value after {}\n{}", option, usage))?;
+        match option.as_str() {
+            "-cp" | "-classpath" | "--class-path" => class_path = Some(ClassPath::parse(&value)?),
+            "-p" | "--module-path" => module_path = ClassPath::parse_module_path(&value)?,
+            "-jar" => {
+                jar = Some(value);
+                break;
+            }
+            _ => bail!("Unknown option {}\n{}", option, usage),
+        }
+    }
+    let (class_path, entry, class_files) = match jar {
+        Some(jar) => {
+            let (class_path, main_class) = ClassPath::for_jar(jar)?;
+            // everything after the jar is an argument to the program rather than a class to load
+            // TODO: pass them to `main` once there are arrays of references
+            let _program_args: Vec<_> = args.collect();
+            (class_path, main_class, Vec::new())
+        }
+        None => {
+            let class_path = class_path.map_or_else(ClassPath::from_env, Ok)?;
+            let entry = args.next().context(usage)?;
+            (class_path, entry, args.collect())
+        }
+    };
 
-    // TODO: Proper CLI
-    let entry_class = jvm.load_class_from_file(std::env::args().nth(1).unwrap())?;
+    let mut jvm = Jvm::new();
+    let mut app_class_path = module_path;
+    app_class_path.append(class_path);
+    jvm.set_class_paths(ClassPaths::new(ClassPath::boot()?, app_class_path));
+
+    jvm.load_classes_from_files(&class_files)?;
+
+    let entry_class = if entry.ends_with(".class") {
+        jvm.load_class_from_file(&entry)?
+    } else {
+        let name = entry.replace('.', "/");
+        jvm.load_class(ClassLoader::Application, &name)?;
+        name
+    };
 
     jvm.set_entry_class(&entry_class);
 
@@ -483,3 +1023,372 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use class_files::{
+        assembler::{Assembler, Jump},
+        builder::ClassFileBuilder,
+        bytecode::Instruction,
+        types::FieldAccessFlags,
+    };
+
+    #[test]
+    fn missing_class() {
+        let Ok(boot) = ClassPath::boot() else {
+            return;
+        };
+        let mut jvm = Jvm::new();
+        jvm.set_class_paths(ClassPaths::new(boot, ClassPath::default()));
+        let error = jvm
+            .resolve_class(ClassLoader::Application, "does/not/Exist")
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Throwable>(),
+            Some(&Throwable::new(
+                throwable::NO_CLASS_DEF_FOUND_ERROR,
+                "does/not/Exist"
+            ))
+        );
+    }
+
+    /// A `ClassLoader` whose `loadClass` asks the loader in its `parent` field if it has one, and
+    /// otherwise the bootstrap loader, before defining the class from the bytes in its `bytes`
+    /// field. Each class it defines swaps `bytes` with its `next` field.
+    fn test_loader() -> ClassFile<'static> {
+        let mut class = ClassFileBuilder::new("TestLoader", Some("java/lang/ClassLoader")).unwrap();
+        class
+            .add_field(
+                FieldAccessFlags::empty(),
+                "parent",
+                "Ljava/lang/ClassLoader;",
+                None,
+            )
+            .unwrap()
+            .add_field(FieldAccessFlags::empty(), "bytes", "[B", None)
+            .unwrap()
+            .add_field(FieldAccessFlags::empty(), "next", "[B", None)
+            .unwrap();
+        let pool = class.constant_pool();
+        let parent = pool
+            .field_ref("TestLoader", "parent", "Ljava/lang/ClassLoader;")
+            .unwrap();
+        let bytes = pool.field_ref("TestLoader", "bytes", "[B").unwrap();
+        let next = pool.field_ref("TestLoader", "next", "[B").unwrap();
+        let load_class = pool
+            .method_ref(
+                "java/lang/ClassLoader",
+                "loadClass",
+                "(Ljava/lang/String;)Ljava/lang/Class;",
+            )
+            .unwrap();
+        let find_bootstrap_class = pool
+            .method_ref(
+                "java/lang/ClassLoader",
+                "findBootstrapClass",
+                "(Ljava/lang/String;)Ljava/lang/Class;",
+            )
+            .unwrap();
+        let define_class1 = pool
+            .method_ref(
+                "java/lang/ClassLoader",
+                "defineClass1",
+                "(Ljava/lang/ClassLoader;Ljava/lang/String;[BIILjava/security/ProtectionDomain;\
+                 Ljava/lang/String;)Ljava/lang/Class;",
+            )
+            .unwrap();
+
+        let mut asm = Assembler::new(2);
+        let (no_parent, define) = (asm.new_label(), asm.new_label());
+        asm.push(Instruction::Aload0)
+            .push(Instruction::Getfield(parent))
+            .push(Instruction::Dup)
+            .jump(Jump::Ifnull, no_parent)
+            .push(Instruction::Aload1)
+            .push(Instruction::Invokevirtual(load_class))
+            .push(Instruction::Areturn)
+            .bind(no_parent)
+            .push(Instruction::Pop)
+            .push(Instruction::Aload1)
+            .push(Instruction::Invokestatic(find_bootstrap_class))
+            .push(Instruction::Dup)
+            .jump(Jump::Ifnull, define)
+            .push(Instruction::Areturn)
+            .bind(define)
+            .push(Instruction::Pop)
+            .push(Instruction::Aload0)
+            .push(Instruction::Getfield(bytes))
+            .push(Instruction::Astore2)
+            .push(Instruction::Aload0)
+            .push(Instruction::Aload0)
+            .push(Instruction::Getfield(next))
+            .push(Instruction::Putfield(bytes))
+            .push(Instruction::Aload0)
+            .push(Instruction::Aload2)
+            .push(Instruction::Putfield(next))
+            .push(Instruction::Aload0)
+            .push(Instruction::Aload1)
+            .push(Instruction::Aload2)
+            .push(Instruction::Iconst0)
+            .push(Instruction::Aload2)
+            .push(Instruction::Arraylength)
+            .push(Instruction::AconstNull)
+            .push(Instruction::AconstNull)
+            .push(Instruction::Invokestatic(define_class1))
+            .push(Instruction::Areturn);
+        let code = asm.assemble(class.constant_pool().pool()).unwrap();
+        class
+            .add_method(
+                MethodAccessFlags::PUBLIC,
+                "loadClass",
+                "(Ljava/lang/String;)Ljava/lang/Class;",
+                Some(&code),
+            )
+            .unwrap();
+        class.build()
+    }
+
+    /// Set up a [`Jvm`] with the [`test_loader`] class and a byte array holding the class `name`,
+    /// or [`None`] without a JDK
+    fn with_test_loader(name: &str) -> Option<(Jvm<'static>, ClassKey, usize)> {
+        let boot = ClassPath::boot().ok()?;
+        let mut jvm = Jvm::new();
+        jvm.set_class_paths(ClassPaths::new(boot, ClassPath::default()));
+        let test_loader = jvm
+            .define_class(ClassLoader::Application, test_loader())
+            .unwrap();
+        let array = class_bytes(&mut jvm, name, "java/lang/Object");
+        Some((jvm, test_loader, array))
+    }
+
+    /// Create a byte array holding the class `name` which extends `super_class`
+    fn class_bytes(jvm: &mut Jvm, name: &str, super_class: &str) -> usize {
+        let bytes = ClassFileBuilder::new(name, Some(super_class))
+            .unwrap()
+            .build()
+            .to_bytes()
+            .unwrap();
+        let array = jvm.heap.create_array(8, bytes.len()).unwrap();
+        for (i, &b) in bytes.iter().enumerate() {
+            let array = jvm.heap.get_array_mut(array).unwrap();
+            array.set(i, DataType::Byte(b as i8)).unwrap();
+        }
+        array
+    }
+
+    /// Create a [`test_loader`] object which defines classes from `bytes`
+    fn new_loader(
+        jvm: &mut Jvm,
+        test_loader: &ClassKey,
+        parent: DataType,
+        bytes: DataType,
+    ) -> usize {
+        let loader = jvm.heap.create_object(test_loader.clone()).unwrap();
+        jvm.heap.set_field(loader, "parent", parent).unwrap();
+        jvm.heap.set_field(loader, "bytes", bytes).unwrap();
+        jvm.heap.set_field(loader, "next", bytes).unwrap();
+        loader
+    }
+
+    #[test]
+    fn delegating_loaders() {
+        let name = "p/Loaded";
+        let Some((mut jvm, test_loader, array)) = with_test_loader(name) else {
+            return;
+        };
+        // `child` delegates to `parent`, which defines the class
+        let array = DataType::ArrayReference(array);
+        let parent = new_loader(&mut jvm, &test_loader, DataType::Null, array);
+        let child = new_loader(
+            &mut jvm,
+            &test_loader,
+            DataType::ClassReference(parent),
+            DataType::Null,
+        );
+
+        let (parent, child) = (ClassLoader::User(parent), ClassLoader::User(child));
+        assert_eq!(jvm.load_class(child, name).unwrap(), parent);
+        assert_eq!(jvm.loaded[&(parent, name.into())], parent);
+        assert!(jvm.classes.contains_key(&(parent, name.into())));
+        assert!(!jvm.classes.contains_key(&(child, name.into())));
+        // its super class came from the bootstrap loader through `parent`
+        assert_eq!(
+            jvm.loaded[&(parent, "java/lang/Object".into())],
+            ClassLoader::Bootstrap
+        );
+        // the class is only defined once
+        assert_eq!(jvm.load_class(parent, name).unwrap(), parent);
+
+        // `parent` defines the bytes of `p/Loaded` when asked for another class
+        let error = jvm.load_class(child, "p/Other").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Throwable>().map(|e| e.class),
+            Some(throwable::NO_CLASS_DEF_FOUND_ERROR)
+        );
+        assert!(!jvm.loaded.contains_key(&(child, "p/Other".into())));
+    }
+
+    #[test]
+    fn new_objects() {
+        let Ok(boot) = ClassPath::boot() else {
+            return;
+        };
+        let mut jvm = Jvm::new();
+        jvm.set_class_paths(ClassPaths::new(boot, ClassPath::default()));
+
+        let mut class = ClassFileBuilder::new("p/Made", Some("java/lang/Object")).unwrap();
+        let pool = class.constant_pool();
+        let this = pool.class("p/Made").unwrap();
+        let init = pool.method_ref("p/Made", "<init>", "()V").unwrap();
+        let object_init = pool
+            .method_ref("java/lang/Object", "<init>", "()V")
+            .unwrap();
+        let mut asm = Assembler::new(1);
+        asm.push(Instruction::Aload0)
+            .push(Instruction::Invokespecial(object_init))
+            .push(Instruction::Return);
+        let init_code = asm.assemble(class.constant_pool().pool()).unwrap();
+        let mut asm = Assembler::new(0);
+        asm.push(Instruction::New(this))
+            .push(Instruction::Dup)
+            .push(Instruction::Invokespecial(init))
+            .push(Instruction::Areturn);
+        let make_code = asm.assemble(class.constant_pool().pool()).unwrap();
+        class
+            .add_method(MethodAccessFlags::PUBLIC, "<init>", "()V", Some(&init_code))
+            .unwrap()
+            .add_method(
+                MethodAccessFlags::PUBLIC | MethodAccessFlags::STATIC,
+                "make",
+                "()Lp/Made;",
+                Some(&make_code),
+            )
+            .unwrap();
+        let key = jvm
+            .define_class(ClassLoader::Application, class.build())
+            .unwrap();
+
+        jvm.stack.push(StackFrame::new(1, 0));
+        jvm.invoke_method(&key, "make", "()Lp/Made;").unwrap();
+        let Some(DataType::ClassReference(object)) = jvm.stack[0].op_stack.pop() else {
+            panic!("Expected an object, found {:?}", jvm.stack[0].op_stack);
+        };
+        assert_eq!(jvm.heap.class_of(object).unwrap(), key);
+    }
+
+    #[test]
+    fn unknown_native_method() {
+        let Ok(boot) = ClassPath::boot() else {
+            return;
+        };
+        let mut jvm = Jvm::new();
+        jvm.set_class_paths(ClassPaths::new(boot, ClassPath::default()));
+        let mut class = ClassFileBuilder::new("p/Native", Some("java/lang/Object")).unwrap();
+        class
+            .add_method(
+                MethodAccessFlags::STATIC | MethodAccessFlags::NATIVE,
+                "missing",
+                "()V",
+                None,
+            )
+            .unwrap();
+        let key = jvm
+            .define_class(ClassLoader::Application, class.build())
+            .unwrap();
+
+        jvm.stack.push(StackFrame::new(0, 0));
+        let error = jvm.invoke_method(&key, "missing", "()V").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Throwable>(),
+            Some(&Throwable::new(
+                throwable::UNSATISFIED_LINK_ERROR,
+                "p/Native.missing()V"
+            ))
+        );
+    }
+
+    #[test]
+    fn isolated_loaders() {
+        let name = "p/Loaded";
+        let Some((mut jvm, test_loader, array)) = with_test_loader(name) else {
+            return;
+        };
+        let bytes = DataType::ArrayReference(array);
+        let a = new_loader(&mut jvm, &test_loader, DataType::Null, bytes);
+        let b = new_loader(&mut jvm, &test_loader, DataType::Null, bytes);
+        let (a, b) = (ClassLoader::User(a), ClassLoader::User(b));
+        assert_eq!(jvm.load_class(a, name).unwrap(), a);
+        assert_eq!(jvm.load_class(b, name).unwrap(), b);
+        let class_a = jvm.class_object(&(a, name.into())).unwrap();
+        let class_b = jvm.class_object(&(b, name.into())).unwrap();
+        assert_ne!(class_a, class_b);
+        assert!(!jvm
+            .classes
+            .contains_key(&(ClassLoader::Bootstrap, name.into())));
+
+        // `a` defining the class a second time
+        let ClassLoader::User(loader) = a else {
+            unreachable!()
+        };
+        let len = jvm.heap.get_array(array).unwrap().len();
+        jvm.stack.push(StackFrame::new(7, 0));
+        jvm.stack[0].op_stack.extend([
+            DataType::ClassReference(loader),
+            DataType::Null,
+            bytes,
+            DataType::Int(0),
+            DataType::Int(len as i32),
+            DataType::Null,
+            DataType::Null,
+        ]);
+        let error = jvm.define_class1().unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Throwable>().map(|e| e.class),
+            Some(throwable::LINKAGE_ERROR)
+        );
+
+        // invalid arguments are left on the stack
+        let args = [
+            DataType::ClassReference(loader),
+            DataType::Null,
+            bytes,
+            DataType::Int(0),
+            DataType::Long(len as i64),
+            DataType::Null,
+            DataType::Null,
+        ];
+        jvm.stack[0].op_stack.clear();
+        jvm.stack[0].op_stack.extend(args);
+        assert!(jvm.define_class1().is_err());
+        assert_eq!(jvm.stack[0].op_stack.len(), args.len());
+    }
+
+    #[test]
+    fn circular_user_loader() {
+        let Some((mut jvm, test_loader, _)) = with_test_loader("p/A") else {
+            return;
+        };
+        // `p/A` extends `p/B` extends `p/A`, each defined in a different call to `loadClass`
+        let a = class_bytes(&mut jvm, "p/A", "p/B");
+        let b = class_bytes(&mut jvm, "p/B", "p/A");
+        let loader = new_loader(
+            &mut jvm,
+            &test_loader,
+            DataType::Null,
+            DataType::ArrayReference(a),
+        );
+        jvm.heap
+            .set_field(loader, "next", DataType::ArrayReference(b))
+            .unwrap();
+
+        let error = jvm
+            .load_class(ClassLoader::User(loader), "p/A")
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<Throwable>().map(|e| e.class),
+            Some(throwable::CLASS_CIRCULARITY_ERROR)
+        );
+        assert!(jvm.loading.is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+//! The main attributes of a jar's `META-INF/MANIFEST.MF`.
+//!
+//! [^see]: <https://docs.oracle.com/en/java/javase/21/docs/specs/jar/jar.html#jar-manifest>
+
+use std::collections::HashMap;
+
+pub(crate) const MANIFEST_NAME: &str = "META-INF/MANIFEST.MF";
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Manifest {
+    /// By lowercase name, since names are case-insensitive
+    attributes: HashMap<String, String>,
+}
+
+impl Manifest {
+    /// Parse the main section, which is everything before the first blank line. Lines that start
+    /// with a space continue the previous one.
+    pub fn parse(bytes: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(bytes);
+        let mut lines: Vec<String> = Vec::new();
+        for line in text.split("\r\n").flat_map(|l| l.split(['\r', '\n'])) {
+            if line.is_empty() {
+                break;
+            }
+            match (line.strip_prefix(' '), lines.last_mut()) {
+                (Some(rest), Some(last)) => last.push_str(rest),
+                _ => lines.push(line.to_string()),
+            }
+        }
+
+        let attributes = lines
+            .iter()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+        Self { attributes }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// The class to run with `java -jar`, as a binary name like `com/example/Main`
+    pub fn main_class(&self) -> Option<String> {
+        self.get("Main-Class").map(|name| name.replace('.', "/"))
+    }
+
+    /// The relative URLs of the other jars and directories that this one depends on
+    pub fn class_path(&self) -> impl Iterator<Item = &str> {
+        self.get("Class-Path")
+            .into_iter()
+            .flat_map(str::split_whitespace)
+    }
+
+    pub fn is_multi_release(&self) -> bool {
+        self.get("Multi-Release")
+            .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn continuation_lines() {
+        let manifest = Manifest::parse(
+            b"Manifest-Version: 1.0\r\nMain-Class: com.example.Ma\r\n in\r\nclass-path: lib/a.j\r\n \
+              ar lib/b%20c.jar\r\nMulti-Release: TRUE\r\n\r\nName: com/example/\r\nMain-Class: No\r\n",
+        );
+        assert_eq!(manifest.main_class().as_deref(), Some("com/example/Main"));
+        assert_eq!(
+            manifest.class_path().collect::<Vec<_>>(),
+            ["lib/a.jar", "lib/b%20c.jar"]
+        );
+        assert!(manifest.is_multi_release());
+        assert_eq!(manifest.get("name"), None);
+    }
+}
@@ -1,32 +1,27 @@
 use std::io::{Read, Seek, SeekFrom};
 
-use anyhow::{bail, ensure, Context};
-use class_files::{
-    bytes::ReadNum,
-    descriptors::MethodDescriptor,
-    types::{
-        raw::RawConstant,
-        resolved::{Attribute, Method},
-        MethodAccessFlags,
-    },
-};
+use anyhow::{bail, Context};
+use class_files::{bytes::ReadNum, descriptors::MethodDescriptor};
 
 use crate::{
-    types::{DataType, StackFrame},
+    class_loader::ClassKey,
+    throwable::{self, Throwable},
+    types::DataType,
     HeapItem, Jvm,
 };
 
-pub(crate) fn handle_op_code<'a, R>(
+pub(crate) fn handle_op_code<R>(
     instruction: u8,
-    jvm: &'a mut Jvm,
-    curr_class: &str,
+    jvm: &mut Jvm,
+    curr_class: &ClassKey,
     code: &mut R,
     stack_frame: usize,
 ) -> anyhow::Result<()>
 where
     R: Read + Seek,
 {
-    let stack_frame = &mut jvm.stack[stack_frame];
+    let frame = stack_frame;
+    let stack_frame = &mut jvm.stack[frame];
     eprintln!("Instruction: 0x{:x}", instruction);
     match instruction {
         0x0 => return Ok(()),
@@ -86,7 +81,10 @@ where
             }
             return Ok(());
         }
-        0x01 => { // aconst_null
+        0x01 => {
+            // aconst_null
+            stack_frame.op_stack.push(DataType::Null);
+            return Ok(());
         }
         0x19 => {
             // aload
@@ -102,9 +100,36 @@ where
         }
         0xbd => { // anewarray
         }
-        0xb0 => { // areturn
-        }
-        0xbe => { // arraylength
+        0xb0 => {
+            // areturn
+            code.seek(SeekFrom::End(0))?;
+            let Some(return_val) = stack_frame.op_stack.pop() else {
+                bail!("Invalid stack args")
+            };
+            jvm.stack.pop();
+            jvm.stack
+                .last_mut()
+                .context("No stack frame to return to")?
+                .op_stack
+                .push(return_val);
+            return Ok(());
+        }
+        0xbe => {
+            // arraylength
+            let len = match stack_frame.op_stack.pop() {
+                Some(DataType::ArrayReference(array)) => jvm.heap.get_array(array)?.len(),
+                Some(DataType::Null) => {
+                    bail!(Throwable::new(
+                        throwable::NULL_POINTER_EXCEPTION,
+                        "arraylength"
+                    ))
+                }
+                _ => bail!("Invalid stack args"),
+            };
+            jvm.stack[frame]
+                .op_stack
+                .push(DataType::Int(len.try_into()?));
+            return Ok(());
         }
         0x3a => {
             // astore
@@ -337,7 +362,24 @@ where
         }
         0x66 => { // fsub
         }
-        0xb4 => { // getfield
+        0xb4 => {
+            // getfield
+            let index = code.read_u16()?;
+            let field = jvm.classes[curr_class]
+                .constant_pool
+                .member_ref(index.into())?;
+            let value = match stack_frame.op_stack.pop() {
+                Some(DataType::ClassReference(object)) => {
+                    jvm.heap.get_field(object, field.name, field.descriptor)?
+                }
+                Some(DataType::Null) => bail!(Throwable::new(
+                    throwable::NULL_POINTER_EXCEPTION,
+                    format!("getfield {}", field.name)
+                )),
+                _ => bail!("Invalid stack args"),
+            };
+            jvm.stack[frame].op_stack.push(value);
+            return Ok(());
         }
         0xb2 => {
             // getstatic -- Get `static` field from class
@@ -345,26 +387,15 @@ where
             eprintln!("Unimpled Instruction: getstatic {:02x}", index);
 
             let class = &jvm.classes[curr_class];
-            let (class_index, name_and_type_index) = match &class.constant_pool[index as usize - 1]
-            {
-                RawConstant::FieldRef {
-                    class_index,
-                    name_and_type_index,
-                } => (class_index, name_and_type_index),
-                c => unreachable!("Expected FieldRef, got {:?}", c),
-            };
-            dbg!(class_index, name_and_type_index);
+            let field = class.constant_pool.member_ref(index.into())?;
+            dbg!(field);
 
-            let RawConstant::Class { name_index } = class.constant_pool[class_index - 1] else {
-                unreachable!();
-            };
-            let class_name = class.constant_pool[name_index - 1]
-                .unwrap_utf8()
-                .to_string();
+            let class_name = field.class.to_string();
 
-            jvm.init_class(&class_name)?;
+            jvm.init_class(curr_class.0, &class_name)?;
 
-            let class = jvm.classes.get_mut(&class_name).context("")?;
+            let class_key = jvm.resolve_class(curr_class.0, &class_name)?.key()?;
+            let class = jvm.classes.get_mut(&class_key).context("")?;
             dbg!(&class);
             //let (class_index, name_and_type_index) = match class.constant_pool[class_index - 1] {
             //    RawConstant::MethodRef {
@@ -501,9 +532,23 @@ where
         }
         0x9a => { // ifne
         }
-        0xc7 => { // ifnonnull
-        }
-        0xc6 => { // ifnull
+        0xc6 | 0xc7 => {
+            // ifnull, ifnonnull
+            let offset = code.read_i16()?;
+            let is_null = match stack_frame.op_stack.pop() {
+                Some(DataType::Null) => true,
+                Some(
+                    DataType::ClassReference(_)
+                    | DataType::ArrayReference(_)
+                    | DataType::InterfaceReference(_),
+                ) => false,
+                _ => bail!("Invalid stack args"),
+            };
+            if is_null == (instruction == 0xc6) {
+                // the offset is from the start of this instruction
+                code.seek(SeekFrom::Current(i64::from(offset) - 3))?;
+            }
+            return Ok(());
         }
         0x84 => { // iinc
         }
@@ -552,7 +597,23 @@ where
         }
         0xb9 => { // invokeinterface
         }
-        0xb7 => { // invokespecial
+        0xb7 => {
+            // invokespecial
+            // TODO: select the method from the super class of the current class when the
+            //       reference is to one of its super classes and it isn't `<init>`
+            let index = code.read_u16()?;
+            let method_ref = jvm.classes[curr_class]
+                .constant_pool
+                .member_ref(index.into())?;
+            let (class_name, name, descriptor) = (
+                method_ref.class.to_string(),
+                method_ref.name.to_string(),
+                method_ref.descriptor.to_string(),
+            );
+            let class_key = jvm.resolve_class(curr_class.0, &class_name)?.key()?;
+            let class_key = jvm.select_method(&class_key, &name, &descriptor)?;
+            jvm.invoke_method(&class_key, &name, &descriptor)?;
+            return Ok(());
         }
         0xb8 => {
             // invokestatic
@@ -560,92 +621,50 @@ where
             let index = code.read_u16()?;
             dbg!(index);
 
-            let class = &jvm.classes[curr_class];
-            let (class, name, descriptor) = match &class.constant_pool[index as usize - 1] {
-                RawConstant::InterfaceMethodRef {
-                    class_index,
-                    name_and_type_index,
-                }
-                | RawConstant::MethodRef {
-                    class_index,
-                    name_and_type_index,
-                } => {
-                    let method_class = &class.constant_pool[class_index - 1];
-                    let method_class = match method_class {
-                        RawConstant::Class { name_index } => {
-                            class.constant_pool[name_index - 1].unwrap_utf8()
-                        }
-                        _ => unreachable!(),
-                    };
-                    let method_class = &jvm.classes[method_class];
-                    match &class.constant_pool[name_and_type_index - 1] {
-                        RawConstant::NameAndType {
-                            name_index,
-                            descriptor_index,
-                        } => (
-                            method_class,
-                            class.constant_pool[name_index - 1].unwrap_utf8(),
-                            class.constant_pool[descriptor_index - 1].unwrap_utf8(),
-                        ),
-                        _ => unreachable!(),
-                    }
-                }
-                _ => unreachable!(),
-            };
-
-            let method = class
-                .methods()
-                .find(|m| m.name == name && m.descriptor == descriptor)
-                .context("Expected method")?;
-
-            dbg!(method.name);
-
-            if method.access_flags.intersects(MethodAccessFlags::NATIVE) {
-                eprintln!("NATIVE METHOD");
-                // TODO: FIND A BETTER WAY THAN THIS:
-                let method = Method {
-                    access_flags: method.access_flags,
-                    name: &method.name.to_string(),
-                    descriptor: &method.name.to_string(),
-                    attributes: &method.attributes.to_vec(),
-                    constant_pool: &vec![], // easier than reallocating this entire vec
-                };
-                let name = class.this_class()?.to_string();
-                jvm.handle_native_method(&name, &method)?;
-                return Ok(());
-            }
-
-            let Attribute::Code {
-                code,
-                exception_table,
-                attributes,
-                ..
-            } = method.code().context("Code attribute not present")?
-            else {
-                bail!("fu");
-            };
-
-            eprintln!("\tcode = {:x?}", code);
-
-            let md: MethodDescriptor = method.descriptor.parse()?;
-
-            let mut new_stack_frame = StackFrame::for_method(&method);
-
-            for i in 0..md.params.len() {
-                let v = stack_frame.op_stack.pop().context("")?;
-                new_stack_frame.variables[md.params.len() - i - 1] = v;
-            }
-            dbg!(&new_stack_frame);
-
-            jvm.stack.push(new_stack_frame);
-
-            let code = code.to_vec().into_boxed_slice();
-            let class = class.this_class()?.to_string();
-
-            jvm.run_code(&class, &code)?;
+            let method_ref = jvm.classes[curr_class]
+                .constant_pool
+                .member_ref(index.into())?;
+            let (class_name, name, descriptor) = (
+                method_ref.class.to_string(),
+                method_ref.name.to_string(),
+                method_ref.descriptor.to_string(),
+            );
+            let class_key = jvm.resolve_class(curr_class.0, &class_name)?.key()?;
+            jvm.invoke_method(&class_key, &name, &descriptor)?;
             return Ok(());
         }
-        0xb6 => { // invokevirtual
+        0xb6 => {
+            // invokevirtual
+            let index = code.read_u16()?;
+            let method_ref = jvm.classes[curr_class]
+                .constant_pool
+                .member_ref(index.into())?;
+            let (class_name, name, descriptor) = (
+                method_ref.class.to_string(),
+                method_ref.name.to_string(),
+                method_ref.descriptor.to_string(),
+            );
+            jvm.resolve_class(curr_class.0, &class_name)?;
+
+            // the object is below the arguments
+            let md: MethodDescriptor = descriptor.parse()?;
+            let op_stack = &jvm.stack[frame].op_stack;
+            let object = op_stack
+                .len()
+                .checked_sub(md.params.len() + 1)
+                .and_then(|i| op_stack.get(i));
+            let object = match object {
+                Some(DataType::ClassReference(object)) => *object,
+                Some(DataType::Null) => bail!(Throwable::new(
+                    throwable::NULL_POINTER_EXCEPTION,
+                    format!("invokevirtual {}.{}", class_name, name)
+                )),
+                _ => bail!("Invalid stack args"),
+            };
+            let class = jvm.heap.class_of(object)?;
+            let class_key = jvm.select_method(&class, &name, &descriptor)?;
+            jvm.invoke_method(&class_key, &name, &descriptor)?;
+            return Ok(());
         }
         0x80 => {
             // ior
@@ -800,7 +819,20 @@ where
         }
         0xc5 => { // multianewarray
         }
-        0xbb => { // new
+        0xbb => {
+            // new
+            // TODO: initialise the class once `init_class` handles classes without `<clinit>`
+            let index = code.read_u16()?;
+            let class_name = jvm.classes[curr_class]
+                .constant_pool
+                .class_name(index.into())?
+                .to_string();
+            let class_key = jvm.resolve_class(curr_class.0, &class_name)?.key()?;
+            let object = jvm.heap.create_object(class_key)?;
+            jvm.stack[frame]
+                .op_stack
+                .push(DataType::ClassReference(object));
+            return Ok(());
         }
         0xbc => {
             // newarray
@@ -819,7 +851,28 @@ where
         }
         0x58 => { // pop2
         }
-        0xb5 => { // putfield
+        0xb5 => {
+            // putfield
+            let index = code.read_u16()?;
+            let field = jvm.classes[curr_class]
+                .constant_pool
+                .member_ref(index.into())?;
+            let (Some(value), Some(object)) =
+                (stack_frame.op_stack.pop(), stack_frame.op_stack.pop())
+            else {
+                bail!("Invalid stack args")
+            };
+            match object {
+                DataType::ClassReference(object) => {
+                    jvm.heap.set_field(object, field.name, value)?
+                }
+                DataType::Null => bail!(Throwable::new(
+                    throwable::NULL_POINTER_EXCEPTION,
+                    format!("putfield {}", field.name)
+                )),
+                _ => bail!("Invalid stack args"),
+            }
+            return Ok(());
         }
         0xb3 => { // putstatic
         }
@@ -0,0 +1,44 @@
+//! Errors which the running Java code would see thrown, such as a `NoClassDefFoundError` when a
+//! class it refers to can't be found, as opposed to the JVM itself failing. They are passed around
+//! inside of [`anyhow::Error`]s and can be told apart with `downcast_ref::<Throwable>()`.
+//!
+//! TODO: catch these with the exception table of the method once there are exception objects,
+//! for now they end the program like an uncaught exception would
+
+use std::fmt;
+
+pub(crate) const ABSTRACT_METHOD_ERROR: &str = "java/lang/AbstractMethodError";
+pub(crate) const ARRAY_INDEX_OUT_OF_BOUNDS_EXCEPTION: &str =
+    "java/lang/ArrayIndexOutOfBoundsException";
+pub(crate) const CLASS_CIRCULARITY_ERROR: &str = "java/lang/ClassCircularityError";
+pub(crate) const CLASS_FORMAT_ERROR: &str = "java/lang/ClassFormatError";
+pub(crate) const LINKAGE_ERROR: &str = "java/lang/LinkageError";
+pub(crate) const NO_CLASS_DEF_FOUND_ERROR: &str = "java/lang/NoClassDefFoundError";
+pub(crate) const NULL_POINTER_EXCEPTION: &str = "java/lang/NullPointerException";
+pub(crate) const UNSATISFIED_LINK_ERROR: &str = "java/lang/UnsatisfiedLinkError";
+pub(crate) const UNSUPPORTED_CLASS_VERSION_ERROR: &str = "java/lang/UnsupportedClassVersionError";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Throwable {
+    /// The binary name of its class, e.g. [`NO_CLASS_DEF_FOUND_ERROR`]
+    pub class: &'static str,
+    pub message: String,
+}
+
+impl Throwable {
+    pub fn new(class: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            class,
+            message: message.into(),
+        }
+    }
+}
+
+/// Shown the way `java` shows an uncaught exception, e.g. `java.lang.NoClassDefFoundError: Foo`
+impl fmt::Display for Throwable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.class.replace('/', "."), self.message)
+    }
+}
+
+impl std::error::Error for Throwable {}
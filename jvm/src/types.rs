@@ -1,3 +1,4 @@
+use anyhow::bail;
 use class_files::{
     descriptors::MethodDescriptor,
     types::resolved::{Attribute, Method},
@@ -96,6 +97,23 @@ impl DataType {
         }
     }
 
+    /// The initial value of a field with the type `descriptor`
+    ///
+    /// [^ref]: See <https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-2.html#jvms-2.3>
+    pub fn default_for(descriptor: &str) -> Self {
+        match descriptor.as_bytes().first() {
+            Some(b'Z') => DataType::Boolean(false),
+            Some(b'B') => DataType::Byte(0),
+            Some(b'C') => DataType::Char(0),
+            Some(b'S') => DataType::Short(0),
+            Some(b'I') => DataType::Int(0),
+            Some(b'F') => DataType::Float(0.0),
+            Some(b'J') => DataType::Long(0),
+            Some(b'D') => DataType::Double(0.0),
+            _ => DataType::Null,
+        }
+    }
+
     /// [^ref]: See https://docs.oracle.com/javase/specs/jvms/se21/html/jvms-2.html#jvms-2.11.1-320
     pub fn get_computation_type(&self) -> Self {
         match self {
@@ -103,16 +121,16 @@ impl DataType {
             DataType::Byte(b) => DataType::Int((*b).into()),
             DataType::Char(c) => DataType::Int((*c).into()),
             DataType::Short(s) => DataType::Int((*s).into()),
-            DataType::Int(_) => self.clone(),
-            DataType::Float(_) => self.clone(),
-            DataType::Long(_) => self.clone(),
-            DataType::Double(_) => self.clone(),
-            DataType::ClassReference(_) => self.clone(),
-            DataType::ArrayReference { .. } => self.clone(),
-            DataType::InterfaceReference(_) => self.clone(),
-            DataType::ReturnAddr(_) => self.clone(),
-            DataType::Null => self.clone(),
-            DataType::Empty => self.clone(),
+            DataType::Int(_) => *self,
+            DataType::Float(_) => *self,
+            DataType::Long(_) => *self,
+            DataType::Double(_) => *self,
+            DataType::ClassReference(_) => *self,
+            DataType::ArrayReference { .. } => *self,
+            DataType::InterfaceReference(_) => *self,
+            DataType::ReturnAddr(_) => *self,
+            DataType::Null => *self,
+            DataType::Empty => *self,
         }
     }
 }
@@ -134,27 +152,25 @@ impl StackFrame {
         }
     }
 
-    pub(crate) fn for_method(method: &Method) -> Self {
+    pub(crate) fn for_method(method: &Method) -> anyhow::Result<Self> {
         let Some(Attribute::Code {
             max_stack,
             max_locals,
-            code,
-            exception_table,
-            attributes,
-        }) = method.code()
+            ..
+        }) = method.code()?
         else {
-            unreachable!()
+            bail!("No code attribute for method '{}'", method.name);
         };
 
-        let md: MethodDescriptor = method.descriptor.parse().unwrap();
+        let md: MethodDescriptor = method.descriptor.parse()?;
         dbg!(md);
 
         let variables = vec![DataType::Empty; max_locals.into()];
 
-        Self {
+        Ok(Self {
             variables,
             op_stack: Vec::with_capacity(max_stack.into()),
             pc: 0,
-        }
+        })
     }
 }
@@ -0,0 +1,515 @@
+//! A reader for ZIP archives such as jars, supporting entries which are stored or compressed with
+//! deflate.
+//!
+//! [^see]: <https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT>
+
+use anyhow::{bail, ensure, Context};
+use std::{collections::HashMap, fs, path::Path};
+
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+/// The size of the end of central directory record without its comment
+const END_OF_CENTRAL_DIRECTORY_LEN: usize = 22;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    method: u16,
+    flags: u16,
+    crc32: u32,
+    compressed_size: usize,
+    uncompressed_size: usize,
+    local_header_offset: usize,
+}
+
+/// A ZIP archive held in memory, whose entries are decompressed when they are read
+#[derive(Debug, Clone)]
+pub(crate) struct ZipArchive {
+    data: Vec<u8>,
+    entries: HashMap<String, Entry>,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> anyhow::Result<u16> {
+    let bytes = data
+        .get(offset..offset + 2)
+        .with_context(|| format!("ZIP archive truncated at offset {}", offset))?;
+    Ok(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> anyhow::Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .with_context(|| format!("ZIP archive truncated at offset {}", offset))?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+impl ZipArchive {
+    pub fn open<P>(path: P) -> anyhow::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let data = fs::read(&path)?;
+        Self::new(data).with_context(|| format!("reading {}", path.as_ref().display()))
+    }
+
+    /// Read the central directory of the archive in `data`
+    pub fn new(data: Vec<u8>) -> anyhow::Result<Self> {
+        // the end of central directory record is followed by a comment of up to 64KiB
+        ensure!(
+            data.len() >= END_OF_CENTRAL_DIRECTORY_LEN,
+            "Not a ZIP archive"
+        );
+        let last = data.len() - END_OF_CENTRAL_DIRECTORY_LEN;
+        let end = (last.saturating_sub(u16::MAX.into())..=last)
+            .rev()
+            .find(|offset| u32_at(&data, *offset).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+            .context("Not a ZIP archive, no end of central directory record")?;
+
+        let count = u16_at(&data, end + 10)?;
+        let size = u32_at(&data, end + 12)?;
+        let mut offset = u32_at(&data, end + 16)? as usize;
+        if count == u16::MAX || size == u32::MAX || offset == u32::MAX as usize {
+            bail!("ZIP64 archives are not supported");
+        }
+
+        let mut entries = HashMap::with_capacity(count.into());
+        for _ in 0..count {
+            ensure!(
+                u32_at(&data, offset)? == CENTRAL_HEADER,
+                "Invalid central directory header at offset {}",
+                offset
+            );
+            let name_len = usize::from(u16_at(&data, offset + 28)?);
+            let extra_len = usize::from(u16_at(&data, offset + 30)?);
+            let comment_len = usize::from(u16_at(&data, offset + 32)?);
+            let name = data
+                .get(offset + 46..offset + 46 + name_len)
+                .context("ZIP archive truncated in entry name")?;
+            let entry = Entry {
+                flags: u16_at(&data, offset + 8)?,
+                method: u16_at(&data, offset + 10)?,
+                crc32: u32_at(&data, offset + 16)?,
+                compressed_size: u32_at(&data, offset + 20)? as usize,
+                uncompressed_size: u32_at(&data, offset + 24)? as usize,
+                local_header_offset: u32_at(&data, offset + 42)? as usize,
+            };
+            entries.insert(String::from_utf8_lossy(name).into_owned(), entry);
+            offset += 46 + name_len + extra_len + comment_len;
+        }
+
+        Ok(Self { data, entries })
+    }
+
+    /// The names of every entry, directories end with `/`
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
+    /// The decompressed contents of the entry called `name`, `None` if there isn't one
+    pub fn read(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(entry) = self.entries.get(name) else {
+            return Ok(None);
+        };
+        self.read_entry(entry)
+            .with_context(|| format!("reading {} from ZIP archive", name))
+            .map(Some)
+    }
+
+    fn read_entry(&self, entry: &Entry) -> anyhow::Result<Vec<u8>> {
+        ensure!(entry.flags & 1 == 0, "Encrypted entries are not supported");
+
+        let offset = entry.local_header_offset;
+        ensure!(
+            u32_at(&self.data, offset)? == LOCAL_HEADER,
+            "Invalid local header at offset {}",
+            offset
+        );
+        // the sizes in the local header can be zero when they follow the data instead, so only
+        // the ones from the central directory are used
+        let name_len = usize::from(u16_at(&self.data, offset + 26)?);
+        let extra_len = usize::from(u16_at(&self.data, offset + 28)?);
+        let start = offset + 30 + name_len + extra_len;
+        let compressed = self
+            .data
+            .get(start..start + entry.compressed_size)
+            .context("ZIP archive truncated in entry data")?;
+
+        let data = match entry.method {
+            STORED => compressed.to_vec(),
+            DEFLATED => inflate(compressed, entry.uncompressed_size)?,
+            method => bail!("Unsupported compression method {}", method),
+        };
+        ensure!(
+            data.len() == entry.uncompressed_size,
+            "Expected {} bytes, found {}",
+            entry.uncompressed_size,
+            data.len()
+        );
+        ensure!(crc32(&data) == entry.crc32, "CRC-32 mismatch");
+        Ok(data)
+    }
+}
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 {
+                    0xedb88320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+
+    !data.iter().fold(!0, |crc, byte| {
+        TABLE[((crc ^ u32::from(*byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Reads bits starting from the least significant bit of each byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            offset: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    fn bits(&mut self, n: u32) -> anyhow::Result<u32> {
+        while self.count < n {
+            let byte = *self
+                .data
+                .get(self.offset)
+                .context("Compressed data ends unexpectedly")?;
+            self.buffer |= u32::from(byte) << self.count;
+            self.offset += 1;
+            self.count += 8;
+        }
+        let bits = self.buffer & ((1 << n) - 1);
+        self.buffer = self.buffer.checked_shr(n).unwrap_or(0);
+        self.count -= n;
+        Ok(bits)
+    }
+
+    /// Skip to the next byte boundary
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+
+    fn bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + len)
+            .context("Compressed data ends unexpectedly")?;
+        self.offset += len;
+        Ok(bytes)
+    }
+}
+
+/// A canonical Huffman code, stored as the number of codes of each length and the symbols in
+/// order of their codes
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// Build the code where symbol `i` has a code of `lengths[i]` bits, zero if it is unused
+    fn new(lengths: &[u8]) -> anyhow::Result<Self> {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[usize::from(*length)] += 1;
+        }
+        counts[0] = 0;
+
+        // each length can only use the codes that shorter ones leave free
+        let mut left = 1i32;
+        for count in &counts[1..] {
+            left = (left << 1) - i32::from(*count);
+            ensure!(left >= 0, "Huffman code is over-subscribed");
+        }
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                let offset = &mut offsets[usize::from(*length)];
+                symbols[usize::from(*offset)] = symbol as u16;
+                *offset += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, r: &mut BitReader) -> anyhow::Result<u16> {
+        // `first` is the first code of the current length and `index` its position in `symbols`
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for count in &self.counts[1..] {
+            code |= r.bits(1)? as i32;
+            let count = i32::from(*count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        bail!("Invalid Huffman code")
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order that the lengths of the code length code are sent in
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompress raw deflate data, `size` is the expected size of the output. Inflating stops with
+/// an error as soon as the output is any larger, so that a small archive entry can't be used to
+/// fill up memory.
+///
+/// [^see]: <https://www.rfc-editor.org/rfc/rfc1951>
+pub(crate) fn inflate(data: &[u8], size: usize) -> anyhow::Result<Vec<u8>> {
+    let mut r = BitReader::new(data);
+    // deflate can't compress by more than 1032:1, so don't trust `size` any further than that
+    let mut out = Vec::with_capacity(size.min(data.len().saturating_mul(1032)));
+    loop {
+        let last = r.bits(1)? == 1;
+        match r.bits(2)? {
+            0 => {
+                r.align();
+                let len = r.bytes(2)?;
+                let complement = r.bytes(2)?;
+                ensure!(
+                    len[0] == !complement[0] && len[1] == !complement[1],
+                    "Stored block length doesn't match its complement"
+                );
+                let len = u16::from_le_bytes([len[0], len[1]]);
+                out.extend_from_slice(r.bytes(len.into())?);
+                check_size(&out, size)?;
+            }
+            1 => {
+                let mut lengths = [0u8; 288 + 30];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..288].fill(8);
+                lengths[288..].fill(5);
+                let literals = Huffman::new(&lengths[..288])?;
+                let distances = Huffman::new(&lengths[288..])?;
+                inflate_block(&mut r, &mut out, size, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut r)?;
+                inflate_block(&mut r, &mut out, size, &literals, &distances)?;
+            }
+            _ => bail!("Invalid deflate block type"),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+/// Read the literal/length and distance codes at the start of a dynamic block
+fn dynamic_codes(r: &mut BitReader) -> anyhow::Result<(Huffman, Huffman)> {
+    let literal_count = r.bits(5)? as usize + 257;
+    let distance_count = r.bits(5)? as usize + 1;
+    let code_length_count = r.bits(4)? as usize + 4;
+    ensure!(
+        literal_count <= 286 && distance_count <= 30,
+        "Too many codes in dynamic block"
+    );
+
+    let mut code_lengths = [0u8; 19];
+    for i in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[*i] = r.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut i = 0;
+    while i < lengths.len() {
+        let (length, repeat) = match code_lengths.decode(r)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                ensure!(i > 0, "Repeated code length with no previous length");
+                (lengths[i - 1], 3 + r.bits(2)? as usize)
+            }
+            17 => (0, 3 + r.bits(3)? as usize),
+            _ => (0, 11 + r.bits(7)? as usize),
+        };
+        ensure!(
+            i + repeat <= lengths.len(),
+            "Code lengths overflow dynamic block"
+        );
+        lengths[i..i + repeat].fill(length);
+        i += repeat;
+    }
+    ensure!(lengths[256] != 0, "Dynamic block has no end of block code");
+
+    Ok((
+        Huffman::new(&lengths[..literal_count])?,
+        Huffman::new(&lengths[literal_count..])?,
+    ))
+}
+
+fn check_size(out: &[u8], size: usize) -> anyhow::Result<()> {
+    ensure!(
+        out.len() <= size,
+        "Inflated data is larger than the expected {} bytes",
+        size
+    );
+    Ok(())
+}
+
+fn inflate_block(
+    r: &mut BitReader,
+    out: &mut Vec<u8>,
+    size: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> anyhow::Result<()> {
+    loop {
+        check_size(out, size)?;
+        let symbol = usize::from(literals.decode(r)?);
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let symbol = symbol - 257;
+                ensure!(symbol < LENGTH_BASE.len(), "Invalid length symbol");
+                let len = usize::from(LENGTH_BASE[symbol])
+                    + r.bits(LENGTH_EXTRA[symbol].into())? as usize;
+                let symbol = usize::from(distances.decode(r)?);
+                ensure!(symbol < DISTANCE_BASE.len(), "Invalid distance symbol");
+                let distance = usize::from(DISTANCE_BASE[symbol])
+                    + r.bits(DISTANCE_EXTRA[symbol].into())? as usize;
+                ensure!(
+                    distance <= out.len(),
+                    "Distance is before the start of output"
+                );
+                // the copy can overlap what it is writing, so it goes a byte at a time
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::class_path::java_home;
+
+    #[test]
+    fn inflate_blocks() {
+        // compressed by zlib, "hello hello hello\n" uses the fixed codes
+        let fixed = [
+            0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x5c, 0x00,
+        ];
+        assert_eq!(inflate(&fixed, 18).unwrap(), b"hello hello hello\n");
+        assert!(inflate(&fixed[..5], 18).is_err());
+
+        #[rustfmt::skip]
+        let dynamic = [
+            0x35, 0x8c, 0x81, 0x0d, 0x00, 0x30, 0x08, 0xc2, 0x6e, 0x85, 0xfe, 0xff, 0xc3, 0x50,
+            0xa6, 0x1a, 0x49, 0xaa, 0x20, 0xc9, 0x12, 0x1e, 0xc9, 0x6a, 0x1d, 0x71, 0xd8, 0x42,
+            0x53, 0x9c, 0xbe, 0xc3, 0xec, 0x7a, 0xa2, 0xa8, 0x66, 0xf6, 0xb3, 0xf4, 0x86, 0x1f,
+            0x89, 0x0d, 0x17, 0xa2, 0x07,
+        ];
+        assert_eq!(
+            inflate(&dynamic, 120).unwrap(),
+            b"aaabaacbaababaaaaaaaabaacbaabbbabbaaaabcaaaabababcbaabbbaaabbababaabbacaabaaaacabc\
+              aababaabaabaabaacaaaaabacbbccaabababca"
+        );
+
+        let stored = [0x01, 0x02, 0x00, 0xfd, 0xff, b'o', b'k'];
+        assert_eq!(inflate(&stored, 2).unwrap(), b"ok");
+    }
+
+    #[test]
+    fn inflate_too_much() {
+        let fixed = [
+            0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40, 0x90, 0x5c, 0x00,
+        ];
+        assert!(inflate(&fixed, 17).is_err());
+        let stored = [0x01, 0x02, 0x00, 0xfd, 0xff, b'o', b'k'];
+        assert!(inflate(&stored, 1).is_err());
+
+        // compressed by zlib, a megabyte of zeros is stopped long before it's all inflated
+        let mut bomb = vec![
+            0xed, 0xc1, 0x01, 0x01, 0x00, 0x00, 0x00, 0x82, 0x20, 0xff, 0xaf, 0x6e, 0x48, 0x40,
+            0x01,
+        ];
+        bomb.resize(bomb.len() + 968, 0);
+        bomb.extend([0xaf, 0x06]);
+        assert_eq!(inflate(&bomb, 1_000_000).unwrap().len(), 1_000_000);
+        let e = inflate(&bomb, 100).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Inflated data is larger than the expected 100 bytes"
+        );
+    }
+
+    #[test]
+    fn jdk_jar() {
+        let Some(path) = java_home().map(|home| home.join("lib/jrt-fs.jar")) else {
+            return;
+        };
+        if !path.exists() {
+            return;
+        }
+        let zip = ZipArchive::open(path).unwrap();
+        let classes: Vec<_> = zip.names().filter(|n| n.ends_with(".class")).collect();
+        assert!(!classes.is_empty());
+        for name in classes {
+            let bytes = zip.read(name).unwrap().unwrap();
+            class_files::ClassFile::parse(&bytes).unwrap();
+        }
+        assert!(zip.read("META-INF/MANIFEST.MF").unwrap().is_some());
+        assert!(zip.read("missing").unwrap().is_none());
+    }
+}
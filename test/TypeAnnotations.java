@@ -0,0 +1,17 @@
+import java.lang.annotation.ElementType;
+import java.lang.annotation.Retention;
+import java.lang.annotation.RetentionPolicy;
+import java.lang.annotation.Target;
+import java.util.List;
+
+public class TypeAnnotations {
+    @Target(ElementType.TYPE_USE)
+    @Retention(RetentionPolicy.RUNTIME)
+    @interface Nullable {}
+
+    List<@Nullable String> names;
+
+    static String first(@Nullable String[] values) {
+        return values[0];
+    }
+}